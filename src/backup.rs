@@ -0,0 +1,713 @@
+//! # Backup
+//!
+//! Writes every folder and task to a single JSON document, so the data can be
+//! kept or inspected outside the app, and reads such a document back in.
+//!
+//! Folders are written as a flat list that points at each parent by id
+//! rather than nested, so however deep the tree goes the document stays
+//! shallow enough to read back.
+
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Duration, Utc};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+
+use crate::db::{self, DbError};
+use crate::folders::Folder;
+use crate::tasks::Task;
+
+/// Bumped whenever the layout of a backup changes in a way older readers
+/// wouldn't understand.
+pub const BACKUP_VERSION: u32 = 1;
+
+/// The whole document as written to disk.
+#[derive(Serialize, Deserialize)]
+pub struct Backup {
+    pub version: u32,
+    pub exported_at: DateTime<Utc>,
+    /// Every folder, each one after its parent.
+    pub folders: Vec<FolderEntry>,
+}
+
+/// A folder along with the tasks directly inside it.
+#[derive(Serialize, Deserialize)]
+pub struct FolderEntry {
+    #[serde(flatten)]
+    pub folder: Folder,
+    pub tasks: Vec<Task>,
+}
+
+/// Defines errors that can occur when writing or reading a backup.
+#[derive(Debug)]
+pub enum BackupError {
+    /// The folders or tasks couldn't be read or written.
+    Db(DbError),
+    /// The document couldn't be built, or isn't a backup.
+    Json(serde_json::Error),
+    /// The file couldn't be written or read.
+    Io(io::Error),
+    /// There was nothing to import.
+    Empty,
+    /// The backup was made by a newer version of the app.
+    UnsupportedVersion(u32),
+    /// A task in the backup has details the app can't work with. Contains
+    /// its summary and what's wrong.
+    InvalidTask(String, &'static str),
+}
+
+/// Implements display formatting for `BackupError`, providing a human-readable description of the error.
+impl fmt::Display for BackupError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BackupError::Db(e) => write!(f, "{}", e),
+            BackupError::Json(e) => write!(f, "{}", e),
+            BackupError::Io(e) => write!(f, "{}", e),
+            BackupError::Empty => write!(f, "The backup is empty"),
+            BackupError::UnsupportedVersion(version) => write!(
+                f,
+                "The backup is version {}, but only up to {} can be read",
+                version, BACKUP_VERSION
+            ),
+            BackupError::InvalidTask(summary, reason) => {
+                write!(f, "\"{}\" can't be imported: {}", summary, reason)
+            }
+        }
+    }
+}
+
+/// Allows `BackupError` to integrate with Rust's standard error handling mechanisms.
+impl Error for BackupError {}
+
+impl From<DbError> for BackupError {
+    fn from(e: DbError) -> Self {
+        BackupError::Db(e)
+    }
+}
+
+impl From<rusqlite::Error> for BackupError {
+    fn from(e: rusqlite::Error) -> Self {
+        BackupError::Db(e.into())
+    }
+}
+
+impl From<serde_json::Error> for BackupError {
+    fn from(e: serde_json::Error) -> Self {
+        BackupError::Json(e)
+    }
+}
+
+impl From<io::Error> for BackupError {
+    fn from(e: io::Error) -> Self {
+        BackupError::Io(e)
+    }
+}
+
+/// Serializes every folder and task, archived ones included.
+///
+/// # Arguments
+///
+/// * `conn: &Connection` - Allows us to access the SQLite db.
+///
+/// # Returns
+///
+/// A pretty printed `Backup`. Folders are listed depth first, and otherwise
+/// folders and tasks appear in the order they were added.
+///
+/// # Notes
+///
+/// Durations are written as whole seconds and dates in RFC 3339. Anything
+/// that can't be reached from a top level folder (e.g. a folder that's its
+/// own ancestor) is left out with a warning.
+pub fn export_json(conn: &Connection) -> Result<String, BackupError> {
+    let backup = Backup {
+        version: BACKUP_VERSION,
+        exported_at: Utc::now(),
+        folders: build_entries(db::read_folders(conn)?, db::read_all_tasks(conn)?),
+    };
+
+    Ok(serde_json::to_string_pretty(&backup)?)
+}
+
+/// Writes `export_json()` to a file, replacing it if it exists.
+pub fn export_to_file(conn: &Connection, path: &Path) -> Result<(), BackupError> {
+    fs::write(path, export_json(conn)?)?;
+    Ok(())
+}
+
+/// How many folders and tasks an import added, and how many it skipped
+/// because they were already there.
+#[derive(Debug, Default, PartialEq)]
+pub struct ImportSummary {
+    pub folders_added: u32,
+    pub folders_skipped: u32,
+    pub tasks_added: u32,
+    pub tasks_skipped: u32,
+    /// Folders and tasks that end up with a different id than the one in the
+    /// backup, either because they were given a new one or merged into a
+    /// folder that was already there.
+    pub ids_remapped: u32,
+}
+
+/// A backup that's been read and checked, along with what importing it would
+/// do. See `plan_json_import()`.
+pub struct BackupPlan {
+    backup: Backup,
+    pub summary: ImportSummary,
+}
+
+impl BackupPlan {
+    /// Re-creates the folders and tasks in the backup.
+    ///
+    /// # Returns
+    ///
+    /// What was imported, which matches `summary` unless the db changed since
+    /// the plan was made.
+    ///
+    /// # Notes
+    ///
+    /// Runs in a single transaction, so a backup that fails part way leaves
+    /// the db as it was.
+    pub fn apply(&self, conn: &Connection) -> Result<ImportSummary, BackupError> {
+        run_import(conn, &self.backup, true)
+    }
+}
+
+/// Reads a backup made by `export_json()` and works out what importing it
+/// would do, without changing the db.
+///
+/// # Arguments
+///
+/// * `conn: &Connection` - Allows us to access the SQLite db.
+/// * `json: &str` - The backup.
+///
+/// # Notes
+///
+/// - Folders and tasks get new ids. Parents are added before their children,
+///   so each is pointed at its parent's new id rather than the one in the
+///   backup.
+/// - A folder with the same name and parent as one that's already there is
+///   merged into it rather than added again. Tasks in it that were created at
+///   the same time with the same summary are skipped, so importing a backup
+///   twice doesn't add anything the second time.
+/// - The import is run in a transaction that's rolled back, so the counts
+///   are exactly what `BackupPlan::apply()` would do.
+/// - Every task is checked with `check_task()` first, and one that fails
+///   stops the whole import. Unknown priorities and malformed dates are
+///   already caught while reading the document.
+pub fn plan_json_import(conn: &Connection, json: &str) -> Result<BackupPlan, BackupError> {
+    if json.trim().is_empty() {
+        return Err(BackupError::Empty);
+    }
+    let backup: Backup = serde_json::from_str(json)?;
+    if backup.version > BACKUP_VERSION {
+        return Err(BackupError::UnsupportedVersion(backup.version));
+    }
+    for task in backup.folders.iter().flat_map(|entry| &entry.tasks) {
+        check_task(task, backup.exported_at)
+            .map_err(|reason| BackupError::InvalidTask(task.summary.clone(), reason))?;
+    }
+
+    let summary = run_import(conn, &backup, false)?;
+    Ok(BackupPlan { backup, summary })
+}
+
+/// Checks that a task from a backup made at `exported_at` can be added
+/// without breaking the maths that schedules and pays for it.
+///
+/// # Returns
+///
+/// What's wrong with the task, if anything.
+fn check_task(task: &Task, exported_at: DateTime<Utc>) -> Result<(), &'static str> {
+    if task.repeat_interval == Some(0) {
+        return Err("it repeats every 0 intervals");
+    }
+    if task.created_at > exported_at {
+        return Err("it was created after the backup was made");
+    }
+    if let (Some(due_date), Some(lead_days)) = (task.due_date, task.lead_days) {
+        if due_date
+            .checked_sub_signed(Duration::days(i64::from(lead_days)))
+            .is_none()
+        {
+            return Err("its lead days start before the earliest possible date");
+        }
+    }
+    if let Some(interval) = task.repeat_duration() {
+        if task.from_date.checked_add_signed(interval).is_none() {
+            return Err("it next comes around after the latest possible date");
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads a backup file and plans its import. See `plan_json_import()`.
+pub fn plan_file_import(conn: &Connection, path: &Path) -> Result<BackupPlan, BackupError> {
+    plan_json_import(conn, &fs::read_to_string(path)?)
+}
+
+/// Imports `backup` in one transaction, and only keeps the result if
+/// `commit` is set.
+///
+/// # Notes
+///
+/// Folders are added parents first, whatever order the backup lists them in.
+/// Any that can't be reached from a top level folder are left out with a
+/// warning, along with their tasks.
+fn run_import(
+    conn: &Connection,
+    backup: &Backup,
+    commit: bool,
+) -> Result<ImportSummary, BackupError> {
+    let tx = conn.unchecked_transaction()?;
+    let mut importer = Importer {
+        conn: &tx,
+        existing_folders: db::read_folders(&tx)?
+            .into_iter()
+            .map(|folder| ((folder.parent_id, folder.name), folder.id))
+            .collect(),
+        existing_tasks: db::read_all_tasks(&tx)?
+            .into_iter()
+            .map(|task| (task.parent_id, task.summary, task.created_at))
+            .collect(),
+        summary: ImportSummary::default(),
+    };
+    let (entries, folders_left) =
+        depth_first(backup.folders.iter().collect(), |entry| &entry.folder);
+    if folders_left > 0 {
+        eprintln!(
+            "Warning: {folders_left} folders in the backup aren't under a top level folder, so they weren't imported."
+        );
+    }
+    // Backup ids to the ids the folders end up with
+    let mut new_ids = HashMap::new();
+    for entry in entries {
+        let parent_id = entry.folder.parent_id.map(|id| new_ids[&id]);
+        let id = importer.add(entry, parent_id)?;
+        new_ids.insert(entry.folder.id, id);
+    }
+    let summary = importer.summary;
+    if commit {
+        tx.commit()?;
+    }
+
+    Ok(summary)
+}
+
+/// The state `run_import()` keeps while it adds each folder.
+struct Importer<'a> {
+    conn: &'a Connection,
+    /// Folder ids by parent and name.
+    existing_folders: HashMap<(Option<u32>, String), u32>,
+    existing_tasks: HashSet<(u32, String, DateTime<Utc>)>,
+    summary: ImportSummary,
+}
+
+impl Importer<'_> {
+    /// Adds a folder under `parent_id`, then the tasks inside it.
+    ///
+    /// # Returns
+    ///
+    /// The folder's id in the db.
+    fn add(&mut self, entry: &FolderEntry, parent_id: Option<u32>) -> Result<u32, BackupError> {
+        let key = (parent_id, entry.folder.name.clone());
+        let id = match self.existing_folders.get(&key) {
+            Some(&id) => {
+                self.summary.folders_skipped += 1;
+                id
+            }
+            None => {
+                let folder = Folder {
+                    id: entry.folder.id,
+                    parent_id,
+                    name: entry.folder.name.clone(),
+                    style: entry.folder.style.clone(),
+                    status: entry.folder.status,
+                };
+                db::add_folder(self.conn, &folder)?;
+                let id = self.conn.last_insert_rowid() as u32;
+                self.existing_folders.insert(key, id);
+                self.summary.folders_added += 1;
+                id
+            }
+        };
+        if id != entry.folder.id {
+            self.summary.ids_remapped += 1;
+        }
+
+        for task in &entry.tasks {
+            let key = (id, task.summary.clone(), task.created_at);
+            if self.existing_tasks.contains(&key) {
+                self.summary.tasks_skipped += 1;
+                continue;
+            }
+            let new_id = db::add_task(
+                self.conn,
+                Task {
+                    parent_id: id,
+                    ..task.clone()
+                },
+            )?;
+            if new_id != task.id {
+                self.summary.ids_remapped += 1;
+            }
+            self.existing_tasks.insert(key);
+            self.summary.tasks_added += 1;
+        }
+
+        Ok(id)
+    }
+}
+
+/// The default name for a backup made at `now`, e.g.
+/// "backup-20240131-183000.json".
+pub fn file_name(now: DateTime<Utc>) -> String {
+    format!("backup-{}.json", now.format("%Y%m%d-%H%M%S"))
+}
+
+/// Finds the most recent backup in `dir` with a name from `file_name()`.
+pub fn latest_file(dir: &Path) -> Option<PathBuf> {
+    fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with("backup-") && name.ends_with(".json"))
+        })
+        // The timestamp sorts the same way as the time it stands for
+        .max()
+}
+
+/// Orders the folders depth first and puts each task in its folder's entry.
+fn build_entries(folders: Vec<Folder>, tasks: Vec<Task>) -> Vec<FolderEntry> {
+    let mut contents: HashMap<u32, Vec<Task>> = HashMap::new();
+    for task in tasks {
+        contents.entry(task.parent_id).or_default().push(task);
+    }
+
+    let (folders, folders_left) = depth_first(folders, |folder| folder);
+    let entries = folders
+        .into_iter()
+        .map(|folder| FolderEntry {
+            tasks: contents.remove(&folder.id).unwrap_or_default(),
+            folder,
+        })
+        .collect();
+
+    let tasks_left: usize = contents.values().map(Vec::len).sum();
+    if folders_left + tasks_left > 0 {
+        eprintln!(
+            "Warning: {folders_left} folders and {tasks_left} tasks aren't under a top level folder, so they weren't backed up."
+        );
+    }
+
+    entries
+}
+
+/// Orders `items` so each folder comes after its parent, walking the tree
+/// depth first and keeping siblings in the order they were given.
+///
+/// # Arguments
+///
+/// * `items: Vec<T>` - Anything that holds a folder.
+/// * `folder: impl Fn(&T) -> &Folder` - Gets the folder out of an item.
+///
+/// # Returns
+///
+/// The ordered items, and how many couldn't be reached from a top level
+/// folder (e.g. a folder that's its own ancestor) and were left out.
+///
+/// # Notes
+///
+/// Each folder's children are taken out as they're reached, so none can be
+/// visited twice, and the walk uses its own stack so a deep tree can't
+/// overflow the real one.
+fn depth_first<T>(items: Vec<T>, folder: impl Fn(&T) -> &Folder) -> (Vec<T>, usize) {
+    let mut children: HashMap<Option<u32>, Vec<T>> = HashMap::new();
+    for item in items {
+        children
+            .entry(folder(&item).parent_id)
+            .or_default()
+            .push(item);
+    }
+
+    let mut ordered = Vec::new();
+    let mut stack = children.remove(&None).unwrap_or_default();
+    stack.reverse();
+    while let Some(item) = stack.pop() {
+        if let Some(mut subfolders) = children.remove(&Some(folder(&item).id)) {
+            subfolders.reverse();
+            stack.extend(subfolders);
+        }
+        ordered.push(item);
+    }
+
+    (ordered, children.values().map(Vec::len).sum())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures;
+    use chrono::Duration;
+    use serde_json::Value;
+
+    #[test]
+    fn test_export_json() {
+        let (conn, seed) = fixtures::seeded();
+        db::set_average_duration(&conn, seed.tasks["dishes"], Duration::minutes(15)).unwrap();
+
+        let json = export_json(&conn).unwrap();
+        let doc: Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(doc["version"], BACKUP_VERSION);
+
+        let folders = doc["folders"].as_array().unwrap();
+        let find = |name: &str| {
+            let index = folders.iter().position(|folder| folder["name"] == name);
+            (index.unwrap(), &folders[index.unwrap()])
+        };
+        let (_, general) = find("General");
+        assert_eq!(general["parent_id"], Value::Null);
+        let (home_index, home) = find("Home");
+        assert_eq!(home["parent_id"], general["id"]);
+        assert_eq!(home["style"], "Directory");
+        assert_eq!(home["tasks"].as_array().unwrap().len(), 2);
+
+        // Folders come straight after their parent, archived tasks are kept,
+        // and durations are in seconds
+        let (chores_index, chores) = find("Chores");
+        assert_eq!(chores_index, home_index + 1);
+        assert_eq!(chores["parent_id"], home["id"]);
+        let summaries: Vec<&Value> = chores["tasks"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|task| &task["summary"])
+            .collect();
+        assert_eq!(summaries, ["Wash the dishes", "Fix the sink"]);
+        assert_eq!(chores["tasks"][0]["average_duration"], 900);
+        assert_eq!(chores["tasks"][1]["is_archived"], true);
+
+        // What's written can be read back in
+        let backup: Backup = serde_json::from_str(&json).unwrap();
+        let office = backup
+            .folders
+            .iter()
+            .find(|node| node.folder.name == "Office")
+            .unwrap();
+        assert_eq!(
+            office.tasks[0],
+            fixtures::read_task(&conn, seed.tasks["report"])
+        );
+    }
+
+    #[test]
+    fn test_import_round_trips_export() {
+        let (conn, seed) = fixtures::seeded();
+        let json = export_json(&conn).unwrap();
+
+        // A fresh db already has General and the placeholder folders, so
+        // those are merged and only the seeded ones are added
+        let fresh = fixtures::setup();
+        let folders = db::read_folders(&fresh).unwrap().len();
+        let plan = plan_json_import(&fresh, &json).unwrap();
+        let expected = ImportSummary {
+            folders_added: 3,
+            folders_skipped: 4,
+            tasks_added: 5,
+            tasks_skipped: 0,
+            ids_remapped: 0,
+        };
+        assert_eq!(plan.summary, expected);
+        // Planning doesn't write anything
+        assert_eq!(db::read_folders(&fresh).unwrap().len(), folders);
+        assert!(db::read_all_tasks(&fresh).unwrap().is_empty());
+        assert_eq!(plan.apply(&fresh).unwrap(), expected);
+
+        let imported = db::read_all_tasks(&fresh).unwrap();
+        let report = imported
+            .iter()
+            .find(|task| task.summary == "Write the report")
+            .unwrap();
+        let office = db::read_folders(&fresh)
+            .unwrap()
+            .into_iter()
+            .find(|folder| folder.name == "Office")
+            .unwrap();
+        assert_eq!(report.parent_id, office.id);
+        assert_eq!(
+            Task {
+                id: report.id,
+                parent_id: report.parent_id,
+                ..fixtures::read_task(&conn, seed.tasks["report"])
+            },
+            *report
+        );
+        let paths: Vec<String> = db::read_all_folders_sorted(&fresh)
+            .unwrap()
+            .into_iter()
+            .map(|(_, path)| path)
+            .collect();
+        assert!(paths.contains(&"General::Home::Chores".to_string()));
+
+        // Importing the same backup again adds nothing
+        let again = plan_json_import(&fresh, &json).unwrap().summary;
+        assert_eq!((again.folders_added, again.tasks_added), (0, 0));
+        assert_eq!(again.tasks_skipped, 5);
+    }
+
+    #[test]
+    fn test_import_round_trips_deep_tree() {
+        // Far deeper than serde_json would read if folders were nested
+        let conn = fixtures::setup();
+        let mut parent_id = 1;
+        for depth in 0..300 {
+            parent_id = fixtures::insert_folder(&conn, Some(parent_id), &format!("Level {depth}"));
+        }
+        fixtures::insert_task(&conn, fixtures::task(parent_id, "Bottom"));
+        let json = export_json(&conn).unwrap();
+
+        let fresh = fixtures::setup();
+        let plan = plan_json_import(&fresh, &json).unwrap();
+        assert_eq!(plan.summary.folders_added, 300);
+        assert_eq!(plan.summary.tasks_added, 1);
+        plan.apply(&fresh).unwrap();
+
+        let bottom = db::read_all_tasks(&fresh).unwrap().remove(0);
+        let path = db::read_all_folders_sorted(&fresh)
+            .unwrap()
+            .into_iter()
+            .find_map(|(id, path)| (id == bottom.parent_id).then_some(path))
+            .unwrap();
+        assert!(path.starts_with("General::Level 0::Level 1::"), "{path}");
+        assert!(path.ends_with("::Level 298::Level 299"), "{path}");
+        assert_eq!(path.matches("::").count(), 300);
+    }
+
+    #[test]
+    fn test_import_reads_folders_in_any_order() {
+        let (conn, _) = fixtures::seeded();
+        let mut backup: Backup = serde_json::from_str(&export_json(&conn).unwrap()).unwrap();
+        backup.folders.reverse();
+        let json = serde_json::to_string(&backup).unwrap();
+
+        let fresh = fixtures::setup();
+        let plan = plan_json_import(&fresh, &json).unwrap();
+        assert_eq!(
+            (plan.summary.folders_added, plan.summary.tasks_added),
+            (3, 5)
+        );
+    }
+
+    #[test]
+    fn test_import_plan_counts_remapped_ids() {
+        let (conn, seed) = fixtures::seeded();
+        let json = export_json(&conn).unwrap();
+
+        // Taking the id Home had pushes every seeded folder along by one
+        let fresh = fixtures::setup();
+        fixtures::insert_folder(&fresh, None, "Garden");
+        let plan = plan_json_import(&fresh, &json).unwrap();
+        assert_eq!(plan.summary.folders_added, 3);
+        assert_eq!(plan.summary.ids_remapped, 3);
+        // Even though working that out added them for a moment
+        assert!(db::read_folders(&fresh)
+            .unwrap()
+            .iter()
+            .all(|folder| folder.id != seed.folders["office"] + 1));
+    }
+
+    #[test]
+    fn test_import_rejects_bad_backups() {
+        let conn = fixtures::setup();
+        let folders = db::read_folders(&conn).unwrap().len();
+
+        assert!(matches!(
+            plan_json_import(&conn, " \n"),
+            Err(BackupError::Empty)
+        ));
+        assert!(matches!(
+            plan_json_import(&conn, "{\"version\": 1, \"folders\": ["),
+            Err(BackupError::Json(_))
+        ));
+        let newer = format!(
+            "{{\"version\": {}, \"exported_at\": \"2024-01-31T18:30:00Z\", \"folders\": []}}",
+            BACKUP_VERSION + 1
+        );
+        assert!(matches!(
+            plan_json_import(&conn, &newer),
+            Err(BackupError::UnsupportedVersion(_))
+        ));
+
+        assert_eq!(db::read_folders(&conn).unwrap().len(), folders);
+    }
+
+    #[test]
+    fn test_import_rejects_invalid_tasks() {
+        let (conn, _) = fixtures::seeded();
+        let json = export_json(&conn).unwrap();
+        let fresh = fixtures::setup();
+
+        let backup_with = |summary: &str, field: &str, value: Value| {
+            let mut doc: Value = serde_json::from_str(&json).unwrap();
+            for folder in doc["folders"].as_array_mut().unwrap() {
+                for task in folder["tasks"].as_array_mut().unwrap() {
+                    if task["summary"] == summary {
+                        task[field] = value.clone();
+                    }
+                }
+            }
+            doc.to_string()
+        };
+
+        let zero_interval = backup_with("Do the laundry", "repeat_interval", 0.into());
+        assert!(matches!(
+            plan_json_import(&fresh, &zero_interval),
+            Err(BackupError::InvalidTask(summary, _)) if summary == "Do the laundry"
+        ));
+        let far_lead = backup_with("Write the report", "lead_days", u32::MAX.into());
+        assert!(matches!(
+            plan_json_import(&fresh, &far_lead),
+            Err(BackupError::InvalidTask(..))
+        ));
+        let future = backup_with(
+            "Wash the dishes",
+            "created_at",
+            "2999-01-01T00:00:00Z".into(),
+        );
+        assert!(matches!(
+            plan_json_import(&fresh, &future),
+            Err(BackupError::InvalidTask(..))
+        ));
+        let priority = backup_with("Wash the dishes", "priority", "P9".into());
+        assert!(matches!(
+            plan_json_import(&fresh, &priority),
+            Err(BackupError::Json(_))
+        ));
+        let date = backup_with("Wash the dishes", "due_date", "tomorrow".into());
+        assert!(matches!(
+            plan_json_import(&fresh, &date),
+            Err(BackupError::Json(_))
+        ));
+
+        // Nothing was added on the way, and the untouched backup still works
+        assert!(db::read_all_tasks(&fresh).unwrap().is_empty());
+        assert!(plan_json_import(&fresh, &json).is_ok());
+    }
+
+    #[test]
+    fn test_file_name() {
+        let now = DateTime::parse_from_rfc3339("2024-01-31T18:30:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        assert_eq!(file_name(now), "backup-20240131-183000.json");
+    }
+}
@@ -0,0 +1,384 @@
+//! # CLI
+//!
+//! Works with tasks from the command line without starting the TUI, so they
+//! can be captured from a shell script or shown in a status bar. Run as
+//! `nextup add --summary "..." [--priority 2] [--folder 1] [--every 7 | --due 2025-03-01 [--lead 3]]`
+//! or `nextup next [count]`.
+
+use std::error::Error;
+use std::fmt;
+
+use chrono::{DateTime, Utc};
+use rusqlite::Connection;
+
+use crate::db::{self, DbError};
+use crate::tasks::{Priority, Task};
+use crate::ui::{self, DueInputError, MoneyFormat};
+
+/// The options given to `add`, before they're checked against the db.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct AddArgs {
+    pub summary: String,
+    pub priority: Option<Priority>,
+    pub folder: Option<u32>,
+    /// Makes the task recurring, every this many days.
+    pub every: Option<u32>,
+    /// Gives the task a deadline.
+    pub due: Option<DateTime<Utc>>,
+    pub lead: Option<u32>,
+}
+
+/// Defines errors that can occur when adding a task from the command line.
+#[derive(Debug)]
+pub enum CliError {
+    /// An option that `add` doesn't know.
+    UnknownOption(String),
+    /// An option that was given without a value after it.
+    MissingValue(String),
+    /// An option whose value couldn't be understood.
+    InvalidValue {
+        option: String,
+        value: String,
+    },
+    /// The summary is missing or blank.
+    EmptySummary,
+    SummaryTooLong(usize),
+    /// Both --every and --due were given.
+    RecurringWithDeadline,
+    /// --lead was given without --due.
+    LeadWithoutDeadline,
+    LeadTooLong(u32),
+    DueInPast,
+    UnknownFolder(u32),
+    Db(DbError),
+}
+
+/// Implements display formatting for `CliError`, providing a human-readable description of the error.
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CliError::UnknownOption(option) => write!(f, "Unknown option {}", option),
+            CliError::MissingValue(option) => write!(f, "{} needs a value", option),
+            CliError::InvalidValue { option, value } => {
+                write!(f, "\"{}\" isn't a valid value for {}", value, option)
+            }
+            CliError::EmptySummary => write!(f, "The summary can't be empty"),
+            CliError::SummaryTooLong(max) => {
+                write!(f, "The summary can't be longer than {} characters", max)
+            }
+            CliError::RecurringWithDeadline => {
+                write!(f, "A task can't have both --every and --due")
+            }
+            CliError::LeadWithoutDeadline => write!(f, "--lead only applies with --due"),
+            CliError::LeadTooLong(max) => write!(f, "The lead can't be more than {} days", max),
+            CliError::DueInPast => write!(f, "The due date can't be in the past"),
+            CliError::UnknownFolder(id) => write!(f, "There's no folder with id {}", id),
+            CliError::Db(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+/// Allows `CliError` to integrate with Rust's standard error handling mechanisms.
+impl Error for CliError {}
+
+impl From<DbError> for CliError {
+    fn from(e: DbError) -> Self {
+        CliError::Db(e)
+    }
+}
+
+/// Reads the options given to `add`.
+///
+/// # Arguments
+///
+/// * `args: &[String]` - Everything after `add`.
+/// * `now: DateTime<Utc>` - Decides which day --due counts from.
+///
+/// # Notes
+///
+/// - --priority is numbered the same way as on the Add Task screen, from 0
+///   (deprioritized) to 3 (top priority), with 4 for neutral.
+/// - --due takes a YYYY-MM-DD date or a number of days, see
+///   `ui::parse_due_input()`.
+pub fn parse_add_args(args: &[String], now: DateTime<Utc>) -> Result<AddArgs, CliError> {
+    let mut parsed = AddArgs::default();
+    let mut args = args.iter();
+
+    while let Some(option) = args.next() {
+        let value = args
+            .next()
+            .ok_or_else(|| CliError::MissingValue(option.clone()))?;
+        let invalid = || CliError::InvalidValue {
+            option: option.clone(),
+            value: value.clone(),
+        };
+
+        match option.as_str() {
+            "--summary" => parsed.summary = value.trim().to_string(),
+            "--priority" => {
+                parsed.priority = Some(match value.parse::<u8>().map(Priority::try_from) {
+                    Ok(Ok(priority)) => priority,
+                    _ => return Err(invalid()),
+                })
+            }
+            "--folder" => parsed.folder = Some(value.parse().map_err(|_| invalid())?),
+            "--every" => match value.parse() {
+                Ok(days) if days > 0 => parsed.every = Some(days),
+                _ => return Err(invalid()),
+            },
+            "--due" => {
+                parsed.due = Some(ui::parse_due_input(value, now).map_err(|e| match e {
+                    DueInputError::InPast => CliError::DueInPast,
+                    DueInputError::Invalid => invalid(),
+                })?)
+            }
+            "--lead" => parsed.lead = Some(value.parse().map_err(|_| invalid())?),
+            _ => return Err(CliError::UnknownOption(option.clone())),
+        }
+    }
+
+    if parsed.summary.is_empty() {
+        return Err(CliError::EmptySummary);
+    }
+    if parsed.every.is_some() && parsed.due.is_some() {
+        return Err(CliError::RecurringWithDeadline);
+    }
+    if parsed.lead.is_some() && parsed.due.is_none() {
+        return Err(CliError::LeadWithoutDeadline);
+    }
+
+    Ok(parsed)
+}
+
+/// Builds the task described by `args`, checking it against the settings
+/// and folders in the db.
+///
+/// # Arguments
+///
+/// * `conn: &Connection` - Allows us to access the SQLite db.
+/// * `args: AddArgs` - From `parse_add_args()`.
+/// * `default_folder: u32` - Where the task goes without --folder.
+///
+/// # Notes
+///
+/// The same limits apply as on the Add Task screen: max_summary_length and
+/// max_lead_days.
+pub fn build_task(conn: &Connection, args: AddArgs, default_folder: u32) -> Result<Task, CliError> {
+    let max_summary = ui::max_summary_length(conn);
+    if args.summary.chars().count() > max_summary {
+        return Err(CliError::SummaryTooLong(max_summary));
+    }
+    let max_lead = ui::max_lead_days(conn);
+    if args.lead.is_some_and(|lead| lead > max_lead) {
+        return Err(CliError::LeadTooLong(max_lead));
+    }
+
+    let parent_id = args.folder.unwrap_or(default_folder);
+    if !db::read_folder_styles(conn)
+        .map_err(DbError::from)?
+        .contains_key(&parent_id)
+    {
+        return Err(CliError::UnknownFolder(parent_id));
+    }
+
+    Ok(Task {
+        from_date: ui::midnight_today_utc(),
+        priority: args.priority.unwrap_or(Priority::P1),
+        repeat_interval: args.every,
+        due_date: args.due,
+        lead_days: args.lead,
+        ..Task::new(parent_id, args.summary)
+    })
+}
+
+/// Reads the options given to `next`.
+///
+/// # Returns
+///
+/// How many tasks to print, or None if no count was given.
+pub fn parse_next_args(args: &[String]) -> Result<Option<usize>, CliError> {
+    match args {
+        [] => Ok(None),
+        [count] => match count.parse() {
+            Ok(count) if count > 0 => Ok(Some(count)),
+            _ => Err(CliError::InvalidValue {
+                option: "the count".into(),
+                value: count.clone(),
+            }),
+        },
+        [_, extra, ..] => Err(CliError::UnknownOption(extra.clone())),
+    }
+}
+
+/// Lists tasks one per line, heaviest first, so the top task is the first
+/// line.
+///
+/// # Arguments
+///
+/// * `tasks: &[(Task, f64)]` - The ranked tasks and their bounties.
+/// * `money: Option<&MoneyFormat>` - If Some, each bounty follows its
+///   summary after a tab.
+pub fn format_top_tasks(tasks: &[(Task, f64)], money: Option<&MoneyFormat>) -> String {
+    tasks
+        .iter()
+        .map(|(task, bounty)| match money {
+            Some(format) => format!("{}\t{}\n", task.summary, ui::format_money(*bounty, format)),
+            None => format!("{}\n", task.summary),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures;
+    use chrono::Duration;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_add_args() {
+        let now = Utc::now();
+        let parsed = parse_add_args(
+            &args(&["--summary", " Stretch ", "--priority", "2", "--every", "1"]),
+            now,
+        )
+        .unwrap();
+        assert_eq!(
+            parsed,
+            AddArgs {
+                summary: "Stretch".into(),
+                priority: Some(Priority::P2),
+                every: Some(1),
+                ..AddArgs::default()
+            }
+        );
+
+        let parsed = parse_add_args(
+            &args(&["--due", "5", "--lead", "3", "--summary", "Taxes"]),
+            now,
+        )
+        .unwrap();
+        assert_eq!(
+            parsed.due,
+            Some(ui::midnight_today_utc() + Duration::days(5))
+        );
+        assert_eq!(parsed.lead, Some(3));
+    }
+
+    #[test]
+    fn test_parse_add_args_rejects_bad_input() {
+        let now = Utc::now();
+        let parse = |given: &[&str]| parse_add_args(&args(given), now);
+
+        assert!(matches!(parse(&[]), Err(CliError::EmptySummary)));
+        assert!(matches!(
+            parse(&["--summary", "  "]),
+            Err(CliError::EmptySummary)
+        ));
+        assert!(matches!(
+            parse(&["--summary", "Both", "--every", "7", "--due", "3"]),
+            Err(CliError::RecurringWithDeadline)
+        ));
+        assert!(matches!(
+            parse(&["--summary", "Lead", "--lead", "3"]),
+            Err(CliError::LeadWithoutDeadline)
+        ));
+        assert!(matches!(
+            parse(&["--summary", "Past", "--due", "2000-01-01"]),
+            Err(CliError::DueInPast)
+        ));
+        assert!(matches!(
+            parse(&["--summary", "Never", "--every", "0"]),
+            Err(CliError::InvalidValue { .. })
+        ));
+        assert!(matches!(
+            parse(&["--summary", "Loud", "--priority", "9"]),
+            Err(CliError::InvalidValue { .. })
+        ));
+        assert!(matches!(
+            parse(&["--summary"]),
+            Err(CliError::MissingValue(_))
+        ));
+        assert!(matches!(
+            parse(&["--summary", "Hi", "--colour", "red"]),
+            Err(CliError::UnknownOption(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_next_args() {
+        assert_eq!(parse_next_args(&[]).unwrap(), None);
+        assert_eq!(parse_next_args(&args(&["3"])).unwrap(), Some(3));
+        assert!(matches!(
+            parse_next_args(&args(&["0"])),
+            Err(CliError::InvalidValue { .. })
+        ));
+        assert!(matches!(
+            parse_next_args(&args(&["3", "4"])),
+            Err(CliError::UnknownOption(_))
+        ));
+    }
+
+    #[test]
+    fn test_format_top_tasks() {
+        let tasks = [
+            (Task::new(1, "Dishes".into()), 1.5),
+            (Task::new(1, "Laundry".into()), 0.25),
+        ];
+        assert_eq!(format_top_tasks(&tasks, None), "Dishes\nLaundry\n");
+        assert_eq!(
+            format_top_tasks(&tasks, Some(&MoneyFormat::default())),
+            "Dishes\t$1.50\nLaundry\t$0.25\n"
+        );
+        assert_eq!(format_top_tasks(&[], None), "");
+    }
+
+    #[test]
+    fn test_build_task() {
+        let (conn, seed) = fixtures::seeded();
+        let office = seed.folders["office"];
+        let given = AddArgs {
+            summary: "File receipts".into(),
+            folder: Some(office),
+            ..AddArgs::default()
+        };
+
+        let task = build_task(&conn, given.clone(), 1).unwrap();
+        assert_eq!(task.parent_id, office);
+        assert_eq!(task.priority, Priority::P1);
+        assert_eq!(task.from_date, ui::midnight_today_utc());
+
+        assert_eq!(
+            build_task(
+                &conn,
+                AddArgs {
+                    folder: None,
+                    ..given.clone()
+                },
+                1
+            )
+            .unwrap()
+            .parent_id,
+            1
+        );
+        assert!(matches!(
+            build_task(
+                &conn,
+                AddArgs {
+                    folder: Some(999),
+                    ..given.clone()
+                },
+                1
+            ),
+            Err(CliError::UnknownFolder(999))
+        ));
+        db::update_setting(&conn, "max_summary_length", 5).unwrap();
+        assert!(matches!(
+            build_task(&conn, given, 1),
+            Err(CliError::SummaryTooLong(5))
+        ));
+    }
+}
@@ -0,0 +1,47 @@
+//! # Clock
+//!
+//! Lets code that depends on the current time be run against a fake clock,
+//! e.g. to fast-forward through a simulated month.
+
+use std::cell::Cell;
+
+use chrono::{DateTime, Duration, Utc};
+
+/// Somewhere to get the current time from.
+pub trait Clock {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// The real time.
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// A clock that only moves when it's told to.
+pub struct FixedClock {
+    now: Cell<DateTime<Utc>>,
+}
+
+impl FixedClock {
+    /// Creates a clock stopped at `now`.
+    pub fn new(now: DateTime<Utc>) -> Self {
+        FixedClock {
+            now: Cell::new(now),
+        }
+    }
+
+    /// Moves the clock forward by `duration`.
+    pub fn advance(&self, duration: Duration) {
+        self.now.set(self.now.get() + duration);
+    }
+}
+
+impl Clock for FixedClock {
+    fn now(&self) -> DateTime<Utc> {
+        self.now.get()
+    }
+}
@@ -0,0 +1,112 @@
+//! # Config
+//!
+//! Gathers the settings that decide how tasks are ranked and paid into one
+//! JSON document, so a tuned profile can be shared or a ranking problem
+//! reproduced from a bug report. Run as `nextup config`.
+
+use rusqlite::Connection;
+use serde_json::{Map, Value};
+
+use crate::db;
+
+/// The settings that change which tasks come up in ToDo, and in what order.
+const WEIGHTING_KEYS: &[&str] = &[
+    "overdue_weight_cap",
+    "overdue_critical_days",
+    "budget_aware_weighting",
+    "exclude_in_progress",
+    "max_tasks_per_folder",
+    "todo_candidate_count",
+    "tasks_per_view",
+    "hide_archived_folder_tasks",
+    "reshuffle_suppression_minutes",
+    "session_repeat_penalty",
+    "vacation_mode",
+];
+
+/// The settings that change what tasks pay.
+const FINANCE_KEYS: &[&str] = &[
+    "finance_enabled",
+    "maximum_monthly_allowance",
+    "target_monthly_allowance",
+    "bounty_floor_p0",
+    "bounty_ceiling_p0",
+    "bounty_floor_p1",
+    "bounty_ceiling_p1",
+    "bounty_floor_p2",
+    "bounty_ceiling_p2",
+    "bounty_floor_p3",
+    "bounty_ceiling_p3",
+    "bounty_floor_neutral",
+    "bounty_ceiling_neutral",
+];
+
+/// Reads the weighting and finance settings in effect.
+///
+/// # Arguments
+///
+/// * `conn: &Connection` - Allows us to access the SQLite db.
+///
+/// # Returns
+///
+/// An object with a `weighting` and a `finance` section, each mapping every
+/// relevant key to its value.
+///
+/// # Notes
+///
+/// A setting that's missing or can't be read is reported with its default,
+/// since that's what the rest of the app falls back to.
+pub fn export_effective(conn: &Connection) -> Value {
+    let mut config = Map::new();
+    config.insert("weighting".into(), section(conn, WEIGHTING_KEYS));
+    config.insert("finance".into(), section(conn, FINANCE_KEYS));
+    Value::Object(config)
+}
+
+fn section(conn: &Connection, keys: &[&str]) -> Value {
+    let values = keys
+        .iter()
+        .map(|key| {
+            let value = match db::read_setting_u32(conn, key) {
+                Ok(Some(value)) => Some(value),
+                _ => default_for(key),
+            };
+            (key.to_string(), value.map_or(Value::Null, Value::from))
+        })
+        .collect();
+
+    Value::Object(values)
+}
+
+fn default_for(key: &str) -> Option<u32> {
+    db::DEFAULT_SETTINGS
+        .iter()
+        .find(|(default_key, _)| *default_key == key)
+        .map(|(_, value)| *value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures;
+
+    #[test]
+    fn test_export_effective() {
+        let conn = fixtures::setup();
+        db::update_setting(&conn, "overdue_weight_cap", 25).unwrap();
+        conn.execute("DELETE FROM settings WHERE key = 'bounty_floor_p2'", ())
+            .unwrap();
+
+        let config = export_effective(&conn);
+        for (section, keys) in [("weighting", WEIGHTING_KEYS), ("finance", FINANCE_KEYS)] {
+            for key in keys {
+                let expected = match *key {
+                    "overdue_weight_cap" => 25,
+                    _ => default_for(key).expect("Every exported key has a default"),
+                };
+                assert_eq!(config[section][key], expected, "{section}.{key}");
+            }
+            assert_eq!(config[section].as_object().unwrap().len(), keys.len());
+        }
+    }
+}
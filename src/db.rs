@@ -1,11 +1,76 @@
 use core::panic;
-use std::collections::HashMap;
-use std::io;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
 
-use super::folders::{Folder, Style};
-use super::tasks::{Priority, Task};
-use chrono::{DateTime, Duration, Utc};
-use rusqlite::{params, params_from_iter, Connection, Error, OptionalExtension, Result, Statement};
+use super::folders::{Folder, Style, STATUS_ARCHIVED};
+use super::tasks::{Priority, RecurrenceAnchor, RepeatUnit, Task, TaskTemplate};
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, TimeZone, Utc};
+use rusqlite::{
+    params, params_from_iter, Connection, Error, OptionalExtension, Params, Result, Statement,
+};
+
+/// Why a read from or write to the db failed.
+#[derive(Debug)]
+pub enum DbError {
+    /// The query itself failed.
+    Sqlite(Error),
+    /// No task has this id, e.g. because it was deleted in the meantime.
+    TaskNotFound(u32),
+    /// The settings table has no row for this key.
+    SettingMissing(String),
+    /// No folder has this id.
+    FolderNotFound(u32),
+    /// General can't be deleted, since it's where tasks go when nowhere else
+    /// will do.
+    GeneralFolder,
+    /// Snoozing for this many days would go past the latest date that can be
+    /// stored.
+    SnoozeTooLong(u32),
+}
+
+impl fmt::Display for DbError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DbError::Sqlite(e) => write!(f, "{e}"),
+            DbError::TaskNotFound(id) => write!(f, "there's no task with id {id}"),
+            DbError::SettingMissing(key) => write!(f, "the {key} setting is missing"),
+            DbError::FolderNotFound(id) => write!(f, "there's no folder with id {id}"),
+            DbError::GeneralFolder => write!(f, "the General folder can't be deleted"),
+            DbError::SnoozeTooLong(days) => write!(f, "a task can't be snoozed for {days} days"),
+        }
+    }
+}
+
+impl std::error::Error for DbError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            DbError::Sqlite(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<Error> for DbError {
+    fn from(e: Error) -> Self {
+        DbError::Sqlite(e)
+    }
+}
+
+/// Turns an UPDATE that didn't touch any rows into `DbError::TaskNotFound`.
+///
+/// # Arguments
+///
+/// * `id: u32` - The id of the task the UPDATE was for.
+/// * `updated: usize` - How many rows it changed.
+fn check_found(id: u32, updated: usize) -> Result<(), DbError> {
+    match updated {
+        0 => Err(DbError::TaskNotFound(id)),
+        _ => Ok(()),
+    }
+}
 
 /// Establishes connection to the SQLite db.
 ///
@@ -28,7 +93,8 @@ pub fn connect_to_db() -> Connection {
     conn
 }
 
-/// Calls helper functions to init each table in the db
+/// Brings the db's schema up to date, then adds any default data that's
+/// missing.
 ///
 /// # Arguments
 ///
@@ -38,38 +104,148 @@ pub fn connect_to_db() -> Connection {
 ///
 /// May panic if there are issues executing the command. I believe this would
 /// only occur if there is an issue with `conn`.
+///
+/// # Notes
+///
+/// SQLite only enforces the schema's foreign keys on connections that ask for
+/// it, so this turns that on for `conn` too.
 pub fn init_tables(conn: &Connection) {
-    init_tasks(conn);
+    conn.pragma_update(None, "foreign_keys", true)
+        .unwrap_or_else(|err| {
+            panic!("Problem enabling foreign keys: {err}");
+        });
+    run_migrations(conn).unwrap_or_else(|err| {
+        panic!("Problem updating the database schema: {err}");
+    });
     init_folders(conn);
-    init_transactions(conn);
     init_settings(conn);
     init_statistics(conn);
 }
 
+/// One step in bringing the schema up to date.
+type Migration = fn(&Connection) -> Result<()>;
+
+/// Every migration, oldest first. A db's schema version is how many of these
+/// it has had applied, so new ones are only ever appended.
+const MIGRATIONS: &[Migration] = &[
+    // 1: The schema as it was when versioning was introduced
+    base_schema,
+    // 2: Recurring tasks can repeat every few hours
+    add_repeat_units,
+    // 3: Tasks can be snoozed
+    add_snoozed_until,
+    // 4: Every completion is logged
+    add_completions,
+    // 5: Completions from before they were logged are recovered from payouts
+    backfill_completions,
+    // 6: Templates remember what recurring tasks are counted from
+    add_template_recurrence_anchors,
+];
+
+/// Applies every migration the db hasn't had yet, in order.
+///
+/// # Arguments
+///
+/// * `conn: &Connection` - Allows us to access the SQLite db.
+///
+/// # Notes
+///
+/// - The version is kept in `PRAGMA user_version`, which starts at 0. That
+///   covers new dbs and ones from before versioning alike.
+/// - Each migration runs in its own transaction along with the version bump,
+///   so one that fails leaves the db at the version before it.
+/// - A db from a newer release is left as it is.
+pub fn run_migrations(conn: &Connection) -> Result<()> {
+    let version = schema_version(conn)?;
+
+    for (applied, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        let tx = conn.unchecked_transaction()?;
+        migration(&tx)?;
+        tx.pragma_update(None, "user_version", applied as u32 + 1)?;
+        tx.commit()?;
+    }
+
+    Ok(())
+}
+
+/// Reads how many migrations the db has had applied.
+pub fn schema_version(conn: &Connection) -> Result<u32> {
+    conn.pragma_query_value(None, "user_version", |row| row.get(0))
+}
+
 fn is_table_empty(table_name: &str, conn: &Connection) -> bool {
     let mut stmt = conn
         .prepare(&(String::from("SELECT COUNT(*) FROM ") + table_name))
         .unwrap();
     let count: i64 = stmt.query_row([], |row| row.get(0)).unwrap();
 
-    if count == 0 {
-        true
-    } else {
-        false
-    }
+    count == 0
 }
 
-/// If necessary, create the tasks table.
-///
-/// # Arguments
-///
-/// * `conn: Connection` - Allows us to access the SQLite db.
+/// Migration 1. Creates every table that doesn't exist yet.
 ///
-/// # Panics
+/// # Notes
 ///
-/// May panic if there are issues executing the command. I believe this would
-/// only occur if there is an issue with `conn`.
-fn init_tasks(conn: &Connection) {
+/// Dbs from before versioning may have some tables, with only some of their
+/// columns, so anything missing is added in place.
+fn base_schema(conn: &Connection) -> Result<()> {
+    create_tasks(conn)?;
+    create_folders(conn)?;
+    create_transactions(conn)?;
+    create_settings(conn)?;
+    create_statistics(conn)?;
+    create_audit_log(conn)?;
+    create_task_templates(conn)
+}
+
+/// Migration 2. Adds repeat_unit to tasks and templates. Every existing
+/// interval was in days, which is what the default means.
+fn add_repeat_units(conn: &Connection) -> Result<()> {
+    ensure_column(conn, "tasks", "repeat_unit", "INTEGER NOT NULL DEFAULT 0")?;
+    ensure_column(
+        conn,
+        "task_templates",
+        "repeat_unit",
+        "INTEGER NOT NULL DEFAULT 0",
+    )?;
+    Ok(())
+}
+
+/// Migration 3. Adds snoozed_until to tasks. See `snooze_task()`.
+fn add_snoozed_until(conn: &Connection) -> Result<()> {
+    ensure_column(conn, "tasks", "snoozed_until", "TEXT")?;
+    Ok(())
+}
+
+/// Migration 4. Adds the completions table. A recurring task is completed
+/// over and over, so each completion gets its own row rather than a column on
+/// the task. Nothing was logged before this, so it starts out empty.
+fn add_completions(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS completions (
+            id INTEGER PRIMARY KEY,
+            task_id INTEGER NOT NULL,
+            completed_at TEXT NOT NULL
+        )",
+        (),
+    )?;
+    Ok(())
+}
+
+/// Migration 6. Adds recurrence_anchor to templates. Tasks made from a
+/// template were always counted from completion, which is what the default
+/// means.
+fn add_template_recurrence_anchors(conn: &Connection) -> Result<()> {
+    ensure_column(
+        conn,
+        "task_templates",
+        "recurrence_anchor",
+        "INTEGER NOT NULL DEFAULT 0",
+    )?;
+    Ok(())
+}
+
+fn create_tasks(conn: &Connection) -> Result<()> {
     conn.execute(
         "CREATE TABLE IF NOT EXISTS tasks (
             id INTEGER PRIMARY KEY,
@@ -86,30 +262,88 @@ fn init_tasks(conn: &Connection) {
             repeat_interval INTEGER,
             times_selected INTEGER NOT NULL,
             times_shown INTEGER NOT NULL,
+            created_at TEXT NOT NULL,
+            in_progress INTEGER NOT NULL DEFAULT 0,
+            recurrence_anchor INTEGER NOT NULL DEFAULT 0,
+            is_triaged INTEGER NOT NULL DEFAULT 1,
+            sort_order INTEGER NOT NULL DEFAULT 0,
+            is_deferred INTEGER NOT NULL DEFAULT 0,
+            last_shown TEXT,
+            boost_factor REAL NOT NULL DEFAULT 1.0,
+            boost_until TEXT,
+            is_cycle_done INTEGER NOT NULL DEFAULT 0,
             FOREIGN KEY (parent_id) REFERENCES folders(id)
         )",
         (),
-    )
-    .unwrap_or_else(|err| {
-        panic!("Problem accessing tasks table: {err}");
-    });
+    )?;
+
+    // Added after release, so older dbs need the column (and a best guess at
+    // its value) added in place
+    if ensure_column(conn, "tasks", "created_at", "TEXT")? {
+        conn.execute("UPDATE tasks SET created_at = from_date", ())?;
+    }
+    ensure_column(conn, "tasks", "in_progress", "INTEGER NOT NULL DEFAULT 0")?;
+    ensure_column(
+        conn,
+        "tasks",
+        "recurrence_anchor",
+        "INTEGER NOT NULL DEFAULT 0",
+    )?;
+    // Only quick captured tasks start out untriaged
+    ensure_column(conn, "tasks", "is_triaged", "INTEGER NOT NULL DEFAULT 1")?;
+    // Keeps existing Iterator folders in the order they had when ids decided it
+    if ensure_column(conn, "tasks", "sort_order", "INTEGER NOT NULL DEFAULT 0")? {
+        conn.execute("UPDATE tasks SET sort_order = id", ())?;
+    }
+    ensure_column(conn, "tasks", "is_deferred", "INTEGER NOT NULL DEFAULT 0")?;
+    ensure_column(conn, "tasks", "last_shown", "TEXT")?;
+    ensure_column(conn, "tasks", "boost_factor", "REAL NOT NULL DEFAULT 1.0")?;
+    ensure_column(conn, "tasks", "boost_until", "TEXT")?;
+    // Set on an Iterator folder's tasks as they're completed, until the last
+    // one is and the folder starts over
+    ensure_column(conn, "tasks", "is_cycle_done", "INTEGER NOT NULL DEFAULT 0")?;
+
+    Ok(())
 }
 
-/// If necessary, create the folders table. Then, add a top-level folder if
-/// "folders" is empty.
+/// Adds a column to an existing table if it isn't there yet.
 ///
 /// # Arguments
 ///
-/// * `conn: Connection` - Allows us to access the SQLite db.
+/// * `conn: &Connection` - Allows us to access the SQLite db.
+/// * `table: &str` - The table to check.
+/// * `column: &str` - The name of the column.
+/// * `definition: &str` - The column's type and constraints.
 ///
-/// # Panics
+/// # Returns
 ///
-/// - May panic if there are issues executing the command. I believe this would
-/// only occur if there is an issue with `conn`.
-/// - May panic if there is an issue inserting the top-level folder.
-fn init_folders(conn: &Connection) {
-    const DEFAULT_FOLDER_NAME: &str = "General";
+/// `Ok(true)` if the column had to be added.
+///
+/// # Notes
+///
+/// SQLite can't add a NOT NULL column without a default, so `definition`
+/// should be nullable. Tables created with the column already in their schema
+/// are left untouched.
+fn ensure_column(conn: &Connection, table: &str, column: &str, definition: &str) -> Result<bool> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({table})"))?;
+    let exists = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .collect::<Result<Vec<_>>>()?
+        .iter()
+        .any(|name| name == column);
+
+    if exists {
+        return Ok(false);
+    }
+
+    conn.execute(
+        &format!("ALTER TABLE {table} ADD COLUMN {column} {definition}"),
+        (),
+    )?;
+    Ok(true)
+}
 
+fn create_folders(conn: &Connection) -> Result<()> {
     conn.execute(
         "CREATE TABLE IF NOT EXISTS folders (
             id INTEGER PRIMARY KEY,
@@ -120,10 +354,94 @@ fn init_folders(conn: &Connection) {
             FOREIGN KEY (parent_id) REFERENCES folders(id)
         )",
         (),
-    )
-    .unwrap_or_else(|err| {
-        panic!("Problem accessing folders table: {err}");
-    });
+    )?;
+    Ok(())
+}
+
+fn create_transactions(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS transactions (
+            id INTEGER PRIMARY KEY,
+            date INTEGER NOT NULL,
+            funds_added INTEGER,
+            funds_subtracted INTEGER,
+            task_id INTEGER
+        )",
+        (),
+    )?;
+
+    // Set for payouts only. Older payouts can't be traced back to a task
+    ensure_column(conn, "transactions", "task_id", "INTEGER")?;
+    Ok(())
+}
+
+fn create_settings(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS settings (
+            id INTEGER PRIMARY KEY,
+            key TEXT NOT NULL,
+            value TEXT NOT NULL
+        )",
+        (),
+    )?;
+    Ok(())
+}
+
+fn create_statistics(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS statistics (
+            id INTEGER PRIMARY KEY,
+            key TEXT NOT NULL,
+            value TEXT
+        )",
+        (),
+    )?;
+    Ok(())
+}
+
+fn create_audit_log(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS audit_log (
+            id INTEGER PRIMARY KEY,
+            task_id INTEGER NOT NULL,
+            date TEXT NOT NULL,
+            action TEXT NOT NULL,
+            fields TEXT NOT NULL
+        )",
+        (),
+    )?;
+    Ok(())
+}
+
+fn create_task_templates(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS task_templates (
+            id INTEGER PRIMARY KEY,
+            name TEXT NOT NULL,
+            parent_id INTEGER NOT NULL,
+            description TEXT,
+            bounty_modifier REAL NOT NULL,
+            lead_days INTEGER,
+            priority INTEGER NOT NULL,
+            repeat_interval INTEGER,
+            FOREIGN KEY (parent_id) REFERENCES folders(id)
+        )",
+        (),
+    )?;
+    Ok(())
+}
+
+/// Adds a top-level folder if "folders" is empty.
+///
+/// # Arguments
+///
+/// * `conn: Connection` - Allows us to access the SQLite db.
+///
+/// # Panics
+///
+/// May panic if there is an issue inserting the top-level folder.
+fn init_folders(conn: &Connection) {
+    const DEFAULT_FOLDER_NAME: &str = "General";
 
     if is_table_empty("folders", conn) {
         conn.execute(
@@ -158,33 +476,79 @@ fn init_folders(conn: &Connection) {
     }
 }
 
-/// If necessary, create the transactions table.
-///
-/// # Arguments
-///
-/// * `conn: Connection` - Allows us to access the SQLite db.
-///
-/// # Panics
-///
-/// May panic if there are issues executing the command. I believe this would
-/// only occur if there is an issue with `conn`.
-fn init_transactions(conn: &Connection) {
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS transactions (
-            id INTEGER PRIMARY KEY,
-            date INTEGER NOT NULL,
-            funds_added INTEGER,
-            funds_subtracted INTEGER
-        )",
-        (),
-    )
-    .unwrap_or_else(|err| {
-        panic!("Problem accessing transactions table: {err}");
-    });
-}
+/// Every setting and the value it starts out with.
+pub const DEFAULT_SETTINGS: &[(&str, u32)] = &[
+    ("maximum_monthly_allowance", 600),
+    ("target_monthly_allowance", 400),
+    ("description_preview_length", 60),
+    ("finance_enabled", 1),
+    // 0 for 12,345.60 or 1 for 12.345,60
+    ("number_style", 0),
+    ("currency_decimals", 2),
+    // One-off tasks older than this that were never selected are stale
+    ("stale_task_days", 60),
+    ("stale_prompt_enabled", 1),
+    // Overdue deadline tasks stop gaining weight at this multiple of what
+    // they weighed on the due date, which they reach after this many days
+    // overdue
+    ("overdue_weight_cap", 3),
+    ("overdue_critical_days", 14),
+    // Set to 1 to record every change made to a task in audit_log
+    ("audit_enabled", 0),
+    ("max_summary_length", 120),
+    // The longest lead a deadline task may have, in days
+    ("max_lead_days", 365),
+    // Set to 1 to reject shop amounts that don't start with + or -
+    ("require_transaction_sign", 0),
+    // Set to 0 to let in progress tasks compete for a ToDo spot as usual
+    ("exclude_in_progress", 1),
+    // Set to 1 to explain each bounty on the task selected screen
+    ("show_bounty_breakdown", 0),
+    // The most ToDo candidates from any one folder, or 0 for no limit
+    ("max_tasks_per_folder", 0),
+    // Set to 1 to favour priority over pay as the monthly maximum nears
+    ("budget_aware_weighting", 0),
+    // How many tasks ToDo offers, not counting tasks in progress
+    ("todo_candidate_count", 5),
+    // How many ToDo candidates to show before the user asks for more
+    ("tasks_per_view", 5),
+    // The folder Quick Add files tasks into. 1 is General
+    ("quick_capture_folder", 1),
+    // The least and most a task of each priority pays, or 0 for no limit
+    ("bounty_floor_p0", 0),
+    ("bounty_ceiling_p0", 0),
+    ("bounty_floor_p1", 0),
+    ("bounty_ceiling_p1", 0),
+    ("bounty_floor_p2", 0),
+    ("bounty_ceiling_p2", 0),
+    ("bounty_floor_p3", 0),
+    ("bounty_ceiling_p3", 0),
+    ("bounty_floor_neutral", 0),
+    ("bounty_ceiling_neutral", 0),
+    // Set to 1 to offer a timed work session when a task is selected
+    ("work_sessions_enabled", 0),
+    ("work_session_minutes", 25),
+    // Set to 1 to show a 0-100 urgency score next to ranked tasks
+    ("show_urgency_scores", 0),
+    // 0 to archive completed one-off tasks or 1 to delete them
+    ("completed_oneoff_policy", 0),
+    // Set to 0 to add tasks that share a summary with another in the same
+    // folder without being warned
+    ("warn_duplicate_summaries", 1),
+    // Set to 0 to keep offering tasks from archived folders in ToDo
+    ("hide_archived_folder_tasks", 1),
+    // 1 while recurring tasks are paused. Use the Vacation Mode screen so
+    // the start date is recorded too
+    ("vacation_mode", 0),
+    // Tasks shown in ToDo within this many minutes are left out of the
+    // next list, so it offers something different. 0 turns it off
+    ("reshuffle_suppression_minutes", 0),
+    // Tasks already shown in ToDo since the app was started have their
+    // weight cut by this percentage, so the list rotates. 0 turns it off
+    ("session_repeat_penalty", 20),
+];
 
-/// If necessary, create the settings table. Then, add the default settings if
-/// they don't already exist.
+/// Adds any default settings that don't already exist.
 ///
 /// # Arguments
 ///
@@ -192,46 +556,27 @@ fn init_transactions(conn: &Connection) {
 ///
 /// # Panics
 ///
-/// - May panic if there are issues executing the command. I believe this would
-/// only occur if there is an issue with `conn`.
-/// - May panic if there is an issue inserting the default settings.
+/// May panic if there is an issue inserting the default settings.
 ///
 /// # Note
 ///
 /// This table is acting as a simple key-value noSQL database.
 fn init_settings(conn: &Connection) {
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS settings (
-            id INTEGER PRIMARY KEY,
-            key TEXT NOT NULL,
-            value TEXT NOT NULL
-        )",
-        (),
-    )
-    .unwrap_or_else(|err| {
-        panic!("Problem accessing settings table: {err}");
-    });
-
-    if is_table_empty("settings", conn) {
-        let default_settings = vec![
-            ("maximum_monthly_allowance", 600),
-            ("target_monthly_allowance", 400),
-        ];
-
-        for (key, value) in default_settings {
-            conn.execute(
-                "INSERT INTO settings (id, key, value) VALUES (?, ?, ?)",
-                params![None::<i64>, key, value],
-            )
-            .unwrap_or_else(|err| {
-                panic!("Problem inserting default data into settings table: {err}");
-            });
-        }
+    // Each key is checked individually so that settings added in newer
+    // versions are picked up by existing databases.
+    for (key, value) in DEFAULT_SETTINGS {
+        conn.execute(
+            "INSERT INTO settings (key, value)
+            SELECT ?1, ?2 WHERE NOT EXISTS (SELECT 1 FROM settings WHERE key = ?1)",
+            params![key, value],
+        )
+        .unwrap_or_else(|err| {
+            panic!("Problem inserting default data into settings table: {err}");
+        });
     }
 }
 
-/// If necessary, create the statistics table. Then, add the default statistics
-/// if they don't already exist.
+/// Adds the default statistics if the statistics table is empty.
 ///
 /// # Arguments
 ///
@@ -239,26 +584,12 @@ fn init_settings(conn: &Connection) {
 ///
 /// # Panics
 ///
-/// - May panic if there are issues executing the command. I believe this would
-/// only occur if there is an issue with `conn`.
-/// - May panic if there is an issue inserting the default statistics.
+/// May panic if there is an issue inserting the default statistics.
 ///
 /// # Note
 ///
 /// This table is acting as a simple key-value noSQL database.
 fn init_statistics(conn: &Connection) {
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS statistics (
-            id INTEGER PRIMARY KEY,
-            key TEXT NOT NULL,
-            value TEXT
-        )",
-        (),
-    )
-    .unwrap_or_else(|err| {
-        panic!("Problem accessing folders table: {err}");
-    });
-
     if is_table_empty("statistics", conn) {
         let default_statistics = vec![
             ("funds_unlocked", Some(0)),
@@ -287,23 +618,14 @@ fn init_statistics(conn: &Connection) {
 /// * `conn: Connection` - Allows us to access the SQLite db.
 /// * `task: Task` - The task to add.
 ///
-/// # Panics
+/// # Returns
 ///
-/// May panic if there are issues executing the sql.
-pub fn add_task(conn: &Connection, task: Task) {
+/// The id of the newly added task.
+pub fn add_task(conn: &Connection, task: Task) -> Result<u32, DbError> {
     // rusqlite can't convert chrono::Duration
-    let average_duration: Option<i64> = match task.average_duration {
-        Some(d) => Some(d.num_seconds()),
-        None => None,
-    };
+    let average_duration: Option<i64> = task.average_duration.map(|d| d.num_seconds());
 
-    // rusqlite can't convert custom enums
-    let priority: u8 = match task.priority {
-        Priority::P0 => 0,
-        Priority::P1 => 1,
-        Priority::P2 => 2,
-        Priority::P3 => 3,
-    };
+    let priority = u8::from(&task.priority);
 
     conn.execute(
         "INSERT INTO tasks (
@@ -319,8 +641,14 @@ pub fn add_task(conn: &Connection, task: Task) {
             priority,
             repeat_interval,
             times_selected,
-            times_shown
-        ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            times_shown,
+            created_at,
+            in_progress,
+            recurrence_anchor,
+            boost_factor,
+            boost_until,
+            repeat_unit
+        ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
         params![
             task.parent_id,
             task.is_archived,
@@ -334,82 +662,332 @@ pub fn add_task(conn: &Connection, task: Task) {
             priority,
             task.repeat_interval,
             task.times_selected,
-            task.times_shown
+            task.times_shown,
+            task.created_at,
+            task.in_progress,
+            anchor_to_sql(&task.recurrence_anchor),
+            task.boost_factor,
+            task.boost_until,
+            unit_to_sql(&task.repeat_unit)
         ],
-    )
-    .unwrap_or_else(|err| {
-        panic!("Problem adding task to table: {err}");
-    });
+    )?;
+
+    let id = conn.last_insert_rowid() as u32;
+
+    // New tasks join the end of their folder
+    conn.execute(
+        "UPDATE tasks SET sort_order = (
+            SELECT COALESCE(MAX(sort_order), 0) + 1 FROM tasks WHERE parent_id = ?1
+        ) WHERE id = ?2",
+        params![task.parent_id, id],
+    )?;
+
+    log_audit(conn, id, "create", "all");
+    Ok(id)
 }
 
-/// Add a Folder to the folders table.
+/// Saves a template to the task_templates table.
 ///
 /// # Arguments
 ///
 /// * `conn: &Connection` - Allows us to access the SQLite db.
-/// * `folder: &Folder` - The folder to add.
+/// * `template: &TaskTemplate` - The template to save. Its id is ignored.
 ///
 /// # Returns
 ///
-/// Result indicating success or containing an error.
-pub fn add_folder(conn: &Connection, folder: &Folder) -> Result<()> {
+/// The id of the newly saved template.
+pub fn save_template(conn: &Connection, template: &TaskTemplate) -> Result<u32> {
     conn.execute(
-        "INSERT INTO folders (
-            parent_id,
+        "INSERT INTO task_templates (
             name,
-            style,
-            status
-        ) VALUES (?, ?, ?, ?)",
+            parent_id,
+            description,
+            bounty_modifier,
+            lead_days,
+            priority,
+            repeat_interval,
+            repeat_unit,
+            recurrence_anchor
+        ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
         params![
-            folder.parent_id,
-            folder.name,
-            folder.style.to_string(),
-            folder.status
+            template.name,
+            template.parent_id,
+            template.description,
+            template.bounty_modifier,
+            template.lead_days,
+            u8::from(&template.priority),
+            template.repeat_interval,
+            unit_to_sql(&template.repeat_unit),
+            anchor_to_sql(&template.recurrence_anchor)
+        ],
+    )?;
+
+    Ok(conn.last_insert_rowid() as u32)
+}
+
+/// Reads every saved template, ordered by name.
+pub fn read_templates(conn: &Connection) -> Result<Vec<TaskTemplate>> {
+    let mut stmt = conn.prepare(
+        "SELECT
+            id,
+            name,
+            parent_id,
+            description,
+            bounty_modifier,
+            lead_days,
+            priority,
+            repeat_interval,
+            repeat_unit,
+            recurrence_anchor
+        FROM task_templates ORDER BY name, id",
+    )?;
+
+    let templates = stmt
+        .query_map([], |row| {
+            let (_, priority) = convert_fields_from_sql(None, row.get(6)?);
+
+            Ok(TaskTemplate {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                parent_id: row.get(2)?,
+                description: row.get(3)?,
+                bounty_modifier: row.get(4)?,
+                lead_days: row.get(5)?,
+                priority,
+                repeat_interval: row.get(7)?,
+                repeat_unit: unit_from_sql(row.get(8)?),
+                recurrence_anchor: anchor_from_sql(row.get(9)?),
+            })
+        })?
+        .collect();
+
+    templates
+}
+
+/// Adds a new task based on a saved template.
+///
+/// # Arguments
+///
+/// * `conn: &Connection` - Allows us to access the SQLite db.
+/// * `template_id: u32` - The id of the template to use.
+/// * `summary: String` - The new task's summary.
+/// * `due_date: Option<DateTime<Utc>>` - The new task's due date, if the
+///   template is for deadline tasks.
+///
+/// # Returns
+///
+/// The id of the new task, or `Error::QueryReturnedNoRows` if there's no
+/// template with that id.
+pub fn instantiate_template(
+    conn: &Connection,
+    template_id: u32,
+    summary: String,
+    due_date: Option<DateTime<Utc>>,
+) -> Result<u32, DbError> {
+    let template = read_templates(conn)?
+        .into_iter()
+        .find(|template| template.id == template_id)
+        .ok_or(Error::QueryReturnedNoRows)?;
+
+    add_task(conn, template.instantiate(summary, due_date))
+}
+
+/// One change made to a task, as recorded in the audit_log table. Nothing in
+/// the app shows the log yet, so it's only read back by tests.
+#[cfg(test)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct AuditEntry {
+    pub task_id: u32,
+    pub date: DateTime<Utc>,
+    /// What happened to the task, such as "create", "edit" or "archive".
+    pub action: String,
+    /// The comma separated names of the fields that changed.
+    pub fields: String,
+}
+
+/// Records a change to a task in the audit_log table, if the
+/// `audit_enabled` setting is on.
+///
+/// # Arguments
+///
+/// * `conn: &Connection` - Allows us to access the SQLite db.
+/// * `task_id: u32` - The id of the task that changed.
+/// * `action: &str` - What happened to the task.
+/// * `fields: &str` - The comma separated names of the fields that changed.
+///
+/// # Notes
+///
+/// The audit log is only a debugging aid, so failing to write to it is
+/// reported rather than stopping the change itself.
+fn log_audit(conn: &Connection, task_id: u32, action: &str, fields: &str) {
+    if !matches!(read_setting_u32(conn, "audit_enabled"), Ok(Some(1))) {
+        return;
+    }
+
+    conn.execute(
+        "INSERT INTO audit_log (task_id, date, action, fields) VALUES (?, ?, ?, ?)",
+        params![task_id, Utc::now(), action, fields],
+    )
+    .map(|_| ())
+    .unwrap_or_else(|err| eprintln!("Problem writing to audit log: {err}"));
+}
+
+/// Reads the recorded history of a task, oldest change first.
+///
+/// # Arguments
+///
+/// * `conn: &Connection` - Allows us to access the SQLite db.
+/// * `task_id: u32` - The id of the task to read the history of.
+#[cfg(test)]
+pub fn read_audit(conn: &Connection, task_id: u32) -> Result<Vec<AuditEntry>> {
+    let mut stmt = conn.prepare(
+        "SELECT task_id, date, action, fields FROM audit_log WHERE task_id = ? ORDER BY id",
+    )?;
+
+    let entries = stmt
+        .query_map([task_id], |row| {
+            Ok(AuditEntry {
+                task_id: row.get(0)?,
+                date: row.get(1)?,
+                action: row.get(2)?,
+                fields: row.get(3)?,
+            })
+        })?
+        .collect();
+
+    entries
+}
+
+/// Add a Folder to the folders table.
+///
+/// # Arguments
+///
+/// * `conn: &Connection` - Allows us to access the SQLite db.
+/// * `folder: &Folder` - The folder to add.
+///
+/// # Returns
+///
+/// Result indicating success or containing an error.
+pub fn add_folder(conn: &Connection, folder: &Folder) -> Result<()> {
+    conn.execute(
+        "INSERT INTO folders (
+            parent_id,
+            name,
+            style,
+            status
+        ) VALUES (?, ?, ?, ?)",
+        params![
+            folder.parent_id,
+            folder.name,
+            folder.style.to_string(),
+            folder.status
         ],
     )?;
 
     Ok(())
 }
 
-// Function to recursively fetch and print the nested rows
+/// Reads every folder, in the order they were added.
+pub fn read_folders(conn: &Connection) -> Result<Vec<Folder>> {
+    let mut stmt =
+        conn.prepare("SELECT id, parent_id, name, style, status FROM folders ORDER BY id")?;
+    let folders = stmt
+        .query_map([], |row| {
+            let style: String = row.get(3)?;
+            Ok(Folder {
+                id: row.get(0)?,
+                parent_id: row.get(1)?,
+                name: row.get(2)?,
+                style: style.parse().unwrap_or(Style::Directory),
+                status: row.get(4)?,
+            })
+        })?
+        .collect();
+
+    folders
+}
+
+/// Reads every folder with its full path, e.g. "General::Home::Chores".
+///
+/// # Arguments
+///
+/// * `conn: &Connection` - Allows us to access the SQLite db.
+///
+/// # Returns
+///
+/// A `Vec<(u32, String)>` of folder ids and paths, sorted by path and then by
+/// id. Prefer `read_all_folders()` when only looking up paths by id.
+pub fn read_all_folders_sorted(conn: &Connection) -> Result<Vec<(u32, String)>, Error> {
+    let mut folders: Vec<(u32, String)> = read_all_folders(conn, None, "".to_string())?
+        .into_iter()
+        .collect();
+
+    folders.sort_by(|a, b| a.1.cmp(&b.1).then(a.0.cmp(&b.0)));
+
+    Ok(folders)
+}
+
+/// Reads the folders below `parent_id` with their full paths.
+///
+/// # Arguments
+///
+/// * `conn: &Connection` - Allows us to access the SQLite db.
+/// * `parent_id: Option<u32>` - Where to start. None starts from the top
+///   level folders.
+/// * `prefix: String` - The path of `parent_id`, prepended to every path.
+///
+/// # Notes
+///
+/// Only paths are returned. Use `read_folders()` when the style or status of
+/// each folder is needed too.
+///
+/// All the folders are read in one query, then the tree is walked in memory
+/// one level at a time, so however deep it goes there's no recursion.
+///
+/// Nothing stops the folders table from containing a cycle, so each folder is
+/// only visited once. If one turns up again, a warning is printed and that
+/// branch is cut off rather than looping forever.
 pub fn read_all_folders(
     conn: &Connection,
     parent_id: Option<u32>,
     prefix: String,
 ) -> Result<HashMap<u32, String>, Error> {
-    let mut stmt = conn.prepare("SELECT id, parent_id, name FROM folders WHERE parent_id IS ?")?;
-    let item_iter = stmt.query_map(params![parent_id], |row| {
-        Ok(Folder {
-            id: row.get(0)?,
-            parent_id: row.get(1)?,
-            name: row.get(2)?,
-            style: Style::Directory, // TODO: set
-            status: None,            // TODO: set
-        })
-    })?;
+    let mut children: HashMap<Option<u32>, Vec<Folder>> = HashMap::new();
+    for folder in read_folders(conn)? {
+        children.entry(folder.parent_id).or_default().push(folder);
+    }
+
+    let mut visited = HashSet::new();
+    visited.extend(parent_id);
 
     let mut folders_hm: HashMap<u32, String> = HashMap::new();
+    let mut queue = VecDeque::from([(parent_id, prefix)]);
 
-    for item in item_iter {
-        let item = item?;
-        let new_prefix = if prefix.is_empty() {
-            item.name.clone()
-        } else {
-            format!("{}::{}", prefix, item.name)
-        };
+    while let Some((parent_id, prefix)) = queue.pop_front() {
+        for folder in children.remove(&parent_id).unwrap_or_default() {
+            if !visited.insert(folder.id) {
+                eprintln!(
+                    "Folder {} ({}) is its own ancestor, so its contents were skipped. Move it to fix the folder tree.",
+                    folder.id, folder.name
+                );
+                continue;
+            }
 
-        // println!("({}, {})", item.id, new_prefix);
-        folders_hm.insert(item.id, new_prefix.clone());
+            let path = if prefix.is_empty() {
+                folder.name
+            } else {
+                format!("{}::{}", prefix, folder.name)
+            };
 
-        // Recursively fetch children
-        // read_all_folders(conn, Some(item.id), new_prefix)?;
-        folders_hm.extend(read_all_folders(conn, Some(item.id), new_prefix)?);
+            folders_hm.insert(folder.id, path.clone());
+            queue.push_back((Some(folder.id), path));
+        }
     }
 
     Ok(folders_hm)
 }
 
-pub fn add_transaction(conn: &Connection, price: f64) {
+pub fn add_transaction(conn: &Connection, price: f64) -> Result<(), DbError> {
     if price >= 0.0 {
         conn.execute(
             "INSERT INTO transactions (
@@ -417,186 +995,513 @@ pub fn add_transaction(conn: &Connection, price: f64) {
                 funds_added
             ) VALUES (?, ?)",
             params![<Utc>::now(), price],
-        )
-        .unwrap_or_else(|err| {
-            panic!("Problem adding task to table: {err}");
-        });
+        )?;
     } else {
         conn.execute(
             "INSERT INTO transactions (
                 date,
                 funds_subtracted
             ) VALUES (?, ?)",
-            params![<Utc>::now(), price * -1.0],
-        )
-        .unwrap_or_else(|err| {
-            panic!("Problem adding task to table: {err}");
-        });
+            params![<Utc>::now(), -price],
+        )?;
     }
+    Ok(())
 }
 
-/// Retrieves the IDs of all descendants of the given parent_id, including those at deeper nesting levels.
+/// Counts the tasks completed on each of the last `days` days.
 ///
 /// # Arguments
-/// * `conn: &Connection` - A reference to the SQLite connection.
-/// * `parent_id: u32` - The ID of the parent for which descendant IDs are sought.
+///
+/// * `conn: &Connection` - Allows us to access the SQLite db.
+/// * `days: u32` - How many days to report on, including today.
 ///
 /// # Returns
-/// * A `Result` containing a vector of descendant IDs or an error if the query fails.
-pub fn get_descendant_ids(conn: &Connection, parent_id: u32) -> Result<Vec<u32>> {
-    // Define a recursive Common Table Expression (CTE) to find all descendants
-    let sql = "
-    WITH RECURSIVE descendants(id) AS (
-        SELECT id FROM folders WHERE parent_id = ?
-        UNION ALL
-        SELECT folders.id FROM folders, descendants WHERE folders.parent_id = descendants.id
-    )
-    SELECT id FROM descendants;
-    ";
-
-    // Prepare and execute the query, collecting the results
-    let mut stmt = conn.prepare(sql)?;
-    let descendant_ids = stmt
-        .query_map(params![parent_id], |row| row.get(0))?
-        .collect::<Result<Vec<u32>>>()?;
+///
+/// One `(date, count)` pair for every day, oldest first. Days without any
+/// completions are included with a count of 0.
+///
+/// # Notes
+///
+/// - Every completion is logged, whether or not finance is enabled, so
+///   funds added in the Shop don't count. See `record_completion()`.
+/// - Days are bucketed in the local timezone, so a task completed late in the
+///   evening counts towards that day rather than the next one in UTC.
+pub fn completions_by_day(conn: &Connection, days: u32) -> Result<Vec<(NaiveDate, u32)>, DbError> {
+    let dates: Vec<DateTime<Utc>> = conn
+        .prepare("SELECT completed_at FROM completions")?
+        .query_map([], |row| row.get(0))?
+        .collect::<Result<Vec<DateTime<Utc>>>>()?;
 
-    Ok(descendant_ids)
+    Ok(bucket_by_day(
+        &dates,
+        &Local,
+        Local::now().date_naive(),
+        days,
+    ))
 }
 
-// fn main() -> Result<()> {
-//     // Example connection to a SQLite database
-//     let conn = Connection::open("my_database.db")?;
-
-//     // Assuming you want to find all descendants of the parent with ID 2
-//     let parent_id = 2;
-
-//     match get_descendant_ids(&conn, parent_id) {
-//         Ok(descendant_ids) => {
-//             println!(
-//                 "Descendants of parent ID {}: {:?}",
-//                 parent_id, descendant_ids
-//             );
-//         }
-//         Err(e) => {
-//             println!("Failed to get descendant IDs: {}", e);
-//         }
-//     }
+/// Migration 5. Streaks and the heatmap are read from the completions table,
+/// so the payouts recorded before it existed are copied in as completions.
+/// Payouts later than the first logged completion were logged already.
+fn backfill_completions(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "INSERT INTO completions (task_id, completed_at)
+            SELECT task_id, date FROM transactions
+            WHERE funds_added > 0 AND task_id IS NOT NULL
+                AND date < COALESCE((SELECT MIN(completed_at) FROM completions), date || '~')
+            ORDER BY date, id",
+        (),
+    )?;
+    Ok(())
+}
 
-//     Ok(())
-// }
+/// Logs that a task was completed. See `complete_task()`.
+fn record_completion(conn: &Connection, task_id: u32, now: DateTime<Utc>) -> Result<()> {
+    conn.execute(
+        "INSERT INTO completions (task_id, completed_at) VALUES (?, ?)",
+        params![task_id, now],
+    )?;
+    Ok(())
+}
 
-/// Reads all active tasks from the db into memory.
+/// Reads the tasks completed from `start` up to, but not including, `end`.
 ///
 /// # Arguments
 ///
-/// * `conn: Connection` - Allows us to access the SQLite db.
+/// * `conn: &Connection` - Allows us to access the SQLite db.
+/// * `start: DateTime<Utc>` - The earliest completion to include.
+/// * `end: DateTime<Utc>` - Completions from here on are left out.
 ///
 /// # Returns
 ///
-/// A `Vec<Task>` of all tasks that are not archived and haven't been completed
-/// within their repeat_interval.
-pub fn read_active_tasks(conn: &Connection) -> Vec<Task> {
-    // Prepare sqlite statement
-    let stmt = conn
-        .prepare(
-            "SELECT
-            id, 
-            parent_id,
-            is_archived,
-            summary, 
-            description,
-            average_duration,
-            bounty_modifier, 
-            due_date, 
-            from_date, 
-            lead_days, 
-            priority, 
-            repeat_interval, 
-            times_selected, 
-            times_shown
-        FROM tasks WHERE is_archived = 0",
-        )
-        .unwrap_or_else(|err| {
-            panic!("Problem preparing SELECT statement: {err}");
-        });
+/// Each completion's task and when it was completed, oldest first. A
+/// recurring task appears once for every time it was completed.
+///
+/// # Notes
+///
+/// - The tasks are read as they are now, so a recurring task has its latest
+///   from_date rather than the one it had when it was completed.
+/// - Completions of tasks that have since been deleted are left out, which
+///   includes every one-off while completed_oneoff_policy is 1.
+pub fn read_completions_between(
+    conn: &Connection,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Result<Vec<(Task, DateTime<Utc>)>, DbError> {
+    let completions = conn
+        .prepare("SELECT task_id, completed_at FROM completions ORDER BY completed_at, id")?
+        .query_map([], |row| {
+            Ok((row.get::<_, u32>(0)?, row.get::<_, DateTime<Utc>>(1)?))
+        })?
+        .collect::<Result<Vec<_>>>()?;
 
-    return tasks_from_stmt(stmt, false);
+    let tasks: HashMap<u32, Task> = read_all_tasks(conn)?
+        .into_iter()
+        .map(|task| (task.id, task))
+        .collect();
+
+    Ok(completions
+        .into_iter()
+        .filter(|(_, completed_at)| *completed_at >= start && *completed_at < end)
+        .filter_map(|(task_id, completed_at)| Some((tasks.get(&task_id)?.clone(), completed_at)))
+        .collect())
 }
 
-/// Reads all tasks from the db into memory.
+/// Adds up the funds added since the start of the current month.
 ///
 /// # Arguments
 ///
-/// * `conn: Connection` - Allows us to access the SQLite db.
+/// * `conn: &Connection` - Allows us to access the SQLite db.
 ///
-/// # Returns
+/// # Notes
 ///
-/// A `Vec<Task>` of all tasks.
-pub fn read_all_tasks(conn: &Connection) -> Vec<Task> {
-    // Prepare sqlite statement
-    let stmt = conn
-        .prepare(
-            "SELECT
-            id, 
-            parent_id,
-            is_archived,
-            summary, 
-            description, 
-            average_duration,
-            bounty_modifier,
-            due_date, 
-            from_date, 
-            lead_days, 
-            priority, 
-            repeat_interval, 
-            times_selected, 
-            times_shown
-        FROM tasks",
-        )
-        .unwrap_or_else(|err| {
-            panic!("Problem preparing SELECT statement: {err}");
-        });
+/// - Months start at midnight UTC on the 1st.
+/// - Only funds_added is counted, so spending doesn't free up more of the
+///   month's allowance. A reversed payout is negative and does.
+pub fn funds_added_this_month(conn: &Connection) -> Result<f64, DbError> {
+    funds_added_this_month_at(conn, Utc::now())
+}
+
+/// Same as `funds_added_this_month()`, but as if the current time were `now`.
+pub fn funds_added_this_month_at(conn: &Connection, now: DateTime<Utc>) -> Result<f64, DbError> {
+    let today = now.date_naive();
+    let month_start = today
+        .with_day(1)
+        .unwrap_or(today)
+        .and_time(Default::default());
+
+    let rows = conn
+        .prepare("SELECT date, funds_added FROM transactions WHERE funds_added IS NOT NULL")?
+        .query_map([], |row| {
+            Ok((row.get::<_, DateTime<Utc>>(0)?, row.get::<_, f64>(1)?))
+        })?
+        .collect::<Result<Vec<_>>>()?;
 
-    return tasks_from_stmt(stmt, true);
+    Ok(rows
+        .iter()
+        .filter(|(date, _)| date.naive_utc() >= month_start)
+        .map(|(_, added)| added)
+        .sum())
 }
 
-// /// Reads all archived tasks from the db into memory.
-// ///
-// /// # Arguments
-// ///
-// /// * `conn: Connection` - Allows us to access the SQLite db.
-// ///
-// /// # Returns
-// ///
-// /// A `Vec<Task>` of all tasks that are archived.
-// pub fn read_archived_tasks(conn: &Connection) -> Vec<Task> {
-//     // Prepare sqlite statement
-//     let stmt = conn
-//         .prepare(
-//             "SELECT
-//             id,
-//             parent_id,
-//             is_archived,
-//             summary,
-//             description,
-//             average_duration,
-//             bounty_modifier,
-//             due_date,
-//             from_date,
-//             lead_days,
-//             priority,
-//             repeat_interval,
-//             times_selected,
-//             times_shown
-//         FROM tasks WHERE is_archived = 1",
-//         )
-//         .unwrap_or_else(|err| {
-//             panic!("Problem preparing SELECT statement: {err}");
-//         });
+/// Groups timestamps into per-day counts in the given timezone.
+///
+/// See `completions_by_day()`. Split out so the bucketing can be tested
+/// without depending on the machine's timezone or the current date.
+fn bucket_by_day<Tz: TimeZone>(
+    dates: &[DateTime<Utc>],
+    tz: &Tz,
+    today: NaiveDate,
+    days: u32,
+) -> Vec<(NaiveDate, u32)> {
+    let mut counts: HashMap<NaiveDate, u32> = HashMap::new();
+    for date in dates {
+        *counts
+            .entry(date.with_timezone(tz).date_naive())
+            .or_insert(0) += 1;
+    }
+
+    (0..days)
+        .rev()
+        .map(|offset| {
+            let day = today - Duration::days(offset as i64);
+            (day, counts.get(&day).copied().unwrap_or(0))
+        })
+        .collect()
+}
+
+/// Sets a folder's status, e.g. `STATUS_ARCHIVED`.
+///
+/// # Arguments
+///
+/// * `conn: &Connection` - Allows us to access the SQLite db.
+/// * `id: u32` - The id for the affected folder.
+/// * `status: Option<u32>` - The new status, or None to clear it.
+pub fn set_folder_status(conn: &Connection, id: u32, status: Option<u32>) -> Result<()> {
+    conn.execute(
+        "UPDATE folders SET status=? WHERE id=?",
+        params![status, id],
+    )?;
+    Ok(())
+}
+
+/// Renames a folder. Paths are built from names as they're read, so the
+/// folders below it pick up the new name too.
+///
+/// # Arguments
+///
+/// * `conn: &Connection` - Allows us to access the SQLite db.
+/// * `id: u32` - The id for the affected folder.
+/// * `new_name: &str` - What to call it from now on.
+pub fn rename_folder(conn: &Connection, id: u32, new_name: &str) -> Result<()> {
+    conn.execute(
+        "UPDATE folders SET name=? WHERE id=?",
+        params![new_name, id],
+    )?;
+    Ok(())
+}
+
+/// The folder `init_folders()` creates first.
+const GENERAL_FOLDER_ID: u32 = 1;
+
+/// What `delete_folder()` does with everything inside the folder.
+#[derive(Clone, Debug, PartialEq)]
+pub enum DeleteMode {
+    /// Its subfolders and tasks move up into its parent.
+    Reparent,
+    /// Its subfolders are deleted too, and every task below it is archived.
+    Cascade,
+}
+
+/// Deletes a folder.
+///
+/// # Arguments
+///
+/// * `conn: &Connection` - Allows us to access the SQLite db.
+/// * `id: u32` - The id for the affected folder.
+/// * `mode: DeleteMode` - What happens to its contents.
+///
+/// # Notes
+///
+/// - Tasks can't be left without a folder, so they move to the deleted
+///   folder's parent, or to General if it was a top level folder. With
+///   `DeleteMode::Cascade` that's where the archived tasks are restored to.
+/// - Templates filed in a deleted folder move the same way as its tasks, or
+///   are deleted along with it when cascading.
+/// - Everything happens in one transaction, so a failure part way leaves the
+///   folders and tasks as they were.
+pub fn delete_folder(conn: &Connection, id: u32, mode: DeleteMode) -> Result<(), DbError> {
+    let tx = conn.unchecked_transaction()?;
+
+    let parent_id: Option<u32> = tx
+        .query_row("SELECT parent_id FROM folders WHERE id=?", [id], |row| {
+            row.get(0)
+        })
+        .optional()?
+        .ok_or(DbError::FolderNotFound(id))?;
+    let new_home = parent_id.unwrap_or(GENERAL_FOLDER_ID);
+
+    let mut doomed = vec![id];
+    if mode == DeleteMode::Cascade {
+        doomed.extend(get_descendant_ids(&tx, id)?);
+    }
+    if doomed.contains(&GENERAL_FOLDER_ID) {
+        return Err(DbError::GeneralFolder);
+    }
+
+    for folder_id in &doomed {
+        if mode == DeleteMode::Cascade {
+            let mut stmt =
+                tx.prepare("SELECT id FROM tasks WHERE parent_id=? AND is_archived=0")?;
+            let task_ids = stmt
+                .query_map([folder_id], |row| row.get(0))?
+                .collect::<Result<Vec<u32>>>()?;
+            for task_id in task_ids {
+                tx.execute("UPDATE tasks SET is_archived=1 WHERE id=?", [task_id])?;
+                log_audit(&tx, task_id, "archive", "is_archived");
+            }
+            tx.execute("DELETE FROM task_templates WHERE parent_id=?", [folder_id])?;
+        } else {
+            tx.execute(
+                "UPDATE task_templates SET parent_id=? WHERE parent_id=?",
+                params![new_home, folder_id],
+            )?;
+        }
+
+        tx.execute(
+            "UPDATE tasks SET parent_id=? WHERE parent_id=?",
+            params![new_home, folder_id],
+        )?;
+        tx.execute(
+            "UPDATE folders SET parent_id=? WHERE parent_id=?",
+            params![parent_id, folder_id],
+        )?;
+    }
+
+    // Subfolders were moved out of the way above, so order doesn't matter
+    for folder_id in &doomed {
+        tx.execute("DELETE FROM folders WHERE id=?", [folder_id])?;
+    }
+
+    tx.commit()?;
+    Ok(())
+}
+
+/// Reads a folder's status.
+///
+/// # Returns
+///
+/// None if the folder has no status, or doesn't exist.
+pub fn read_folder_status(conn: &Connection, id: u32) -> Result<Option<u32>> {
+    Ok(conn
+        .query_row("SELECT status FROM folders WHERE id=?", [id], |row| {
+            row.get(0)
+        })
+        .optional()?
+        .flatten())
+}
+
+/// Reads the style of every folder.
+///
+/// # Notes
+///
+/// A style that can't be parsed is read as `Style::Directory`, which is how
+/// every folder behaved before styles took effect.
+pub fn read_folder_styles(conn: &Connection) -> Result<HashMap<u32, Style>> {
+    let mut stmt = conn.prepare("SELECT id, style FROM folders")?;
+    let styles = stmt
+        .query_map([], |row| {
+            let style: String = row.get(1)?;
+            Ok((row.get(0)?, style.parse().unwrap_or(Style::Directory)))
+        })?
+        .collect::<Result<HashMap<u32, Style>>>()?;
+
+    Ok(styles)
+}
+
+/// Finds every folder that counts as archived: those with `STATUS_ARCHIVED`
+/// and everything below them.
+///
+/// # Notes
+///
+/// Uses UNION for the same reason as `get_descendant_ids()`, so a cycle in
+/// the folders table can't make it run forever.
+pub fn archived_folder_ids(conn: &Connection) -> Result<HashSet<u32>> {
+    let sql = "
+    WITH RECURSIVE archived(id) AS (
+        SELECT id FROM folders WHERE status = ?1
+        UNION
+        SELECT folders.id FROM folders, archived WHERE folders.parent_id = archived.id
+    )
+    SELECT id FROM archived;
+    ";
+
+    let mut stmt = conn.prepare(sql)?;
+    let ids = stmt
+        .query_map(params![STATUS_ARCHIVED], |row| row.get(0))?
+        .collect::<Result<HashSet<u32>>>()?;
+
+    Ok(ids)
+}
+
+/// Retrieves the IDs of all descendants of the given parent_id, including those at deeper nesting levels.
+///
+/// # Arguments
+/// * `conn: &Connection` - A reference to the SQLite connection.
+/// * `parent_id: u32` - The ID of the parent for which descendant IDs are sought.
+///
+/// # Returns
+/// * A `Result` containing a vector of descendant IDs or an error if the query fails.
+///
+/// # Notes
+/// UNION (rather than UNION ALL) stops the query once no new ids turn up, so a
+/// cycle in the folders table can't make it run forever. A parent that's in a
+/// cycle is left out of its own descendants.
+pub fn get_descendant_ids(conn: &Connection, parent_id: u32) -> Result<Vec<u32>> {
+    // Define a recursive Common Table Expression (CTE) to find all descendants
+    let sql = "
+    WITH RECURSIVE descendants(id) AS (
+        SELECT id FROM folders WHERE parent_id = ?1
+        UNION
+        SELECT folders.id FROM folders, descendants WHERE folders.parent_id = descendants.id
+    )
+    SELECT id FROM descendants WHERE id != ?1;
+    ";
+
+    // Prepare and execute the query, collecting the results
+    let mut stmt = conn.prepare(sql)?;
+    let descendant_ids = stmt
+        .query_map(params![parent_id], |row| row.get(0))?
+        .collect::<Result<Vec<u32>>>()?;
+
+    Ok(descendant_ids)
+}
+
+// fn main() -> Result<()> {
+//     // Example connection to a SQLite database
+//     let conn = Connection::open("my_database.db")?;
+
+//     // Assuming you want to find all descendants of the parent with ID 2
+//     let parent_id = 2;
+
+//     match get_descendant_ids(&conn, parent_id) {
+//         Ok(descendant_ids) => {
+//             println!(
+//                 "Descendants of parent ID {}: {:?}",
+//                 parent_id, descendant_ids
+//             );
+//         }
+//         Err(e) => {
+//             println!("Failed to get descendant IDs: {}", e);
+//         }
+//     }
 
-//     return tasks_from_stmt(stmt, true);
+//     Ok(())
 // }
 
+/// Reads all active tasks from the db into memory.
+///
+/// # Arguments
+///
+/// * `conn: Connection` - Allows us to access the SQLite db.
+///
+/// # Returns
+///
+/// A `Vec<Task>` of all tasks that are not archived and haven't been completed
+/// within their repeat_interval.
+pub fn read_active_tasks(conn: &Connection) -> Result<Vec<Task>, DbError> {
+    // Prepare sqlite statement
+    let stmt = conn.prepare(
+        "SELECT
+            id, 
+            parent_id,
+            is_archived,
+            summary, 
+            description,
+            average_duration,
+            bounty_modifier, 
+            due_date, 
+            from_date, 
+            lead_days, 
+            priority, 
+            repeat_interval, 
+            times_selected, 
+            times_shown,
+            created_at,
+            in_progress,
+            recurrence_anchor,
+            boost_factor,
+            boost_until,
+            repeat_unit
+        FROM tasks WHERE is_archived = 0 AND is_deferred = 0",
+    )?;
+
+    tasks_from_stmt(stmt, [], false)
+}
+
+/// Reads all tasks from the db into memory.
+///
+/// # Arguments
+///
+/// * `conn: Connection` - Allows us to access the SQLite db.
+///
+/// # Returns
+///
+/// A `Vec<Task>` of all tasks.
+pub fn read_all_tasks(conn: &Connection) -> Result<Vec<Task>, DbError> {
+    // Prepare sqlite statement
+    let stmt = conn.prepare(
+        "SELECT
+            id, 
+            parent_id,
+            is_archived,
+            summary, 
+            description, 
+            average_duration,
+            bounty_modifier,
+            due_date, 
+            from_date, 
+            lead_days, 
+            priority, 
+            repeat_interval, 
+            times_selected, 
+            times_shown,
+            created_at,
+            in_progress,
+            recurrence_anchor,
+            boost_factor,
+            boost_until,
+            repeat_unit
+        FROM tasks",
+    )?;
+
+    tasks_from_stmt(stmt, [], true)
+}
+
+/// Reads all archived tasks from the db into memory, most recently started
+/// first.
+///
+/// # Arguments
+///
+/// * `conn: &Connection` - Allows us to access the SQLite db.
+///
+/// # Returns
+///
+/// A `Vec<Task>` of all tasks that are archived.
+pub fn read_archived_tasks(conn: &Connection) -> Result<Vec<Task>, DbError> {
+    let stmt = conn.prepare(
+        "SELECT
+            id, parent_id, is_archived, summary, description, average_duration,
+            bounty_modifier, due_date, from_date, lead_days, priority,
+            repeat_interval, times_selected, times_shown, created_at, in_progress,
+            recurrence_anchor, boost_factor, boost_until, repeat_unit
+        FROM tasks WHERE is_archived = 1
+        ORDER BY from_date DESC, id DESC",
+    )?;
+
+    tasks_from_stmt(stmt, [], true)
+}
+
 // pub fn read_active_tasks(conn: &Connection) -> Vec<Task> {
 //     // Prepare sqlite statement
 //     let mut stmt = conn
@@ -648,7 +1553,7 @@ pub fn read_all_tasks(conn: &Connection) -> Vec<Task> {
 //                 due_date: row.get(4)?,
 //                 from_date: row.get(5)?,
 //                 lead_days: row.get(6)?,
-//                 priority: priority,
+//                 priority,
 //                 repeat_interval: row.get(8)?,
 //                 times_selected: row.get(9)?,
 //                 times_shown: row.get(10)?,
@@ -677,68 +1582,325 @@ pub fn read_all_tasks(conn: &Connection) -> Vec<Task> {
 //     query_result_as_vec
 // }
 
-/// Fetches Tasks from the database where `parent_id` matches any u32 in the given vector.
+/// Reads the one-off tasks that have been sitting in the backlog for a long
+/// time without ever being picked.
 ///
 /// # Arguments
 ///
-/// * `conn: &Connection` - A reference to the SQLite connection.
-/// * `parent_ids: Vec<u32>` - A vector of `u32` representing parent IDs to query for.
+/// * `conn: &Connection` - Allows us to access the SQLite db.
+/// * `threshold_days: u32` - Tasks older than this many days are stale.
 ///
 /// # Returns
 ///
-/// A result containing a vector of tuples, each representing a row from the database,
-/// or an error if the query fails.
+/// A `Vec<Task>` of active tasks with no repeat_interval or due_date, a
+/// created_at more than `threshold_days` ago, and a times_selected of 0.
+pub fn read_stale_tasks(conn: &Connection, threshold_days: u32) -> Result<Vec<Task>, DbError> {
+    let cutoff = Utc::now() - Duration::days(threshold_days as i64);
+
+    Ok(read_active_tasks(conn)?
+        .into_iter()
+        .filter(|task| {
+            task.repeat_interval.is_none()
+                && task.due_date.is_none()
+                && task.times_selected == 0
+                && task.created_at < cutoff
+        })
+        .collect())
+}
+
+/// Finds the unarchived tasks whose summary or description contains a
+/// keyword, ignoring case.
 ///
-/// # Examples
+/// # Arguments
 ///
-/// ```text
-/// let mut conn = Connection::open("my_database.db").unwrap();
-/// let parent_ids = vec![1, 2, 3];
-/// let rows = fetch_by_parent_ids(&mut conn, &parent_ids).unwrap();
-/// for row in rows {
-///     println!("{:?}", row);
-/// }
-/// ```
-pub fn fetch_tasks_by_parent_ids(conn: &Connection, parent_ids: Vec<u32>) -> Result<Vec<Task>> {
-    // Prepare the SQL query using parameterized placeholders.
-    // The number of placeholders must match the number of parent_ids.
-    // Produces an output like `SELECT * FROM my_table WHERE parent_id IN (?, ?, ?).`
-    let query = format!(
-        "SELECT * FROM tasks WHERE parent_id IN ({})",
-        parent_ids
-            .iter()
-            .map(|_| "?")
-            .collect::<Vec<_>>()
-            .join(", ")
-    );
+/// * `conn: &Connection` - Allows us to access the SQLite db.
+/// * `keyword: &str` - The text to look for.
+///
+/// # Returns
+///
+/// A `Vec<Task>` of matches, including recurring tasks that aren't eligible
+/// yet. Archived tasks are never included.
+pub fn search_tasks(conn: &Connection, keyword: &str) -> Result<Vec<Task>, DbError> {
+    let keyword = keyword.to_lowercase();
 
-    // Prepare the statement.
-    let mut stmt = conn.prepare(&query)?;
+    Ok(read_all_tasks(conn)?
+        .into_iter()
+        .filter(|task| {
+            !task.is_archived
+                && (task.summary.to_lowercase().contains(&keyword)
+                    || task
+                        .description
+                        .as_ref()
+                        .is_some_and(|d| d.to_lowercase().contains(&keyword)))
+        })
+        .collect())
+}
 
-    // Convert `parent_ids` to a dynamic type that `rusqlite` can use for the query.
-    // We use `params_from_iter` to convert the vector into a suitable parameter list.
-    let params = params_from_iter(parent_ids.iter());
+/// Finds the tasks that are still snoozed at `now`. See `snooze_task()`.
+///
+/// # Arguments
+///
+/// * `conn: &Connection` - Allows us to access the SQLite db.
+/// * `now: DateTime<Utc>` - Snoozes that end at or before this are over.
+pub fn snoozed_ids(conn: &Connection, now: DateTime<Utc>) -> Result<HashSet<u32>> {
+    let mut stmt =
+        conn.prepare("SELECT id, snoozed_until FROM tasks WHERE snoozed_until IS NOT NULL")?;
+    let rows = stmt.query_map([], |row| {
+        Ok((row.get::<_, u32>(0)?, row.get::<_, DateTime<Utc>>(1)?))
+    })?;
 
-    // Execute the query and map the results to a Vec of tuples (or whatever your row structure is).
-    let rows = stmt
-        .query_map(params, |row| {
-            let (average_duration, priority) = convert_fields_from_sql(row.get(5)?, row.get(10)?);
+    let mut ids = HashSet::new();
+    for row in rows {
+        let (id, snoozed_until) = row?;
+        if snoozed_until > now {
+            ids.insert(id);
+        }
+    }
+    Ok(ids)
+}
 
-            Ok(Task {
-                id: row.get(0)?,
+/// Finds the tasks that were shown in the ToDo list at or after `since`.
+///
+/// # Arguments
+///
+/// * `conn: &Connection` - Allows us to access the SQLite db.
+/// * `since: DateTime<Utc>` - The start of the window.
+pub fn shown_since(conn: &Connection, since: DateTime<Utc>) -> Result<HashSet<u32>> {
+    let mut stmt = conn.prepare("SELECT id, last_shown FROM tasks WHERE last_shown IS NOT NULL")?;
+    let rows = stmt.query_map([], |row| {
+        Ok((row.get::<_, u32>(0)?, row.get::<_, DateTime<Utc>>(1)?))
+    })?;
+
+    let mut ids = HashSet::new();
+    for row in rows {
+        let (id, last_shown) = row?;
+        if last_shown >= since {
+            ids.insert(id);
+        }
+    }
+    Ok(ids)
+}
+
+/// Looks for an unarchived task in a folder with the same summary, ignoring
+/// case and surrounding whitespace.
+///
+/// # Arguments
+///
+/// * `conn: &Connection` - Allows us to access the SQLite db.
+/// * `parent_id: u32` - The folder to look in. Subfolders aren't checked.
+/// * `summary: &str` - The summary of the task about to be added.
+///
+/// # Returns
+///
+/// The first matching task, if there is one.
+pub fn find_duplicate_task(
+    conn: &Connection,
+    parent_id: u32,
+    summary: &str,
+) -> Result<Option<Task>, DbError> {
+    let summary = summary.trim().to_lowercase();
+
+    Ok(read_all_tasks(conn)?.into_iter().find(|task| {
+        !task.is_archived
+            && task.parent_id == parent_id
+            && task.summary.trim().to_lowercase() == summary
+    }))
+}
+
+/// Reads the active tasks that were quick captured and haven't been triaged
+/// yet, oldest first.
+///
+/// # Arguments
+///
+/// * `conn: &Connection` - Allows us to access the SQLite db.
+pub fn read_untriaged(conn: &Connection) -> Result<Vec<Task>, DbError> {
+    let stmt = conn.prepare(
+        "SELECT
+            id, parent_id, is_archived, summary, description, average_duration,
+            bounty_modifier, due_date, from_date, lead_days, priority,
+            repeat_interval, times_selected, times_shown, created_at, in_progress,
+            recurrence_anchor, boost_factor, boost_until, repeat_unit
+        FROM tasks WHERE is_archived = 0 AND is_triaged = 0
+        ORDER BY created_at, id",
+    )?;
+
+    tasks_from_stmt(stmt, [], true)
+}
+
+/// Reads the unarchived tasks in a folder in the order an Iterator folder
+/// works through them.
+///
+/// # Arguments
+///
+/// * `conn: &Connection` - Allows us to access the SQLite db.
+/// * `folder_id: u32` - The id of the folder.
+///
+/// # Returns
+///
+/// A `Vec<Task>` sorted by sort_order, with ties broken by id.
+pub fn read_tasks_in_order(conn: &Connection, folder_id: u32) -> Result<Vec<Task>, DbError> {
+    let stmt = conn.prepare(
+        "SELECT
+            id, parent_id, is_archived, summary, description, average_duration,
+            bounty_modifier, due_date, from_date, lead_days, priority,
+            repeat_interval, times_selected, times_shown, created_at, in_progress,
+            recurrence_anchor, boost_factor, boost_until, repeat_unit
+        FROM tasks WHERE is_archived = 0 AND is_deferred = 0 AND parent_id = ?1
+        ORDER BY sort_order, id",
+    )?;
+
+    tasks_from_stmt(stmt, [folder_id], true)
+}
+
+/// Reads the task an Iterator folder should offer next.
+///
+/// # Arguments
+///
+/// * `conn: &Connection` - Allows us to access the SQLite db.
+/// * `folder_id: u32` - The id of the Iterator folder.
+///
+/// # Returns
+///
+/// The first task from `read_tasks_in_order()` that hasn't been completed
+/// this time round, or `None` if there's nothing left in the folder.
+///
+/// # Notes
+///
+/// Archived and deferred tasks are skipped, so archiving the current task
+/// moves the folder on to the next one.
+pub fn next_iterator_task(conn: &Connection, folder_id: u32) -> Result<Option<Task>, DbError> {
+    let done: HashSet<u32> = conn
+        .prepare("SELECT id FROM tasks WHERE parent_id=? AND is_cycle_done=1")?
+        .query_map([folder_id], |row| row.get(0))?
+        .collect::<Result<HashSet<u32>>>()?;
+
+    Ok(read_tasks_in_order(conn, folder_id)?
+        .into_iter()
+        .find(|task| !done.contains(&task.id)))
+}
+
+/// Marks a task in an Iterator folder as completed this time round, so the
+/// folder moves on to the next one. See `next_iterator_task()`.
+///
+/// # Arguments
+///
+/// * `conn: &Connection` - Allows us to access the SQLite db.
+/// * `id: u32` - The id for the affected task.
+pub fn mark_cycle_done(conn: &Connection, id: u32) -> Result<(), DbError> {
+    let updated = conn.execute("UPDATE tasks SET is_cycle_done=1 WHERE id=?", [id])?;
+    check_found(id, updated)?;
+
+    log_audit(conn, id, "complete", "is_cycle_done");
+    Ok(())
+}
+
+/// Starts an Iterator folder over from its first task.
+///
+/// # Arguments
+///
+/// * `conn: &Connection` - Allows us to access the SQLite db.
+/// * `folder_id: u32` - The id of the Iterator folder.
+pub fn restart_cycle(conn: &Connection, folder_id: u32) -> Result<(), DbError> {
+    conn.execute(
+        "UPDATE tasks SET is_cycle_done=0 WHERE parent_id=?",
+        [folder_id],
+    )?;
+    Ok(())
+}
+
+/// Reads the tasks saved for later, oldest first.
+///
+/// # Arguments
+///
+/// * `conn: &Connection` - Allows us to access the SQLite db.
+pub fn read_deferred(conn: &Connection) -> Result<Vec<Task>, DbError> {
+    let stmt = conn.prepare(
+        "SELECT
+            id, parent_id, is_archived, summary, description, average_duration,
+            bounty_modifier, due_date, from_date, lead_days, priority,
+            repeat_interval, times_selected, times_shown, created_at, in_progress,
+            recurrence_anchor, boost_factor, boost_until, repeat_unit
+        FROM tasks WHERE is_archived = 0 AND is_deferred = 1
+        ORDER BY created_at, id",
+    )?;
+
+    tasks_from_stmt(stmt, [], true)
+}
+
+/// Fetches Tasks from the database where `parent_id` matches any u32 in the given vector.
+/// Tasks saved for later are left out.
+///
+/// # Arguments
+///
+/// * `conn: &Connection` - A reference to the SQLite connection.
+/// * `parent_ids: Vec<u32>` - A vector of `u32` representing parent IDs to query for.
+///
+/// # Returns
+///
+/// A result containing a vector of tuples, each representing a row from the database,
+/// or an error if the query fails.
+///
+/// # Examples
+///
+/// ```text
+/// let mut conn = Connection::open("my_database.db").unwrap();
+/// let parent_ids = vec![1, 2, 3];
+/// let rows = fetch_by_parent_ids(&mut conn, &parent_ids).unwrap();
+/// for row in rows {
+///     println!("{:?}", row);
+/// }
+/// ```
+pub fn fetch_tasks_by_parent_ids(conn: &Connection, parent_ids: Vec<u32>) -> Result<Vec<Task>> {
+    // Prepare the SQL query using parameterized placeholders.
+    // The number of placeholders must match the number of parent_ids.
+    // Produces an output like `SELECT ... FROM tasks WHERE parent_id IN (?, ?, ?)`.
+    let query = format!(
+        "SELECT
+            id, parent_id, is_archived, summary, description, average_duration,
+            bounty_modifier, due_date, from_date, lead_days, priority,
+            repeat_interval, times_selected, times_shown, created_at, in_progress,
+            recurrence_anchor, boost_factor, boost_until, repeat_unit
+        FROM tasks WHERE is_deferred = 0 AND parent_id IN ({})",
+        parent_ids
+            .iter()
+            .map(|_| "?")
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+
+    // Prepare the statement.
+    let mut stmt = conn.prepare(&query)?;
+
+    // Convert `parent_ids` to a dynamic type that `rusqlite` can use for the query.
+    // We use `params_from_iter` to convert the vector into a suitable parameter list.
+    let params = params_from_iter(parent_ids.iter());
+
+    // Execute the query and map the results to a Vec of tuples (or whatever your row structure is).
+    let rows = stmt
+        .query_map(params, |row| {
+            let (average_duration, priority) = convert_fields_from_sql(row.get(5)?, row.get(10)?);
+
+            Ok(Task {
+                id: row.get(0)?,
                 parent_id: row.get(1)?,
                 is_archived: row.get(2)?,
                 summary: row.get(3)?,
                 description: row.get(4)?,
-                average_duration: average_duration,
+                average_duration,
                 bounty_modifier: row.get(6)?,
                 due_date: row.get(7)?,
                 from_date: row.get(8)?,
                 lead_days: row.get(9)?,
-                priority: priority,
+                priority,
                 repeat_interval: row.get(11)?,
+                repeat_unit: unit_from_sql(row.get(19)?),
                 times_selected: row.get(12)?,
                 times_shown: row.get(13)?,
+                created_at: row.get(14)?,
+                in_progress: row.get(15)?,
+                recurrence_anchor: anchor_from_sql(row.get(16)?),
+                boost_factor: row.get(17)?,
+                boost_until: row.get(18)?,
             })
         })?
         .collect();
@@ -746,28 +1908,50 @@ pub fn fetch_tasks_by_parent_ids(conn: &Connection, parent_ids: Vec<u32>) -> Res
     rows
 }
 
+/// rusqlite can't convert custom enums, so recurrence anchors are stored as
+/// integers, the same as priorities. Anything unrecognized is treated as
+/// Completion.
+fn anchor_to_sql(anchor: &RecurrenceAnchor) -> u8 {
+    match anchor {
+        RecurrenceAnchor::Completion => 0,
+        RecurrenceAnchor::Schedule => 1,
+    }
+}
+
+fn anchor_from_sql(anchor_row: u8) -> RecurrenceAnchor {
+    match anchor_row {
+        1 => RecurrenceAnchor::Schedule,
+        _ => RecurrenceAnchor::Completion,
+    }
+}
+
+/// Repeat units are stored as integers too. Anything unrecognized is treated
+/// as Days.
+fn unit_to_sql(unit: &RepeatUnit) -> u8 {
+    match unit {
+        RepeatUnit::Days => 0,
+        RepeatUnit::Hours => 1,
+    }
+}
+
+fn unit_from_sql(unit_row: u8) -> RepeatUnit {
+    match unit_row {
+        1 => RepeatUnit::Hours,
+        _ => RepeatUnit::Days,
+    }
+}
+
 fn convert_fields_from_sql(
-    average_duration_row: Option<u32>,
-    priority_row: u32,
+    average_duration_row: Option<String>,
+    priority_row: u8,
 ) -> (Option<Duration>, Priority) {
-    let average_duration = match average_duration_row {
-        Some(d) => Some(Duration::seconds(d as i64)),
-        None => None,
-    };
+    // The column has TEXT affinity, so the seconds written come back as text
+    let average_duration = average_duration_row
+        .and_then(|d| d.parse::<i64>().ok())
+        .map(Duration::seconds);
 
-    let priority: Priority = {
-        if priority_row == 0 {
-            Priority::P0
-        } else if priority_row == 1 {
-            Priority::P1
-        } else if priority_row == 2 {
-            Priority::P2
-        } else if priority_row == 3 {
-            Priority::P3
-        } else {
-            Priority::P1
-        }
-    };
+    // Anything unrecognized is treated as the default priority
+    let priority = Priority::try_from(priority_row).unwrap_or(Priority::P1);
 
     (average_duration, priority)
 }
@@ -778,8 +1962,9 @@ fn convert_fields_from_sql(
 /// # Arguments
 ///
 /// * `mut stmt: Statement<'_>` - The statement to be queried.
+/// * `params: impl Params` - Bound to the statement's placeholders.
 /// * `include_inactive: bool` - Set true to include tasks that have been
-/// completed recently and have not passed their repeat_interval since.
+///   completed recently and have not passed their repeat_interval since.
 ///
 /// # Returns
 ///
@@ -791,62 +1976,66 @@ fn convert_fields_from_sql(
 /// rusqlite uses some strange types that I'm struggling to fully wrap my head
 /// around. There's a good chance that this function could be rewritten more
 /// effectively.
-fn tasks_from_stmt(mut stmt: Statement<'_>, include_inactive: bool) -> Vec<Task> {
-    let rows = stmt
-        .query_map([], |row| {
-            // let average_duration = match row.get(5) {
-            //     Ok(Some(d)) => Some(Duration::seconds(d)),
-            //     Ok(None) => None,
-            //     Err(_) => None,
-            // };
-
-            // let priority: Priority = {
-            //     if row.get(10) == Ok(0) {
-            //         Priority::P0
-            //     } else if row.get(10) == Ok(1) {
-            //         Priority::P1
-            //     } else if row.get(10) == Ok(2) {
-            //         Priority::P2
-            //     } else if row.get(10) == Ok(3) {
-            //         Priority::P3
-            //     } else {
-            //         Priority::P1
-            //     }
-            // };
+fn tasks_from_stmt(
+    mut stmt: Statement<'_>,
+    params: impl Params,
+    include_inactive: bool,
+) -> Result<Vec<Task>, DbError> {
+    let rows = stmt.query_map(params, |row| {
+        // let average_duration = match row.get(5) {
+        //     Ok(Some(d)) => Some(Duration::seconds(d)),
+        //     Ok(None) => None,
+        //     Err(_) => None,
+        // };
 
-            let (average_duration, priority) = convert_fields_from_sql(row.get(5)?, row.get(10)?);
+        // let priority: Priority = {
+        //     if row.get(10) == Ok(0) {
+        //         Priority::P0
+        //     } else if row.get(10) == Ok(1) {
+        //         Priority::P1
+        //     } else if row.get(10) == Ok(2) {
+        //         Priority::P2
+        //     } else if row.get(10) == Ok(3) {
+        //         Priority::P3
+        //     } else {
+        //         Priority::P1
+        //     }
+        // };
 
-            Ok(Task {
-                id: row.get(0)?,
-                parent_id: row.get(1)?,
-                is_archived: row.get(2)?,
-                summary: row.get(3)?,
-                description: row.get(4)?,
-                average_duration: average_duration,
-                bounty_modifier: row.get(6)?,
-                due_date: row.get(7)?,
-                from_date: row.get(8)?,
-                lead_days: row.get(9)?,
-                priority: priority,
-                repeat_interval: row.get(11)?,
-                times_selected: row.get(12)?,
-                times_shown: row.get(13)?,
-            })
+        let (average_duration, priority) = convert_fields_from_sql(row.get(5)?, row.get(10)?);
+
+        Ok(Task {
+            id: row.get(0)?,
+            parent_id: row.get(1)?,
+            is_archived: row.get(2)?,
+            summary: row.get(3)?,
+            description: row.get(4)?,
+            average_duration,
+            bounty_modifier: row.get(6)?,
+            due_date: row.get(7)?,
+            from_date: row.get(8)?,
+            lead_days: row.get(9)?,
+            priority,
+            repeat_interval: row.get(11)?,
+            repeat_unit: unit_from_sql(row.get(19)?),
+            times_selected: row.get(12)?,
+            times_shown: row.get(13)?,
+            created_at: row.get(14)?,
+            in_progress: row.get(15)?,
+            recurrence_anchor: anchor_from_sql(row.get(16)?),
+            boost_factor: row.get(17)?,
+            boost_until: row.get(18)?,
         })
-        .unwrap_or_else(|err| {
-            panic!("Problem running SELECT statement or processing results: {err}");
-        });
+    })?;
 
     // Converting it from a rusqlite MappedRows<Task> to a Vec<Task>.
     let mut query_result_as_vec: Vec<Task> = Vec::new();
     for row in rows {
-        let task = row.unwrap_or_else(|err| {
-            panic!("Problem unwrapping row after SELECT query: {err}");
-        });
+        let task = row?;
 
         // Only push tasks that should be added
         if task.repeat_interval.is_none()
-            || task.from_date + Duration::days(task.repeat_interval.unwrap_or(0) as i64)
+            || task.effective_from_date() + task.repeat_duration().unwrap_or(Duration::zero())
                 < <Utc>::now()
             || include_inactive
         {
@@ -854,309 +2043,2375 @@ fn tasks_from_stmt(mut stmt: Statement<'_>, include_inactive: bool) -> Vec<Task>
         }
     }
 
-    query_result_as_vec
+    Ok(query_result_as_vec)
 }
 
-/// TODO: Doc comment. I got it working, I need to take a break.
-pub fn read_target_allowance(conn: &Connection) -> Result<u32, Error> {
-    let sql = "SELECT value FROM settings WHERE key = ?1";
-
-    let value: Option<String> = conn
-        .query_row(sql, ["target_monthly_allowance"], |row| row.get(0))
-        .optional()?;
+/// Reads the target_monthly_allowance setting.
+///
+/// # Returns
+///
+/// `Err(SettingMissing)` if the setting is missing. Most callers want
+/// `finance::monthly_allowances()`, which falls back to a default instead.
+pub fn read_target_allowance(conn: &Connection) -> Result<u32, DbError> {
+    const KEY: &str = "target_monthly_allowance";
 
-    match value {
-        Some(v) => v
-            .parse::<u32>()
-            .map_err(|_| Error::InvalidColumnName(String::from("Failed to parse TEXT to u32"))),
-        None => Err(Error::QueryReturnedNoRows),
-    }
+    read_setting_u32(conn, KEY)?.ok_or_else(|| DbError::SettingMissing(KEY.into()))
 }
 
-pub fn read_transactions(conn: &Connection) -> Vec<(DateTime<Utc>, Option<f64>, Option<f64>)> {
-    let mut stmt = conn
-        .prepare(
-            "SELECT
-            date,
-            funds_added,
-            funds_subtracted
-        FROM transactions",
+/// Reads the raw value of a statistic from the statistics table.
+///
+/// # Arguments
+///
+/// * `conn: &Connection` - Allows us to access the SQLite db.
+/// * `key: &str` - The name of the statistic.
+///
+/// # Returns
+///
+/// `Ok(None)` if the statistic doesn't exist or has no value.
+pub fn read_statistic(conn: &Connection, key: &str) -> Result<Option<String>, Error> {
+    let value: Option<Option<String>> = conn
+        .query_row(
+            "SELECT value FROM statistics WHERE key = ?1",
+            [key],
+            |row| row.get(0),
         )
-        .unwrap_or_else(|err| {
-            panic!("Problem preparing SELECT statement: {err}");
-        });
-
-    let rows = stmt
-        // .query_map([], |row| Ok([row.get(0).unwrap(), row.get(1).unwrap()]))
-        .query_map([], |row| match row.get(1).unwrap() {
-            Some(price) => Ok((row.get(0).unwrap(), Some(price), None)),
-            None => Ok((row.get(0).unwrap(), None, Some(row.get(2).unwrap()))),
-        })
-        .unwrap();
-
-    // Converting it from a rusqlite MappedRows<Task> to a Vec<Task>.
-    // This might not be necessary if I was more comfortable with rusqlite.
-    let mut query_result_as_vec: Vec<(DateTime<Utc>, Option<f64>, Option<f64>)> = Vec::new();
-    for row in rows {
-        let transaction = row.unwrap_or_else(|err| {
-            panic!("Problem unwrapping row after SELECT query: {err}");
-        });
-
-        query_result_as_vec.push(transaction)
-    }
+        .optional()?;
 
-    query_result_as_vec
+    Ok(value.flatten())
 }
 
-// pub fn delete_task_by_id(conn: &Connection, id: u32) {
-//     conn.execute("DELETE FROM tasks WHERE id=?1", [&id])
-//         .unwrap_or_else(|err| {
-//             panic!("Problem deleting task {id} from table: {err}");
-//         });
-// }
-
-/// Incriments a task's times_shown by 1 in the db.
+/// Reads a statistic that holds a count, e.g. total_tasks_completed.
 ///
-/// # Arguments
+/// # Returns
 ///
-/// * `conn: Connection` - Allows us to access the SQLite db.
-/// * `id: u32` - The id for the affected task.
-/// * `times_shown` - The current value to be incremented (before adding 1)
-pub fn increment_times_shown(conn: &Connection, id: u32, times_shown: u32) {
-    conn.execute(
-        "UPDATE tasks SET times_shown=?1 WHERE id=?2",
-        [times_shown + 1, id],
-    )
-    .unwrap_or_else(|err| {
-        panic!("Problem updating task: {err}");
-    });
+/// `Ok(None)` if the statistic doesn't exist, has no value, or isn't a whole
+/// number.
+pub fn read_statistic_u32(conn: &Connection, key: &str) -> Result<Option<u32>, Error> {
+    Ok(read_statistic(conn, key)?.and_then(|value| value.trim().parse().ok()))
 }
 
-pub fn increment_times_selected(conn: &Connection, id: u32, times_selected: u32) {
-    conn.execute(
-        "UPDATE tasks SET times_selected=?1 WHERE id=?2",
-        [times_selected + 1, id],
-    )
-    .unwrap_or_else(|err| {
-        panic!("Problem updating task: {err}");
-    });
+/// Reads a statistic that holds an amount, e.g. funds_loaded.
+///
+/// # Returns
+///
+/// `Ok(None)` if the statistic doesn't exist, has no value, or isn't a
+/// number. baseline_bounty has no value until it's been worked out.
+pub fn read_statistic_f64(conn: &Connection, key: &str) -> Result<Option<f64>, Error> {
+    Ok(read_statistic(conn, key)?.and_then(|value| value.trim().parse().ok()))
 }
 
-pub fn reset_from_date(conn: &Connection, id: u32) {
-    conn.execute(
-        "UPDATE tasks SET from_date=? WHERE id=?",
-        params![<Utc>::now(), id],
-    )
-    .unwrap_or_else(|err| {
-        panic!("Problem updating task: {err}");
-    });
+/// Writes a statistic to the statistics table, adding it if it doesn't exist
+/// yet.
+///
+/// # Arguments
+///
+/// * `conn: &Connection` - Allows us to access the SQLite db.
+/// * `key: &str` - The name of the statistic.
+/// * `value: &str` - The new value.
+pub fn write_statistic(conn: &Connection, key: &str, value: &str) -> Result<()> {
+    write_statistic_value(conn, key, Some(value))
 }
 
-pub fn archive_task(conn: &Connection, id: u32) {
-    println!("Archiving task by id {}", &id);
+/// Adds one to a counting statistic, e.g. total_tasks_completed.
+///
+/// # Arguments
+///
+/// * `conn: &Connection` - Allows us to access the SQLite db.
+/// * `key: &str` - The name of the statistic.
+///
+/// # Notes
+///
+/// Values are stored as text, so it's cast to a number and back. A missing
+/// or NULL statistic counts from 0.
+pub fn increment_statistic(conn: &Connection, key: &str) -> Result<()> {
+    let updated = conn.execute(
+        "UPDATE statistics SET value = CAST(COALESCE(value, 0) AS INTEGER) + 1 WHERE key = ?1",
+        [key],
+    )?;
 
-    conn.execute("UPDATE tasks SET is_archived=1 WHERE id=?", params![id])
-        .unwrap_or_else(|err| {
-            panic!("Problem updating task: {err}");
-        });
+    if updated == 0 {
+        write_statistic(conn, key, "1")?;
+    }
+
+    Ok(())
 }
 
-#[cfg(test)]
-mod tests {
+/// Same as `write_statistic()`, but a value of None is stored as NULL.
+fn write_statistic_value(conn: &Connection, key: &str, value: Option<&str>) -> Result<()> {
+    let updated = conn.execute(
+        "UPDATE statistics SET value = ?1 WHERE key = ?2",
+        params![value, key],
+    )?;
+
+    if updated == 0 {
+        conn.execute(
+            "INSERT INTO statistics (key, value) VALUES (?1, ?2)",
+            params![key, value],
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Reads a numeric setting from the settings table.
+///
+/// # Arguments
+///
+/// * `conn: &Connection` - Allows us to access the SQLite db.
+/// * `key: &str` - The name of the setting.
+///
+/// # Returns
+///
+/// `Ok(None)` if the setting doesn't exist, or an error if its value can't be
+/// parsed as a `u32`.
+pub fn read_setting_u32(conn: &Connection, key: &str) -> Result<Option<u32>, Error> {
+    let value: Option<String> = conn
+        .query_row("SELECT value FROM settings WHERE key = ?1", [key], |row| {
+            row.get(0)
+        })
+        .optional()?;
+
+    value
+        .map(|v| {
+            v.parse::<u32>()
+                .map_err(|_| Error::InvalidColumnName(format!("Failed to parse {key} to u32")))
+        })
+        .transpose()
+}
+
+/// Looks up a setting's default in `DEFAULT_SETTINGS`, or 0 if it has none.
+pub fn default_setting(key: &str) -> u32 {
+    DEFAULT_SETTINGS
+        .iter()
+        .find(|(default_key, _)| *default_key == key)
+        .map_or(0, |(_, value)| *value)
+}
+
+/// Reads a numeric setting, falling back to `default_setting()` if it's
+/// missing or can't be parsed.
+pub fn setting_or_default(conn: &Connection, key: &str) -> u32 {
+    match read_setting_u32(conn, key) {
+        Ok(Some(value)) => value,
+        _ => default_setting(key),
+    }
+}
+
+/// Writes a numeric setting to the settings table, adding it if it doesn't
+/// exist yet.
+///
+/// # Arguments
+///
+/// * `conn: &Connection` - Allows us to access the SQLite db.
+/// * `key: &str` - The name of the setting.
+/// * `value: u32` - The new value.
+pub fn update_setting(conn: &Connection, key: &str, value: u32) -> Result<()> {
+    let updated = conn.execute(
+        "UPDATE settings SET value = ?1 WHERE key = ?2",
+        params![value, key],
+    )?;
+
+    if updated == 0 {
+        conn.execute(
+            "INSERT INTO settings (key, value) VALUES (?1, ?2)",
+            params![key, value],
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Records the bounty paid out for completing a task.
+///
+/// # Arguments
+///
+/// * `conn: &Connection` - Allows us to access the SQLite db.
+/// * `task_id: u32` - The task that was completed.
+/// * `bounty: f64` - The amount paid.
+/// * `now: DateTime<Utc>` - When it was paid.
+pub fn add_payout(
+    conn: &Connection,
+    task_id: u32,
+    bounty: f64,
+    now: DateTime<Utc>,
+) -> Result<(), DbError> {
+    conn.execute(
+        "INSERT INTO transactions (date, funds_added, task_id) VALUES (?, ?, ?)",
+        params![now, bounty, task_id],
+    )?;
+    Ok(())
+}
+
+/// Takes back a bounty that was paid out by mistake.
+///
+/// # Arguments
+///
+/// * `conn: &Connection` - Allows us to access the SQLite db.
+/// * `task_id: u32` - The task the bounty was paid for.
+/// * `bounty: f64` - The amount to take back.
+///
+/// # Notes
+///
+/// The payout stays in the ledger, with a negative payout for the same task
+/// after it. That takes the bounty back out of this month's earnings too,
+/// but neither counts as a completion.
+pub fn reverse_payout(conn: &Connection, task_id: u32, bounty: f64) -> Result<(), DbError> {
+    conn.execute(
+        "INSERT INTO transactions (date, funds_added, task_id) VALUES (?, ?, ?)",
+        params![<Utc>::now(), -bounty, task_id],
+    )?;
+    Ok(())
+}
+
+/// The date and amount of a bounty paid out for a task.
+pub type Payout = (DateTime<Utc>, f64);
+
+/// Reads every bounty paid out for a task, oldest first.
+///
+/// # Arguments
+///
+/// * `conn: &Connection` - Allows us to access the SQLite db.
+/// * `task_id: u32` - The task to read payouts for.
+///
+pub fn read_payouts(conn: &Connection, task_id: u32) -> Result<Vec<Payout>> {
+    let mut stmt = conn.prepare(
+        "SELECT date, funds_added FROM transactions
+        WHERE task_id = ? AND funds_added > 0
+        ORDER BY date, id",
+    )?;
+    let payouts = stmt
+        .query_map([task_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect();
+
+    payouts
+}
+
+pub fn read_transactions(conn: &Connection) -> Vec<(DateTime<Utc>, Option<f64>, Option<f64>)> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT
+            date,
+            funds_added,
+            funds_subtracted
+        FROM transactions",
+        )
+        .unwrap_or_else(|err| {
+            panic!("Problem preparing SELECT statement: {err}");
+        });
+
+    let rows = stmt
+        // .query_map([], |row| Ok([row.get(0).unwrap(), row.get(1).unwrap()]))
+        // A row with neither amount set is passed on as (date, None, None)
+        .query_map([], |row| match row.get(1)? {
+            Some(price) => Ok((row.get(0)?, Some(price), None)),
+            None => Ok((row.get(0)?, None, row.get(2)?)),
+        })
+        .unwrap();
+
+    // Converting it from a rusqlite MappedRows<Task> to a Vec<Task>.
+    // This might not be necessary if I was more comfortable with rusqlite.
+    let mut query_result_as_vec: Vec<(DateTime<Utc>, Option<f64>, Option<f64>)> = Vec::new();
+    for row in rows {
+        let transaction = row.unwrap_or_else(|err| {
+            panic!("Problem unwrapping row after SELECT query: {err}");
+        });
+
+        query_result_as_vec.push(transaction)
+    }
+
+    query_result_as_vec
+}
+
+/// Writes every task to a CSV file for use in a spreadsheet.
+///
+/// # Arguments
+///
+/// * `conn: &Connection` - Allows us to access the SQLite db.
+/// * `path: &Path` - Where to write the file. It's overwritten if it exists.
+///
+/// # Notes
+///
+/// Dates are written in RFC 3339 and missing values are left blank. Fields
+/// containing commas, quotes or newlines are quoted.
+pub fn export_tasks_csv(conn: &Connection, path: &Path) -> io::Result<()> {
+    let folder_paths = read_all_folders(conn, None, "".to_string()).map_err(io::Error::other)?;
+    let mut file = BufWriter::new(File::create(path)?);
+
+    writeln!(
+        file,
+        "id,folder,summary,priority,due_date,repeat_interval,times_shown,times_selected,bounty_modifier"
+    )?;
+
+    for task in read_all_tasks(conn).map_err(io::Error::other)? {
+        let row = [
+            task.id.to_string(),
+            folder_paths
+                .get(&task.parent_id)
+                .cloned()
+                .unwrap_or_default(),
+            task.summary,
+            format!("{:?}", task.priority),
+            task.due_date.map(|d| d.to_rfc3339()).unwrap_or_default(),
+            task.repeat_interval
+                .map(|r| r.to_string())
+                .unwrap_or_default(),
+            task.times_shown.to_string(),
+            task.times_selected.to_string(),
+            task.bounty_modifier.to_string(),
+        ];
+
+        let row: Vec<String> = row.iter().map(|field| csv_field(field)).collect();
+        writeln!(file, "{}", row.join(","))?;
+    }
+
+    file.flush()
+}
+
+/// How `plan_key_values_import()` treats the settings and statistics already
+/// in the db.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ImportMode {
+    /// Imported keys replace their current values. Keys missing from the
+    /// file are left alone.
+    Merge,
+    /// The db ends up matching the file. Keys missing from the file are
+    /// removed, and any settings among them go back to their defaults.
+    Overwrite,
+}
+
+/// Why a settings file couldn't be imported.
+#[derive(Debug)]
+pub enum ImportError {
+    Io(io::Error),
+    /// A line of the file couldn't be used. `line` counts from 1.
+    Invalid {
+        line: usize,
+        reason: String,
+    },
+    Db(Error),
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportError::Io(e) => write!(f, "couldn't read the file: {e}"),
+            ImportError::Invalid { line, reason } => write!(f, "line {line}: {reason}"),
+            ImportError::Db(e) => write!(f, "couldn't save the imported values: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for ImportError {}
+
+impl From<io::Error> for ImportError {
+    fn from(e: io::Error) -> Self {
+        ImportError::Io(e)
+    }
+}
+
+impl From<Error> for ImportError {
+    fn from(e: Error) -> Self {
+        ImportError::Db(e)
+    }
+}
+
+/// Reads every key and value from one of the key-value tables, ordered by key.
+fn read_key_values(conn: &Connection, table: &str) -> Result<Vec<(String, Option<String>)>> {
+    let mut stmt = conn.prepare(&format!("SELECT key, value FROM {table} ORDER BY key, id"))?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect();
+
+    rows
+}
+
+/// Writes every setting and statistic to a CSV file, so they can be moved to
+/// another machine or restored after a reset.
+///
+/// # Arguments
+///
+/// * `conn: &Connection` - Allows us to access the SQLite db.
+/// * `path: &Path` - Where to write the file. It's overwritten if it exists.
+///
+/// # Notes
+///
+/// Each row is `table,key,value`. Statistics without a value have an empty
+/// value field.
+pub fn export_key_values_csv(conn: &Connection, path: &Path) -> io::Result<()> {
+    let mut file = BufWriter::new(File::create(path)?);
+
+    writeln!(file, "table,key,value")?;
+
+    for table in ["settings", "statistics"] {
+        for (key, value) in read_key_values(conn, table).map_err(io::Error::other)? {
+            let row = [table, &key, value.as_deref().unwrap_or_default()];
+            let row: Vec<String> = row.iter().map(|field| csv_field(field)).collect();
+            writeln!(file, "{}", row.join(","))?;
+        }
+    }
+
+    file.flush()
+}
+
+/// What importing a settings file would change, worked out without writing
+/// anything. See `plan_key_values_import()`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImportPlan {
+    pub mode: ImportMode,
+    pub settings: Vec<(String, u32)>,
+    pub statistics: Vec<(String, Option<String>)>,
+    /// Keys in the file that aren't in the db yet.
+    pub new_keys: usize,
+    /// Keys already in the db whose value would change.
+    pub changed: usize,
+    /// Keys in the db but not the file, which Overwrite removes or resets to
+    /// their defaults. Always 0 for Merge.
+    pub removed: usize,
+    /// Blank lines that were passed over.
+    pub skipped: usize,
+}
+
+impl ImportPlan {
+    /// How many values the file holds.
+    pub fn len(&self) -> usize {
+        self.settings.len() + self.statistics.len()
+    }
+
+    /// Writes the planned values to the settings and statistics tables.
+    ///
+    /// # Returns
+    ///
+    /// The number of values imported.
+    ///
+    /// # Notes
+    ///
+    /// Runs in a single transaction, so either everything is imported or
+    /// nothing is.
+    pub fn apply(&self, conn: &Connection) -> Result<usize, ImportError> {
+        // Everything else shares a plain &Connection, so we can't borrow it
+        // mutably for a checked transaction
+        let tx = conn.unchecked_transaction()?;
+        if self.mode == ImportMode::Overwrite {
+            tx.execute("DELETE FROM settings", ())?;
+            tx.execute("DELETE FROM statistics", ())?;
+        }
+        for (key, value) in &self.settings {
+            update_setting(&tx, key, *value)?;
+        }
+        for (key, value) in &self.statistics {
+            write_statistic_value(&tx, key, value.as_deref())?;
+        }
+        if self.mode == ImportMode::Overwrite {
+            // Put back the defaults for anything the file didn't have
+            init_settings(&tx);
+            init_statistics(&tx);
+        }
+        tx.commit()?;
+
+        Ok(self.len())
+    }
+}
+
+/// Reads a file written by `export_key_values_csv()` and works out what
+/// importing it would do, without touching the db.
+///
+/// # Arguments
+///
+/// * `conn: &Connection` - The current values are read from the db.
+/// * `path: &Path` - The file to read.
+/// * `mode: &ImportMode` - What happens to the values already in the db.
+///
+/// # Notes
+///
+/// Every line is checked, so a plan that's returned can be applied as is.
+/// Settings must be whole numbers.
+pub fn plan_key_values_import(
+    conn: &Connection,
+    path: &Path,
+    mode: &ImportMode,
+) -> Result<ImportPlan, ImportError> {
+    let contents = std::fs::read_to_string(path)?;
+    let mut settings: Vec<(String, u32)> = Vec::new();
+    let mut statistics: Vec<(String, Option<String>)> = Vec::new();
+    let mut skipped = 0;
+
+    for (index, line) in contents.lines().enumerate().skip(1) {
+        let invalid = |reason: String| ImportError::Invalid {
+            line: index + 1,
+            reason,
+        };
+        if line.trim().is_empty() {
+            skipped += 1;
+            continue;
+        }
+
+        let fields = parse_csv_line(line).ok_or_else(|| invalid("unclosed quote".into()))?;
+        let [table, key, value] = &fields[..] else {
+            return Err(invalid(format!(
+                "expected 3 fields, found {}",
+                fields.len()
+            )));
+        };
+
+        match table.as_str() {
+            "settings" => {
+                let value = value.parse::<u32>().map_err(|_| {
+                    invalid(format!("{key} should be a whole number, not {value:?}"))
+                })?;
+                settings.push((key.clone(), value));
+            }
+            "statistics" => {
+                let value = (!value.is_empty()).then(|| value.clone());
+                statistics.push((key.clone(), value));
+            }
+            other => return Err(invalid(format!("unknown table {other:?}"))),
+        }
+    }
+
+    // Compare against what's there now
+    let mut plan = ImportPlan {
+        mode: mode.clone(),
+        settings,
+        statistics,
+        new_keys: 0,
+        changed: 0,
+        removed: 0,
+        skipped,
+    };
+    let imported = [
+        (
+            "settings",
+            plan.settings
+                .iter()
+                .map(|(key, value)| (key.clone(), Some(value.to_string())))
+                .collect::<HashMap<_, _>>(),
+        ),
+        (
+            "statistics",
+            plan.statistics.iter().cloned().collect::<HashMap<_, _>>(),
+        ),
+    ];
+    for (table, imported) in imported {
+        let current: HashMap<String, Option<String>> =
+            read_key_values(conn, table)?.into_iter().collect();
+        for (key, value) in &imported {
+            match current.get(key) {
+                None => plan.new_keys += 1,
+                Some(existing) if existing != value => plan.changed += 1,
+                Some(_) => {}
+            }
+        }
+        if *mode == ImportMode::Overwrite {
+            plan.removed += current
+                .keys()
+                .filter(|key| !imported.contains_key(*key))
+                .count();
+        }
+    }
+
+    Ok(plan)
+}
+
+/// Splits one line of CSV into its fields, undoing `csv_field()`. Returns
+/// None if a quoted field isn't closed.
+fn parse_csv_line(line: &str) -> Option<Vec<String>> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match (c, in_quotes) {
+            ('"', true) if chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            ('"', true) => in_quotes = false,
+            ('"', false) if field.is_empty() => in_quotes = true,
+            (',', false) => fields.push(std::mem::take(&mut field)),
+            (c, _) => field.push(c),
+        }
+    }
+
+    if in_quotes {
+        return None;
+    }
+    fields.push(field);
+    Some(fields)
+}
+
+/// Quotes a CSV field if it contains anything that would break the row.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Incriments a task's times_shown by 1 in the db.
+///
+/// # Arguments
+///
+/// * `conn: Connection` - Allows us to access the SQLite db.
+/// * `id: u32` - The id for the affected task.
+///
+/// # Notes
+///
+/// The count is incremented by SQLite rather than written back from a copy
+/// of the task, so an out of date copy can't undo another increment.
+pub fn increment_times_shown(conn: &Connection, id: u32) -> Result<(), DbError> {
+    let updated = conn.execute(
+        "UPDATE tasks SET times_shown=times_shown+1, last_shown=?1 WHERE id=?2",
+        params![Utc::now(), id],
+    )?;
+    check_found(id, updated)?;
+    Ok(())
+}
+
+/// Incriments a task's times_selected by 1 in the db. Like
+/// `increment_times_shown()`, SQLite does the incrementing.
+pub fn increment_times_selected(conn: &Connection, id: u32) -> Result<(), DbError> {
+    let updated = conn.execute(
+        "UPDATE tasks SET times_selected=times_selected+1 WHERE id=?",
+        [id],
+    )?;
+    check_found(id, updated)?;
+
+    log_audit(conn, id, "complete", "times_selected");
+    Ok(())
+}
+
+/// Sets a task's from_date, restarting its recurrence. See
+/// `Task::next_from_date()`.
+pub fn reset_from_date(
+    conn: &Connection,
+    id: u32,
+    from_date: DateTime<Utc>,
+) -> Result<(), DbError> {
+    let updated = conn.execute(
+        "UPDATE tasks SET from_date=? WHERE id=?",
+        params![from_date, id],
+    )?;
+    check_found(id, updated)?;
+
+    log_audit(conn, id, "reset", "from_date");
+    Ok(())
+}
+
+/// Sets the from_date of every unarchived task in a folder at once, so a
+/// Selector or Iterator folder's tasks all come around again together.
+///
+/// # Arguments
+///
+/// * `conn: &Connection` - Allows us to access the SQLite db.
+/// * `parent_id: u32` - The id of the folder.
+/// * `from_date: DateTime<Utc>` - The new from_date.
+///
+/// # Notes
+///
+/// Only the folder's own tasks are reset, not those in folders below it.
+pub fn reset_folder_from_dates(
+    conn: &Connection,
+    parent_id: u32,
+    from_date: DateTime<Utc>,
+) -> Result<(), DbError> {
+    let ids: Vec<u32> = conn
+        .prepare("SELECT id FROM tasks WHERE parent_id=? AND is_archived=0")?
+        .query_map([parent_id], |row| row.get(0))?
+        .collect::<Result<Vec<u32>>>()?;
+
+    for id in ids {
+        reset_from_date(conn, id, from_date)?;
+    }
+    Ok(())
+}
+
+/// Marks a task as started, or clears the mark once it's been completed or
+/// abandoned.
+///
+/// # Arguments
+///
+/// * `conn: &Connection` - Allows us to access the SQLite db.
+/// * `id: u32` - The id for the affected task.
+/// * `in_progress: bool` - The new value.
+pub fn set_in_progress(conn: &Connection, id: u32, in_progress: bool) -> Result<(), DbError> {
+    let updated = conn.execute(
+        "UPDATE tasks SET in_progress=? WHERE id=?",
+        params![in_progress, id],
+    )?;
+    check_found(id, updated)?;
+
+    log_audit(conn, id, "edit", "in_progress");
+    Ok(())
+}
+
+/// Records how long a task usually takes.
+///
+/// # Arguments
+///
+/// * `conn: &Connection` - Allows us to access the SQLite db.
+/// * `id: u32` - The id for the affected task.
+/// * `average_duration: Duration` - The new average.
+pub fn set_average_duration(
+    conn: &Connection,
+    id: u32,
+    average_duration: Duration,
+) -> Result<(), DbError> {
+    let updated = conn.execute(
+        "UPDATE tasks SET average_duration=? WHERE id=?",
+        params![average_duration.num_seconds(), id],
+    )?;
+    check_found(id, updated)?;
+
+    log_audit(conn, id, "edit", "average_duration");
+    Ok(())
+}
+
+/// Gives a task a temporary boost in the ToDo list, which fades out by
+/// `until`. See `weighting::boost_multiplier()`.
+///
+/// # Arguments
+///
+/// * `conn: &Connection` - Allows us to access the SQLite db.
+/// * `id: u32` - The id for the affected task.
+/// * `factor: f32` - What the task's weight is multiplied by to start with.
+/// * `until: DateTime<Utc>` - When the boost has faded out completely.
+pub fn boost_task(
+    conn: &Connection,
+    id: u32,
+    factor: f32,
+    until: DateTime<Utc>,
+) -> Result<(), DbError> {
+    let updated = conn.execute(
+        "UPDATE tasks SET boost_factor=?, boost_until=? WHERE id=?",
+        params![factor, until, id],
+    )?;
+    check_found(id, updated)?;
+
+    log_audit(conn, id, "edit", "boost_factor,boost_until");
+    Ok(())
+}
+
+/// Moves a task onto the later list, which keeps it out of the ToDo list
+/// without archiving it.
+///
+/// # Arguments
+///
+/// * `conn: &Connection` - Allows us to access the SQLite db.
+/// * `id: u32` - The id for the affected task.
+pub fn defer_task(conn: &Connection, id: u32) -> Result<(), DbError> {
+    set_deferred(conn, id, true)
+}
+
+/// Keeps a task out of the ToDo list for a few days, without completing it.
+///
+/// # Arguments
+///
+/// * `conn: &Connection` - Allows us to access the SQLite db.
+/// * `id: u32` - The id for the affected task.
+/// * `days: u32` - How long until it can come up again.
+///
+/// # Notes
+///
+/// A recurring task's from_date is reset as well, so it skips the occurrence
+/// it was snoozed on instead of coming back overdue. Nothing counts it as
+/// selected.
+pub fn snooze_task(conn: &Connection, id: u32, days: u32) -> Result<(), DbError> {
+    let now = Utc::now();
+    let snoozed_until = now
+        .checked_add_signed(Duration::days(i64::from(days)))
+        .ok_or(DbError::SnoozeTooLong(days))?;
+    let updated = conn.execute(
+        "UPDATE tasks SET
+            snoozed_until=?1,
+            from_date=CASE WHEN repeat_interval IS NULL THEN from_date ELSE ?2 END
+        WHERE id=?3",
+        params![snoozed_until, now, id],
+    )?;
+    check_found(id, updated)?;
+
+    log_audit(conn, id, "snooze", "snoozed_until,from_date");
+    Ok(())
+}
+
+/// Moves a task off the later list and back into the active set.
+///
+/// # Arguments
+///
+/// * `conn: &Connection` - Allows us to access the SQLite db.
+/// * `id: u32` - The id for the affected task.
+pub fn promote_task(conn: &Connection, id: u32) -> Result<(), DbError> {
+    set_deferred(conn, id, false)
+}
+
+fn set_deferred(conn: &Connection, id: u32, is_deferred: bool) -> Result<(), DbError> {
+    let updated = conn.execute(
+        "UPDATE tasks SET is_deferred=? WHERE id=?",
+        params![is_deferred, id],
+    )?;
+    check_found(id, updated)?;
+
+    log_audit(conn, id, "edit", "is_deferred");
+    Ok(())
+}
+
+/// Marks a task as waiting in the inbox, or takes it out once triaged.
+///
+/// # Arguments
+///
+/// * `conn: &Connection` - Allows us to access the SQLite db.
+/// * `id: u32` - The id for the affected task.
+/// * `is_triaged: bool` - The new value.
+pub fn set_triaged(conn: &Connection, id: u32, is_triaged: bool) -> Result<(), DbError> {
+    let updated = conn.execute(
+        "UPDATE tasks SET is_triaged=? WHERE id=?",
+        params![is_triaged, id],
+    )?;
+    check_found(id, updated)?;
+
+    log_audit(conn, id, "edit", "is_triaged");
+    Ok(())
+}
+
+/// Saves the details chosen while triaging a task and marks it as triaged.
+///
+/// # Arguments
+///
+/// * `conn: &Connection` - Allows us to access the SQLite db.
+/// * `task: &Task` - The task with its new folder, priority and type. Only
+///   those fields are written.
+pub fn triage_task(conn: &Connection, task: &Task) -> Result<()> {
+    conn.execute(
+        "UPDATE tasks SET
+            parent_id=?,
+            priority=?,
+            repeat_interval=?,
+            repeat_unit=?,
+            recurrence_anchor=?,
+            due_date=?,
+            lead_days=?,
+            is_triaged=1
+        WHERE id=?",
+        params![
+            task.parent_id,
+            u8::from(&task.priority),
+            task.repeat_interval,
+            unit_to_sql(&task.repeat_unit),
+            anchor_to_sql(&task.recurrence_anchor),
+            task.due_date,
+            task.lead_days,
+            task.id
+        ],
+    )?;
+
+    log_audit(
+        conn,
+        task.id,
+        "triage",
+        "parent_id,priority,repeat_interval,repeat_unit,recurrence_anchor,due_date,lead_days,is_triaged",
+    );
+    Ok(())
+}
+
+/// Sets the priority of several tasks at once.
+///
+/// # Arguments
+///
+/// * `conn: &Connection` - Allows us to access the SQLite db.
+/// * `ids: &[u32]` - The ids of the affected tasks.
+/// * `priority: &Priority` - The new priority.
+///
+/// # Notes
+///
+/// Runs in a single transaction, so either every task is updated or none are.
+pub fn set_priority_bulk(conn: &Connection, ids: &[u32], priority: &Priority) -> Result<()> {
+    update_tasks_bulk(
+        conn,
+        ids,
+        "UPDATE tasks SET priority=?1 WHERE id=?2",
+        u8::from(priority),
+        ("edit", "priority"),
+    )
+}
+
+/// Moves several tasks into the same folder at once.
+///
+/// # Arguments
+///
+/// * `conn: &Connection` - Allows us to access the SQLite db.
+/// * `ids: &[u32]` - The ids of the affected tasks.
+/// * `parent_id: u32` - The id of the folder to move them into.
+///
+/// # Notes
+///
+/// Runs in a single transaction, so either every task is moved or none are.
+pub fn move_tasks_bulk(conn: &Connection, ids: &[u32], parent_id: u32) -> Result<()> {
+    update_tasks_bulk(
+        conn,
+        ids,
+        // Moved tasks join the end of their new folder
+        "UPDATE tasks SET
+            parent_id=?1,
+            sort_order=(SELECT COALESCE(MAX(sort_order), 0) + 1 FROM tasks WHERE parent_id=?1)
+        WHERE id=?2",
+        parent_id,
+        ("edit", "parent_id"),
+    )
+}
+
+/// Archives several tasks at once.
+///
+/// # Arguments
+///
+/// * `conn: &Connection` - Allows us to access the SQLite db.
+/// * `ids: &[u32]` - The ids of the affected tasks.
+///
+/// # Notes
+///
+/// Runs in a single transaction, so either every task is archived or none are.
+pub fn archive_tasks_bulk(conn: &Connection, ids: &[u32]) -> Result<()> {
+    update_tasks_bulk(
+        conn,
+        ids,
+        "UPDATE tasks SET is_archived=?1 WHERE id=?2",
+        true,
+        ("archive", "is_archived"),
+    )
+}
+
+/// Moves the from_date of every unarchived recurring task forward, as if
+/// `by` had never passed for them.
+///
+/// # Arguments
+///
+/// * `conn: &Connection` - Allows us to access the SQLite db.
+/// * `by: Duration` - How far to move them.
+///
+/// # Notes
+///
+/// Runs in a single transaction, so either every task is moved or none are.
+pub fn shift_recurring_from_dates(conn: &Connection, by: Duration) -> Result<(), DbError> {
+    let recurring: Vec<Task> = read_all_tasks(conn)?
+        .into_iter()
+        .filter(|task| !task.is_archived && task.repeat_interval.is_some())
+        .collect();
+
+    let tx = conn.unchecked_transaction()?;
+    {
+        let mut stmt = tx.prepare("UPDATE tasks SET from_date=?1 WHERE id=?2")?;
+        for task in &recurring {
+            stmt.execute(params![task.from_date + by, task.id])?;
+            log_audit(&tx, task.id, "reset", "from_date");
+        }
+    }
+    Ok(tx.commit()?)
+}
+
+/// Moves a task to a new position within its folder.
+///
+/// # Arguments
+///
+/// * `conn: &Connection` - Allows us to access the SQLite db.
+/// * `id: u32` - The id for the task to move.
+/// * `new_position: usize` - Where it should sit among the folder's unarchived
+///   tasks, starting from 0. Positions past the end move it to the end.
+///
+/// # Notes
+///
+/// Renumbers every unarchived task in the folder inside one transaction, so
+/// the order never ends up half applied. Only Iterator folders use it.
+pub fn reorder_task(conn: &Connection, id: u32, new_position: usize) -> Result<(), DbError> {
+    let parent_id: u32 = conn.query_row("SELECT parent_id FROM tasks WHERE id=?", [id], |row| {
+        row.get(0)
+    })?;
+
+    let mut ids: Vec<u32> = read_tasks_in_order(conn, parent_id)?
+        .iter()
+        .map(|task| task.id)
+        .filter(|&other| other != id)
+        .collect();
+    ids.insert(new_position.min(ids.len()), id);
+
+    let tx = conn.unchecked_transaction()?;
+    {
+        let mut stmt = tx.prepare("UPDATE tasks SET sort_order=?1 WHERE id=?2")?;
+        for (position, task_id) in ids.iter().enumerate() {
+            stmt.execute(params![position as u32, task_id])?;
+        }
+    }
+    log_audit(&tx, id, "edit", "sort_order");
+    Ok(tx.commit()?)
+}
+
+/// Runs `sql` once for each of `ids` inside a transaction. `sql` should take
+/// `value` as ?1 and the task id as ?2. `audit` is the action and fields
+/// recorded for each task.
+fn update_tasks_bulk(
+    conn: &Connection,
+    ids: &[u32],
+    sql: &str,
+    value: impl rusqlite::ToSql,
+    audit: (&str, &str),
+) -> Result<()> {
+    // Everything else shares a plain &Connection, so we can't borrow it
+    // mutably for a checked transaction
+    let tx = conn.unchecked_transaction()?;
+    {
+        let mut stmt = tx.prepare(sql)?;
+        for id in ids {
+            stmt.execute(params![value, id])?;
+            log_audit(&tx, *id, audit.0, audit.1);
+        }
+    }
+    tx.commit()
+}
+
+/// Records a task as complete: counts it as selected, logs when it was done,
+/// pays out its bounty, then resets or archives it.
+///
+/// # Arguments
+///
+/// * `conn: &Connection` - Allows us to access the SQLite db.
+/// * `task: &Task` - The completed task.
+/// * `paid: Option<f64>` - The bounty to record, or None while finance is
+///   disabled.
+/// * `now: DateTime<Utc>` - When it was completed.
+///
+/// # Notes
+///
+/// Runs in a single transaction, so a bounty is never paid for a task that
+/// wasn't completed, or the other way around. Recurring tasks have their
+/// from_date reset, and one-offs are archived, or deleted if the
+/// completed_oneoff_policy setting is 1.
+pub fn complete_task(
+    conn: &Connection,
+    task: &Task,
+    paid: Option<f64>,
+    now: DateTime<Utc>,
+) -> Result<(), DbError> {
+    let tx = conn.unchecked_transaction()?;
+
+    increment_times_selected(&tx, task.id)?;
+    record_completion(&tx, task.id, now)?;
+    increment_statistic(&tx, "total_tasks_completed")?;
+    if let Some(paid) = paid {
+        add_payout(&tx, task.id, paid, now)?;
+    }
+    if task.in_progress {
+        set_in_progress(&tx, task.id, false)?;
+    }
+    let from_date = task.next_from_date(now);
+
+    // The rest of a Selector's tasks come around again with this one, and an
+    // Iterator's once its last task is done. This happens before a one-off
+    // may be deleted below, while the task can still be marked done
+    match read_folder_styles(&tx)?.get(&task.parent_id) {
+        Some(Style::Selector) => reset_folder_from_dates(&tx, task.parent_id, from_date)?,
+        Some(Style::Iterator) => {
+            mark_cycle_done(&tx, task.id)?;
+            if next_iterator_task(&tx, task.parent_id)?.is_none() {
+                reset_folder_from_dates(&tx, task.parent_id, from_date)?;
+                restart_cycle(&tx, task.parent_id)?;
+            }
+        }
+        _ => {}
+    }
+
+    if task.repeat_interval.is_some() {
+        reset_from_date(&tx, task.id, from_date)?;
+    } else if matches!(
+        read_setting_u32(&tx, "completed_oneoff_policy"),
+        Ok(Some(1))
+    ) {
+        delete_task_by_id(&tx, task.id)?;
+    } else {
+        archive_task(&tx, task.id)?;
+    }
+
+    Ok(tx.commit()?)
+}
+
+pub fn archive_task(conn: &Connection, id: u32) -> Result<(), DbError> {
+    println!("Archiving task by id {}", &id);
+
+    let updated = conn.execute("UPDATE tasks SET is_archived=1 WHERE id=?", params![id])?;
+    check_found(id, updated)?;
+
+    log_audit(conn, id, "archive", "is_archived");
+    Ok(())
+}
+
+/// Brings an archived task back into the active set, as if it had just been
+/// added.
+///
+/// # Arguments
+///
+/// * `conn: &Connection` - Allows us to access the SQLite db.
+/// * `id: u32` - The id for the affected task.
+///
+/// # Notes
+///
+/// Any bounty paid when it was completed is left alone. See
+/// `reverse_payout()`.
+pub fn unarchive_task(conn: &Connection, id: u32) -> Result<(), DbError> {
+    let updated = conn.execute(
+        "UPDATE tasks SET is_archived=0, from_date=? WHERE id=?",
+        params![Utc::now(), id],
+    )?;
+    check_found(id, updated)?;
+
+    log_audit(conn, id, "restore", "is_archived");
+    Ok(())
+}
+
+/// Removes a task from the tasks table entirely. Completed tasks are
+/// archived instead unless the user asks otherwise, so this is mostly for
+/// tasks added by mistake.
+///
+/// # Arguments
+///
+/// * `conn: &Connection` - Allows us to access the SQLite db.
+/// * `id: u32` - The id for the task to delete.
+///
+/// # Returns
+///
+/// How many tasks were deleted, which is 0 if no task had that id.
+///
+/// # Notes
+///
+/// Payouts keep the id of the task they were for, so completion stats still
+/// count them.
+pub fn delete_task_by_id(conn: &Connection, id: u32) -> Result<usize> {
+    let deleted = conn.execute("DELETE FROM tasks WHERE id=?1", params![id])?;
+
+    if deleted > 0 {
+        log_audit(conn, id, "delete", "all");
+    }
+    Ok(deleted)
+}
+
+#[cfg(test)]
+mod tests {
     use std::collections::HashMap;
 
-    use super::*;
-    use chrono::TimeZone;
-    use rusqlite::Result;
+    use super::*;
+    use crate::fixtures;
+    use chrono::TimeZone;
+    use rusqlite::Result;
+
+    #[test]
+    fn test_init_tables() {
+        let conn = Connection::open_in_memory().unwrap();
+        init_tables(&conn);
+
+        // Verify table creation
+        let mut stmt = conn
+            .prepare("SELECT name FROM sqlite_master WHERE type='table'")
+            .unwrap();
+        let res_tables: Result<Vec<String>> =
+            stmt.query_map([], |row| row.get(0)).unwrap().collect();
+
+        let tables = res_tables.unwrap();
+
+        assert!(tables.contains(&"tasks".to_string()));
+        assert!(tables.contains(&"folders".to_string()));
+        assert!(tables.contains(&"transactions".to_string()));
+        assert!(tables.contains(&"settings".to_string()));
+        assert!(!tables.contains(&"does_not_exist".to_string()));
+
+        // Verify the initial folder insertion
+        let mut stmt = conn
+            .prepare("SELECT name FROM folders WHERE id = '1'")
+            .unwrap();
+        let folder_exists: bool = stmt.query_row((), |_| Ok(true)).is_ok();
+        assert!(folder_exists, "The initial folder should be inserted.");
+    }
+
+    // Setup function to create an in-memory database and initialize the tasks table
+    fn setup_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        run_migrations(&conn).unwrap();
+        init_folders(&conn);
+        conn
+    }
+
+    fn as_all_task_types(key_stub: String, input_task: Task) -> HashMap<String, Task> {
+        let one_off = Task {
+            due_date: None,
+            lead_days: None,
+            repeat_interval: None,
+            ..input_task.clone()
+        };
+        let due = Task {
+            due_date: Some(Utc.timestamp_opt(1234567890, 0).unwrap()),
+            lead_days: Some(3),
+            repeat_interval: None,
+            ..input_task.clone()
+        };
+        let repeat = Task {
+            due_date: None,
+            lead_days: None,
+            repeat_interval: Some(7),
+            ..input_task.clone()
+        };
+
+        let mut tasks = HashMap::new();
+        tasks.insert(key_stub.clone() + "_one_off", one_off);
+        tasks.insert(key_stub.clone() + "_due", due);
+        tasks.insert(key_stub.clone() + "_repeat", repeat);
+
+        tasks
+    }
+
+    // Generate training tasks
+    fn generate_training_tasks() -> HashMap<String, Task> {
+        let mut tasks = HashMap::new();
+
+        let all_fields_full = Task {
+            id: 0, // This will be ignored by add_task().unwrap()
+            parent_id: 1,
+            is_archived: false,
+            summary: "Test task".into(),
+            description: Some("Test description".into()),
+            average_duration: Some(Duration::seconds(3600)),
+            bounty_modifier: 1.0,
+            due_date: Some(Utc.timestamp_opt(1234567890, 0).unwrap()),
+            from_date: Utc.timestamp_opt(1234567890, 0).unwrap(),
+            lead_days: Some(3),
+            priority: Priority::P1,
+            repeat_interval: Some(7),
+            repeat_unit: RepeatUnit::Hours,
+            times_selected: 5,
+            times_shown: 10,
+            created_at: Utc.timestamp_opt(1234567890, 0).unwrap(),
+            in_progress: false,
+            recurrence_anchor: RecurrenceAnchor::Schedule,
+            boost_factor: 1.5,
+            boost_until: Some(Utc.timestamp_opt(1234567890, 0).unwrap()),
+        };
+        tasks.insert(String::from("all fields full"), all_fields_full.clone());
+
+        tasks.insert(
+            String::from("all_optional_fields_empty"),
+            Task {
+                description: None,
+                average_duration: None,
+                due_date: None,
+                lead_days: None,
+                repeat_interval: None,
+                boost_until: None,
+                ..all_fields_full.clone()
+            },
+        );
+
+        tasks.extend(as_all_task_types(
+            String::from("basic"),
+            Task {
+                ..all_fields_full.clone()
+            },
+        ));
+
+        tasks.extend(as_all_task_types(
+            String::from("is_archived_true"),
+            Task {
+                is_archived: true,
+                ..all_fields_full.clone()
+            },
+        ));
+
+        tasks.extend(as_all_task_types(
+            String::from("priority_0"),
+            Task {
+                priority: Priority::P0,
+                ..all_fields_full.clone()
+            },
+        ));
+        tasks.extend(as_all_task_types(
+            String::from("priority_2"),
+            Task {
+                priority: Priority::P2,
+                ..all_fields_full.clone()
+            },
+        ));
+        tasks.extend(as_all_task_types(
+            String::from("priority_3"),
+            Task {
+                priority: Priority::P3,
+                ..all_fields_full.clone()
+            },
+        ));
+
+        tasks.extend(as_all_task_types(
+            String::from("priority_neutral"),
+            Task {
+                priority: Priority::Neutral,
+                ..all_fields_full.clone()
+            },
+        ));
+
+        tasks.extend(as_all_task_types(
+            String::from("bounty_mod_0"),
+            Task {
+                bounty_modifier: 0.0,
+                ..all_fields_full.clone()
+            },
+        ));
+        tasks.extend(as_all_task_types(
+            String::from("bounty_mod_negative"),
+            Task {
+                bounty_modifier: -1.0,
+                ..all_fields_full.clone()
+            },
+        ));
+        tasks.extend(as_all_task_types(
+            String::from("bounty_mod_less_than_1"),
+            Task {
+                bounty_modifier: 0.3,
+                ..all_fields_full.clone()
+            },
+        ));
+        tasks.extend(as_all_task_types(
+            String::from("bounty_mod_more_than_1"),
+            Task {
+                bounty_modifier: 1.7,
+                ..all_fields_full.clone()
+            },
+        ));
+        tasks.extend(as_all_task_types(
+            String::from("bounty_mod_more_than_2"),
+            Task {
+                bounty_modifier: 5.6,
+                ..all_fields_full.clone()
+            },
+        ));
+
+        tasks
+    }
+
+    #[test]
+    fn test_add_task() {
+        let conn = setup_db();
+
+        let tasks_input = generate_training_tasks();
+
+        for (_, task) in tasks_input.clone() {
+            add_task(&conn, task).unwrap();
+        }
+
+        // Verify that the task was inserted correctly
+        let mut stmt = conn.prepare("SELECT * FROM tasks").unwrap();
+        let tasks_output = stmt.query_map((), |_| Ok(())).unwrap();
+
+        assert_eq!(tasks_output.count(), tasks_input.len());
+    }
+
+    #[test]
+    fn test_foreign_keys_are_enforced() {
+        let conn = fixtures::setup();
+        // The General folder and its placeholders are seeded in order
+        assert_eq!(read_all_folders_sorted(&conn).unwrap().len(), 4);
+
+        let orphan = fixtures::task(999, "Nowhere to go");
+        assert!(matches!(
+            add_task(&conn, orphan),
+            Err(DbError::Sqlite(Error::SqliteFailure(e, _)))
+                if e.code == rusqlite::ErrorCode::ConstraintViolation
+        ));
+        assert!(add_task(&conn, fixtures::task(1, "Filed under General")).is_ok());
+    }
+
+    #[test]
+    fn test_read_setting_u32() {
+        let conn = Connection::open_in_memory().unwrap();
+        create_settings(&conn).unwrap();
+        init_settings(&conn);
+
+        assert_eq!(
+            read_setting_u32(&conn, "description_preview_length").unwrap(),
+            Some(60)
+        );
+        assert_eq!(read_setting_u32(&conn, "does_not_exist").unwrap(), None);
+    }
+
+    #[test]
+    fn test_setting_or_default() {
+        let conn = Connection::open_in_memory().unwrap();
+        create_settings(&conn).unwrap();
+        init_settings(&conn);
+
+        update_setting(&conn, "tasks_per_view", 9).unwrap();
+        assert_eq!(setting_or_default(&conn, "tasks_per_view"), 9);
+
+        // Missing and unparseable values both fall back to the default
+        conn.execute("DELETE FROM settings WHERE key = 'stale_task_days'", ())
+            .unwrap();
+        assert_eq!(setting_or_default(&conn, "stale_task_days"), 60);
+        conn.execute(
+            "UPDATE settings SET value = 'lots' WHERE key = 'max_lead_days'",
+            (),
+        )
+        .unwrap();
+        assert_eq!(setting_or_default(&conn, "max_lead_days"), 365);
+        assert_eq!(setting_or_default(&conn, "does_not_exist"), 0);
+    }
+
+    #[test]
+    fn test_priority_round_trip() {
+        let conn = setup_db();
+
+        for priority in [
+            Priority::Neutral,
+            Priority::P0,
+            Priority::P1,
+            Priority::P2,
+            Priority::P3,
+        ] {
+            add_task(
+                &conn,
+                Task {
+                    priority: priority.clone(),
+                    ..fixtures::task(1, "Round trip")
+                },
+            )
+            .unwrap();
+            let task = read_all_tasks(&conn).unwrap().pop().unwrap();
+
+            assert_eq!(task.priority, priority);
+        }
+    }
+
+    #[test]
+    fn test_bucket_by_day_uses_timezone() {
+        let today = NaiveDate::from_ymd_opt(2024, 3, 10).unwrap();
+        let dates = vec![
+            // 23:30 on the 9th in UTC-5, but already the 10th in UTC
+            Utc.with_ymd_and_hms(2024, 3, 10, 4, 30, 0).unwrap(),
+            Utc.with_ymd_and_hms(2024, 3, 10, 18, 0, 0).unwrap(),
+            Utc.with_ymd_and_hms(2024, 3, 10, 19, 0, 0).unwrap(),
+        ];
+
+        let utc = bucket_by_day(&dates, &Utc, today, 3);
+        assert_eq!(
+            utc,
+            vec![
+                (today - Duration::days(2), 0),
+                (today - Duration::days(1), 0),
+                (today, 3),
+            ]
+        );
+
+        let eastern = chrono::FixedOffset::west_opt(5 * 3600).unwrap();
+        let local = bucket_by_day(&dates, &eastern, today, 3);
+        assert_eq!(local[1], (today - Duration::days(1), 1));
+        assert_eq!(local[2], (today, 2));
+    }
+
+    #[test]
+    fn test_update_setting() {
+        let conn = Connection::open_in_memory().unwrap();
+        create_settings(&conn).unwrap();
+        init_settings(&conn);
+
+        update_setting(&conn, "finance_enabled", 0).unwrap();
+        update_setting(&conn, "brand_new_setting", 7).unwrap();
+
+        assert_eq!(read_setting_u32(&conn, "finance_enabled").unwrap(), Some(0));
+        assert_eq!(
+            read_setting_u32(&conn, "brand_new_setting").unwrap(),
+            Some(7)
+        );
+    }
+
+    #[test]
+    fn test_read_stale_tasks() {
+        let conn = fixtures::setup();
+        let long_ago = Utc::now() - Duration::days(90);
+
+        let stale = fixtures::insert_task(
+            &conn,
+            Task {
+                created_at: long_ago,
+                ..fixtures::task(1, "Stale")
+            },
+        );
+        // Each of these is old, but shouldn't be considered stale
+        for task in [
+            Task {
+                times_selected: 1,
+                ..fixtures::task(1, "Selected before")
+            },
+            Task {
+                repeat_interval: Some(7),
+                ..fixtures::task(1, "Recurring")
+            },
+            Task {
+                is_archived: true,
+                ..fixtures::task(1, "Archived")
+            },
+        ] {
+            fixtures::insert_task(
+                &conn,
+                Task {
+                    created_at: long_ago,
+                    ..task
+                },
+            );
+        }
+        fixtures::insert_task(&conn, fixtures::task(1, "Fresh"));
+
+        let ids: Vec<u32> = read_stale_tasks(&conn, 60)
+            .unwrap()
+            .iter()
+            .map(|t| t.id)
+            .collect();
+        assert_eq!(ids, vec![stale]);
+    }
+
+    #[test]
+    fn test_write_statistic() {
+        let conn = fixtures::setup();
+
+        assert_eq!(read_statistic(&conn, "baseline_bounty").unwrap(), None);
+        assert_eq!(read_statistic(&conn, "does_not_exist").unwrap(), None);
+
+        write_statistic(&conn, "baseline_bounty", "1.25").unwrap();
+        write_statistic(&conn, "brand_new_statistic", "x").unwrap();
+
+        assert_eq!(
+            read_statistic(&conn, "baseline_bounty").unwrap(),
+            Some("1.25".to_string())
+        );
+        assert_eq!(
+            read_statistic(&conn, "brand_new_statistic").unwrap(),
+            Some("x".to_string())
+        );
+    }
+
+    #[test]
+    fn test_reset_from_date_keeps_created_at() {
+        let conn = fixtures::setup();
+        let created_at = Utc::now() - Duration::days(30);
+        let id = fixtures::insert_task(
+            &conn,
+            Task {
+                from_date: created_at,
+                created_at,
+                ..fixtures::task(1, "Recurring")
+            },
+        );
+
+        reset_from_date(&conn, id, Utc::now()).unwrap();
+
+        let task = fixtures::read_task(&conn, id);
+        assert!(task.from_date > created_at);
+        assert_eq!(task.created_at, created_at);
+    }
+
+    #[test]
+    fn test_repeat_units_migration() {
+        let conn = Connection::open_in_memory().unwrap();
+        base_schema(&conn).unwrap();
+        init_folders(&conn);
+        conn.pragma_update(None, "user_version", 1).unwrap();
+        conn.execute(
+            "INSERT INTO tasks (
+                parent_id, is_archived, summary, bounty_modifier, from_date, priority,
+                repeat_interval, times_selected, times_shown, created_at
+            ) VALUES (1, 0, 'Weekly', 0.0, ?1, 1, 7, 0, 0, ?1)",
+            params![Utc::now()],
+        )
+        .unwrap();
+
+        run_migrations(&conn).unwrap();
+        assert_eq!(
+            read_all_tasks(&conn).unwrap()[0].repeat_unit,
+            RepeatUnit::Days
+        );
+
+        let id = add_task(
+            &conn,
+            Task {
+                repeat_interval: Some(4),
+                repeat_unit: RepeatUnit::Hours,
+                ..fixtures::task(1, "Stretch")
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            fixtures::read_task(&conn, id).repeat_unit,
+            RepeatUnit::Hours
+        );
+    }
+
+    #[test]
+    fn test_completions_count_towards_total() {
+        let (conn, seed) = fixtures::seeded();
+        let total = |conn: &Connection| read_statistic(conn, "total_tasks_completed").unwrap();
+        assert_eq!(total(&conn).as_deref(), Some("0"));
+
+        for id in [seed.tasks["laundry"], seed.tasks["dishes"]] {
+            complete_task(&conn, &fixtures::read_task(&conn, id), None, Utc::now()).unwrap();
+        }
+        assert_eq!(total(&conn).as_deref(), Some("2"));
+
+        // A missing statistic starts over from 1
+        conn.execute(
+            "DELETE FROM statistics WHERE key = 'total_tasks_completed'",
+            (),
+        )
+        .unwrap();
+        increment_statistic(&conn, "total_tasks_completed").unwrap();
+        assert_eq!(total(&conn).as_deref(), Some("1"));
+    }
+
+    #[test]
+    fn test_backfill_completions_migration() {
+        let conn = Connection::open_in_memory().unwrap();
+        base_schema(&conn).unwrap();
+        init_folders(&conn);
+        conn.pragma_update(None, "user_version", 1).unwrap();
+        let paid_at = Utc::now() - Duration::days(2);
+        conn.execute(
+            "INSERT INTO transactions (date, funds_added, task_id) VALUES (?1, 1.5, 7), (?1, 5.0, NULL)",
+            params![paid_at],
+        )
+        .unwrap();
+
+        run_migrations(&conn).unwrap();
+        let backfilled: Vec<(u32, DateTime<Utc>)> = conn
+            .prepare("SELECT task_id, completed_at FROM completions")
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(backfilled, vec![(7, paid_at)]);
+    }
+
+    #[test]
+    fn test_read_completions_between() {
+        let (conn, seed) = fixtures::seeded();
+        let laundry = fixtures::read_task(&conn, seed.tasks["laundry"]);
+        let dishes = fixtures::read_task(&conn, seed.tasks["dishes"]);
+        let monday = Utc.with_ymd_and_hms(2024, 6, 3, 9, 0, 0).unwrap();
+
+        complete_task(&conn, &laundry, None, monday).unwrap();
+        complete_task(&conn, &dishes, None, monday + Duration::days(2)).unwrap();
+        // Recurring tasks are logged every time
+        complete_task(&conn, &laundry, None, monday + Duration::days(7)).unwrap();
+
+        let completed = |start, end| -> Vec<(u32, DateTime<Utc>)> {
+            read_completions_between(&conn, start, end)
+                .unwrap()
+                .iter()
+                .map(|(task, completed_at)| (task.id, *completed_at))
+                .collect()
+        };
+        assert_eq!(
+            completed(monday, monday + Duration::days(7)),
+            vec![
+                (laundry.id, monday),
+                (dishes.id, monday + Duration::days(2))
+            ]
+        );
+        assert_eq!(
+            completed(monday + Duration::days(1), monday + Duration::days(8))
+                .iter()
+                .map(|(id, _)| *id)
+                .collect::<Vec<u32>>(),
+            vec![dishes.id, laundry.id]
+        );
+        assert!(completed(monday - Duration::days(7), monday).is_empty());
+    }
+
+    #[test]
+    fn test_run_migrations() {
+        let conn = Connection::open_in_memory().unwrap();
+        // A db from before versioning, which has no schema version
+        conn.execute(
+            "CREATE TABLE transactions (
+                id INTEGER PRIMARY KEY,
+                date INTEGER NOT NULL,
+                funds_added INTEGER,
+                funds_subtracted INTEGER
+            )",
+            (),
+        )
+        .unwrap();
+        add_transaction(&conn, 5.0).unwrap();
+        assert_eq!(schema_version(&conn).unwrap(), 0);
+
+        run_migrations(&conn).unwrap();
+        assert_eq!(schema_version(&conn).unwrap(), MIGRATIONS.len() as u32);
+        add_payout(&conn, 1, 2.0, Utc::now()).unwrap();
+        assert_eq!(funds_added_this_month(&conn).unwrap(), 7.0);
+
+        // Already up to date, so nothing runs twice
+        run_migrations(&conn).unwrap();
+        assert_eq!(schema_version(&conn).unwrap(), MIGRATIONS.len() as u32);
+
+        // A db from a newer release is left alone
+        conn.pragma_update(None, "user_version", 999).unwrap();
+        run_migrations(&conn).unwrap();
+        assert_eq!(schema_version(&conn).unwrap(), 999);
+    }
+
+    #[test]
+    fn test_created_at_migration() {
+        let conn = Connection::open_in_memory().unwrap();
+        // The tasks table as it was before created_at existed
+        conn.execute(
+            "CREATE TABLE tasks (
+                id INTEGER PRIMARY KEY,
+                parent_id INTEGER NOT NULL,
+                is_archived INTEGER NOT NULL,
+                summary TEXT NOT NULL,
+                description TEXT,
+                average_duration TEXT,
+                bounty_modifier REAL NOT NULL,
+                due_date TEXT,
+                from_date TEXT NOT NULL,
+                lead_days INTEGER,
+                priority INTEGER NOT NULL,
+                repeat_interval INTEGER,
+                times_selected INTEGER NOT NULL,
+                times_shown INTEGER NOT NULL
+            )",
+            (),
+        )
+        .unwrap();
+        let from_date = Utc.timestamp_opt(1234567890, 0).unwrap();
+        conn.execute(
+            "INSERT INTO tasks VALUES (1, 1, 0, 'Old task', NULL, NULL, 0.0, NULL, ?1, NULL, 1, NULL, 0, 0)",
+            [from_date],
+        )
+        .unwrap();
+
+        init_tables(&conn);
+        // Running it again shouldn't try to add the column twice
+        init_tables(&conn);
+
+        assert_eq!(fixtures::read_task(&conn, 1).created_at, from_date);
+    }
+
+    #[test]
+    fn test_bulk_updates_only_touch_selected_tasks() {
+        let (conn, seed) = fixtures::seeded();
+        let selected = [seed.tasks["laundry"], seed.tasks["dishes"]];
+        let untouched = seed.tasks["report"];
+
+        set_priority_bulk(&conn, &selected, &Priority::P3).unwrap();
+        move_tasks_bulk(&conn, &selected, seed.folders["office"]).unwrap();
+        archive_tasks_bulk(&conn, &selected).unwrap();
+
+        for id in selected {
+            let task = fixtures::read_task(&conn, id);
+            assert_eq!(task.priority, Priority::P3);
+            assert_eq!(task.parent_id, seed.folders["office"]);
+            assert!(task.is_archived);
+        }
+
+        let report = fixtures::read_task(&conn, untouched);
+        assert_eq!(report.priority, Priority::P2);
+        assert!(!report.is_archived);
+    }
+
+    #[test]
+    fn test_audit_log() {
+        let (conn, seed) = fixtures::seeded();
+        let laundry = seed.tasks["laundry"];
+
+        // Nothing is recorded until auditing is turned on
+        reset_from_date(&conn, laundry, Utc::now()).unwrap();
+        assert!(read_audit(&conn, laundry).unwrap().is_empty());
+
+        update_setting(&conn, "audit_enabled", 1).unwrap();
+        let id = add_task(&conn, fixtures::task(1, "Audited")).unwrap();
+        set_priority_bulk(&conn, &[id, laundry], &Priority::P2).unwrap();
+        increment_times_selected(&conn, id).unwrap();
+        archive_task(&conn, id).unwrap();
+
+        let history: Vec<(String, String)> = read_audit(&conn, id)
+            .unwrap()
+            .into_iter()
+            .map(|entry| (entry.action, entry.fields))
+            .collect();
+        assert_eq!(
+            history,
+            [
+                ("create", "all"),
+                ("edit", "priority"),
+                ("complete", "times_selected"),
+                ("archive", "is_archived"),
+            ]
+            .map(|(action, fields)| (action.to_string(), fields.to_string()))
+        );
+        assert_eq!(read_audit(&conn, laundry).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_instantiate_template() {
+        let (conn, seed) = fixtures::seeded();
+        let report = fixtures::read_task(&conn, seed.tasks["report"]);
+        let template = TaskTemplate {
+            description: Some("Sections: summary, risks".into()),
+            bounty_modifier: 0.5,
+            ..TaskTemplate::from_task(&report, "Report".into())
+        };
+
+        let template_id = save_template(&conn, &template).unwrap();
+        let due_date = Utc::now() + Duration::days(9);
+        let id =
+            instantiate_template(&conn, template_id, "Q3 report".into(), Some(due_date)).unwrap();
+
+        let task = fixtures::read_task(&conn, id);
+        assert_eq!(task.summary, "Q3 report");
+        assert_eq!(task.parent_id, seed.folders["office"]);
+        assert_eq!(task.description, template.description);
+        assert_eq!(task.bounty_modifier, 0.5);
+        assert_eq!(task.priority, Priority::P2);
+        assert_eq!(task.lead_days, Some(3));
+        assert_eq!(task.due_date, Some(due_date));
+        assert_eq!(task.times_selected, 0);
+
+        assert!(matches!(
+            instantiate_template(&conn, template_id + 1, "Nope".into(), None),
+            Err(DbError::Sqlite(Error::QueryReturnedNoRows))
+        ));
+    }
+
+    #[test]
+    fn test_template_recurrence_anchor_round_trips() {
+        let conn = Connection::open_in_memory().unwrap();
+        // A db from just before templates had an anchor
+        for migration in &MIGRATIONS[..5] {
+            migration(&conn).unwrap();
+        }
+        init_folders(&conn);
+        conn.pragma_update(None, "user_version", 5).unwrap();
+        conn.execute(
+            "INSERT INTO task_templates (name, parent_id, bounty_modifier, priority, repeat_interval)
+            VALUES ('Weekly', 1, 0.0, 1, 7)",
+            (),
+        )
+        .unwrap();
+
+        // Templates from before the column are counted from completion
+        run_migrations(&conn).unwrap();
+        assert_eq!(
+            read_templates(&conn).unwrap()[0].recurrence_anchor,
+            RecurrenceAnchor::Completion
+        );
+
+        let task = Task {
+            repeat_interval: Some(7),
+            recurrence_anchor: RecurrenceAnchor::Schedule,
+            ..fixtures::task(1, "Bins")
+        };
+        let template = TaskTemplate::from_task(&task, "Bins".into());
+        let template_id = save_template(&conn, &template).unwrap();
+        let saved = read_templates(&conn)
+            .unwrap()
+            .into_iter()
+            .find(|saved| saved.id == template_id)
+            .unwrap();
+        assert_eq!(
+            saved,
+            TaskTemplate {
+                id: template_id,
+                ..template
+            }
+        );
+
+        let id = instantiate_template(&conn, template_id, "Bins".into(), None).unwrap();
+        assert_eq!(
+            fixtures::read_task(&conn, id).recurrence_anchor,
+            RecurrenceAnchor::Schedule
+        );
+    }
+
+    #[test]
+    fn test_read_all_folders_sorted() {
+        let (conn, seed) = fixtures::seeded();
+        // Two folders with the same path can only be told apart by id
+        let duplicate = fixtures::insert_folder(&conn, None, "Office");
+
+        let folders = read_all_folders_sorted(&conn).unwrap();
+
+        for _ in 0..10 {
+            assert_eq!(read_all_folders_sorted(&conn).unwrap(), folders);
+        }
+        let paths: Vec<&String> = folders.iter().map(|(_, path)| path).collect();
+        let mut expected = paths.clone();
+        expected.sort();
+        assert_eq!(paths, expected);
+
+        let office = folders.iter().position(|f| f.0 == seed.folders["office"]);
+        let duplicate = folders.iter().position(|f| f.0 == duplicate);
+        assert_eq!(office.unwrap() + 1, duplicate.unwrap());
+    }
+
+    #[test]
+    fn test_rename_folder_updates_descendant_paths() {
+        let (conn, seed) = fixtures::seeded();
+        let (home, chores) = (seed.folders["home"], seed.folders["chores"]);
+
+        rename_folder(&conn, home, "House").unwrap();
+
+        let paths = read_all_folders(&conn, None, "".into()).unwrap();
+        assert_eq!(paths[&home], "General::House");
+        assert_eq!(paths[&chores], "General::House::Chores");
+        assert_eq!(paths[&seed.folders["office"]], "Office");
+    }
 
     #[test]
-    fn test_init_tables() {
-        let conn = Connection::open_in_memory().unwrap();
-        init_tables(&conn);
+    fn test_delete_folder_reparents() {
+        let (conn, seed) = fixtures::seeded();
+        let (home, chores) = (seed.folders["home"], seed.folders["chores"]);
+        let office = seed.folders["office"];
 
-        // Verify table creation
-        let mut stmt = conn
-            .prepare("SELECT name FROM sqlite_master WHERE type='table'")
-            .unwrap();
-        let res_tables: Result<Vec<String>> =
-            stmt.query_map([], |row| row.get(0)).unwrap().collect();
+        delete_folder(&conn, home, DeleteMode::Reparent).unwrap();
 
-        let tables = res_tables.unwrap();
+        let paths = read_all_folders(&conn, None, "".into()).unwrap();
+        assert!(!paths.contains_key(&home));
+        assert_eq!(paths[&chores], "General::Chores");
+        assert_eq!(
+            fixtures::read_task(&conn, seed.tasks["water_plants"]).parent_id,
+            1
+        );
+        fixtures::assert_archived(&conn, seed.tasks["water_plants"], false);
 
-        assert!(tables.contains(&"tasks".to_string()));
-        assert!(tables.contains(&"folders".to_string()));
-        assert!(tables.contains(&"transactions".to_string()));
-        assert!(tables.contains(&"settings".to_string()));
-        assert!(!tables.contains(&"does_not_exist".to_string()));
+        // A top level folder's tasks can only go to General
+        delete_folder(&conn, office, DeleteMode::Reparent).unwrap();
+        assert_eq!(
+            fixtures::read_task(&conn, seed.tasks["report"]).parent_id,
+            1
+        );
 
-        // Verify the initial folder insertion
-        let mut stmt = conn
-            .prepare("SELECT name FROM folders WHERE id = '1'")
-            .unwrap();
-        let folder_exists: bool = stmt.query_row((), |_| Ok(true)).is_ok();
-        assert!(folder_exists, "The initial folder should be inserted.");
+        assert!(matches!(
+            delete_folder(&conn, 1, DeleteMode::Reparent),
+            Err(DbError::GeneralFolder)
+        ));
+        assert!(matches!(
+            delete_folder(&conn, home, DeleteMode::Reparent),
+            Err(DbError::FolderNotFound(_))
+        ));
     }
 
-    // Setup function to create an in-memory database and initialize the tasks table
-    fn setup_db() -> Connection {
-        let conn = Connection::open_in_memory().unwrap();
-        init_tasks(&conn);
-        init_folders(&conn);
-        conn
+    #[test]
+    fn test_delete_folder_cascades() {
+        let (conn, seed) = fixtures::seeded();
+        let (home, chores) = (seed.folders["home"], seed.folders["chores"]);
+        let below_home: Vec<u32> = read_all_tasks(&conn)
+            .unwrap()
+            .iter()
+            .filter(|task| task.parent_id == home || task.parent_id == chores)
+            .map(|task| task.id)
+            .collect();
+        assert!(!below_home.is_empty());
+
+        // Nothing changes if part of it fails
+        conn.execute_batch(
+            "CREATE TRIGGER no_delete BEFORE DELETE ON folders
+             BEGIN SELECT RAISE(ABORT, 'no deleting'); END",
+        )
+        .unwrap();
+        assert!(delete_folder(&conn, home, DeleteMode::Cascade).is_err());
+        for id in &below_home {
+            let task = fixtures::read_task(&conn, *id);
+            assert!(task.parent_id == home || task.parent_id == chores);
+        }
+        fixtures::assert_archived(&conn, seed.tasks["dishes"], false);
+
+        conn.execute_batch("DROP TRIGGER no_delete").unwrap();
+        delete_folder(&conn, home, DeleteMode::Cascade).unwrap();
+
+        let paths = read_all_folders(&conn, None, "".into()).unwrap();
+        assert!(!paths.contains_key(&home) && !paths.contains_key(&chores));
+        for id in &below_home {
+            fixtures::assert_archived(&conn, *id, true);
+            assert_eq!(fixtures::read_task(&conn, *id).parent_id, 1);
+        }
+        fixtures::assert_archived(&conn, seed.tasks["report"], false);
     }
 
-    fn as_all_task_types(key_stub: String, input_task: Task) -> HashMap<String, Task> {
-        let one_off = Task {
-            due_date: None,
-            lead_days: None,
-            repeat_interval: None,
-            ..input_task.clone()
-        };
-        let due = Task {
-            due_date: Some(Utc.timestamp_opt(1234567890, 0).unwrap()),
-            lead_days: Some(3),
-            repeat_interval: None,
-            ..input_task.clone()
-        };
-        let repeat = Task {
-            due_date: None,
-            lead_days: None,
-            repeat_interval: Some(7),
-            ..input_task.clone()
-        };
+    #[test]
+    fn test_read_folders_keeps_style_and_status() {
+        let conn = fixtures::setup();
+        let morning = fixtures::insert_styled_folder(&conn, None, "Morning", Style::Iterator);
+        set_folder_status(&conn, morning, Some(STATUS_ARCHIVED)).unwrap();
 
-        let mut tasks = HashMap::new();
-        tasks.insert(key_stub.clone() + "_one_off", one_off);
-        tasks.insert(key_stub.clone() + "_due", due);
-        tasks.insert(key_stub.clone() + "_repeat", repeat);
+        let folders = read_folders(&conn).unwrap();
+        let morning = folders.iter().find(|folder| folder.id == morning).unwrap();
+        assert!(matches!(morning.style, Style::Iterator));
+        assert_eq!(morning.status, Some(STATUS_ARCHIVED));
+        assert!(folders
+            .iter()
+            .filter(|folder| folder.id != morning.id)
+            .all(|folder| matches!(folder.style, Style::Directory) && folder.status.is_none()));
+    }
 
-        tasks
+    #[test]
+    fn test_read_all_folders_handles_deep_trees() {
+        let conn = fixtures::setup();
+        let mut parent_id = None;
+        for depth in 0..1000 {
+            parent_id = Some(fixtures::insert_folder(
+                &conn,
+                parent_id,
+                &format!("F{depth}"),
+            ));
+        }
+
+        let folders = read_all_folders(&conn, None, "".into()).unwrap();
+        let expected: Vec<String> = (0..1000).map(|depth| format!("F{depth}")).collect();
+        assert_eq!(folders[&parent_id.unwrap()], expected.join("::"));
+        assert_eq!(folders[&1], "General");
+        assert_eq!(folders[&2], "General::sub-folder");
     }
 
-    // Generate training tasks
-    fn generate_training_tasks() -> HashMap<String, Task> {
-        let mut tasks = HashMap::new();
+    #[test]
+    fn test_folder_cycle_is_bounded() {
+        let conn = fixtures::setup();
+        let a = fixtures::insert_folder(&conn, None, "A");
+        let b = fixtures::insert_folder(&conn, Some(a), "B");
+        let c = fixtures::insert_folder(&conn, Some(b), "C");
+        // A -> B -> C -> A, so none of them is reachable from the top level
+        conn.execute("UPDATE folders SET parent_id=? WHERE id=?", params![c, a])
+            .unwrap();
 
-        let all_fields_full = Task {
-            id: 0, // This will be ignored by add_task()
-            parent_id: 1,
-            is_archived: false,
-            summary: "Test task".into(),
-            description: Some("Test description".into()),
-            average_duration: Some(Duration::seconds(3600)),
-            bounty_modifier: 1.0,
-            due_date: Some(Utc.timestamp_opt(1234567890, 0).unwrap()),
-            from_date: Utc.timestamp_opt(1234567890, 0).unwrap(),
-            lead_days: Some(3),
-            priority: Priority::P1,
-            repeat_interval: Some(7),
-            times_selected: 5,
-            times_shown: 10,
+        let folders = read_all_folders(&conn, Some(a), "A".into()).unwrap();
+        let mut ids: Vec<&u32> = folders.keys().collect();
+        ids.sort();
+        assert_eq!(ids, vec![&b, &c]);
+        assert!(read_all_folders_sorted(&conn)
+            .unwrap()
+            .iter()
+            .all(|(id, _)| ![a, b, c].contains(id)));
+
+        let mut descendants = get_descendant_ids(&conn, a).unwrap();
+        descendants.sort();
+        assert_eq!(descendants, vec![b, c]);
+
+        // A folder that's its own parent
+        let d = fixtures::insert_folder(&conn, None, "D");
+        conn.execute("UPDATE folders SET parent_id=? WHERE id=?", params![d, d])
+            .unwrap();
+        assert!(read_all_folders(&conn, Some(d), "D".into())
+            .unwrap()
+            .is_empty());
+        assert!(get_descendant_ids(&conn, d).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_reorder_iterator_tasks() {
+        let conn = fixtures::setup();
+        let folder = Folder {
+            id: 0,
+            parent_id: None,
+            name: "Morning".into(),
+            style: Style::Iterator,
+            status: None,
         };
-        tasks.insert(String::from("all fields full"), all_fields_full.clone());
+        add_folder(&conn, &folder).unwrap();
+        let folder_id = conn.last_insert_rowid() as u32;
+        let stretch = fixtures::insert_task(&conn, fixtures::task(folder_id, "Stretch"));
+        let shower = fixtures::insert_task(&conn, fixtures::task(folder_id, "Shower"));
+        let coffee = fixtures::insert_task(&conn, fixtures::task(folder_id, "Make coffee"));
+        let order = |conn: &Connection| -> Vec<u32> {
+            read_tasks_in_order(conn, folder_id)
+                .unwrap()
+                .iter()
+                .map(|task| task.id)
+                .collect()
+        };
+        assert_eq!(order(&conn), vec![stretch, shower, coffee]);
+        assert_eq!(
+            next_iterator_task(&conn, folder_id).unwrap().unwrap().id,
+            stretch
+        );
 
-        tasks.insert(
-            String::from("all_optional_fields_empty"),
-            Task {
-                description: None,
-                average_duration: None,
-                due_date: None,
-                lead_days: None,
-                repeat_interval: None,
-                ..all_fields_full.clone()
-            },
+        reorder_task(&conn, coffee, 0).unwrap();
+        assert_eq!(order(&conn), vec![coffee, stretch, shower]);
+        assert_eq!(
+            next_iterator_task(&conn, folder_id).unwrap().unwrap().id,
+            coffee
         );
 
-        tasks.extend(as_all_task_types(
-            String::from("basic"),
-            Task {
-                ..all_fields_full.clone()
-            },
-        ));
+        // Positions past the end move the task to the end
+        reorder_task(&conn, coffee, 10).unwrap();
+        assert_eq!(order(&conn), vec![stretch, shower, coffee]);
 
-        tasks.extend(as_all_task_types(
-            String::from("is_archived_true"),
-            Task {
-                is_archived: true,
-                ..all_fields_full.clone()
-            },
-        ));
+        // New and moved tasks join the end
+        let walk = fixtures::insert_task(&conn, fixtures::task(folder_id, "Walk"));
+        let read = fixtures::insert_task(&conn, fixtures::task(1, "Read"));
+        move_tasks_bulk(&conn, &[read], folder_id).unwrap();
+        assert_eq!(order(&conn), vec![stretch, shower, coffee, walk, read]);
+    }
 
-        tasks.extend(as_all_task_types(
-            String::from("priority_0"),
-            Task {
-                priority: Priority::P0,
-                ..all_fields_full.clone()
-            },
-        ));
-        tasks.extend(as_all_task_types(
-            String::from("priority_2"),
-            Task {
-                priority: Priority::P2,
-                ..all_fields_full.clone()
-            },
-        ));
-        tasks.extend(as_all_task_types(
-            String::from("priority_3"),
-            Task {
-                priority: Priority::P3,
-                ..all_fields_full.clone()
-            },
-        ));
+    #[test]
+    fn test_delete_task_by_id() {
+        let (conn, seed) = fixtures::seeded();
+        let dishes = seed.tasks["dishes"];
 
-        tasks.extend(as_all_task_types(
-            String::from("bounty_mod_0"),
-            Task {
-                bounty_modifier: 0.0,
-                ..all_fields_full.clone()
-            },
-        ));
-        tasks.extend(as_all_task_types(
-            String::from("bounty_mod_negative"),
-            Task {
-                bounty_modifier: -1.0,
-                ..all_fields_full.clone()
-            },
+        assert_eq!(delete_task_by_id(&conn, dishes).unwrap(), 1);
+        assert!(read_all_tasks(&conn)
+            .unwrap()
+            .iter()
+            .all(|task| task.id != dishes));
+        assert_eq!(delete_task_by_id(&conn, dishes).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_missing_task_and_setting_errors() {
+        let (conn, seed) = fixtures::seeded();
+        let dishes = seed.tasks["dishes"];
+        delete_task_by_id(&conn, dishes).unwrap();
+
+        assert!(matches!(
+            increment_times_shown(&conn, dishes),
+            Err(DbError::TaskNotFound(id)) if id == dishes
         ));
-        tasks.extend(as_all_task_types(
-            String::from("bounty_mod_less_than_1"),
-            Task {
-                bounty_modifier: 0.3,
-                ..all_fields_full.clone()
-            },
+        assert!(matches!(
+            archive_task(&conn, dishes),
+            Err(DbError::TaskNotFound(id)) if id == dishes
         ));
-        tasks.extend(as_all_task_types(
-            String::from("bounty_mod_more_than_1"),
-            Task {
-                bounty_modifier: 1.7,
-                ..all_fields_full.clone()
-            },
+
+        conn.execute(
+            "DELETE FROM settings WHERE key = 'target_monthly_allowance'",
+            (),
+        )
+        .unwrap();
+        assert!(matches!(
+            read_target_allowance(&conn),
+            Err(DbError::SettingMissing(key)) if key == "target_monthly_allowance"
         ));
-        tasks.extend(as_all_task_types(
-            String::from("bounty_mod_more_than_2"),
+    }
+
+    #[test]
+    fn test_funds_added_this_month() {
+        let conn = fixtures::setup();
+        assert_eq!(funds_added_this_month(&conn).unwrap(), 0.0);
+
+        add_transaction(&conn, 40.0).unwrap();
+        add_transaction(&conn, -15.0).unwrap();
+        add_payout(&conn, 1, 2.5, Utc::now()).unwrap();
+        reverse_payout(&conn, 1, 2.5).unwrap();
+
+        // Anything from before the 1st isn't counted
+        let month_start = Utc::now().date_naive().with_day(1).unwrap();
+        let last_month = month_start.and_time(Default::default()).and_utc() - Duration::seconds(1);
+        conn.execute(
+            "INSERT INTO transactions (date, funds_added) VALUES (?, ?)",
+            params![last_month, 100.0],
+        )
+        .unwrap();
+
+        assert_eq!(funds_added_this_month(&conn).unwrap(), 40.0);
+    }
+
+    #[test]
+    fn test_increments_add_to_the_stored_count() {
+        let (conn, seed) = fixtures::seeded();
+        let dishes = seed.tasks["dishes"];
+
+        increment_times_shown(&conn, dishes).unwrap();
+        increment_times_shown(&conn, dishes).unwrap();
+        increment_times_selected(&conn, dishes).unwrap();
+        increment_times_selected(&conn, dishes).unwrap();
+
+        let task = fixtures::read_task(&conn, dishes);
+        assert_eq!((task.times_shown, task.times_selected), (2, 2));
+    }
+
+    #[test]
+    fn test_complete_task_is_atomic() {
+        let conn = fixtures::setup();
+        let id = fixtures::insert_task(&conn, fixtures::task(1, "Post the letter"));
+        let task = fixtures::read_task(&conn, id);
+
+        // Make archiving fail after the payout has been written
+        conn.execute_batch(
+            "CREATE TRIGGER no_archive BEFORE UPDATE OF is_archived ON tasks
+             BEGIN SELECT RAISE(ABORT, 'no archiving'); END",
+        )
+        .unwrap();
+        assert!(complete_task(&conn, &task, Some(2.5), Utc::now()).is_err());
+        assert_eq!(fixtures::read_task(&conn, id).times_selected, 0);
+        assert_eq!(funds_added_this_month(&conn).unwrap(), 0.0);
+
+        conn.execute_batch("DROP TRIGGER no_archive").unwrap();
+        complete_task(&conn, &task, Some(2.5), Utc::now()).unwrap();
+        fixtures::assert_archived(&conn, id, true);
+        assert_eq!(fixtures::read_task(&conn, id).times_selected, 1);
+        assert_eq!(funds_added_this_month(&conn).unwrap(), 2.5);
+    }
+
+    #[test]
+    fn test_csv_field() {
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field("a, b"), "\"a, b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+    }
+
+    #[test]
+    fn test_export_tasks_csv() {
+        let (conn, seed) = fixtures::seeded();
+        let due_date = Utc.timestamp_opt(1234567890, 0).unwrap();
+        let id = fixtures::insert_task(
+            &conn,
             Task {
-                bounty_modifier: 5.6,
-                ..all_fields_full.clone()
+                due_date: Some(due_date),
+                lead_days: Some(2),
+                times_shown: 4,
+                ..fixtures::task(seed.folders["chores"], "Mop, then \"dry\"")
             },
-        ));
+        );
+        let path = std::env::temp_dir().join(format!("backlist_export_{}.csv", std::process::id()));
 
-        tasks
+        export_tasks_csv(&conn, &path).unwrap();
+        let contents = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let mut lines = contents.lines();
+        assert_eq!(
+            lines.next(),
+            Some("id,folder,summary,priority,due_date,repeat_interval,times_shown,times_selected,bounty_modifier")
+        );
+        assert_eq!(lines.clone().count(), read_all_tasks(&conn).unwrap().len());
+        let expected = format!(
+            "{id},General::Home::Chores,\"Mop, then \"\"dry\"\"\",P1,{},,4,0,0",
+            due_date.to_rfc3339()
+        );
+        assert!(lines.any(|line| line == expected), "{contents}");
+    }
+
+    fn temp_path(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("backlist_{name}_{}.csv", std::process::id()))
     }
 
     #[test]
-    fn test_add_task() {
-        let conn = setup_db();
+    fn test_key_values_round_trip() {
+        let conn = fixtures::setup();
+        update_setting(&conn, "tasks_per_view", 8).unwrap();
+        update_setting(&conn, "audit_enabled", 1).unwrap();
+        write_statistic(
+            &conn,
+            "stale_prompt_last_shown",
+            "2024-01-02T03:04:05+00:00",
+        )
+        .unwrap();
+        let path = temp_path("round_trip");
+        export_key_values_csv(&conn, &path).unwrap();
 
-        let tasks_input = generate_training_tasks();
+        let fresh = fixtures::setup();
+        update_setting(&fresh, "tasks_per_view", 3).unwrap();
+        let imported = plan_key_values_import(&fresh, &path, &ImportMode::Merge)
+            .unwrap()
+            .apply(&fresh)
+            .unwrap();
+        std::fs::remove_file(&path).unwrap();
 
-        for (_, task) in tasks_input.clone() {
-            add_task(&conn, task);
+        assert!(imported > 3);
+        assert_eq!(read_setting_u32(&fresh, "tasks_per_view").unwrap(), Some(8));
+        assert_eq!(read_setting_u32(&fresh, "audit_enabled").unwrap(), Some(1));
+        assert_eq!(
+            read_statistic(&fresh, "stale_prompt_last_shown").unwrap(),
+            Some("2024-01-02T03:04:05+00:00".into())
+        );
+        assert_eq!(read_statistic(&fresh, "baseline_bounty").unwrap(), None);
+        for table in ["settings", "statistics"] {
+            assert_eq!(
+                read_key_values(&fresh, table).unwrap(),
+                read_key_values(&conn, table).unwrap()
+            );
         }
+    }
 
-        // Verify that the task was inserted correctly
-        let mut stmt = conn.prepare("SELECT * FROM tasks").unwrap();
-        let tasks_output = stmt.query_map((), |_| Ok(())).unwrap();
+    #[test]
+    fn test_import_key_values_validates_before_writing() {
+        let conn = fixtures::setup();
+        let path = temp_path("invalid");
+        std::fs::write(
+            &path,
+            "table,key,value\nsettings,tasks_per_view,9\nsettings,audit_enabled,yes\n",
+        )
+        .unwrap();
 
-        assert_eq!(tasks_output.count(), tasks_input.len());
+        let result = plan_key_values_import(&conn, &path, &ImportMode::Merge);
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(matches!(result, Err(ImportError::Invalid { line: 3, .. })));
+        assert_eq!(read_setting_u32(&conn, "tasks_per_view").unwrap(), Some(5));
+    }
+
+    #[test]
+    fn test_import_key_values_overwrite_restores_defaults() {
+        let conn = fixtures::setup();
+        update_setting(&conn, "tasks_per_view", 8).unwrap();
+        write_statistic(&conn, "custom", "1").unwrap();
+        let path = temp_path("overwrite");
+        std::fs::write(&path, "table,key,value\nsettings,audit_enabled,1\n").unwrap();
+
+        plan_key_values_import(&conn, &path, &ImportMode::Overwrite)
+            .unwrap()
+            .apply(&conn)
+            .unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(read_setting_u32(&conn, "audit_enabled").unwrap(), Some(1));
+        assert_eq!(read_setting_u32(&conn, "tasks_per_view").unwrap(), Some(5));
+        assert_eq!(read_statistic(&conn, "custom").unwrap(), None);
+        assert_eq!(
+            read_statistic(&conn, "funds_loaded").unwrap(),
+            Some("400".into())
+        );
+    }
+
+    #[test]
+    fn test_import_plan_is_a_dry_run() {
+        let conn = fixtures::setup();
+        let path = temp_path("plan");
+        std::fs::write(
+            &path,
+            "table,key,value\nsettings,tasks_per_view,9\nsettings,audit_enabled,0\n\nstatistics,custom,1\n",
+        )
+        .unwrap();
+        let before: Vec<_> = ["settings", "statistics"]
+            .iter()
+            .map(|table| read_key_values(&conn, table).unwrap())
+            .collect();
+
+        let merge = plan_key_values_import(&conn, &path, &ImportMode::Merge).unwrap();
+        let overwrite = plan_key_values_import(&conn, &path, &ImportMode::Overwrite).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let after: Vec<_> = ["settings", "statistics"]
+            .iter()
+            .map(|table| read_key_values(&conn, table).unwrap())
+            .collect();
+        assert_eq!(before, after);
+
+        assert_eq!(merge.len(), 3);
+        assert_eq!(merge.new_keys, 1);
+        // audit_enabled is already 0
+        assert_eq!(merge.changed, 1);
+        assert_eq!(merge.removed, 0);
+        assert_eq!(merge.skipped, 1);
+        let total = before.iter().map(Vec::len).sum::<usize>();
+        assert_eq!(overwrite.removed, total - 2);
+
+        merge.apply(&conn).unwrap();
+        assert_eq!(read_setting_u32(&conn, "tasks_per_view").unwrap(), Some(9));
+    }
+
+    #[test]
+    fn test_parse_csv_line() {
+        for field in ["plain", "a, b", "say \"hi\"", ""] {
+            let line = format!("{},{}", csv_field(field), csv_field("x"));
+            assert_eq!(parse_csv_line(&line), Some(vec![field.into(), "x".into()]));
+        }
+        assert_eq!(parse_csv_line("\"open"), None);
     }
 }
@@ -1,7 +1,23 @@
-use crate::db;
-use crate::tasks::Task;
-use chrono::{Duration, Utc};
+use crate::db::{self, DbError};
+use crate::tasks::{Priority, RepeatUnit, Task};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
 use rusqlite::Connection;
+use std::error::Error;
+use std::fmt;
+
+/// Checks whether the user has the bounty/finance system turned on.
+///
+/// # Arguments
+///
+/// * `conn: &Connection` - Allows connection to the db.
+///
+/// # Notes
+///
+/// Defaults to enabled if the `finance_enabled` setting is missing or can't be
+/// read. Any value other than 0 counts as enabled.
+pub fn is_enabled(conn: &Connection) -> bool {
+    !matches!(db::read_setting_u32(conn, "finance_enabled"), Ok(Some(0)))
+}
 
 /// Determines the average number of tasks the user can expect to complete in a
 /// month.
@@ -9,6 +25,7 @@ use rusqlite::Connection;
 /// # Arguments
 ///
 /// * `conn: &Connection` - Allows connection to the db.
+/// * `now: DateTime<Utc>` - Decides which one-time tasks are recent.
 ///
 /// # Returns
 ///
@@ -19,16 +36,27 @@ use rusqlite::Connection;
 /// This includes all recurring tasks weighted by their repeat_interval in
 /// addition to the number of one-time tasks and tasks with due dates created
 /// in the last 30 days.
-fn calc_monthly_tasks(conn: &Connection) -> u32 {
-    let task_list = db::read_all_tasks(conn);
+fn calc_monthly_tasks(conn: &Connection, now: DateTime<Utc>) -> u32 {
+    let task_list = db::read_all_tasks(conn).unwrap_or_else(|e| {
+        eprintln!("Warning: couldn't read tasks to estimate the monthly workload ({e}).");
+        vec![]
+    });
 
     let mut avg_monthly_tasks = 0;
 
     for task in task_list {
         match task.repeat_interval {
-            Some(interval) => avg_monthly_tasks += 30 / interval,
+            Some(interval) if interval > 0 => {
+                let month = match task.repeat_unit {
+                    RepeatUnit::Days => 30,
+                    RepeatUnit::Hours => 30 * 24,
+                };
+                avg_monthly_tasks += month / interval
+            }
+            // A zero interval can't be spread over the month
+            Some(_) => {}
             None => {
-                if task.from_date + Duration::days(3) > <Utc>::now() {
+                if task.from_date + Duration::days(3) > now {
                     avg_monthly_tasks += 1;
                 }
             }
@@ -38,48 +66,366 @@ fn calc_monthly_tasks(conn: &Connection) -> u32 {
     avg_monthly_tasks
 }
 
+/// Matches the target_monthly_allowance default setting.
+pub const DEFAULT_TARGET_ALLOWANCE: u32 = 400;
+
+/// The user's monthly budget, as set in the settings table.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Allowances {
+    /// What the user hopes to earn in a typical month.
+    pub target: u32,
+    /// The most the user may earn in a month. Never below `target`.
+    pub maximum: u32,
+}
+
+/// Defines errors that can occur when changing the monthly allowances.
+#[derive(Debug)]
+pub enum AllowanceError {
+    /// The target would be higher than the maximum.
+    TargetAboveMaximum { target: u32, maximum: u32 },
+    /// The settings couldn't be read or written.
+    Db(rusqlite::Error),
+}
+
+/// Implements display formatting for `AllowanceError`, providing a human-readable description of the error.
+impl fmt::Display for AllowanceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AllowanceError::TargetAboveMaximum { target, maximum } => write!(
+                f,
+                "The target monthly allowance ({}) can't be more than the maximum ({})",
+                target, maximum
+            ),
+            AllowanceError::Db(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+/// Allows `AllowanceError` to integrate with Rust's standard error handling mechanisms.
+impl Error for AllowanceError {}
+
+impl From<rusqlite::Error> for AllowanceError {
+    fn from(e: rusqlite::Error) -> Self {
+        AllowanceError::Db(e)
+    }
+}
+
+/// Checks that a target allowance doesn't exceed the maximum.
+pub fn validate_allowances(target: u32, maximum: u32) -> Result<(), AllowanceError> {
+    if target > maximum {
+        Err(AllowanceError::TargetAboveMaximum { target, maximum })
+    } else {
+        Ok(())
+    }
+}
+
+/// Saves new monthly allowances, rejecting a target above the maximum.
+///
+/// # Arguments
+///
+/// * `conn: &Connection` - Allows connection to the db.
+/// * `target: u32` - The new target_monthly_allowance.
+/// * `maximum: u32` - The new maximum_monthly_allowance.
+pub fn set_allowances(conn: &Connection, target: u32, maximum: u32) -> Result<(), AllowanceError> {
+    validate_allowances(target, maximum)?;
+
+    db::update_setting(conn, "target_monthly_allowance", target)?;
+    db::update_setting(conn, "maximum_monthly_allowance", maximum)?;
+
+    Ok(())
+}
+
+/// Reads the monthly allowances from the settings table.
+///
+/// # Arguments
+///
+/// * `conn: &Connection` - Allows connection to the db.
+///
+/// # Notes
+///
+/// If the target is somehow above the maximum (e.g. the db was edited by
+/// hand), it's clamped to the maximum and a warning is printed. A missing
+/// maximum is treated as no limit. A missing or unreadable target falls back
+/// to `DEFAULT_TARGET_ALLOWANCE`, also with a warning.
+pub fn monthly_allowances(conn: &Connection) -> Allowances {
+    let target = match db::read_target_allowance(conn) {
+        Ok(n) => n,
+        Err(e) => {
+            eprintln!(
+                "Warning: couldn't read the target monthly allowance ({e}). Using {DEFAULT_TARGET_ALLOWANCE}."
+            );
+            DEFAULT_TARGET_ALLOWANCE
+        }
+    };
+    let maximum = match db::read_setting_u32(conn, "maximum_monthly_allowance") {
+        Ok(Some(n)) => n,
+        _ => u32::MAX,
+    };
+
+    if let Err(e) = validate_allowances(target, maximum) {
+        eprintln!("Warning: {e}. Using {maximum} as the target.");
+        return Allowances {
+            target: maximum,
+            maximum,
+        };
+    }
+
+    Allowances { target, maximum }
+}
+
+/// Adds up everything earned since the start of the month `now` falls in.
+/// See `db::funds_added_this_month_at()`.
+fn earned_this_month(conn: &Connection, now: DateTime<Utc>) -> f64 {
+    db::funds_added_this_month_at(conn, now).unwrap_or_else(|e| {
+        eprintln!("Warning: couldn't read this month's earnings ({e}).");
+        0.0
+    })
+}
+
+/// Works out how much of this month's maximum allowance is still to be
+/// earned.
+///
+/// # Arguments
+///
+/// * `conn: &Connection` - Allows connection to the db.
+/// * `now: DateTime<Utc>` - Decides which month is the current one.
+///
+/// # Returns
+///
+/// A fraction from 0.0 (the cap has been reached) to 1.0 (nothing earned
+/// yet).
+pub fn budget_remaining(conn: &Connection, now: DateTime<Utc>) -> f32 {
+    let maximum = monthly_allowances(conn).maximum as f64;
+    if maximum <= 0.0 {
+        return 0.0;
+    }
+
+    (1.0 - earned_this_month(conn, now) / maximum).clamp(0.0, 1.0) as f32
+}
+
 /// Calculate the payout for the average task, before any weighting.
 ///
 /// # Arguments
 ///
 /// * `conn: &Connection` - Allows connection to the db.
+/// * `now: DateTime<Utc>` - See `calc_monthly_tasks()`.
 ///
 /// # Returns
 ///
-/// An `f64` of the expected payout.
-fn base_value(conn: &Connection) -> f64 {
+/// An `f64` of the expected payout. Always finite, even with no tasks.
+fn base_value(conn: &Connection, now: DateTime<Utc>) -> f64 {
     // Determine how many tasks will be completed each month and how much the
     // user hopes to add to their budget.
-    let monthly_tasks = calc_monthly_tasks(conn);
-    // let target_allowance = db::read_settings(conn)[0];
-    let target_allowance: f64;
-    match db::read_target_allowance(conn) {
-        Ok(n) => target_allowance = n as f64,
-        Err(e) => panic!("Error reading target allowance: {e}"),
-    }
+    // A new db may have nothing that counts yet. Treat that as one task, so
+    // the first one pays the whole target rather than infinity
+    let monthly_tasks = calc_monthly_tasks(conn, now).max(1);
+    let target_allowance = monthly_allowances(conn).target as f64;
 
     // Divide the factors
     let result: f64 = target_allowance / (monthly_tasks as f64);
 
     // Round the result to 2 decimal places
-    let base_value = (result * 100.0).round() / 100.0;
+    (result * 100.0).round() / 100.0
+}
+
+/// The factors that multiply together to make a task's bounty.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BountyBreakdown {
+    /// The payout for the average task. See `base_value()`.
+    pub base: f64,
+    /// Rewards tasks that are often shown but rarely selected. See
+    /// `selection_factor()`.
+    pub selection_factor: f64,
+    /// 1.0 plus the task's bounty_modifier, but never below 0.0. A modifier
+    /// of 0.5 pays 50% more and -1.0 pays nothing.
+    pub modifier: f64,
+    /// The range set for the task's priority. See `bounty_limits()`.
+    pub limits: BountyLimits,
+}
+
+impl BountyBreakdown {
+    /// The payout before `limits` are applied.
+    pub fn unclamped(&self) -> f64 {
+        self.base * self.selection_factor * self.modifier
+    }
+
+    /// The final payout.
+    pub fn total(&self) -> f64 {
+        self.limits.clamp(self.unclamped())
+    }
+}
+
+/// The least and most a task of a given priority may pay. None means there's
+/// no limit on that side.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BountyLimits {
+    pub floor: Option<f64>,
+    pub ceiling: Option<f64>,
+}
+
+impl BountyLimits {
+    /// Raises `bounty` to the floor or lowers it to the ceiling, if needed.
+    pub fn clamp(&self, bounty: f64) -> f64 {
+        let bounty = match self.floor {
+            Some(floor) => bounty.max(floor),
+            None => bounty,
+        };
+        match self.ceiling {
+            Some(ceiling) => bounty.min(ceiling),
+            None => bounty,
+        }
+    }
+}
+
+/// Reads the bounty floor and ceiling for `priority` from the settings table.
+///
+/// # Arguments
+///
+/// * `conn: &Connection` - Allows connection to the db.
+/// * `priority: &Priority` - Picks which pair of settings to read.
+///
+/// # Notes
+///
+/// A setting of 0 (the default) means no limit. If the floor is above the
+/// ceiling, a warning is printed and the ceiling is used for both.
+pub fn bounty_limits(conn: &Connection, priority: &Priority) -> BountyLimits {
+    let suffix = match priority {
+        Priority::P0 => "p0",
+        Priority::P1 => "p1",
+        Priority::P2 => "p2",
+        Priority::P3 => "p3",
+        Priority::Neutral => "neutral",
+    };
+    let read_limit = |key: String| match db::read_setting_u32(conn, &key) {
+        Ok(Some(n)) if n > 0 => Some(n as f64),
+        _ => None,
+    };
+
+    let floor = read_limit(format!("bounty_floor_{suffix}"));
+    let ceiling = read_limit(format!("bounty_ceiling_{suffix}"));
+
+    match (floor, ceiling) {
+        (Some(f), Some(c)) if f > c => {
+            eprintln!(
+                "Warning: the {suffix} bounty floor ({f}) is above its ceiling ({c}). Using {c} for both."
+            );
+            BountyLimits {
+                floor: Some(c),
+                ceiling: Some(c),
+            }
+        }
+        _ => BountyLimits { floor, ceiling },
+    }
+}
+
+/// How much the bounty grows for each time a task is shown per time it's been
+/// selected.
+const SKIP_BONUS: f64 = 0.05;
+
+/// The most the selection factor can multiply a bounty by.
+const MAX_SELECTION_FACTOR: f64 = 2.0;
+
+/// Works out how much extra a task pays for being passed over.
+///
+/// # Returns
+///
+/// `1 + SKIP_BONUS * times_shown / (times_selected + 1)`, capped at
+/// `MAX_SELECTION_FACTOR`. A task that's never been shown gets 1.0, and one
+/// shown 20 times without being picked reaches the cap.
+fn selection_factor(task: &Task) -> f64 {
+    let skips = task.times_shown as f64 / (task.times_selected as f64 + 1.0);
+    (1.0 + SKIP_BONUS * skips).min(MAX_SELECTION_FACTOR)
+}
+
+/// Works out each of the factors that make up a task's bounty.
+///
+/// # Arguments
+///
+/// * `conn: &Connection` - Allows connection to the db.
+/// * `task: &Task` - The task being paid out.
+pub fn bounty_breakdown(conn: &Connection, task: &Task) -> BountyBreakdown {
+    bounty_breakdown_at(conn, task, Utc::now())
+}
+
+/// Same as `bounty_breakdown()`, but as if the current time were `now`.
+pub fn bounty_breakdown_at(conn: &Connection, task: &Task, now: DateTime<Utc>) -> BountyBreakdown {
+    BountyBreakdown {
+        base: base_value(conn, now),
+        selection_factor: selection_factor(task),
+        modifier: (1.0 + task.bounty_modifier as f64).max(0.0),
+        limits: bounty_limits(conn, &task.priority),
+    }
+}
+
+/// Calculates an individual payout for each task. See `bounty_breakdown()`
+/// for the factors involved.
+///
+/// # Notes
+///
+/// There's always a base to work from. If the target allowance can't be
+/// read, `DEFAULT_TARGET_ALLOWANCE` is used, and a month with no tasks is
+/// treated as having one. See `base_value()`.
+pub fn adjusted_value(conn: &Connection, task: &Task) -> f64 {
+    adjusted_value_at(conn, task, Utc::now())
+}
+
+/// Same as `adjusted_value()`, but as if the current time were `now`.
+pub fn adjusted_value_at(conn: &Connection, task: &Task, now: DateTime<Utc>) -> f64 {
+    bounty_breakdown_at(conn, task, now).total()
+}
+
+/// Reduces a bounty to whatever is left of this month's maximum allowance.
+///
+/// # Arguments
+///
+/// * `conn: &Connection` - Allows connection to the db.
+/// * `bounty: f64` - The amount the task would pay.
+/// * `now: DateTime<Utc>` - When it's paid, which decides the month.
+///
+/// # Returns
+///
+/// The bounty, or less if paying all of it would take this month's earnings
+/// over the maximum. Never negative.
+pub fn cap_bounty(conn: &Connection, bounty: f64, now: DateTime<Utc>) -> Result<f64, DbError> {
+    let maximum = monthly_allowances(conn).maximum as f64;
+    let left = (maximum - db::funds_added_this_month_at(conn, now)?).max(0.0);
 
-    base_value
+    Ok(bounty.min(left).max(0.0))
 }
 
-/// Will eventually calculate an individual payout for each task based on the
-/// number of times shown vs times selected. For now it just passes through the
-/// base_value of all tasks.
-pub fn adjusted_value(conn: &Connection, _task: &Task) -> f64 {
-    // This whole function is TODO
-    base_value(conn)
+/// How far back `completion_streak()` looks. Streaks longer than this are
+/// reported as this many days.
+const STREAK_LOOKBACK_DAYS: u32 = 366;
+
+/// Counts how many consecutive days the user has completed at least one task.
+///
+/// # Arguments
+///
+/// * `conn: &Connection` - Allows connection to the db.
+///
+/// # Notes
+///
+/// A streak isn't broken until a full day passes without a completion, so if
+/// nothing has been completed yet today the count starts from yesterday.
+pub fn completion_streak(conn: &Connection) -> Result<u32, DbError> {
+    Ok(streak_from_history(&db::completions_by_day(
+        conn,
+        STREAK_LOOKBACK_DAYS,
+    )?))
 }
 
-// pub fn payout(conn: &Connection, task: &Task) {
-//     let bounty = adjusted_value(conn, task);
+/// Counts the run of non-zero days at the end of a per-day history, oldest
+/// first, allowing the final day (today) to be empty.
+fn streak_from_history(history: &[(NaiveDate, u32)]) -> u32 {
+    let mut days = history.iter().rev().peekable();
 
-//     db::add_transaction(conn, bounty as f64);
-// }
+    // Today doesn't break the streak until it's over
+    if let Some((_, 0)) = days.peek() {
+        days.next();
+    }
+
+    days.take_while(|(_, count)| *count > 0).count() as u32
+}
 
 pub fn calc_funds(conn: &Connection) -> f64 {
     let transactions = db::read_transactions(conn);
@@ -87,11 +433,426 @@ pub fn calc_funds(conn: &Connection) -> f64 {
     let mut total_funds = 0.0;
 
     for transaction in transactions {
-        match transaction.1 {
-            Some(v) => total_funds += v,
-            None => total_funds -= transaction.2.unwrap(),
+        match transaction {
+            (_, Some(v), _) => total_funds += v,
+            (_, None, Some(v)) => total_funds -= v,
+            // Neither amount was recorded, so there's nothing to count
+            (_, None, None) => {}
         }
     }
 
     total_funds
 }
+
+/// One row of the transaction history.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LedgerEntry {
+    pub date: DateTime<Utc>,
+    /// Positive for funds added, negative for funds spent.
+    pub amount: f64,
+    /// The funds available once this transaction was made.
+    pub balance: f64,
+}
+
+/// Puts transactions in date order and works out the balance after each.
+///
+/// # Arguments
+///
+/// * `transactions: &[(DateTime<Utc>, Option<f64>, Option<f64>)]` - Rows
+///   from `db::read_transactions()`.
+///
+/// # Notes
+///
+/// Rows with neither amount are skipped. Ties keep their original order.
+pub fn ledger(transactions: &[(DateTime<Utc>, Option<f64>, Option<f64>)]) -> Vec<LedgerEntry> {
+    let mut rows: Vec<(DateTime<Utc>, f64)> = transactions
+        .iter()
+        .filter_map(|transaction| match *transaction {
+            (date, Some(added), _) => Some((date, added)),
+            (date, None, Some(subtracted)) => Some((date, -subtracted)),
+            (_, None, None) => None,
+        })
+        .collect();
+    rows.sort_by_key(|(date, _)| *date);
+
+    let mut balance = 0.0;
+    rows.into_iter()
+        .map(|(date, amount)| {
+            balance += amount;
+            LedgerEntry {
+                date,
+                amount,
+                balance,
+            }
+        })
+        .collect()
+}
+
+/// Writes every transaction as CSV, for use in a budgeting tool.
+///
+/// # Arguments
+///
+/// * `conn: &Connection` - Allows connection to the db.
+///
+/// # Returns
+///
+/// A `date,funds_added,funds_subtracted` header, then one row per
+/// transaction in date order.
+///
+/// # Notes
+///
+/// Dates are written in RFC 3339. Each row only has one amount, so the other
+/// cell is left empty.
+pub fn export_transactions_csv(conn: &Connection) -> String {
+    let mut transactions = db::read_transactions(conn);
+    transactions.sort_by_key(|(date, _, _)| *date);
+
+    let mut csv = String::from("date,funds_added,funds_subtracted\n");
+    for (date, added, subtracted) in transactions {
+        let cell = |amount: Option<f64>| amount.map(|v| v.to_string()).unwrap_or_default();
+        csv.push_str(&format!(
+            "{},{},{}\n",
+            date.to_rfc3339(),
+            cell(added),
+            cell(subtracted)
+        ));
+    }
+
+    csv
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::{Clock, FixedClock};
+    use crate::fixtures;
+    use chrono::{Local, TimeZone};
+    use rusqlite::params;
+
+    /// Records a payout at noon local time, `days_ago` days before today.
+    fn complete_days_ago(conn: &Connection, days_ago: i64) {
+        let day = Local::now().date_naive() - Duration::days(days_ago);
+        let noon = Local
+            .from_local_datetime(&day.and_hms_opt(12, 0, 0).unwrap())
+            .unwrap()
+            .with_timezone(&Utc);
+
+        conn.execute(
+            "INSERT INTO completions (task_id, completed_at) VALUES (1, ?)",
+            params![noon],
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_calc_monthly_tasks() {
+        let conn = fixtures::setup();
+        let clock = FixedClock::new(Utc::now());
+        fixtures::insert_task(
+            &conn,
+            Task {
+                repeat_interval: Some(7),
+                ..fixtures::task(1, "Weekly")
+            },
+        );
+        fixtures::insert_task(
+            &conn,
+            Task {
+                from_date: clock.now(),
+                ..fixtures::task(1, "Just added")
+            },
+        );
+
+        // 30 / 7 rounds down to 4, plus the new one-off
+        assert_eq!(calc_monthly_tasks(&conn, clock.now()), 5);
+
+        // One-offs only count while they're recent
+        clock.advance(Duration::days(3));
+        assert_eq!(calc_monthly_tasks(&conn, clock.now()), 4);
+
+        // A zero interval is ignored rather than dividing by zero
+        fixtures::insert_task(
+            &conn,
+            Task {
+                repeat_interval: Some(0),
+                ..fixtures::task(1, "Broken")
+            },
+        );
+        assert_eq!(calc_monthly_tasks(&conn, clock.now()), 4);
+    }
+
+    #[test]
+    fn test_base_value_with_no_monthly_tasks() {
+        let conn = fixtures::setup();
+        assert_eq!(calc_monthly_tasks(&conn, Utc::now()), 0);
+
+        let base = base_value(&conn, Utc::now());
+        assert!(base.is_finite() && base > 0.0, "{base}");
+        assert_eq!(base, f64::from(DEFAULT_TARGET_ALLOWANCE));
+    }
+
+    #[test]
+    fn test_ledger_running_balance() {
+        let conn = fixtures::setup();
+        let at = |days_ago: i64| Utc::now() - Duration::days(days_ago);
+        // Inserted out of order, with a row that has neither amount
+        for (date, added, subtracted) in [
+            (at(1), None, Some(4.25)),
+            (at(3), Some(10.0), None),
+            (at(2), None, None),
+            (at(2), Some(2.5), None),
+        ] {
+            conn.execute(
+                "INSERT INTO transactions (date, funds_added, funds_subtracted) VALUES (?, ?, ?)",
+                params![date, added, subtracted],
+            )
+            .unwrap();
+        }
+
+        let entries = ledger(&db::read_transactions(&conn));
+        let amounts: Vec<f64> = entries.iter().map(|entry| entry.amount).collect();
+        let balances: Vec<f64> = entries.iter().map(|entry| entry.balance).collect();
+        assert_eq!(amounts, vec![10.0, 2.5, -4.25]);
+        assert_eq!(balances, vec![10.0, 12.5, 8.25]);
+        assert_eq!(entries.last().unwrap().balance, calc_funds(&conn));
+    }
+
+    #[test]
+    fn test_completion_streak() {
+        let conn = fixtures::setup();
+        assert_eq!(completion_streak(&conn).unwrap(), 0);
+
+        // Three days in a row, then a gap, then an older completion
+        for days_ago in [0, 1, 1, 2, 4] {
+            complete_days_ago(&conn, days_ago);
+        }
+
+        assert_eq!(completion_streak(&conn).unwrap(), 3);
+    }
+
+    #[test]
+    fn test_completion_streak_survives_until_end_of_day() {
+        let conn = fixtures::setup();
+
+        for days_ago in [1, 2] {
+            complete_days_ago(&conn, days_ago);
+        }
+
+        assert_eq!(completion_streak(&conn).unwrap(), 2);
+    }
+
+    #[test]
+    fn test_spending_is_not_a_completion() {
+        let conn = fixtures::setup();
+        db::add_transaction(&conn, -20.0).unwrap();
+        db::add_transaction(&conn, 20.0).unwrap();
+
+        assert_eq!(completion_streak(&conn).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_bounty_breakdown_multiplies_to_bounty() {
+        let (conn, seed) = fixtures::seeded();
+        let task = Task {
+            bounty_modifier: 0.5,
+            ..fixtures::read_task(&conn, seed.tasks["dishes"])
+        };
+
+        let breakdown = bounty_breakdown(&conn, &task);
+
+        assert_eq!(breakdown.modifier, 1.5);
+        assert_eq!(
+            breakdown.base * breakdown.selection_factor * breakdown.modifier,
+            adjusted_value(&conn, &task)
+        );
+    }
+
+    #[test]
+    fn test_skipped_tasks_pay_more() {
+        let (conn, seed) = fixtures::seeded();
+        let fresh = fixtures::read_task(&conn, seed.tasks["dishes"]);
+        assert_eq!(selection_factor(&fresh), 1.0);
+        let skipped = Task {
+            times_shown: 10,
+            ..fresh.clone()
+        };
+        let picked = Task {
+            times_shown: 10,
+            times_selected: 9,
+            ..fresh.clone()
+        };
+
+        assert!((selection_factor(&skipped) - 1.5).abs() < 1e-9);
+        assert!(adjusted_value(&conn, &skipped) > adjusted_value(&conn, &picked));
+        assert!(adjusted_value(&conn, &picked) > adjusted_value(&conn, &fresh));
+
+        let ignored = Task {
+            times_shown: 500,
+            ..fresh
+        };
+        assert_eq!(selection_factor(&ignored), MAX_SELECTION_FACTOR);
+    }
+
+    #[test]
+    fn test_bounty_modifier_scales_bounty() {
+        let (conn, seed) = fixtures::seeded();
+        let dishes = fixtures::read_task(&conn, seed.tasks["dishes"]);
+        let base = base_value(&conn, Utc::now());
+        let pays = |bounty_modifier: f32| {
+            adjusted_value(
+                &conn,
+                &Task {
+                    bounty_modifier,
+                    ..dishes.clone()
+                },
+            )
+        };
+
+        assert_eq!(pays(0.0), base);
+        assert!((pays(1.7) - base * 2.7).abs() < 1e-4);
+        assert_eq!(pays(-1.0), 0.0);
+        // Anything lower still pays nothing, rather than charging the user
+        assert_eq!(pays(-3.0), 0.0);
+    }
+
+    #[test]
+    fn test_bounty_limits_clamp_by_priority() {
+        let (conn, seed) = fixtures::seeded();
+        let dishes = fixtures::read_task(&conn, seed.tasks["dishes"]);
+        let unclamped = adjusted_value(&conn, &dishes);
+        let floor = unclamped.ceil() as u32 + 5;
+        let ceiling = (unclamped.floor() as u32).max(2) - 1;
+        db::update_setting(&conn, "bounty_floor_p3", floor).unwrap();
+        db::update_setting(&conn, "bounty_ceiling_p0", ceiling).unwrap();
+
+        let top = Task {
+            priority: Priority::P3,
+            ..dishes.clone()
+        };
+        let bottom = Task {
+            priority: Priority::P0,
+            ..dishes.clone()
+        };
+
+        assert_eq!(adjusted_value(&conn, &top), floor as f64);
+        assert_eq!(adjusted_value(&conn, &bottom), ceiling as f64);
+        assert_eq!(adjusted_value(&conn, &dishes), unclamped);
+    }
+
+    #[test]
+    fn test_bounty_floor_above_ceiling_uses_ceiling() {
+        let conn = fixtures::setup();
+        db::update_setting(&conn, "bounty_floor_p2", 10).unwrap();
+        db::update_setting(&conn, "bounty_ceiling_p2", 4).unwrap();
+
+        let limits = bounty_limits(&conn, &Priority::P2);
+
+        assert_eq!(limits.clamp(1.0), 4.0);
+        assert_eq!(limits.clamp(20.0), 4.0);
+    }
+
+    #[test]
+    fn test_budget_remaining() {
+        let conn = fixtures::setup();
+        assert_eq!(budget_remaining(&conn, Utc::now()), 1.0);
+
+        db::add_transaction(&conn, 150.0).unwrap();
+        db::add_transaction(&conn, -100.0).unwrap();
+        assert_eq!(budget_remaining(&conn, Utc::now()), 0.75);
+
+        db::add_transaction(&conn, 500.0).unwrap();
+        assert_eq!(budget_remaining(&conn, Utc::now()), 0.0);
+    }
+
+    #[test]
+    fn test_payout_stops_at_maximum_allowance() {
+        let conn = fixtures::setup();
+        db::update_setting(&conn, "target_monthly_allowance", 50).unwrap();
+        db::update_setting(&conn, "maximum_monthly_allowance", 100).unwrap();
+
+        // Spending doesn't make room for more
+        db::add_transaction(&conn, 95.0).unwrap();
+        db::add_transaction(&conn, -40.0).unwrap();
+
+        assert_eq!(cap_bounty(&conn, 3.0, Utc::now()).unwrap(), 3.0);
+        assert_eq!(cap_bounty(&conn, 8.0, Utc::now()).unwrap(), 5.0);
+        db::add_payout(&conn, 1, 5.0, Utc::now()).unwrap();
+        assert_eq!(cap_bounty(&conn, 2.0, Utc::now()).unwrap(), 0.0);
+        assert_eq!(db::funds_added_this_month(&conn).unwrap(), 100.0);
+    }
+
+    #[test]
+    fn test_export_transactions_csv() {
+        let conn = fixtures::setup();
+        let added = Utc.with_ymd_and_hms(2024, 3, 1, 9, 30, 0).unwrap();
+        let spent = Utc.with_ymd_and_hms(2024, 3, 2, 18, 0, 0).unwrap();
+        // Inserted out of order, to check they're sorted
+        conn.execute(
+            "INSERT INTO transactions (date, funds_subtracted) VALUES (?, ?)",
+            params![spent, 12.5],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO transactions (date, funds_added) VALUES (?, ?)",
+            params![added, 40.0],
+        )
+        .unwrap();
+
+        assert_eq!(
+            export_transactions_csv(&conn),
+            "date,funds_added,funds_subtracted\n\
+            2024-03-01T09:30:00+00:00,40,\n\
+            2024-03-02T18:00:00+00:00,,12.5\n"
+        );
+    }
+
+    #[test]
+    fn test_missing_target_allowance_uses_default() {
+        let conn = fixtures::setup();
+        conn.execute(
+            "DELETE FROM settings WHERE key = 'target_monthly_allowance'",
+            (),
+        )
+        .unwrap();
+
+        assert_eq!(monthly_allowances(&conn).target, DEFAULT_TARGET_ALLOWANCE);
+    }
+
+    #[test]
+    fn test_set_allowances_rejects_target_above_maximum() {
+        let conn = fixtures::setup();
+
+        assert!(matches!(
+            set_allowances(&conn, 700, 600),
+            Err(AllowanceError::TargetAboveMaximum {
+                target: 700,
+                maximum: 600
+            })
+        ));
+        assert_eq!(
+            monthly_allowances(&conn),
+            Allowances {
+                target: 400,
+                maximum: 600
+            }
+        );
+
+        set_allowances(&conn, 600, 600).unwrap();
+        assert_eq!(monthly_allowances(&conn).target, 600);
+    }
+
+    #[test]
+    fn test_inverted_allowances_are_clamped() {
+        let conn = fixtures::setup();
+        // As if the db had been edited by hand
+        db::update_setting(&conn, "target_monthly_allowance", 900).unwrap();
+
+        assert_eq!(
+            monthly_allowances(&conn),
+            Allowances {
+                target: 600,
+                maximum: 600
+            }
+        );
+    }
+}
@@ -0,0 +1,177 @@
+//! # Fixtures
+//!
+//! Shared test harness. Spins up an in-memory db seeded with a representative
+//! folder tree and task mix, and offers helpers to assert on funds and task
+//! states.
+
+use std::collections::HashMap;
+
+use chrono::{Duration, Utc};
+use rusqlite::Connection;
+
+use crate::db;
+use crate::finance;
+use crate::folders::{Folder, Style};
+use crate::tasks::{Priority, Task};
+
+/// The ids of everything `seeded()` added to the db, keyed by a short name.
+pub struct Seed {
+    pub folders: HashMap<&'static str, u32>,
+    pub tasks: HashMap<&'static str, u32>,
+}
+
+/// Creates an in-memory db with every table initialized.
+pub fn setup() -> Connection {
+    let conn = Connection::open_in_memory().unwrap();
+    db::init_tables(&conn);
+    conn
+}
+
+/// Creates an in-memory db populated with the following:
+///
+/// ```text
+/// General (1)
+/// ├── Home
+/// │   ├── laundry       (recurring every 7 days, eligible)
+/// │   ├── water_plants  (recurring every 3 days, not yet eligible)
+/// │   └── Chores
+/// │       ├── dishes        (one-off)
+/// │       └── old_archived  (one-off, archived)
+/// Office
+/// └── report            (due in 5 days, 3 lead days)
+/// ```
+pub fn seeded() -> (Connection, Seed) {
+    let conn = setup();
+    let mut folders = HashMap::new();
+    let mut tasks = HashMap::new();
+
+    folders.insert("general", 1);
+    folders.insert("home", insert_folder(&conn, Some(1), "Home"));
+    folders.insert(
+        "chores",
+        insert_folder(&conn, Some(folders["home"]), "Chores"),
+    );
+    folders.insert("office", insert_folder(&conn, None, "Office"));
+
+    tasks.insert(
+        "laundry",
+        insert_task(
+            &conn,
+            Task {
+                repeat_interval: Some(7),
+                from_date: Utc::now() - Duration::days(8),
+                ..task(folders["home"], "Do the laundry")
+            },
+        ),
+    );
+    tasks.insert(
+        "water_plants",
+        insert_task(
+            &conn,
+            Task {
+                repeat_interval: Some(3),
+                ..task(folders["home"], "Water the plants")
+            },
+        ),
+    );
+    tasks.insert(
+        "dishes",
+        insert_task(
+            &conn,
+            Task {
+                from_date: Utc::now() - Duration::days(10),
+                ..task(folders["chores"], "Wash the dishes")
+            },
+        ),
+    );
+    tasks.insert(
+        "old_archived",
+        insert_task(
+            &conn,
+            Task {
+                is_archived: true,
+                ..task(folders["chores"], "Fix the sink")
+            },
+        ),
+    );
+    tasks.insert(
+        "report",
+        insert_task(
+            &conn,
+            Task {
+                due_date: Some(Utc::now() + Duration::days(5)),
+                lead_days: Some(3),
+                priority: Priority::P2,
+                ..task(folders["office"], "Write the report")
+            },
+        ),
+    );
+
+    (conn, Seed { folders, tasks })
+}
+
+/// Builds an active, one-off P1 task created now.
+///
+/// Use struct update syntax to adjust the fields a test cares about.
+pub fn task(parent_id: u32, summary: &str) -> Task {
+    Task::new(parent_id, summary.into())
+}
+
+/// Adds a task to the db and returns its id.
+pub fn insert_task(conn: &Connection, task: Task) -> u32 {
+    db::add_task(conn, task).unwrap()
+}
+
+/// Adds a Directory folder to the db and returns its id.
+pub fn insert_folder(conn: &Connection, parent_id: Option<u32>, name: &str) -> u32 {
+    insert_styled_folder(conn, parent_id, name, Style::Directory)
+}
+
+/// Adds a folder of any style to the db and returns its id.
+pub fn insert_styled_folder(
+    conn: &Connection,
+    parent_id: Option<u32>,
+    name: &str,
+    style: Style,
+) -> u32 {
+    let folder = Folder {
+        id: 0,
+        parent_id,
+        name: name.into(),
+        style,
+        status: None,
+    };
+    db::add_folder(conn, &folder).unwrap();
+    conn.last_insert_rowid() as u32
+}
+
+/// Reads a task back from the db.
+///
+/// # Panics
+///
+/// Panics if no task has the given id.
+pub fn read_task(conn: &Connection, id: u32) -> Task {
+    db::read_all_tasks(conn)
+        .unwrap()
+        .into_iter()
+        .find(|task| task.id == id)
+        .unwrap_or_else(|| panic!("No task with id {id}"))
+}
+
+/// Asserts that the user's funds match `expected` to the cent.
+pub fn assert_funds(conn: &Connection, expected: f64) {
+    let funds = finance::calc_funds(conn);
+    assert!(
+        (funds - expected).abs() < 0.005,
+        "Expected ${expected:.2} in funds, found ${funds:.2}"
+    );
+}
+
+/// Asserts whether the task with the given id has been archived.
+pub fn assert_archived(conn: &Connection, id: u32, expected: bool) {
+    assert_eq!(
+        read_task(conn, id).is_archived,
+        expected,
+        "Task {id} is_archived should be {expected}"
+    );
+}
@@ -1,3 +1,4 @@
+use serde::{Deserialize, Serialize};
 use std::error::Error;
 use std::fmt;
 use std::str::FromStr;
@@ -11,6 +12,7 @@ use std::str::FromStr;
 /// * `name` - The name of the folder.
 /// * `style` - The functional style of the folder, as defined by the `Style` enum.
 /// * `status` - A numerical status code representing the folder's current state or condition. Specific meanings are context-dependent.
+#[derive(Serialize, Deserialize)]
 pub struct Folder {
     pub id: u32,
     pub parent_id: Option<u32>,
@@ -19,10 +21,14 @@ pub struct Folder {
     pub status: Option<u32>,
 }
 
+/// The `status` of a folder that's been archived. Everything below it counts
+/// as archived too.
+pub const STATUS_ARCHIVED: u32 = 1;
+
 /// Enumerates the different styles a folder can have.
 ///
 /// This affects how the folder is interacted with.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Style {
     /// Represents a standard directory that can contain files and other directories.
     Directory,
@@ -46,14 +52,15 @@ impl fmt::Display for Style {
 /// Implements parsing from a string slice to a `Style` enum.
 ///
 /// This allows for easy conversion from textual representations (e.g., the database) into the strongly typed `Style` enum.
+/// Casing and surrounding whitespace are ignored, so a hand-edited row still reads.
 impl FromStr for Style {
     type Err = ParseStyleError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s {
-            "Directory" => Ok(Style::Directory),
-            "Selector" => Ok(Style::Selector),
-            "Iterator" => Ok(Style::Iterator),
+        match s.trim().to_lowercase().as_str() {
+            "directory" => Ok(Style::Directory),
+            "selector" => Ok(Style::Selector),
+            "iterator" => Ok(Style::Iterator),
             _ => Err(ParseStyleError::InvalidInput(s.to_string())),
         }
     }
@@ -79,3 +86,23 @@ impl fmt::Display for ParseStyleError {
 
 /// Allows `ParseStyleError` to integrate with Rust's standard error handling mechanisms.
 impl Error for ParseStyleError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_style() {
+        for input in ["Directory", "directory", " Directory ", "DIRECTORY\n"] {
+            assert!(matches!(input.parse(), Ok(Style::Directory)), "{input:?}");
+        }
+        assert!(matches!(" selector".parse(), Ok(Style::Selector)));
+        assert!(matches!("ITERATOR".parse(), Ok(Style::Iterator)));
+        assert!(matches!(
+            "Dir ectory".parse::<Style>(),
+            Err(ParseStyleError::InvalidInput(_))
+        ));
+
+        assert_eq!(Style::Iterator.to_string(), "Iterator");
+    }
+}
@@ -1,34 +1,111 @@
+//! # Backlist
+//!
+//! The NextUp task picker. Most people use it through the `nextup` binary,
+//! but the task store can be used as a library too:
+//!
+//! ```no_run
+//! use backlist::{add_task, connect_to_db, delete_task_by_id, init_tables, read_all, Task};
+//!
+//! # fn main() -> Result<(), backlist::DbError> {
+//! let conn = connect_to_db();
+//! init_tables(&conn);
+//!
+//! // Folder 1 is General, which every db starts with
+//! let id = add_task(&conn, Task::new(1, "Water the plants".into()))?;
+//! assert!(read_all(&conn)?.iter().any(|task| task.id == id));
+//! assert_eq!(delete_task_by_id(&conn, id)?, 1);
+//! # Ok(())
+//! # }
+//! ```
+
+mod backup;
+mod cli;
+mod clock;
+mod config;
 mod db;
 mod finance;
+#[cfg(test)]
+mod fixtures;
 mod folders;
+mod notify;
+mod session;
+mod simulation;
 mod tasks;
 mod ui;
 mod weighting;
 
-use std::io;
+pub use db::{
+    add_task, connect_to_db, delete_task_by_id, init_tables, read_all_tasks as read_all, DbError,
+};
+pub use tasks::{Priority, RecurrenceAnchor, RepeatUnit, Task};
+
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
 
+use chrono::{DateTime, Duration, Utc};
 use rusqlite::Connection;
 
 use crate::{
-    tasks::{Priority, Task},
-    weighting::calculate_weight,
+    folders::Style,
+    tasks::TaskTemplate,
+    ui::InputError,
+    weighting::{calculate_weight, calculate_weight_at, OverdueCurve},
 };
 
 /// Enumerates the possible states that the application can be in.
-#[derive(Clone)]
+#[derive(Clone, Debug, PartialEq)]
 enum AppState {
     /// Walks the user through adding a new folder to the folders table.
     AddFolder,
     /// Walks the user through adding a new task to the tasks table.
     AddTask,
+    /// Lists the tasks that have been completed or archived, so one can be
+    /// restored.
+    Archive,
+    /// Archives a folder and everything in it, or restores one.
+    ArchiveFolder,
+    /// Archives every task that mentions a keyword.
+    ArchiveMatching,
+    /// Completes several tasks from the ToDo list at once.
+    CompleteSeveral,
     /// Allows the user to edit a specific task.
     _EditTask,
+    /// Writes the user's tasks or settings to a file for use elsewhere.
+    Export,
+    /// Shows how the bounties paid for a task have changed.
+    History,
+    /// Reads settings and statistics, or a backup of folders and tasks, back
+    /// from an exported file.
+    Import,
+    /// Lists the tasks saved for later so they can be brought back.
+    Later,
     /// Loops AppState::SelectAppState(). May add more functionality later.
     MainLoop,
+    /// Renames or deletes a folder.
+    ManageFolders,
+    /// Applies one change to several tasks at once.
+    Organize,
+    /// Adds a task from just its summary, defaulting everything else.
+    QuickAdd,
+    /// Changes the order an Iterator folder works through its tasks.
+    Reorder,
+    /// Changes the values in the settings table.
+    Settings,
     /// Where user can make adjustments to their funds.
     Shop,
-    /// Presents the user with 5 possible tasks to select.
+    /// Fast-forwards a month of completions against a copy of the db.
+    Simulate,
+    /// Shows the user how consistently they've been completing tasks, and
+    /// the running totals behind their finances.
+    Stats,
+    /// Presents the user with a shortlist of tasks to select.
     ToDo,
+    /// Pauses recurring tasks while the user is away, or resumes them.
+    Vacation,
+    /// Files quick captured tasks one at a time.
+    Triage,
 }
 
 trait ToString {
@@ -40,10 +117,27 @@ impl ToString for AppState {
         match self {
             AppState::AddFolder => "Add Folder",
             AppState::AddTask => "Add Task",
+            AppState::Archive => "Archived Tasks",
+            AppState::ArchiveFolder => "Archive Folder",
+            AppState::ArchiveMatching => "Archive by Keyword",
+            AppState::CompleteSeveral => "Complete Several",
             AppState::_EditTask => "Edit Task",
+            AppState::Export => "Export",
+            AppState::History => "Task History",
+            AppState::Import => "Import",
+            AppState::Later => "Later",
             AppState::MainLoop => "Home",
+            AppState::ManageFolders => "Manage Folders",
+            AppState::Organize => "Organize",
+            AppState::QuickAdd => "Quick Add",
+            AppState::Reorder => "Reorder Tasks",
+            AppState::Settings => "Settings",
             AppState::Shop => "Shop",
+            AppState::Simulate => "Simulate Month",
+            AppState::Stats => "Stats",
             AppState::ToDo => "ToDo",
+            AppState::Triage => "Triage Inbox",
+            AppState::Vacation => "Vacation Mode",
         }
     }
 }
@@ -54,23 +148,192 @@ impl ToString for AppState {
 ///
 /// * `state: AppState` - Determines which state to assume.
 /// * `conn: Option<&Connection>` - Allows the new state to connect to the db
-/// if necessary.
-fn assume_state(state: AppState, conn: Option<&Connection>) -> Result<(), io::Error> {
+///   if necessary.
+/// * `shown_this_session: &mut HashSet<u32>` - Collects the tasks shown in
+///   ToDo until the app exits. See `session_penalty()`.
+fn assume_state(
+    state: AppState,
+    conn: Option<&Connection>,
+    shown_this_session: &mut HashSet<u32>,
+) -> Result<(), Box<dyn Error>> {
     // Writing this once to avoid repeating myself
     let db_lost =
         String::from("Value was None, but expected Some(&Connection).\nLost connection to db.");
 
     match state {
         // TODO: Remove Ok()s while improving error handling
-        AppState::AddFolder => Ok(add_folder(conn.expect(&db_lost))),
-        AppState::AddTask => Ok(add_task(conn.expect(&db_lost))),
+        AppState::AddFolder => {
+            add_folder(&mut ui::StdIo, conn.expect(&db_lost));
+            Ok(())
+        }
+        AppState::AddTask => {
+            add_new_task(&mut ui::StdIo, conn.expect(&db_lost));
+            Ok(())
+        }
+        AppState::Archive => {
+            archive(&mut ui::StdIo, conn.expect(&db_lost));
+            Ok(())
+        }
+        AppState::ArchiveFolder => {
+            archive_folder(&mut ui::StdIo, conn.expect(&db_lost));
+            Ok(())
+        }
+        AppState::ArchiveMatching => {
+            archive_matching(&mut ui::StdIo, conn.expect(&db_lost));
+            Ok(())
+        }
+        AppState::CompleteSeveral => {
+            complete_several(&mut ui::StdIo, conn.expect(&db_lost));
+            Ok(())
+        }
         AppState::_EditTask => unimplemented!(),
-        AppState::MainLoop => Ok(main_loop(conn.expect(&db_lost))),
-        AppState::Shop => Ok(shop(conn.expect(&db_lost))),
-        AppState::ToDo => to_do(conn.expect(&db_lost)),
+        AppState::Export => {
+            export(&mut ui::StdIo, conn.expect(&db_lost));
+            Ok(())
+        }
+        AppState::History => {
+            task_history(&mut ui::StdIo, conn.expect(&db_lost));
+            Ok(())
+        }
+        AppState::Import => {
+            import(&mut ui::StdIo, conn.expect(&db_lost));
+            Ok(())
+        }
+        AppState::Later => {
+            later(&mut ui::StdIo, conn.expect(&db_lost));
+            Ok(())
+        }
+        AppState::MainLoop => {
+            main_loop(conn.expect(&db_lost));
+            Ok(())
+        }
+        AppState::ManageFolders => {
+            manage_folders(&mut ui::StdIo, conn.expect(&db_lost));
+            Ok(())
+        }
+        AppState::Organize => {
+            organize(&mut ui::StdIo, conn.expect(&db_lost));
+            Ok(())
+        }
+        AppState::QuickAdd => {
+            quick_add(&mut ui::StdIo, conn.expect(&db_lost));
+            Ok(())
+        }
+        AppState::Reorder => {
+            reorder(&mut ui::StdIo, conn.expect(&db_lost));
+            Ok(())
+        }
+        AppState::Settings => {
+            settings(&mut ui::StdIo, conn.expect(&db_lost));
+            Ok(())
+        }
+        AppState::Shop => {
+            shop(&mut ui::StdIo, conn.expect(&db_lost));
+            Ok(())
+        }
+        AppState::Simulate => {
+            simulate(conn.expect(&db_lost));
+            Ok(())
+        }
+        AppState::Stats => {
+            stats(&mut ui::StdIo, conn.expect(&db_lost));
+            Ok(())
+        }
+        AppState::ToDo => to_do(conn.expect(&db_lost), shown_this_session),
+        AppState::Triage => {
+            triage(&mut ui::StdIo, conn.expect(&db_lost));
+            Ok(())
+        }
+        AppState::Vacation => {
+            vacation(&mut ui::StdIo, conn.expect(&db_lost));
+            Ok(())
+        }
+    }
+}
+
+/// Raises a desktop notification for any overdue tasks, then exits. See
+/// `notify::run()`.
+pub fn notify() {
+    let conn = db::connect_to_db();
+    db::init_tables(&conn);
+
+    notify::run(&conn);
+}
+
+/// Adds a task described by command line options, then exits. See
+/// `cli::parse_add_args()`.
+///
+/// # Arguments
+///
+/// * `args: &[String]` - Everything after `add`.
+///
+/// # Returns
+///
+/// The exit code: 0 if the task was added, or 1 if it wasn't.
+pub fn add_from_args(args: &[String]) -> i32 {
+    let conn = db::connect_to_db();
+    db::init_tables(&conn);
+
+    let task = cli::parse_add_args(args, Utc::now())
+        .and_then(|parsed| cli::build_task(&conn, parsed, quick_capture_folder(&conn)));
+    let result = task.and_then(|task| Ok(db::add_task(&conn, task)?));
+
+    match result {
+        Ok(id) => {
+            println!("Added task {id}");
+            0
+        }
+        Err(e) => {
+            eprintln!("Problem adding task: {e}");
+            1
+        }
+    }
+}
+
+/// Prints the top of the ToDo shortlist across every folder, then exits.
+/// Nothing is recorded as shown.
+///
+/// # Arguments
+///
+/// * `args: &[String]` - Everything after `next`. See
+///   `cli::parse_next_args()`.
+///
+/// # Returns
+///
+/// The exit code: 0 if the list was printed, or 1 if it wasn't.
+pub fn print_next(args: &[String]) -> i32 {
+    let conn = db::connect_to_db();
+    db::init_tables(&conn);
+
+    let tasks = cli::parse_next_args(args)
+        .and_then(|count| Ok(top_tasks(&conn, count.unwrap_or(candidate_count(&conn)))?));
+
+    match tasks {
+        Ok(tasks) => {
+            let money = finance::is_enabled(&conn).then(|| money_format(&conn));
+            print!("{}", cli::format_top_tasks(&tasks, money.as_ref()));
+            0
+        }
+        Err(e) => {
+            eprintln!("Problem listing tasks: {e}");
+            1
+        }
     }
 }
 
+/// Prints the weighting and finance settings in effect as JSON, then exits.
+/// See `config::export_effective()`.
+pub fn export_config() {
+    let conn = db::connect_to_db();
+    db::init_tables(&conn);
+
+    let config = config::export_effective(&conn);
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&config).expect("A json::Value always serializes")
+    );
+}
+
 /// Initializes the program for use by a user through the TUI.
 ///
 /// # Notes
@@ -93,7 +356,9 @@ pub fn startup() {
     // the program's main loop too early.
     ui::wait_for_interaction();
 
-    match assume_state(AppState::MainLoop, Some(&conn)) {
+    review_stale_tasks(&mut ui::StdIo, &conn);
+
+    match assume_state(AppState::MainLoop, Some(&conn), &mut HashSet::new()) {
         Ok(_) => unimplemented!(),
         Err(e) => {
             eprintln!("Something went wrong: {e}\n\nReturning to main loop.");
@@ -102,12 +367,116 @@ pub fn startup() {
     }
 }
 
+/// Offers to archive any stale tasks, at most once a day.
+///
+/// # Arguments
+///
+/// * `io: &mut impl ui::IoPort` - Where prompts are written and input read.
+/// * `conn: &Connection` - Stale tasks are read from and archived in the db.
+///
+/// # Notes
+///
+/// Controlled by the `stale_prompt_enabled` and `stale_task_days` settings.
+/// See `db::read_stale_tasks()` for what counts as stale.
+fn review_stale_tasks(io: &mut impl ui::IoPort, conn: &Connection) {
+    let (stale_tasks, threshold_days) = stale_tasks_to_review(conn, Utc::now());
+    if stale_tasks.is_empty() {
+        return;
+    }
+
+    // Record the prompt up front so cancelling doesn't cause it to nag again
+    db::write_statistic(conn, STALE_PROMPT_STATISTIC, &Utc::now().to_rfc3339())
+        .unwrap_or_else(|err| eprintln!("Problem recording stale task prompt: {err}"));
+
+    if let Ok(true) = ui::confirm_archive_stale(io, &stale_tasks, threshold_days) {
+        for task in &stale_tasks {
+            if let Err(err) = db::archive_task(conn, task.id) {
+                return eprintln!("Problem archiving stale tasks: {err}");
+            }
+        }
+    }
+}
+
+/// The statistic that records when the user was last asked about stale tasks.
+const STALE_PROMPT_STATISTIC: &str = "stale_prompt_last_shown";
+
+/// Finds the stale tasks the user should be asked about right now.
+///
+/// # Arguments
+///
+/// * `conn: &Connection` - Allows us to access the SQLite db.
+/// * `now: DateTime<Utc>` - Used to check whether the user was already asked
+///   within the last day.
+///
+/// # Returns
+///
+/// The stale tasks and the threshold used to find them. The list is empty if
+/// the prompt is disabled or was shown less than a day before `now`.
+fn stale_tasks_to_review(conn: &Connection, now: DateTime<Utc>) -> (Vec<Task>, u32) {
+    let threshold_days = db::setting_or_default(conn, "stale_task_days");
+
+    if let Ok(Some(0)) = db::read_setting_u32(conn, "stale_prompt_enabled") {
+        return (vec![], threshold_days);
+    }
+
+    let last_shown = db::read_statistic(conn, STALE_PROMPT_STATISTIC)
+        .ok()
+        .flatten()
+        .and_then(|value| DateTime::parse_from_rfc3339(&value).ok());
+    if let Some(last_shown) = last_shown {
+        if now - last_shown.with_timezone(&Utc) < Duration::days(1) {
+            return (vec![], threshold_days);
+        }
+    }
+
+    let stale_tasks = db::read_stale_tasks(conn, threshold_days).unwrap_or_else(|err| {
+        eprintln!("Problem reading stale tasks: {err}");
+        vec![]
+    });
+    (stale_tasks, threshold_days)
+}
+
+/// The statistic that records when vacation mode was turned on.
+const VACATION_STATISTIC: &str = "vacation_started";
+
+/// When the current vacation started, or None if vacation mode is off.
+///
+/// If the start date is missing, the vacation is taken to start at `now`.
+fn vacation_started(conn: &Connection, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    if !matches!(db::read_setting_u32(conn, "vacation_mode"), Ok(Some(1))) {
+        return None;
+    }
+
+    let started = db::read_statistic(conn, VACATION_STATISTIC)
+        .ok()
+        .flatten()
+        .and_then(|value| DateTime::parse_from_rfc3339(&value).ok())
+        .map(|date| date.with_timezone(&Utc));
+    Some(started.unwrap_or(now).min(now))
+}
+
+/// Pauses every recurring task until `end_vacation()`.
+fn start_vacation(conn: &Connection, now: DateTime<Utc>) -> Result<(), DbError> {
+    db::write_statistic(conn, VACATION_STATISTIC, &now.to_rfc3339())?;
+    Ok(db::update_setting(conn, "vacation_mode", 1)?)
+}
+
+/// Turns vacation mode off. The time spent on vacation doesn't count towards
+/// any recurring task's interval, so they pick up where they left off instead
+/// of all coming due at once.
+fn end_vacation(conn: &Connection, now: DateTime<Utc>) -> Result<(), DbError> {
+    if let Some(started) = vacation_started(conn, now) {
+        db::shift_recurring_from_dates(conn, now - started)?;
+    }
+    Ok(db::update_setting(conn, "vacation_mode", 0)?)
+}
+
 /// Asks the user to select one of the top-level app states.
 ///
 /// # Arguments
 ///
 /// * `conn: &Connection` - main_loop will be launching AppStates that require
-/// a &Connection, so it requires one too.
+///   a &Connection, so it requires one too.
 ///
 /// # Notes
 ///
@@ -115,18 +484,41 @@ pub fn startup() {
 /// and come back here. This allows us to avoid passing the &Connection to
 /// functions that don't need it.
 fn main_loop(conn: &Connection) {
+    let mut shown_this_session = HashSet::new();
+
     loop {
         ui::print_header(AppState::MainLoop);
 
-        let result = assume_state(
-            ui::select_app_state(&[
-                AppState::ToDo,
-                AppState::Shop,
-                AppState::AddTask,
-                AppState::AddFolder,
-            ]),
-            Some(conn),
-        );
+        let mut states = vec![AppState::ToDo, AppState::CompleteSeveral];
+        if finance::is_enabled(conn) {
+            states.push(AppState::Shop);
+        }
+        states.extend([
+            AppState::Stats,
+            AppState::History,
+            AppState::QuickAdd,
+            AppState::Triage,
+            AppState::Later,
+            AppState::Archive,
+            AppState::AddTask,
+            AppState::AddFolder,
+            AppState::ManageFolders,
+            AppState::ArchiveFolder,
+            AppState::Organize,
+            AppState::Reorder,
+            AppState::ArchiveMatching,
+            AppState::Settings,
+            AppState::Export,
+            AppState::Import,
+            AppState::Simulate,
+            AppState::Vacation,
+        ]);
+
+        let state = match ui::select_app_state(&mut ui::StdIo, &states) {
+            Ok(state) => state,
+            Err(e) => return eprintln!("Problem reading input: {e}"),
+        };
+        let result = assume_state(state, Some(conn), &mut shown_this_session);
 
         match result {
             Ok(_) => continue,
@@ -135,130 +527,3333 @@ fn main_loop(conn: &Connection) {
     }
 }
 
-fn add_folder(conn: &Connection) {
+fn add_folder(io: &mut impl ui::IoPort, conn: &Connection) {
     ui::print_header(AppState::AddFolder);
 
-    let folder = ui::request_folder_input(conn);
+    let folder = ui::request_folder_input(io, conn);
 
     match folder {
         Ok(f) => db::add_folder(conn, &f).unwrap_or_else(|err| {
             eprintln!("Problem adding folder to db: {}", err);
         }),
+        Err(InputError::Cancelled) => io.write_line("\nNo folder was added."),
         Err(e) => eprintln!("Problem building folder: {}", e),
     }
 }
 
-fn add_task(conn: &Connection) {
+fn add_new_task(io: &mut impl ui::IoPort, conn: &Connection) {
     ui::print_header(AppState::AddTask);
 
-    let task = ui::request_task_input(conn);
+    let templates = db::read_templates(conn).unwrap_or_else(|err| {
+        eprintln!("Problem reading templates: {}", err);
+        vec![]
+    });
+
+    // Only offer templates once some have been saved
+    let template = if templates.is_empty() {
+        Ok(None)
+    } else {
+        ui::request_template_choice(io, &templates)
+    };
+
+    let result = template.and_then(|template| match &template {
+        Some(template) => add_task_from_template(io, conn, template),
+        None => add_task_from_scratch(io, conn),
+    });
 
-    match task {
-        Ok(t) => db::add_task(conn, t),
+    match result {
+        Ok(()) => {}
+        Err(InputError::Cancelled) => io.write_line("\nNo task was added."),
         Err(e) => eprintln!("Problem adding task: {}", e),
     }
 }
 
-/// Shows the user their current funds and allows them to enter a custom
-/// transaction.
+/// Adds a P1 one-off task to the quick capture folder, asking only for its
+/// summary. It waits in the inbox until it's triaged.
+fn quick_add(io: &mut impl ui::IoPort, conn: &Connection) {
+    ui::print_header(AppState::QuickAdd);
+
+    match ui::request_task_summary(io, ui::max_summary_length(conn)) {
+        Ok(summary) => {
+            let parent_id = quick_capture_folder(conn);
+            match confirm_if_duplicate(io, conn, parent_id, &summary) {
+                Ok(()) => {
+                    let result = db::add_task(conn, Task::new(parent_id, summary))
+                        .and_then(|id| db::set_triaged(conn, id, false));
+                    if let Err(e) = result {
+                        eprintln!("Problem adding task: {}", e);
+                    }
+                }
+                Err(InputError::Cancelled) => io.write_line("\nNo task was added."),
+                Err(e) => eprintln!("Problem adding task: {}", e),
+            }
+        }
+        Err(InputError::Cancelled) => io.write_line("\nNo task was added."),
+        Err(e) => eprintln!("Problem adding task: {}", e),
+    }
+}
+
+/// Walks through the inbox one task at a time, oldest first, until it's empty
+/// or the user backs out.
+fn triage(io: &mut impl ui::IoPort, conn: &Connection) {
+    ui::print_header(AppState::Triage);
+
+    let inbox = match db::read_untriaged(conn) {
+        Ok(inbox) => inbox,
+        Err(e) => return eprintln!("Problem reading the inbox: {}", e),
+    };
+    if inbox.is_empty() {
+        io.write_line("The inbox is empty.");
+        return;
+    }
+
+    for task in &inbox {
+        match ui::request_triage_details(io, conn, task) {
+            Ok(triaged) => {
+                if let Err(e) = db::triage_task(conn, &triaged) {
+                    eprintln!("Problem triaging task: {}", e);
+                }
+            }
+            Err(InputError::Cancelled) => break,
+            Err(e) => {
+                eprintln!("Problem triaging task: {}", e);
+                break;
+            }
+        }
+    }
+
+    let remaining = db::read_untriaged(conn).map_or(0, |inbox| inbox.len());
+    if remaining > 0 {
+        io.write_line(&format!("\n{} task(s) left in the inbox.", remaining));
+    }
+}
+
+/// Reads the folder that Quick Add files tasks into from the settings table.
 ///
-/// # Arguments
+/// # Notes
 ///
-/// * `conn: &Connection` - `ui::display_funds()` requires a &Connection, so
-/// it's required here too.
-fn shop(conn: &Connection) {
-    ui::print_header(AppState::Shop);
-    ui::display_funds(finance::calc_funds(conn));
-    ui::request_transaction(conn);
-    ui::display_funds(finance::calc_funds(conn));
-    ui::wait_for_interaction();
+/// Falls back to General if the setting points at a folder that no longer
+/// exists.
+fn quick_capture_folder(conn: &Connection) -> u32 {
+    let id = db::setting_or_default(conn, "quick_capture_folder");
+
+    match db::read_all_folders_sorted(conn) {
+        Ok(folders) if folders.iter().any(|(folder_id, _)| *folder_id == id) => id,
+        _ => db::default_setting("quick_capture_folder"),
+    }
 }
 
-fn to_do(conn: &Connection) -> Result<(), io::Error> {
-    ui::print_header(AppState::ToDo);
+/// Walks the user through every detail of a new task, then offers to save it
+/// as a template.
+fn add_task_from_scratch(io: &mut impl ui::IoPort, conn: &Connection) -> Result<(), InputError> {
+    let task = ui::request_task_input(io, conn)?;
+    confirm_if_duplicate(io, conn, task.parent_id, &task.summary)?;
 
-    // Print the folder tree
-    // Request folder selection
-    let parent_id = ui::request_parent_id(conn)?;
+    preview_rank(io, conn, &task);
+    if let Err(e) = db::add_task(conn, task.clone()) {
+        eprintln!("Problem adding task: {}", e);
+        return Ok(());
+    }
+    offer_to_save_template(io, conn, &task);
 
-    // Construct a task list from folder
-    let mut folder_ids: Vec<u32> = vec![];
-    match db::get_descendant_ids(conn, parent_id) {
-        Ok(v) => folder_ids = v,
-        Err(e) => {
-            // Need to do error handling here because I can't propigate the rusqlite error
-            eprintln!("Error getting descendent ids: {e}\n\nReturning to main loop.");
-            // TODO: This is probably bad for memory usage... fix it
-            main_loop(conn);
+    Ok(())
+}
+
+/// Asks the user only for the details that `template` can't fill in, then
+/// adds the task.
+fn add_task_from_template(
+    io: &mut impl ui::IoPort,
+    conn: &Connection,
+    template: &TaskTemplate,
+) -> Result<(), InputError> {
+    let (summary, due_date) = ui::request_template_details(io, conn, template)?;
+    confirm_if_duplicate(io, conn, template.parent_id, &summary)?;
+
+    preview_rank(io, conn, &template.instantiate(summary.clone(), due_date));
+    if let Err(e) = db::instantiate_template(conn, template.id, summary, due_date) {
+        eprintln!("Problem adding task from template: {}", e);
+    }
+
+    Ok(())
+}
+
+/// Warns the user if the folder already has a task with this summary and asks
+/// whether to add it anyway. Does nothing if the warn_duplicate_summaries
+/// setting is off.
+///
+/// # Returns
+///
+/// `Err(InputError::Cancelled)` if the user decides not to add it.
+fn confirm_if_duplicate(
+    io: &mut impl ui::IoPort,
+    conn: &Connection,
+    parent_id: u32,
+    summary: &str,
+) -> Result<(), InputError> {
+    if matches!(
+        db::read_setting_u32(conn, "warn_duplicate_summaries"),
+        Ok(Some(0))
+    ) {
+        return Ok(());
+    }
+
+    // Failing to check shouldn't stop the task being added
+    match db::find_duplicate_task(conn, parent_id, summary).unwrap_or_else(|e| {
+        eprintln!("Problem checking for duplicates: {}", e);
+        None
+    }) {
+        Some(existing) => {
+            io.write_line(&format!(
+                "\nThere's already a task called \"{}\" in this folder.",
+                existing.summary
+            ));
+            if ui::confirm(io, "Add it anyway?")? {
+                Ok(())
+            } else {
+                Err(InputError::Cancelled)
+            }
         }
+        None => Ok(()),
     }
-    folder_ids.push(parent_id);
+}
+
+/// Tells the user where a new deadline task would rank. See
+/// `provisional_rank()`.
+fn preview_rank(io: &mut impl ui::IoPort, conn: &Connection, task: &Task) {
+    if task.due_date.is_some() {
+        let (rank, total) = match provisional_rank(conn, task) {
+            Ok(rank) => rank,
+            Err(e) => return eprintln!("Problem ranking task: {}", e),
+        };
+        let urgency = match db::read_active_tasks(conn) {
+            Ok(mut tasks) if show_urgency(conn) => {
+                tasks.push(task.clone());
+                let scores =
+                    weighting::normalized_scores(&tasks, &overdue_curve(conn), &clock::SystemClock);
+                format!(" (urgency {:.0})", scores[scores.len() - 1])
+            }
+            _ => String::new(),
+        };
+        io.write_line(&format!(
+            "\nRight now, this task would rank #{} of {} active tasks{}.",
+            rank, total, urgency
+        ));
+    }
+}
+
+/// Whether to show 0-100 urgency scores next to ranked tasks.
+fn show_urgency(conn: &Connection) -> bool {
+    matches!(
+        db::read_setting_u32(conn, "show_urgency_scores"),
+        Ok(Some(1))
+    )
+}
 
-    // Get all tasks
-    let mut task_list: Vec<Task> = vec![];
-    match db::fetch_tasks_by_parent_ids(conn, folder_ids) {
-        Ok(rows) => {
-            for row in rows {
-                task_list.push(row)
+/// Asks the user whether to save a newly added task as a template.
+fn offer_to_save_template(io: &mut impl ui::IoPort, conn: &Connection, task: &Task) {
+    let name = ui::confirm(io, "\nSave this task as a template?").and_then(|save| {
+        if save {
+            ui::request_template_name(io).map(Some)
+        } else {
+            Ok(None)
+        }
+    });
+
+    match name {
+        Ok(Some(name)) => {
+            let template = TaskTemplate::from_task(task, name);
+            if let Err(e) = db::save_template(conn, &template) {
+                eprintln!("Problem saving template: {}", e);
             }
         }
-        Err(e) => eprintln!("Database error: {}", e),
+        Ok(None) | Err(InputError::Cancelled) => {}
+        Err(e) => eprintln!("Problem saving template: {}", e),
     }
+}
 
-    // Order the list
-    task_list.sort_by(|a, b| {
-        calculate_weight(b)
-            .partial_cmp(&calculate_weight(a))
-            .unwrap()
+/// Lets the user pick several active tasks and change them all at once.
+///
+/// # Arguments
+///
+/// * `io: &mut impl ui::IoPort` - Where prompts are written and input read.
+/// * `conn: &Connection` - The tasks are read from and updated in the db.
+fn organize(io: &mut impl ui::IoPort, conn: &Connection) {
+    ui::print_header(AppState::Organize);
+
+    let tasks: Vec<Task> = match db::read_all_tasks(conn) {
+        Ok(tasks) => tasks.into_iter().filter(|task| !task.is_archived).collect(),
+        Err(e) => return eprintln!("Problem reading tasks: {}", e),
+    };
+    if tasks.is_empty() {
+        io.write_line("\nThere are no tasks to organize.");
+        return;
+    }
+
+    let selection = ui::select_tasks(io, &tasks)
+        .and_then(|selected| Ok((selected, ui::request_bulk_action(io, conn)?)));
+
+    match selection {
+        Ok((selected, action)) => {
+            let ids: Vec<u32> = selected.iter().map(|task| task.id).collect();
+
+            // Deleting can't be undone, so make sure it wasn't a slip
+            if action == ui::BulkAction::Delete {
+                let prompt = format!(
+                    "\nDelete {} task(s) for good? To put away finished tasks, archive them instead.",
+                    ids.len()
+                );
+                match ui::confirm(io, &prompt) {
+                    Ok(true) => {}
+                    Ok(false) | Err(InputError::Cancelled) => {
+                        return io.write_line("\nNo tasks were changed.");
+                    }
+                    Err(e) => return eprintln!("Problem reading confirmation: {}", e),
+                }
+            }
+
+            match apply_bulk_action(conn, &ids, &action) {
+                Ok(()) => io.write_line(&format!("\nUpdated {} task(s).", ids.len())),
+                Err(e) => eprintln!("Problem updating tasks: {}", e),
+            }
+        }
+        Err(InputError::Cancelled) => io.write_line("\nNo tasks were changed."),
+        Err(e) => eprintln!("Problem selecting tasks: {}", e),
+    }
+}
+
+/// Asks the user what to export and where to save it, then writes it.
+///
+/// # Notes
+///
+/// There are three kinds of export: a CSV of every task, a CSV of the
+/// settings and statistics, and a JSON backup of every folder and task that
+/// Import can read back in. See `backup::export_json()`.
+fn export(io: &mut impl ui::IoPort, conn: &Connection) {
+    ui::print_header(AppState::Export);
+
+    let result = ui::request_export_kind(io).and_then(|kind| {
+        let default = match kind {
+            ui::ExportKind::Tasks => "tasks.csv".to_string(),
+            ui::ExportKind::SettingsAndStatistics => SETTINGS_FILE.to_string(),
+            ui::ExportKind::Backup => backup::file_name(Utc::now()),
+        };
+        Ok((kind, ui::request_export_path(io, &default)?))
     });
 
-    // Shorten the list to the top 5
-    if task_list.len() > 5 {
-        task_list.drain(5..);
+    match result {
+        Ok((ui::ExportKind::Tasks, path)) => match db::export_tasks_csv(conn, &path) {
+            Ok(()) => io.write_line(&format!("\nSaved your tasks to {}", path.display())),
+            Err(e) => eprintln!("Problem exporting tasks: {}", e),
+        },
+        Ok((ui::ExportKind::SettingsAndStatistics, path)) => {
+            match db::export_key_values_csv(conn, &path) {
+                Ok(()) => io.write_line(&format!(
+                    "\nSaved your settings and statistics to {}",
+                    path.display()
+                )),
+                Err(e) => eprintln!("Problem exporting settings: {}", e),
+            }
+        }
+        Ok((ui::ExportKind::Backup, path)) => match backup::export_to_file(conn, &path) {
+            Ok(()) => io.write_line(&format!(
+                "\nSaved your folders and tasks to {}",
+                path.display()
+            )),
+            Err(e) => eprintln!("Problem backing up: {}", e),
+        },
+        Err(InputError::Cancelled) => io.write_line("\nNothing was exported."),
+        Err(e) => eprintln!("Problem reading path: {}", e),
     }
+}
+
+/// Where settings and statistics are exported to and imported from unless
+/// the user picks somewhere else.
+const SETTINGS_FILE: &str = "settings.csv";
 
-    // Record that each task has been displayed
-    for task in &task_list {
-        db::increment_times_shown(conn, task.id, task.times_shown);
+fn import(io: &mut impl ui::IoPort, conn: &Connection) {
+    ui::print_header(AppState::Import);
+
+    match ui::request_import_kind(io) {
+        Ok(ui::ImportKind::SettingsAndStatistics) => import_settings(io, conn),
+        Ok(ui::ImportKind::Backup) => import_backup(io, conn),
+        Err(InputError::Cancelled) => io.write_line("\nNothing was imported."),
+        Err(e) => eprintln!("Problem reading input: {}", e),
     }
+}
 
-    // Calculate the bounty for each task
-    let tasks_w_bounties: Vec<(Task, f64)> = task_list
-        .iter()
-        .map(|task| (task.clone(), finance::adjusted_value(conn, &task)))
-        .collect();
+/// Adds the folders and tasks from a backup made on the Export screen.
+fn import_backup(io: &mut impl ui::IoPort, conn: &Connection) {
+    let default = backup::latest_file(Path::new("."))
+        .unwrap_or_else(|| PathBuf::from(backup::file_name(Utc::now())));
 
-    // User selects a task from the remaining list
-    let (selected_task, bounty) = ui::select_task(&tasks_w_bounties);
+    let path = match ui::request_import_path(io, &default.to_string_lossy()) {
+        Ok(path) => path,
+        Err(InputError::Cancelled) => return io.write_line("\nNothing was imported."),
+        Err(e) => return eprintln!("Problem reading input: {}", e),
+    };
+    let plan = match backup::plan_file_import(conn, &path) {
+        Ok(plan) => plan,
+        Err(e) => return io.write_line(&format!("\nNothing was imported: {}", e)),
+    };
+    if plan.summary.folders_added + plan.summary.tasks_added == 0 {
+        return io.write_line("\nEverything in the backup is already here.");
+    }
+
+    // Show what would be added before anything is written
+    ui::display_backup_plan(io, &plan.summary);
+    match ui::confirm(io, "\nImport this backup?") {
+        Ok(true) => match plan.apply(conn) {
+            Ok(summary) => io.write_line(&format!(
+                "\nAdded {} folder(s) and {} task(s).",
+                summary.folders_added, summary.tasks_added
+            )),
+            Err(e) => io.write_line(&format!("\nNothing was imported: {}", e)),
+        },
+        Ok(false) | Err(InputError::Cancelled) => io.write_line("\nNothing was imported."),
+        Err(e) => eprintln!("Problem reading input: {}", e),
+    }
+}
 
-    // Record that the task has been selected
-    db::increment_times_selected(conn, selected_task.id, selected_task.times_selected);
+fn import_settings(io: &mut impl ui::IoPort, conn: &Connection) {
+    let choices = ui::request_import_path(io, SETTINGS_FILE)
+        .and_then(|path| Ok((path, ui::request_import_mode(io)?)));
 
-    // Display the selected task
-    ui::display_task(&selected_task);
+    let plan = match choices {
+        Ok((path, mode)) => match db::plan_key_values_import(conn, &path, &mode) {
+            Ok(plan) => plan,
+            Err(e) => return io.write_line(&format!("\nNothing was imported: {}", e)),
+        },
+        Err(InputError::Cancelled) => return io.write_line("\nNothing was imported."),
+        Err(e) => return eprintln!("Problem reading input: {}", e),
+    };
+
+    // Show what would change before anything is written
+    ui::display_import_plan(io, &plan);
+    match ui::confirm(io, "\nImport these values?") {
+        Ok(true) => match plan.apply(conn) {
+            Ok(count) => io.write_line(&format!("\nImported {} value(s).", count)),
+            Err(e) => io.write_line(&format!("\nNothing was imported: {}", e)),
+        },
+        Ok(false) | Err(InputError::Cancelled) => io.write_line("\nNothing was imported."),
+        Err(e) => eprintln!("Problem reading input: {}", e),
+    }
+}
+
+/// Applies a `ui::BulkAction` to every task in `ids`.
+fn apply_bulk_action(
+    conn: &Connection,
+    ids: &[u32],
+    action: &ui::BulkAction,
+) -> rusqlite::Result<()> {
+    match action {
+        ui::BulkAction::SetPriority(priority) => db::set_priority_bulk(conn, ids, priority),
+        ui::BulkAction::Move(parent_id) => db::move_tasks_bulk(conn, ids, *parent_id),
+        ui::BulkAction::Archive => db::archive_tasks_bulk(conn, ids),
+        ui::BulkAction::Delete => {
+            // Either every task goes or none do
+            let tx = conn.unchecked_transaction()?;
+            for id in ids {
+                db::delete_task_by_id(&tx, *id)?;
+            }
+            tx.commit()
+        }
+    }
+}
+
+/// Shows the user their current funds and allows them to enter a custom
+/// transaction.
+///
+/// # Arguments
+///
+/// * `conn: &Connection` - `ui::display_funds()` requires a &Connection, so
+///   it's required here too.
+fn shop(io: &mut impl ui::IoPort, conn: &Connection) {
+    let money = money_format(conn);
+
+    ui::print_header(AppState::Shop);
+    ui::display_funds(finance::calc_funds(conn), &money);
+    match ui::request_shop_action(io) {
+        Ok(ui::ShopAction::Transaction) => {
+            let require_sign = matches!(
+                db::read_setting_u32(conn, "require_transaction_sign"),
+                Ok(Some(1))
+            );
+            match ui::request_transaction(io, conn, require_sign) {
+                Ok(()) | Err(InputError::Cancelled) => {}
+                Err(e) => eprintln!("Problem reading transaction: {}", e),
+            }
+            ui::display_funds(finance::calc_funds(conn), &money);
+        }
+        Ok(ui::ShopAction::Export) => export_transactions(io, conn),
+        Err(InputError::Cancelled) => return,
+        Err(e) => return eprintln!("Problem reading input: {}", e),
+    }
     ui::wait_for_interaction();
+}
 
-    // Payout the bounty
-    db::add_transaction(conn, bounty);
+/// Lists the settings so one can be changed.
+fn settings(io: &mut impl ui::IoPort, conn: &Connection) {
+    ui::print_header(AppState::Settings);
 
-    // Record the task as complete
-    if selected_task.repeat_interval.is_some() {
-        db::reset_from_date(conn, selected_task.id);
-    } else {
-        db::archive_task(conn, selected_task.id);
+    let settings = current_settings(conn);
+    let chosen = ui::request_setting(io, &settings)
+        .and_then(|i| Ok((settings[i].0, ui::request_setting_value(io, settings[i].0)?)));
+
+    match chosen {
+        Ok((key, value)) => match save_setting(conn, &settings, key, value) {
+            Ok(()) => io.write_line(&format!("\n{key} is now {value}.")),
+            Err(e) => eprintln!("Problem saving setting: {}", e),
+        },
+        Err(InputError::Cancelled) => io.write_line("\nNo settings were changed."),
+        Err(e) => eprintln!("Problem reading input: {}", e),
     }
+}
 
-    Ok(())
+/// Reads every setting in `db::DEFAULT_SETTINGS` with the value in effect,
+/// which is the default if it's missing from the db.
+///
+/// # Notes
+///
+/// vacation_mode is left out. It's turned on and off from the Vacation
+/// screen, which also records when the vacation started.
+fn current_settings(conn: &Connection) -> Vec<(&'static str, u32)> {
+    db::DEFAULT_SETTINGS
+        .iter()
+        .filter(|(key, _)| *key != "vacation_mode")
+        .map(|(key, _)| (*key, db::setting_or_default(conn, key)))
+        .collect()
 }
 
-// fn task_selected(conn: &Connection, task: &Task) {
-//     ui::display_task(task);
-//     finance::payout(conn, task);
-//     db::increment_times_selected(conn, task.id, task.times_selected);
+/// Writes one setting. The monthly allowances are saved as a pair, so the
+/// target can't end up above the maximum, and quick_capture_folder has to be
+/// an existing folder.
+///
+/// # Arguments
+///
+/// * `conn: &Connection` - Allows us to access the SQLite db.
+/// * `settings: &[(&str, u32)]` - The current values. See
+///   `current_settings()`.
+/// * `key: &str` - The setting to change.
+/// * `value: u32` - Its new value.
+fn save_setting(
+    conn: &Connection,
+    settings: &[(&str, u32)],
+    key: &str,
+    value: u32,
+) -> Result<(), Box<dyn Error>> {
+    let current = |key: &str| {
+        settings
+            .iter()
+            .find(|(current_key, _)| *current_key == key)
+            .map_or(0, |(_, value)| *value)
+    };
 
-//     if task.repeat_interval.is_some() {
-//         db::reset_from_date(conn, task.id);
-//     } else {
-//         db::archive_task(conn, task.id);
-//     }
-// }
+    match key {
+        "target_monthly_allowance" => Ok(finance::set_allowances(
+            conn,
+            value,
+            current("maximum_monthly_allowance"),
+        )?),
+        "maximum_monthly_allowance" => Ok(finance::set_allowances(
+            conn,
+            current("target_monthly_allowance"),
+            value,
+        )?),
+        "quick_capture_folder"
+            if !db::read_all_folders_sorted(conn)?
+                .iter()
+                .any(|(id, _)| *id == value) =>
+        {
+            Err(DbError::FolderNotFound(value).into())
+        }
+        _ => Ok(db::update_setting(conn, key, value)?),
+    }
+}
+
+/// Saves every transaction to a CSV file the user picks.
+fn export_transactions(io: &mut impl ui::IoPort, conn: &Connection) {
+    let path = match ui::request_export_path(io, "transactions.csv") {
+        Ok(path) => path,
+        Err(InputError::Cancelled) => return io.write_line("\nNothing was exported."),
+        Err(e) => return eprintln!("Problem reading path: {}", e),
+    };
+    match fs::write(&path, finance::export_transactions_csv(conn)) {
+        Ok(()) => io.write_line(&format!("\nSaved your transactions to {}", path.display())),
+        Err(e) => eprintln!("Problem exporting transactions: {}", e),
+    }
+}
+
+/// Reads the user's preferred way of displaying money from the settings
+/// table, falling back to the defaults for anything missing or invalid.
+fn money_format(conn: &Connection) -> ui::MoneyFormat {
+    // More places than this would just be showing floating point noise
+    const MAX_DECIMALS: u32 = 6;
+
+    ui::MoneyFormat {
+        style: match db::setting_or_default(conn, "number_style") {
+            1 => ui::NumberStyle::PeriodGrouped,
+            _ => ui::NumberStyle::CommaGrouped,
+        },
+        decimals: db::setting_or_default(conn, "currency_decimals").min(MAX_DECIMALS) as usize,
+    }
+}
+
+/// Reads how heavily overdue deadline tasks are weighted from the settings
+/// table, falling back to the defaults for anything missing.
+fn overdue_curve(conn: &Connection) -> OverdueCurve {
+    OverdueCurve {
+        cap: db::setting_or_default(conn, "overdue_weight_cap") as f32,
+        critical_days: db::setting_or_default(conn, "overdue_critical_days"),
+    }
+}
+
+/// Reads the settings that affect how task lists are displayed.
+fn list_options(conn: &Connection) -> ui::ListOptions {
+    ui::ListOptions {
+        preview_length: db::setting_or_default(conn, "description_preview_length") as usize,
+        summary_length: ui::max_summary_length(conn),
+        per_view: match db::setting_or_default(conn, "tasks_per_view") {
+            0 => db::default_setting("tasks_per_view") as usize,
+            n => n as usize,
+        },
+        show_bounties: finance::is_enabled(conn),
+        money: money_format(conn),
+        show_urgency: show_urgency(conn),
+    }
+}
+
+/// Lets the user tick off several tasks from a folder's ToDo list in one go,
+/// then sums up what they earned.
+fn complete_several(io: &mut impl ui::IoPort, conn: &Connection) {
+    ui::print_header(AppState::CompleteSeveral);
+
+    let parent_id = match ui::request_parent_id(io, conn) {
+        Ok(parent_id) => parent_id,
+        Err(InputError::Cancelled) => return,
+        Err(e) => return eprintln!("Problem reading folder: {}", e),
+    };
+    let candidates = match shortlist(conn, parent_id) {
+        Ok(candidates) if !candidates.is_empty() => candidates,
+        Ok(_) => return io.write_line("\nThere's nothing to do in that folder right now."),
+        Err(e) => return eprintln!("Database error: {e}"),
+    };
+
+    io.write_line("\nWhich tasks did you finish?\n");
+    let tasks: Vec<Task> = candidates.iter().map(|(task, _)| task.clone()).collect();
+    let done: Vec<(Task, f64)> = match ui::select_tasks(io, &tasks) {
+        Ok(selected) => candidates
+            .into_iter()
+            .filter(|(task, _)| selected.iter().any(|picked| picked.id == task.id))
+            .collect(),
+        Err(InputError::Cancelled) => return,
+        Err(e) => return eprintln!("Problem selecting tasks: {}", e),
+    };
+
+    match complete_queue(conn, &done) {
+        Ok(summary) => {
+            ui::display_queue_summary(io, &summary, finance::is_enabled(conn), &money_format(conn))
+        }
+        Err(e) => eprintln!("Problem completing tasks: {}", e),
+    }
+}
+
+/// Completes each task in a queue as if it had been picked from the ToDo
+/// list, so every payout and completion is recorded as usual.
+///
+/// # Arguments
+///
+/// * `conn: &Connection` - Allows us to access the SQLite db.
+/// * `queue: &[(Task, f64)]` - The tasks with the bounty each should pay.
+fn complete_queue(conn: &Connection, queue: &[(Task, f64)]) -> Result<ui::QueueSummary, DbError> {
+    let mut summary = ui::QueueSummary::default();
+    for (task, bounty) in queue {
+        summary.funds_earned += complete_task(conn, task, *bounty)?;
+        summary.tasks_completed += 1;
+    }
+
+    Ok(summary)
+}
+
+/// Shows the tasks saved for later and moves the ones the user picks back
+/// into the active set.
+fn later(io: &mut impl ui::IoPort, conn: &Connection) {
+    ui::print_header(AppState::Later);
+
+    let deferred = match db::read_deferred(conn) {
+        Ok(deferred) => deferred,
+        Err(e) => return eprintln!("Problem reading tasks: {}", e),
+    };
+    if deferred.is_empty() {
+        io.write_line("\nNothing has been saved for later.");
+        return;
+    }
+
+    io.write_line("\nPick any tasks you're ready to do again.\n");
+    match ui::select_tasks(io, &deferred) {
+        Ok(selected) => {
+            for task in &selected {
+                if let Err(e) = db::promote_task(conn, task.id) {
+                    return eprintln!("Problem bringing back tasks: {}", e);
+                }
+            }
+            io.write_line(&format!("\nBrought back {} task(s).", selected.len()));
+        }
+        Err(InputError::Cancelled) => {}
+        Err(e) => eprintln!("Problem selecting tasks: {}", e),
+    }
+}
+
+/// How many archived tasks are listed at a time.
+const ARCHIVE_PAGE_LENGTH: usize = 20;
+
+/// Lists the archived tasks a page at a time, most recent first, and restores
+/// the one the user picks. Taking back its bounty is offered as a separate
+/// step.
+fn archive(io: &mut impl ui::IoPort, conn: &Connection) {
+    ui::print_header(AppState::Archive);
+
+    let archived = match db::read_archived_tasks(conn) {
+        Ok(archived) => archived,
+        Err(e) => return eprintln!("Problem reading tasks: {}", e),
+    };
+    if archived.is_empty() {
+        return io.write_line("\nNothing has been archived yet.");
+    }
+
+    io.write_line("");
+    let task = match ui::request_task_to_restore(io, &archived, ARCHIVE_PAGE_LENGTH) {
+        Ok(Some(index)) => &archived[index],
+        Ok(None) | Err(InputError::Cancelled) => return,
+        Err(e) => return eprintln!("Problem reading selection: {}", e),
+    };
+    if let Err(e) = db::unarchive_task(conn, task.id) {
+        return eprintln!("Problem restoring task: {}", e);
+    }
+    io.write_line(&format!("\nRestored \"{}\".", task.summary));
+
+    let last_payout = match db::read_payouts(conn, task.id) {
+        Ok(payouts) => payouts.last().map(|(_, bounty)| *bounty),
+        Err(e) => return eprintln!("Problem reading payouts: {}", e),
+    };
+    if let Some(bounty) = last_payout {
+        let prompt = format!(
+            "\nIt paid {} when it was completed. Take that back?",
+            ui::format_money(bounty, &money_format(conn))
+        );
+        match ui::confirm(io, &prompt) {
+            Ok(true) => {
+                if let Err(e) = db::reverse_payout(conn, task.id, bounty) {
+                    eprintln!("Problem taking back the bounty: {}", e);
+                }
+            }
+            Ok(false) | Err(InputError::Cancelled) => {}
+            Err(e) => eprintln!("Problem reading confirmation: {}", e),
+        }
+    }
+}
+
+/// Turns vacation mode on, or off if it's already on.
+fn vacation(io: &mut impl ui::IoPort, conn: &Connection) {
+    ui::print_header(AppState::Vacation);
+
+    let now = Utc::now();
+    let started = vacation_started(conn, now);
+    let prompt = match started {
+        Some(started) => format!(
+            "\nYou've been on vacation since {}. Welcome back! End vacation mode?",
+            started.with_timezone(&chrono::Local).format("%a %b %d")
+        ),
+        None => {
+            "\nRecurring tasks will be paused until you come back. Start vacation mode?".to_string()
+        }
+    };
+
+    match ui::confirm(io, &prompt) {
+        Ok(true) => {
+            let result = match started {
+                Some(_) => end_vacation(conn, now),
+                None => start_vacation(conn, now),
+            };
+            if let Err(e) = result {
+                eprintln!("Problem updating vacation mode: {}", e);
+            }
+        }
+        Ok(false) | Err(InputError::Cancelled) => {}
+        Err(e) => eprintln!("Problem reading confirmation: {}", e),
+    }
+}
+
+/// Archives the folder the user picks, or restores it if it's already
+/// archived.
+fn archive_folder(io: &mut impl ui::IoPort, conn: &Connection) {
+    ui::print_header(AppState::ArchiveFolder);
+
+    let id = match ui::request_parent_id(io, conn) {
+        Ok(id) => id,
+        Err(InputError::Cancelled) => return,
+        Err(e) => return eprintln!("Problem reading folder: {}", e),
+    };
+    let is_archived = match db::read_folder_status(conn, id) {
+        Ok(status) => status == Some(folders::STATUS_ARCHIVED),
+        Err(e) => return eprintln!("Problem reading folder: {}", e),
+    };
+
+    let (prompt, status) = if is_archived {
+        ("\nThis folder is archived. Restore it?", None)
+    } else {
+        (
+            "\nArchive this folder? Its tasks and subfolders will be hidden from ToDo.",
+            Some(folders::STATUS_ARCHIVED),
+        )
+    };
+    match ui::confirm(io, prompt) {
+        Ok(true) => {
+            if let Err(e) = db::set_folder_status(conn, id, status) {
+                eprintln!("Problem updating folder: {}", e);
+            }
+        }
+        Ok(false) | Err(InputError::Cancelled) => io.write_line("\nThe folder wasn't changed."),
+        Err(e) => eprintln!("Problem reading confirmation: {}", e),
+    }
+}
+
+/// Picks a folder and changes it.
+fn manage_folders(io: &mut impl ui::IoPort, conn: &Connection) {
+    ui::print_header(AppState::ManageFolders);
+
+    let id = match ui::request_parent_id(io, conn) {
+        Ok(id) => id,
+        Err(InputError::Cancelled) => return,
+        Err(e) => return eprintln!("Problem reading folder: {}", e),
+    };
+
+    match ui::request_folder_action(io) {
+        Ok(ui::FolderAction::Rename) => rename_folder(io, conn, id),
+        Ok(ui::FolderAction::Delete(mode)) => delete_folder(io, conn, id, mode),
+        Err(InputError::Cancelled) => io.write_line("\nThe folder wasn't changed."),
+        Err(e) => eprintln!("Problem reading input: {}", e),
+    }
+}
+
+fn rename_folder(io: &mut impl ui::IoPort, conn: &Connection, id: u32) {
+    let name = match ui::request_folder_name(io) {
+        Ok(name) => name,
+        Err(InputError::Cancelled) => return io.write_line("\nThe folder wasn't changed."),
+        Err(e) => return eprintln!("Problem reading folder name: {}", e),
+    };
+
+    match db::rename_folder(conn, id, &name) {
+        Ok(()) => io.write_line(&format!("\nRenamed the folder to \"{name}\".")),
+        Err(e) => eprintln!("Problem renaming folder: {}", e),
+    }
+}
+
+fn delete_folder(io: &mut impl ui::IoPort, conn: &Connection, id: u32, mode: db::DeleteMode) {
+    // Cascading can archive a lot of tasks, so spell out what will happen
+    let prompt = match mode {
+        db::DeleteMode::Reparent => {
+            "\nDelete this folder? Its tasks and subfolders will move up into its parent."
+        }
+        db::DeleteMode::Cascade => {
+            "\nDelete this folder and every folder below it? All of their tasks will be archived."
+        }
+    };
+    match ui::confirm(io, prompt) {
+        Ok(true) => {}
+        Ok(false) | Err(InputError::Cancelled) => {
+            return io.write_line("\nThe folder wasn't changed.")
+        }
+        Err(e) => return eprintln!("Problem reading confirmation: {}", e),
+    }
+
+    match db::delete_folder(conn, id, mode) {
+        Ok(()) => io.write_line("\nDeleted the folder."),
+        Err(e) => eprintln!("Problem deleting folder: {}", e),
+    }
+}
+
+/// Searches the unarchived tasks for a keyword and archives every match once
+/// the user confirms.
+fn archive_matching(io: &mut impl ui::IoPort, conn: &Connection) {
+    ui::print_header(AppState::ArchiveMatching);
+
+    let keyword = match ui::request_keyword(io) {
+        Ok(keyword) => keyword,
+        Err(InputError::Cancelled) => return,
+        Err(e) => return eprintln!("Problem reading keyword: {}", e),
+    };
+
+    let matches = match db::search_tasks(conn, &keyword) {
+        Ok(matches) => matches,
+        Err(e) => return eprintln!("Problem searching tasks: {}", e),
+    };
+    if matches.is_empty() {
+        io.write_line(&format!("\nNo unarchived tasks mention \"{keyword}\"."));
+        return;
+    }
+
+    io.write_line("");
+    for task in &matches {
+        io.write_line(&ui::truncate_to(
+            &task.summary,
+            ui::DEFAULT_MAX_SUMMARY_LENGTH,
+        ));
+    }
+    let prompt = format!(
+        "\n{} unarchived task(s) mention \"{keyword}\". Archive them all?",
+        matches.len()
+    );
+    match ui::confirm(io, &prompt) {
+        Ok(true) => {}
+        Ok(false) | Err(InputError::Cancelled) => {
+            return io.write_line("\nNo tasks were changed.");
+        }
+        Err(e) => return eprintln!("Problem reading confirmation: {}", e),
+    }
+
+    let ids: Vec<u32> = matches.iter().map(|task| task.id).collect();
+    match db::archive_tasks_bulk(conn, &ids) {
+        Ok(()) => io.write_line(&format!("\nArchived {} task(s).", ids.len())),
+        Err(e) => eprintln!("Problem archiving tasks: {}", e),
+    }
+}
+
+/// Lets the user pick a folder, then move its tasks up or down one place at a
+/// time until they cancel.
+fn reorder(io: &mut impl ui::IoPort, conn: &Connection) {
+    ui::print_header(AppState::Reorder);
+
+    let parent_id = match ui::request_parent_id(io, conn) {
+        Ok(parent_id) => parent_id,
+        Err(InputError::Cancelled) => return,
+        Err(e) => return eprintln!("Problem reading folder: {}", e),
+    };
+
+    loop {
+        let tasks = match db::read_tasks_in_order(conn, parent_id) {
+            Ok(tasks) => tasks,
+            Err(e) => return eprintln!("Problem reading tasks: {}", e),
+        };
+        if tasks.len() < 2 {
+            io.write_line("\nThere's nothing to reorder in that folder.");
+            return;
+        }
+
+        match ui::request_move(io, &tasks) {
+            Ok((index, new_index)) => {
+                if let Err(e) = db::reorder_task(conn, tasks[index].id, new_index) {
+                    return eprintln!("Problem reordering tasks: {}", e);
+                }
+            }
+            Err(InputError::Cancelled) => return,
+            Err(e) => return eprintln!("Problem reading selection: {}", e),
+        }
+    }
+}
+
+/// Lets the user pick from the tasks that have been paid out, then shows how
+/// each one's bounty has changed.
+fn task_history(io: &mut impl ui::IoPort, conn: &Connection) {
+    ui::print_header(AppState::History);
+
+    let tasks = match db::read_all_tasks(conn) {
+        Ok(tasks) => tasks,
+        Err(e) => return eprintln!("Problem reading tasks: {}", e),
+    };
+    let paid: Vec<(Task, Vec<db::Payout>)> = tasks
+        .into_iter()
+        .filter_map(|task| match db::read_payouts(conn, task.id) {
+            Ok(payouts) if !payouts.is_empty() => Some((task, payouts)),
+            _ => None,
+        })
+        .collect();
+    if paid.is_empty() {
+        io.write_line("No bounties have been paid out yet.");
+        return;
+    }
+
+    let tasks: Vec<Task> = paid.iter().map(|(task, _)| task.clone()).collect();
+    let selected = match ui::select_tasks(io, &tasks) {
+        Ok(selected) => selected,
+        Err(InputError::Cancelled) => return,
+        Err(e) => return eprintln!("Problem reading selection: {}", e),
+    };
+
+    let money = money_format(conn);
+    for task in selected {
+        if let Some((_, payouts)) = paid.iter().find(|(paid, _)| paid.id == task.id) {
+            ui::display_task_history(io, &task, payouts, &money);
+        }
+    }
+}
+
+/// Shows how a month of completing the top task each day would play out.
+fn simulate(conn: &Connection) {
+    // Matches the length of a month everywhere else in the app
+    const SIMULATED_DAYS: u32 = 30;
+
+    ui::print_header(AppState::Simulate);
+
+    let clock = clock::FixedClock::new(Utc::now());
+    match simulation::simulate(conn, SIMULATED_DAYS, &clock) {
+        Ok(report) => ui::display_simulation(&report, &money_format(conn)),
+        Err(e) => eprintln!("Problem running simulation: {e}"),
+    }
+    ui::wait_for_interaction();
+}
+
+/// Shows the user their current completion streak and a heatmap of recent
+/// completions.
+///
+/// # Arguments
+///
+/// * `io: &mut impl ui::IoPort` - Where the stats are written and input read.
+/// * `conn: &Connection` - The stats are read from the db.
+fn stats(io: &mut impl ui::IoPort, conn: &Connection) {
+    ui::print_header(AppState::Stats);
+    let history = finance::completion_streak(conn)
+        .and_then(|streak| Ok((streak, db::completions_by_day(conn, HEATMAP_DAYS)?)));
+    match history {
+        Ok((streak, history)) => ui::display_streak(streak, &history),
+        Err(e) => return eprintln!("Problem reading completions: {}", e),
+    }
+    ui::display_stats(io, &read_stats_summary(conn), &money_format(conn));
+
+    let now = Utc::now();
+    let since = now - Duration::days(RECENT_COMPLETION_DAYS as i64);
+    match db::read_completions_between(conn, since, now) {
+        Ok(completions) => ui::display_completions(io, &completions, RECENT_COMPLETION_DAYS),
+        Err(e) => eprintln!("Problem reading completions: {}", e),
+    }
+
+    if finance::is_enabled(conn) {
+        let shown = ui::confirm(io, "\nShow the transaction history?").and_then(|show| {
+            if show {
+                let ledger = finance::ledger(&db::read_transactions(conn));
+                ui::display_transaction_log(io, &ledger, &money_format(conn))
+            } else {
+                Ok(())
+            }
+        });
+        match shown {
+            Ok(()) | Err(InputError::Cancelled) => {}
+            Err(e) => eprintln!("Problem reading input: {}", e),
+        }
+    }
+    ui::wait_for_interaction();
+}
+
+/// Gathers the running totals for Stats.
+///
+/// # Notes
+///
+/// Totals that are missing or unreadable are shown as 0 rather than hiding
+/// the rest, with a warning for the unreadable ones. See `read_total()`.
+fn read_stats_summary(conn: &Connection) -> ui::StatsSummary {
+    let funds = finance::is_enabled(conn).then(|| ui::FundsSummary {
+        remaining: finance::calc_funds(conn),
+        earned_this_month: read_total(
+            db::funds_added_this_month(conn).map(Some),
+            "funds_added_this_month",
+        )
+        .unwrap_or(0.0),
+        unlocked: read_total(
+            db::read_statistic_f64(conn, "funds_unlocked"),
+            "funds_unlocked",
+        )
+        .unwrap_or(0.0),
+        loaded: read_total(db::read_statistic_f64(conn, "funds_loaded"), "funds_loaded")
+            .unwrap_or(0.0),
+        baseline_bounty: read_total(
+            db::read_statistic_f64(conn, "baseline_bounty"),
+            "baseline_bounty",
+        ),
+    });
+
+    ui::StatsSummary {
+        total_tasks_completed: read_total(
+            db::read_statistic_u32(conn, "total_tasks_completed"),
+            "total_tasks_completed",
+        )
+        .unwrap_or(0),
+        average_completion: read_total(
+            db::read_statistic_u32(conn, "average_completion_seconds"),
+            "average_completion_seconds",
+        )
+        .map(|seconds| Duration::seconds(seconds as i64)),
+        funds,
+    }
+}
+
+/// Unwraps one of `read_stats_summary()`'s totals, treating one that can't be
+/// read as missing and warning about it.
+fn read_total<T, E: std::fmt::Display>(total: Result<Option<T>, E>, name: &str) -> Option<T> {
+    total.unwrap_or_else(|e| {
+        eprintln!("Warning: couldn't read {name} ({e}).");
+        None
+    })
+}
+
+/// How many days of history the Stats heatmap covers.
+const HEATMAP_DAYS: u32 = 28;
+
+/// How many days back Stats lists completed tasks.
+const RECENT_COMPLETION_DAYS: u32 = 7;
+
+fn to_do(conn: &Connection, shown_this_session: &mut HashSet<u32>) -> Result<(), Box<dyn Error>> {
+    ui::print_header(AppState::ToDo);
+
+    // Print the folder tree
+    // Request folder selection
+    let parent_id = match ui::request_parent_id(&mut ui::StdIo, conn) {
+        Ok(id) => id,
+        Err(InputError::Cancelled) => return Ok(()),
+        Err(InputError::Io(e)) => return Err(e.into()),
+    };
+
+    // Build the ranked shortlist from the folder and its descendants
+    let tasks_w_bounties = session_shortlist(conn, parent_id, shown_this_session)?;
+
+    // With nothing to pick from, show what's coming up instead
+    if tasks_w_bounties.is_empty() {
+        let now = Utc::now();
+        let upcoming = folder_tasks(conn, parent_id, now)
+            .map(|tasks| forecast(tasks, now, FORECAST_LENGTH))
+            .unwrap_or_default();
+        ui::display_forecast(&mut ui::StdIo, &upcoming);
+        ui::wait_for_interaction();
+        return Ok(());
+    }
+
+    // User selects a task from the remaining list
+    let (selected_task, bounty) = match choose_task(
+        &mut ui::StdIo,
+        conn,
+        &tasks_w_bounties,
+        &list_options(conn),
+        shown_this_session,
+    ) {
+        Ok(choice) => choice,
+        Err(InputError::Cancelled) => return Ok(()),
+        Err(InputError::Io(e)) => return Err(e.into()),
+    };
+
+    // Display the selected task
+    ui::display_task(&selected_task);
+    if finance::is_enabled(conn)
+        && matches!(
+            db::read_setting_u32(conn, "show_bounty_breakdown"),
+            Ok(Some(1))
+        )
+    {
+        let breakdown = finance::bounty_breakdown(conn, &selected_task);
+        ui::display_bounty_breakdown(&breakdown, &money_format(conn));
+    }
+    let shown_at = Utc::now();
+
+    // Optionally time the work, so its length can count towards the average.
+    // Otherwise the time until the user answers stands in for it
+    let mut elapsed = None;
+    if let Some(length) = work_session_length(conn) {
+        let prompt = format!("\nStart a {} minute work session?", length.num_minutes());
+        match ui::confirm(&mut ui::StdIo, &prompt) {
+            Ok(true) => {
+                let end = session::run(&clock::SystemClock, length)?;
+                if end.completed {
+                    warn_if_capped(conn, bounty)?;
+                    record_work_time(conn, &selected_task, end.elapsed)?;
+                    record_outcome(conn, &selected_task, bounty, &ui::TaskOutcome::Complete)?;
+                    return Ok(());
+                }
+                elapsed = Some(end.elapsed);
+            }
+            Ok(false) => {}
+            Err(InputError::Cancelled) => return Ok(()),
+            Err(InputError::Io(e)) => return Err(e.into()),
+        }
+    }
+
+    match ui::request_task_outcome(&mut ui::StdIo) {
+        Ok(outcome) => {
+            if outcome == ui::TaskOutcome::Complete {
+                warn_if_capped(conn, bounty)?;
+                let elapsed = elapsed.unwrap_or_else(|| Utc::now() - shown_at);
+                record_work_time(conn, &selected_task, elapsed)?;
+            }
+            record_outcome(conn, &selected_task, bounty, &outcome)?
+        }
+        Err(InputError::Cancelled) => {}
+        Err(InputError::Io(e)) => return Err(e.into()),
+    }
+
+    Ok(())
+}
+
+/// How long a work session lasts, or None if they're turned off.
+fn work_session_length(conn: &Connection) -> Option<Duration> {
+    if !matches!(
+        db::read_setting_u32(conn, "work_sessions_enabled"),
+        Ok(Some(1))
+    ) {
+        return None;
+    }
+
+    match db::setting_or_default(conn, "work_session_minutes") {
+        0 => None,
+        minutes => Some(Duration::minutes(i64::from(minutes))),
+    }
+}
+
+/// Anything longer than this many hours is taken to mean the user walked
+/// away, rather than how long the task took.
+const MAX_WORK_HOURS: i64 = 24;
+
+/// Counts the time spent on a completed task towards its average_duration.
+/// Times over `MAX_WORK_HOURS` are ignored.
+fn record_work_time(conn: &Connection, task: &Task, elapsed: Duration) -> Result<(), DbError> {
+    if elapsed > Duration::hours(MAX_WORK_HOURS) {
+        return Ok(());
+    }
+    db::set_average_duration(conn, task.id, task.average_with(elapsed))
+}
+
+/// Reads the unarchived tasks in a folder and all of its descendants.
+///
+/// # Notes
+///
+/// Iterator folders only give up the task they're up to. See
+/// `db::next_iterator_task()`. Tasks still snoozed at `now` are left out, see
+/// `db::snooze_task()`.
+fn folder_tasks(
+    conn: &Connection,
+    parent_id: u32,
+    now: DateTime<Utc>,
+) -> Result<Vec<Task>, DbError> {
+    let mut folder_ids = db::get_descendant_ids(conn, parent_id)?;
+    folder_ids.push(parent_id);
+
+    // Tasks in archived folders are treated as archived too, unless the user
+    // wants them anyway
+    let hidden = if matches!(
+        db::read_setting_u32(conn, "hide_archived_folder_tasks"),
+        Ok(Some(0))
+    ) {
+        HashSet::new()
+    } else {
+        db::archived_folder_ids(conn)?
+    };
+
+    let snoozed = db::snoozed_ids(conn, now)?;
+
+    let styles = db::read_folder_styles(conn)?;
+    let mut current = HashSet::new();
+    for id in folder_ids
+        .iter()
+        .filter(|id| styles.get(id) == Some(&Style::Iterator))
+    {
+        if let Some(task) = db::next_iterator_task(conn, *id)? {
+            current.insert(task.id);
+        }
+    }
+
+    Ok(db::fetch_tasks_by_parent_ids(conn, folder_ids)?
+        .into_iter()
+        .filter(|task| {
+            !task.is_archived && !hidden.contains(&task.parent_id) && !snoozed.contains(&task.id)
+        })
+        .filter(|task| {
+            styles.get(&task.parent_id) != Some(&Style::Iterator) || current.contains(&task.id)
+        })
+        .collect())
+}
+
+/// How many upcoming tasks to show when there's nothing to do right now.
+const FORECAST_LENGTH: usize = 5;
+
+/// Finds the tasks that will become eligible soonest. See
+/// `Task::next_eligible_date()`.
+///
+/// # Arguments
+///
+/// * `tasks: Vec<Task>` - The tasks to look through.
+/// * `now: DateTime<Utc>` - Tasks that are already eligible are left out.
+/// * `count: usize` - The most tasks to return.
+///
+/// # Returns
+///
+/// Up to `count` tasks with the date each becomes eligible, soonest first.
+fn forecast(tasks: Vec<Task>, now: DateTime<Utc>, count: usize) -> Vec<(Task, DateTime<Utc>)> {
+    let mut upcoming: Vec<(Task, DateTime<Utc>)> = tasks
+        .into_iter()
+        .filter_map(|task| {
+            let date = task.next_eligible_date()?;
+            (date > now).then_some((task, date))
+        })
+        .collect();
+
+    upcoming.sort_by_key(|(task, date)| (*date, task.id));
+    upcoming.truncate(count);
+    upcoming
+}
+
+/// Sorts tasks by their weight at `now`, heaviest first.
+///
+/// If `budget_remaining` is Some, the weights are adjusted with
+/// `weighting::apply_budget_pressure()`. See `budget_pressure()`. If
+/// `penalty` is Some, the weights of the tasks in its set are multiplied by
+/// its factor. See `session_penalty()`.
+///
+/// There's no randomness involved. Equal weights are ordered by priority,
+/// highest first, then by from_date, oldest first, and finally by id, so the
+/// same tasks at the same `now` always rank the same way, whatever order the
+/// db returned them in. NaN weights sort last.
+fn rank_tasks(
+    tasks: &mut [Task],
+    curve: &OverdueCurve,
+    now: DateTime<Utc>,
+    budget_remaining: Option<f32>,
+    penalty: Option<(&HashSet<u32>, f32)>,
+) {
+    let weigh = |task: &Task| {
+        let weight = calculate_weight_at(task, curve, now);
+        let weight = match budget_remaining {
+            Some(remaining) => weighting::apply_budget_pressure(weight, task, remaining),
+            None => weight,
+        };
+        let weight = match penalty {
+            Some((penalized, factor)) if penalized.contains(&task.id) => weight * factor,
+            _ => weight,
+        };
+        // A weight that couldn't be worked out ranks below everything else
+        if weight.is_nan() {
+            f32::NEG_INFINITY
+        } else {
+            weight
+        }
+    };
+
+    tasks.sort_by(|a, b| {
+        weigh(b)
+            .total_cmp(&weigh(a))
+            .then(b.priority.cmp(&a.priority))
+            .then(a.from_date.cmp(&b.from_date))
+            .then(a.id.cmp(&b.id))
+    });
+}
+
+/// Reads how much of the monthly budget is left at `now` for `rank_tasks()`,
+/// if the user has turned on budget aware weighting.
+fn budget_pressure(conn: &Connection, now: DateTime<Utc>) -> Option<f32> {
+    let enabled = matches!(
+        db::read_setting_u32(conn, "budget_aware_weighting"),
+        Ok(Some(1))
+    );
+
+    (enabled && finance::is_enabled(conn)).then(|| finance::budget_remaining(conn, now))
+}
+
+/// Drops tasks the user saw in the ToDo list within the last
+/// `reshuffle_suppression_minutes`, so coming back to the list offers
+/// something new. In progress tasks are always kept.
+///
+/// # Notes
+///
+/// This is only about being shown. Completed tasks are held back by their
+/// own schedule instead. If every task was shown recently, nothing is
+/// dropped, since an old list beats an empty one.
+fn suppress_recently_shown(
+    conn: &Connection,
+    tasks: &mut Vec<Task>,
+    now: DateTime<Utc>,
+) -> Result<(), DbError> {
+    let minutes = match db::read_setting_u32(conn, "reshuffle_suppression_minutes") {
+        Ok(Some(n)) if n > 0 => n,
+        _ => return Ok(()),
+    };
+
+    let shown = db::shown_since(conn, now - Duration::minutes(i64::from(minutes)))?;
+    if tasks.iter().any(|task| !shown.contains(&task.id)) {
+        tasks.retain(|task| task.in_progress || !shown.contains(&task.id));
+    }
+    Ok(())
+}
+
+/// Drops all but the heaviest task from each Selector folder. Tasks in other
+/// folders are untouched.
+///
+/// # Arguments
+///
+/// * `sorted: &mut Vec<Task>` - The candidates, heaviest first.
+/// * `styles: &HashMap<u32, Style>` - Each folder's style, from
+///   `db::read_folder_styles()`.
+///
+/// # Notes
+///
+/// Tasks are grouped by the folder they're directly in, so a Selector nested
+/// anywhere below the folder being browsed still offers exactly one task.
+fn keep_one_per_selector(sorted: &mut Vec<Task>, styles: &HashMap<u32, Style>) {
+    let mut offered = HashSet::new();
+    sorted.retain(|task| {
+        styles.get(&task.parent_id) != Some(&Style::Selector) || offered.insert(task.parent_id)
+    });
+}
+
+/// Takes the first `count` tasks from a list that's already sorted by weight,
+/// optionally limiting how many may come from any one folder.
+///
+/// # Arguments
+///
+/// * `sorted: Vec<Task>` - The candidates, heaviest first.
+/// * `count: usize` - How many tasks to pick.
+/// * `folder_cap: Option<usize>` - The most tasks to pick from a single
+///   folder, or None for no limit.
+///
+/// # Notes
+///
+/// If there aren't enough tasks in other folders to reach `count`, the
+/// skipped tasks are used to fill the gap rather than showing a short list.
+fn pick_candidates(sorted: Vec<Task>, count: usize, folder_cap: Option<usize>) -> Vec<Task> {
+    let Some(cap) = folder_cap else {
+        return sorted.into_iter().take(count).collect();
+    };
+
+    // Work with positions so the heaviest-first order can be restored after
+    // backfilling
+    let mut picked: Vec<usize> = Vec::with_capacity(count);
+    let mut skipped: Vec<usize> = Vec::new();
+    let mut per_folder: HashMap<u32, usize> = HashMap::new();
+
+    for (index, task) in sorted.iter().enumerate() {
+        if picked.len() == count {
+            break;
+        }
+
+        let taken = per_folder.entry(task.parent_id).or_insert(0);
+        if *taken < cap {
+            *taken += 1;
+            picked.push(index);
+        } else {
+            skipped.push(index);
+        }
+    }
+
+    let shortfall = count - picked.len();
+    picked.extend(skipped.into_iter().take(shortfall));
+    picked.sort_unstable();
+
+    let mut sorted: Vec<Option<Task>> = sorted.into_iter().map(Some).collect();
+    picked
+        .into_iter()
+        .filter_map(|index| sorted[index].take())
+        .collect()
+}
+
+/// Shows the user the start of a ranked list and lets them pick a task,
+/// revealing more of the list on request.
+///
+/// # Arguments
+///
+/// * `io: &mut impl ui::IoPort` - Where prompts are written and input read.
+/// * `conn: &Connection` - Used to record which tasks were shown.
+/// * `tasks: &[(Task, f64)]` - The ranked tasks and their bounties.
+/// * `options: &ui::ListOptions` - `per_view` sets how many tasks are
+///   revealed at a time.
+/// * `shown_this_session: &mut HashSet<u32>` - Revealed tasks are added to
+///   it.
+///
+/// # Notes
+///
+/// Only the tasks that were actually revealed are recorded as shown.
+fn choose_task(
+    io: &mut impl ui::IoPort,
+    conn: &Connection,
+    tasks: &[(Task, f64)],
+    options: &ui::ListOptions,
+    shown_this_session: &mut HashSet<u32>,
+) -> Result<(Task, f64), InputError> {
+    let list: Vec<Task> = tasks.iter().map(|(task, _)| task.clone()).collect();
+    let scores = weighting::normalized_scores(&list, &overdue_curve(conn), &clock::SystemClock);
+    let mut visible = 0;
+
+    loop {
+        let revealed = (visible + options.per_view.max(1)).min(tasks.len());
+
+        // Record that each newly revealed task has been displayed
+        // Failing to record this shouldn't get in the way of picking a task
+        for (task, _) in &tasks[visible..revealed] {
+            shown_this_session.insert(task.id);
+            if let Err(e) = db::increment_times_shown(conn, task.id) {
+                eprintln!("Problem recording shown task: {}", e);
+            }
+        }
+        visible = revealed;
+
+        match ui::select_task(
+            io,
+            &tasks[..visible],
+            &scores[..visible],
+            visible < tasks.len(),
+            options,
+        )? {
+            ui::Selection::Pick(index) => return Ok(tasks[index].clone()),
+            ui::Selection::ShowMore => continue,
+        }
+    }
+}
+
+/// Lets the user know when the maximum monthly allowance means a task won't
+/// pay its full bounty.
+fn warn_if_capped(conn: &Connection, bounty: f64) -> Result<(), DbError> {
+    if !finance::is_enabled(conn) {
+        return Ok(());
+    }
+
+    let paid = finance::cap_bounty(conn, bounty, Utc::now())?;
+    if paid < bounty {
+        let format = money_format(conn);
+        println!(
+            "\nThis month's maximum allowance has been reached, so this task pays {} instead of {}.",
+            ui::format_money(paid, &format),
+            ui::format_money(bounty, &format)
+        );
+    }
+
+    Ok(())
+}
+
+/// Updates a task the user selected in the ToDo state, based on how they got
+/// on with it.
+///
+/// # Arguments
+///
+/// * `conn: &Connection` - Allows us to access the SQLite db.
+/// * `task: &Task` - The selected task.
+/// * `bounty: f64` - Paid out if the task was completed.
+/// * `outcome: &ui::TaskOutcome` - What the user did with the task.
+fn record_outcome(
+    conn: &Connection,
+    task: &Task,
+    bounty: f64,
+    outcome: &ui::TaskOutcome,
+) -> Result<(), DbError> {
+    match outcome {
+        ui::TaskOutcome::Complete => complete_task(conn, task, bounty).map(|_| ()),
+        ui::TaskOutcome::InProgress => db::set_in_progress(conn, task.id, true),
+        ui::TaskOutcome::Abandon => {
+            if task.in_progress {
+                db::set_in_progress(conn, task.id, false)?;
+            }
+            Ok(())
+        }
+        ui::TaskOutcome::Defer => {
+            if task.in_progress {
+                db::set_in_progress(conn, task.id, false)?;
+            }
+            db::defer_task(conn, task.id)
+        }
+        ui::TaskOutcome::Boost => {
+            if task.in_progress {
+                db::set_in_progress(conn, task.id, false)?;
+            }
+            let until = Utc::now() + Duration::days(weighting::BOOST_DAYS);
+            db::boost_task(conn, task.id, weighting::BOOST_FACTOR, until)
+        }
+        ui::TaskOutcome::Snooze(days) => {
+            if task.in_progress {
+                db::set_in_progress(conn, task.id, false)?;
+            }
+            db::snooze_task(conn, task.id, *days)
+        }
+    }
+}
+
+/// Works out where a task that hasn't been saved yet would rank among the
+/// active tasks.
+///
+/// # Arguments
+///
+/// * `conn: &Connection` - The active tasks are read from the db.
+/// * `task: &Task` - The unsaved task.
+///
+/// # Returns
+///
+/// The task's 1-based rank, and the number of tasks it was ranked against
+/// (including itself).
+///
+/// # Notes
+///
+/// This is only a preview, so nothing is recorded as shown.
+fn provisional_rank(conn: &Connection, task: &Task) -> Result<(usize, usize), DbError> {
+    let curve = overdue_curve(conn);
+    let weight = calculate_weight(task, &curve, &clock::SystemClock);
+    let active_tasks = db::read_active_tasks(conn)?;
+
+    let heavier = active_tasks
+        .iter()
+        .filter(|other| calculate_weight(other, &curve, &clock::SystemClock) > weight)
+        .count();
+
+    Ok((heavier + 1, active_tasks.len() + 1))
+}
+
+/// Builds the list of tasks the user can pick from in the ToDo state.
+///
+/// # Arguments
+///
+/// * `conn: &Connection` - Allows us to access the SQLite db.
+/// * `parent_id: u32` - The folder to draw tasks from. Tasks in any of its
+///   descendant folders are included too.
+///
+/// # Returns
+///
+/// The top `todo_candidate_count` (5 by default) active tasks ordered by
+/// weight, each paired with its bounty. The bounties are all 0.0 if finance
+/// is disabled. Unless the `exclude_in_progress` setting is off, tasks in
+/// progress come first and don't count towards the total.
+///
+/// # Notes
+///
+/// This function has no side effects, so it doesn't record the tasks as shown.
+fn shortlist(conn: &Connection, parent_id: u32) -> Result<Vec<(Task, f64)>, DbError> {
+    session_shortlist(conn, parent_id, &HashSet::new())
+}
+
+/// Builds the same list as `shortlist()`, but holds back the tasks in
+/// `shown_this_session`. See `session_penalty()`.
+fn session_shortlist(
+    conn: &Connection,
+    parent_id: u32,
+    shown_this_session: &HashSet<u32>,
+) -> Result<Vec<(Task, f64)>, DbError> {
+    let now = Utc::now();
+    rank_shortlist(
+        conn,
+        folder_tasks(conn, parent_id, now)?,
+        candidate_count(conn),
+        shown_this_session,
+        now,
+    )
+}
+
+/// Reads how much to cut the weight of tasks that have already been shown
+/// this session, as a factor to multiply by.
+///
+/// # Returns
+///
+/// None if the session_repeat_penalty setting is 0, or if every task in
+/// `tasks` has been shown already, since holding all of them back would
+/// change nothing.
+fn session_penalty(
+    conn: &Connection,
+    tasks: &[Task],
+    shown_this_session: &HashSet<u32>,
+) -> Option<f32> {
+    let percent = db::setting_or_default(conn, "session_repeat_penalty").min(100);
+    if percent == 0
+        || tasks
+            .iter()
+            .all(|task| shown_this_session.contains(&task.id))
+    {
+        return None;
+    }
+
+    Some(1.0 - percent as f32 / 100.0)
+}
+
+/// Builds the same list as `shortlist()`, but from every folder.
+///
+/// # Arguments
+///
+/// * `conn: &Connection` - Allows us to access the SQLite db.
+/// * `count: usize` - How many tasks to offer, not counting tasks in
+///   progress.
+///
+/// # Notes
+///
+/// This function has no side effects, so it doesn't record the tasks as shown.
+fn top_tasks(conn: &Connection, count: usize) -> Result<Vec<(Task, f64)>, DbError> {
+    top_tasks_at(conn, count, Utc::now())
+}
+
+/// Same as `top_tasks()`, but as if the current time were `now`.
+fn top_tasks_at(
+    conn: &Connection,
+    count: usize,
+    now: DateTime<Utc>,
+) -> Result<Vec<(Task, f64)>, DbError> {
+    let mut task_list = Vec::new();
+    for folder in db::read_folders(conn)?
+        .iter()
+        .filter(|folder| folder.parent_id.is_none())
+    {
+        task_list.extend(folder_tasks(conn, folder.id, now)?);
+    }
+    rank_shortlist(conn, task_list, count, &HashSet::new(), now)
+}
+
+/// Orders `task_list` by weight at `now` and cuts it down to the top
+/// `count`, then pairs each task with its bounty. See `shortlist()`.
+fn rank_shortlist(
+    conn: &Connection,
+    mut task_list: Vec<Task>,
+    count: usize,
+    shown_this_session: &HashSet<u32>,
+    now: DateTime<Utc>,
+) -> Result<Vec<(Task, f64)>, DbError> {
+    // Order the list, dropping recurring tasks that aren't due again yet, or
+    // all of them while the user is on vacation
+    let curve = overdue_curve(conn);
+    let on_vacation = vacation_started(conn, now).is_some();
+    task_list.retain(|task| {
+        !(on_vacation && task.repeat_interval.is_some())
+            && calculate_weight_at(task, &curve, now) > 0.0
+    });
+    let penalty = session_penalty(conn, &task_list, shown_this_session)
+        .map(|factor| (shown_this_session, factor));
+    rank_tasks(
+        &mut task_list,
+        &curve,
+        now,
+        budget_pressure(conn, now),
+        penalty,
+    );
+    suppress_recently_shown(conn, &mut task_list, now)?;
+    keep_one_per_selector(&mut task_list, &db::read_folder_styles(conn)?);
+
+    // Pull out the tasks the user has already started so they don't use up
+    // the fresh picks. The sort is stable, so both groups stay in weight order
+    if !matches!(
+        db::read_setting_u32(conn, "exclude_in_progress"),
+        Ok(Some(0))
+    ) {
+        task_list.sort_by_key(|task| !task.in_progress);
+    }
+    let started = task_list.iter().take_while(|task| task.in_progress).count();
+
+    // Shorten the list to the top few, not counting tasks in progress
+    let fresh = task_list.split_off(started);
+    let folder_cap = match db::read_setting_u32(conn, "max_tasks_per_folder") {
+        Ok(Some(n)) if n > 0 => Some(n as usize),
+        _ => None,
+    };
+    task_list.extend(pick_candidates(fresh, count, folder_cap));
+
+    // Calculate the bounty for each task
+    let finance_enabled = finance::is_enabled(conn);
+    Ok(task_list
+        .into_iter()
+        .map(|task| {
+            let bounty = if finance_enabled {
+                finance::adjusted_value_at(conn, &task, now)
+            } else {
+                0.0
+            };
+            (task, bounty)
+        })
+        .collect())
+}
+
+/// Reads how many tasks `shortlist()` offers from the settings table.
+fn candidate_count(conn: &Connection) -> usize {
+    match db::setting_or_default(conn, "todo_candidate_count") {
+        0 => db::default_setting("todo_candidate_count") as usize,
+        n => n as usize,
+    }
+}
+
+/// Records the selected task as complete and pays out its bounty.
+///
+/// # Arguments
+///
+/// * `conn: &Connection` - Allows us to access the SQLite db.
+/// * `task: &Task` - The task the user selected.
+/// * `bounty: f64` - The amount to pay out.
+///
+/// # Returns
+///
+/// The amount actually paid out.
+///
+/// # Notes
+///
+/// Recurring tasks have their from_date reset, everything else is archived.
+/// Nothing is paid out while finance is disabled, and a bounty is cut short
+/// rather than take the month over the maximum allowance.
+fn complete_task(conn: &Connection, task: &Task, bounty: f64) -> Result<f64, DbError> {
+    complete_task_at(conn, task, bounty, Utc::now())
+}
+
+/// Same as `complete_task()`, but as if the current time were `now`.
+fn complete_task_at(
+    conn: &Connection,
+    task: &Task,
+    bounty: f64,
+    now: DateTime<Utc>,
+) -> Result<f64, DbError> {
+    // Payout the bounty, up to what's left of the month's maximum
+    let paid = if finance::is_enabled(conn) {
+        Some(finance::cap_bounty(conn, bounty, now)?)
+    } else {
+        None
+    };
+
+    db::complete_task(conn, task, paid, now)?;
+    Ok(paid.unwrap_or(0.0))
+}
+
+// fn task_selected(conn: &Connection, task: &Task) {
+//     ui::display_task(task);
+//     finance::payout(conn, task);
+//     db::increment_times_selected(conn, task.id, task.times_selected);
+
+//     if task.repeat_interval.is_some() {
+//         db::reset_from_date(conn, task.id);
+//     } else {
+//         db::archive_task(conn, task.id);
+//     }
+// }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures;
+    use crate::tasks::{RecurrenceAnchor, RepeatUnit};
+
+    #[test]
+    fn test_add_select_complete_payout() {
+        let (conn, seed) = fixtures::seeded();
+        let chores = seed.folders["chores"];
+
+        // Add a new task to the Chores folder
+        let id = fixtures::insert_task(&conn, fixtures::task(chores, "Take out the trash"));
+
+        // It should be offered alongside the other chores
+        let candidates = shortlist(&conn, chores).unwrap();
+        let (task, bounty) = candidates
+            .into_iter()
+            .find(|(task, _)| task.id == id)
+            .expect("The new task should be in the shortlist.");
+        assert!(bounty > 0.0);
+
+        // Complete it and check the payout
+        fixtures::assert_funds(&conn, 0.0);
+        complete_task(&conn, &task, bounty).unwrap();
+        fixtures::assert_funds(&conn, bounty);
+
+        // One-off tasks are archived, so it should not be offered again
+        fixtures::assert_archived(&conn, id, true);
+        assert_eq!(fixtures::read_task(&conn, id).times_selected, 1);
+        assert!(shortlist(&conn, chores)
+            .unwrap()
+            .iter()
+            .all(|(task, _)| task.id != id));
+    }
+
+    #[test]
+    fn test_complete_recurring_task_resets_from_date() {
+        let (conn, seed) = fixtures::seeded();
+        let laundry = fixtures::read_task(&conn, seed.tasks["laundry"]);
+
+        complete_task(&conn, &laundry, 2.5).unwrap();
+
+        fixtures::assert_funds(&conn, 2.5);
+        fixtures::assert_archived(&conn, laundry.id, false);
+        let completed = fixtures::read_task(&conn, laundry.id);
+        assert!(completed.from_date > laundry.from_date);
+        assert_eq!(completed.created_at, laundry.created_at);
+    }
+
+    #[test]
+    fn test_complete_schedule_anchored_task_late_keeps_cadence() {
+        let conn = fixtures::setup();
+        let scheduled = Utc::now() - Duration::days(9);
+        let id = fixtures::insert_task(
+            &conn,
+            Task {
+                repeat_interval: Some(7),
+                from_date: scheduled,
+                recurrence_anchor: RecurrenceAnchor::Schedule,
+                ..fixtures::task(1, "Take out the bins")
+            },
+        );
+        let task = fixtures::read_task(&conn, id);
+
+        // Two days late, so the next occurrence is still 7 days after the
+        // one that was missed
+        let now = Utc::now();
+        complete_task_at(&conn, &task, 0.0, now).unwrap();
+        let completed = fixtures::read_task(&conn, id);
+        assert_eq!(completed.from_date, scheduled + Duration::days(7));
+
+        // Several intervals late skips the missed occurrences
+        complete_task_at(&conn, &completed, 0.0, now + Duration::days(20)).unwrap();
+        assert_eq!(
+            fixtures::read_task(&conn, id).from_date,
+            scheduled + Duration::days(28)
+        );
+    }
+
+    #[test]
+    fn test_forecast_lists_dormant_tasks_soonest_first() {
+        let conn = fixtures::setup();
+        let now = Utc::now();
+        let add = |task: Task| fixtures::insert_task(&conn, task);
+        let weekly = add(Task {
+            repeat_interval: Some(7),
+            from_date: now - Duration::days(2),
+            ..fixtures::task(1, "Weekly")
+        });
+        let deadline = add(Task {
+            due_date: Some(now + Duration::days(10)),
+            lead_days: Some(7),
+            ..fixtures::task(1, "Deadline")
+        });
+        let daily = add(Task {
+            repeat_interval: Some(2),
+            from_date: now - Duration::hours(1),
+            ..fixtures::task(1, "Every other day")
+        });
+
+        // Only the deadline task can be picked before its lead days start
+        let picks: Vec<u32> = shortlist(&conn, 1)
+            .unwrap()
+            .iter()
+            .map(|(task, _)| task.id)
+            .collect();
+        assert_eq!(picks, vec![deadline]);
+
+        let upcoming = forecast(folder_tasks(&conn, 1, now).unwrap(), now, FORECAST_LENGTH);
+        let ids: Vec<u32> = upcoming.iter().map(|(task, _)| task.id).collect();
+        assert_eq!(ids, vec![daily, deadline, weekly]);
+        assert_eq!(upcoming[1].1, now + Duration::days(3));
+
+        let mut io = ui::MockIo::new(&[]);
+        ui::display_forecast(&mut io, &upcoming);
+        let output = io.output.borrow().join("\n");
+        assert!(output.contains("Coming up"));
+        assert!(output.find("Every other day") < output.find("Weekly"));
+    }
+
+    #[test]
+    fn test_nan_weight_ranks_last() {
+        let now = Utc::now();
+        let task = |id: u32, summary: &str| Task {
+            id,
+            from_date: now - Duration::days(2),
+            ..fixtures::task(1, summary)
+        };
+        // An infinite weight with nothing left of its bounty factor is inf * 0
+        let broken = Task {
+            boost_factor: f32::INFINITY,
+            boost_until: Some(now + Duration::days(1)),
+            bounty_modifier: -1.0,
+            ..task(1, "Broken")
+        };
+        let curve = OverdueCurve::default();
+        let weight = calculate_weight_at(&broken, &curve, now);
+        assert!(weighting::apply_budget_pressure(weight, &broken, 1.0).is_nan());
+
+        let mut tasks = vec![broken, task(2, "Sweep"), task(3, "Mop")];
+        rank_tasks(&mut tasks, &curve, now, Some(1.0), None);
+        let ids: Vec<u32> = tasks.iter().map(|task| task.id).collect();
+        assert_eq!(ids, vec![2, 3, 1]);
+    }
+
+    #[test]
+    fn test_ranking_is_deterministic() {
+        let (conn, seed) = fixtures::seeded();
+        let from_date = Utc::now() - Duration::days(2);
+        for summary in ["Sweep", "Mop", "Dust"] {
+            fixtures::insert_task(
+                &conn,
+                Task {
+                    from_date,
+                    ..fixtures::task(seed.folders["home"], summary)
+                },
+            );
+        }
+        let (curve, now) = (overdue_curve(&conn), Utc::now());
+
+        // Those three weigh the same, so only the tie-break decides their order
+        let mut forwards = db::read_active_tasks(&conn).unwrap();
+        let mut backwards: Vec<Task> = forwards.iter().rev().cloned().collect();
+        rank_tasks(&mut forwards, &curve, now, None, None);
+        rank_tasks(&mut backwards, &curve, now, None, None);
+        let ids = |tasks: &[Task]| tasks.iter().map(|task| task.id).collect::<Vec<u32>>();
+        assert_eq!(ids(&forwards), ids(&backwards));
+
+        let picks = |conn: &Connection| {
+            shortlist(conn, seed.folders["general"])
+                .unwrap()
+                .iter()
+                .map(|(task, _)| task.id)
+                .collect::<Vec<u32>>()
+        };
+        assert_eq!(picks(&conn), picks(&conn));
+    }
+
+    #[test]
+    fn test_equal_weights_rank_by_priority_then_age() {
+        let (curve, now) = (OverdueCurve::default(), Utc::now());
+        let from_date = now - Duration::days(2);
+        // A P0 boosted by 4x weighs exactly the same as an unboosted P3
+        let boosted = Task {
+            id: 1,
+            priority: Priority::P0,
+            from_date,
+            boost_factor: 4.0,
+            boost_until: Some(now + Duration::days(weighting::BOOST_DAYS + 1)),
+            ..fixtures::task(1, "Boosted")
+        };
+        let urgent = Task {
+            id: 2,
+            priority: Priority::P3,
+            from_date,
+            ..fixtures::task(1, "Urgent")
+        };
+        assert_eq!(
+            calculate_weight_at(&boosted, &curve, now),
+            calculate_weight_at(&urgent, &curve, now)
+        );
+
+        let mut tasks = vec![boosted, urgent];
+        rank_tasks(&mut tasks, &curve, now, None, None);
+        assert_eq!(tasks[0].priority, Priority::P3);
+
+        // Recurring tasks that aren't due yet all weigh 0, so the older goes first
+        let waiting = |id, days_ago| Task {
+            id,
+            repeat_interval: Some(30),
+            from_date: now - Duration::days(days_ago),
+            ..fixtures::task(1, "Waiting")
+        };
+        let mut tasks = vec![waiting(3, 1), waiting(4, 5)];
+        rank_tasks(&mut tasks, &curve, now, None, None);
+        assert_eq!(tasks[0].id, 4);
+    }
+
+    #[test]
+    fn test_budget_aware_weighting_shifts_ranking_near_cap() {
+        let conn = fixtures::setup();
+        let well_paid = fixtures::insert_task(
+            &conn,
+            Task {
+                bounty_modifier: 1.0,
+                ..fixtures::task(1, "Well paid")
+            },
+        );
+        let important = fixtures::insert_task(
+            &conn,
+            Task {
+                priority: Priority::P2,
+                ..fixtures::task(1, "Important")
+            },
+        );
+        let top = |conn: &Connection| shortlist(conn, 1).unwrap()[0].0.id;
+
+        // Off by default, so priority wins as usual
+        assert_eq!(top(&conn), important);
+
+        db::update_setting(&conn, "budget_aware_weighting", 1).unwrap();
+        assert_eq!(top(&conn), well_paid);
+
+        // 590 of the 600 maximum has been earned this month
+        db::add_transaction(&conn, 590.0).unwrap();
+        assert_eq!(top(&conn), important);
+    }
+
+    #[test]
+    fn test_complete_queue_sums_payouts() {
+        let (conn, seed) = fixtures::seeded();
+        let dishes = fixtures::read_task(&conn, seed.tasks["dishes"]);
+        let laundry = fixtures::read_task(&conn, seed.tasks["laundry"]);
+
+        let summary =
+            complete_queue(&conn, &[(dishes.clone(), 1.5), (laundry.clone(), 2.25)]).unwrap();
+        assert_eq!(
+            summary,
+            ui::QueueSummary {
+                tasks_completed: 2,
+                funds_earned: 3.75,
+            }
+        );
+        assert_eq!(db::read_payouts(&conn, dishes.id).unwrap()[0].1, 1.5);
+        assert_eq!(db::read_payouts(&conn, laundry.id).unwrap()[0].1, 2.25);
+        assert_eq!(fixtures::read_task(&conn, laundry.id).times_selected, 1);
+        fixtures::assert_archived(&conn, dishes.id, true);
+        fixtures::assert_funds(&conn, 3.75);
+
+        // Through the screen, picking both of what's left in Office
+        fixtures::insert_task(
+            &conn,
+            fixtures::task(seed.folders["office"], "File receipts"),
+        );
+        let office = db::read_all_folders_sorted(&conn)
+            .unwrap()
+            .iter()
+            .position(|(id, _)| *id == seed.folders["office"])
+            .unwrap();
+        let office = format!("{}\n", office + 1);
+        let mut io = ui::MockIo::new(&[&office, "1 2\n"]);
+        complete_several(&mut io, &conn);
+
+        let earned: f64 = db::read_transactions(&conn)
+            .iter()
+            .filter_map(|(_, amount, _)| *amount)
+            .sum::<f64>()
+            - 3.75;
+        let expected = format!(
+            "\nYou completed 2 tasks and earned {}.",
+            ui::format_money(earned, &money_format(&conn))
+        );
+        assert!(io.output.borrow().contains(&expected), "{:?}", io.output);
+    }
+
+    #[test]
+    fn test_archived_folders_hide_their_tasks() {
+        let (conn, seed) = fixtures::seeded();
+        let shortlisted = |conn: &Connection, folder: &str| -> Vec<u32> {
+            shortlist(conn, seed.folders[folder])
+                .unwrap()
+                .iter()
+                .map(|(task, _)| task.id)
+                .collect()
+        };
+        let (laundry, dishes) = (seed.tasks["laundry"], seed.tasks["dishes"]);
+        assert!(shortlisted(&conn, "general").contains(&dishes));
+
+        let home = db::read_all_folders_sorted(&conn)
+            .unwrap()
+            .iter()
+            .position(|(id, _)| *id == seed.folders["home"])
+            .unwrap();
+        let home = format!("{}\n", home + 1);
+        let mut io = ui::MockIo::new(&[&home, "y\n"]);
+        archive_folder(&mut io, &conn);
+
+        // Home's own tasks and those in Chores below it are hidden
+        for folder in ["general", "home", "chores"] {
+            let ids = shortlisted(&conn, folder);
+            assert!(
+                !ids.contains(&laundry) && !ids.contains(&dishes),
+                "{folder}"
+            );
+        }
+        assert_eq!(shortlisted(&conn, "office"), vec![seed.tasks["report"]]);
+        fixtures::assert_archived(&conn, dishes, false);
+
+        db::update_setting(&conn, "hide_archived_folder_tasks", 0).unwrap();
+        assert!(shortlisted(&conn, "general").contains(&dishes));
+        db::update_setting(&conn, "hide_archived_folder_tasks", 1).unwrap();
+
+        // Picking it again offers to restore it
+        let mut io = ui::MockIo::new(&[&home, "y\n"]);
+        archive_folder(&mut io, &conn);
+        assert!(shortlisted(&conn, "general").contains(&dishes));
+    }
+
+    #[test]
+    fn test_manage_folders_renames() {
+        let (conn, seed) = fixtures::seeded();
+        let office = seed.folders["office"];
+        let pick = db::read_all_folders_sorted(&conn)
+            .unwrap()
+            .iter()
+            .position(|(id, _)| *id == office)
+            .unwrap();
+
+        // A blank name is asked for again
+        let mut io = ui::MockIo::new(&[&format!("{}\n", pick + 1), "1\n", " \n", "Work\n"]);
+        manage_folders(&mut io, &conn);
+
+        assert_eq!(
+            db::read_all_folders(&conn, None, "".into()).unwrap()[&office],
+            "Work"
+        );
+        assert!(io
+            .output
+            .borrow()
+            .iter()
+            .any(|line| line.contains("The folder's name cannot be empty!")));
+    }
+
+    #[test]
+    fn test_manage_folders_deletes_after_confirming() {
+        let (conn, seed) = fixtures::seeded();
+        let office = seed.folders["office"];
+        let pick = db::read_all_folders_sorted(&conn)
+            .unwrap()
+            .iter()
+            .position(|(id, _)| *id == office)
+            .unwrap();
+        let pick = format!("{}\n", pick + 1);
+
+        let mut io = ui::MockIo::new(&[&pick, "3\n", "n\n"]);
+        manage_folders(&mut io, &conn);
+        assert!(db::read_all_folders(&conn, None, "".into())
+            .unwrap()
+            .contains_key(&office));
+
+        let mut io = ui::MockIo::new(&[&pick, "3\n", "y\n"]);
+        manage_folders(&mut io, &conn);
+        assert!(!db::read_all_folders(&conn, None, "".into())
+            .unwrap()
+            .contains_key(&office));
+        fixtures::assert_archived(&conn, seed.tasks["report"], true);
+    }
+
+    #[test]
+    fn test_read_stats_summary() {
+        let (conn, seed) = fixtures::seeded();
+        let laundry = fixtures::read_task(&conn, seed.tasks["laundry"]);
+        db::complete_task(&conn, &laundry, Some(1.5), Utc::now()).unwrap();
+
+        let summary = read_stats_summary(&conn);
+        assert_eq!(summary.total_tasks_completed, 1);
+        assert_eq!(summary.average_completion, Some(Duration::minutes(10)));
+        let funds = summary.funds.unwrap();
+        assert_eq!(funds.earned_this_month, 1.5);
+        assert_eq!(funds.loaded, 400.0);
+        assert_eq!(funds.baseline_bounty, None);
+
+        db::update_setting(&conn, "finance_enabled", 0).unwrap();
+        assert_eq!(read_stats_summary(&conn).funds, None);
+
+        // An unreadable total shows as 0 without hiding the others
+        db::update_setting(&conn, "finance_enabled", 1).unwrap();
+        conn.execute("DROP TABLE statistics", []).unwrap();
+        let summary = read_stats_summary(&conn);
+        assert_eq!(summary.total_tasks_completed, 0);
+        let funds = summary.funds.unwrap();
+        assert_eq!(funds.loaded, 0.0);
+        assert_eq!(funds.earned_this_month, 1.5);
+    }
+
+    #[test]
+    fn test_import_backup_asks_first() {
+        let (seeded, _) = fixtures::seeded();
+        let path =
+            std::env::temp_dir().join(format!("backlist_backup_{}.json", std::process::id()));
+        backup::export_to_file(&seeded, &path).unwrap();
+        let path_input = format!("{}\n", path.display());
+
+        let conn = fixtures::setup();
+        let mut io = ui::MockIo::new(&[&path_input, "n\n"]);
+        import_backup(&mut io, &conn);
+        assert!(io
+            .output
+            .borrow()
+            .iter()
+            .any(|line| line.contains("add 3 folder(s) and 5 task(s)")));
+        assert!(db::read_all_tasks(&conn).unwrap().is_empty());
+
+        let mut io = ui::MockIo::new(&[&path_input, "y\n"]);
+        import_backup(&mut io, &conn);
+        assert_eq!(db::read_all_tasks(&conn).unwrap().len(), 5);
+
+        let mut io = ui::MockIo::new(&[&path_input]);
+        import_backup(&mut io, &conn);
+        assert!(io
+            .output
+            .borrow()
+            .iter()
+            .any(|line| line.contains("already here")));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_settings_screen() {
+        let (conn, seed) = fixtures::seeded();
+        let pick = |key: &str| {
+            let i = current_settings(&conn)
+                .iter()
+                .position(|(current_key, _)| *current_key == key)
+                .unwrap();
+            format!("{}\n", i + 1)
+        };
+        assert!(current_settings(&conn)
+            .iter()
+            .all(|(key, _)| *key != "vacation_mode"));
+
+        let mut io = ui::MockIo::new(&[&pick("tasks_per_view"), "x\n", "12\n"]);
+        settings(&mut io, &conn);
+        assert_eq!(
+            db::read_setting_u32(&conn, "tasks_per_view").unwrap(),
+            Some(12)
+        );
+
+        // The target can't go above the maximum
+        let mut io = ui::MockIo::new(&[&pick("target_monthly_allowance"), "700\n"]);
+        settings(&mut io, &conn);
+        assert_eq!(finance::monthly_allowances(&conn).target, 400);
+
+        let mut io = ui::MockIo::new(&[&pick("maximum_monthly_allowance"), "800\n"]);
+        settings(&mut io, &conn);
+        let mut io = ui::MockIo::new(&[&pick("target_monthly_allowance"), "700\n"]);
+        settings(&mut io, &conn);
+        assert_eq!(finance::monthly_allowances(&conn).target, 700);
+
+        // Quick Add can only file into a folder that exists
+        let mut io = ui::MockIo::new(&[&pick("quick_capture_folder"), "999\n"]);
+        settings(&mut io, &conn);
+        assert_ne!(
+            db::read_setting_u32(&conn, "quick_capture_folder").unwrap(),
+            Some(999)
+        );
+        let office = format!("{}\n", seed.folders["office"]);
+        let mut io = ui::MockIo::new(&[&pick("quick_capture_folder"), &office]);
+        settings(&mut io, &conn);
+        assert_eq!(quick_capture_folder(&conn), seed.folders["office"]);
+    }
+
+    #[test]
+    fn test_vacation_pauses_recurring_tasks() {
+        let (conn, seed) = fixtures::seeded();
+        let now = Utc::now();
+        // Due a day after the vacation started, and two days overdue by now
+        let weekly = fixtures::insert_task(
+            &conn,
+            Task {
+                repeat_interval: Some(7),
+                from_date: now - Duration::days(9),
+                ..fixtures::task(seed.folders["home"], "Mow the lawn")
+            },
+        );
+        let shortlisted = |conn: &Connection| -> Vec<u32> {
+            shortlist(conn, seed.folders["general"])
+                .unwrap()
+                .iter()
+                .map(|(task, _)| task.id)
+                .collect()
+        };
+        assert!(shortlisted(&conn).contains(&weekly));
+
+        start_vacation(&conn, now - Duration::days(3)).unwrap();
+        let ids = shortlisted(&conn);
+        assert!(!ids.contains(&weekly) && !ids.contains(&seed.tasks["laundry"]));
+        assert!(ids.contains(&seed.tasks["dishes"]));
+
+        // Only the 6 days before the vacation count, so it's still a day off
+        end_vacation(&conn, now).unwrap();
+        assert!(!shortlisted(&conn).contains(&weekly));
+        let from_date = fixtures::read_task(&conn, weekly).from_date;
+        assert_eq!(from_date, now - Duration::days(6));
+        assert_eq!(vacation_started(&conn, now), None);
+    }
+
+    #[test]
+    fn test_completed_oneoff_policy() {
+        let (conn, seed) = fixtures::seeded();
+        let dishes = fixtures::read_task(&conn, seed.tasks["dishes"]);
+        complete_task(&conn, &dishes, 2.0).unwrap();
+        fixtures::assert_archived(&conn, dishes.id, true);
+
+        db::update_setting(&conn, "completed_oneoff_policy", 1).unwrap();
+        let bins = fixtures::insert_task(&conn, fixtures::task(1, "Take out the bins"));
+        complete_task(&conn, &fixtures::read_task(&conn, bins), 3.0).unwrap();
+        assert!(db::read_all_tasks(&conn)
+            .unwrap()
+            .iter()
+            .all(|task| task.id != bins));
+        assert_eq!(db::read_payouts(&conn, bins).unwrap().len(), 1);
+        fixtures::assert_funds(&conn, 5.0);
+
+        // Recurring tasks are reset either way
+        let laundry = fixtures::read_task(&conn, seed.tasks["laundry"]);
+        complete_task(&conn, &laundry, 0.0).unwrap();
+        fixtures::assert_archived(&conn, laundry.id, false);
+    }
+
+    #[test]
+    fn test_payouts_record_each_bounty() {
+        let (conn, seed) = fixtures::seeded();
+        let laundry = seed.tasks["laundry"];
+
+        let complete = |conn: &Connection| {
+            let task = fixtures::read_task(conn, laundry);
+            complete_task(conn, &task, finance::adjusted_value(conn, &task)).unwrap();
+        };
+        complete(&conn);
+        // More tasks a month means each pays less
+        fixtures::insert_task(
+            &conn,
+            Task {
+                repeat_interval: Some(1),
+                ..fixtures::task(seed.folders["home"], "Make the bed")
+            },
+        );
+        complete(&conn);
+
+        let payouts = db::read_payouts(&conn, laundry).unwrap();
+        assert_eq!(payouts.len(), 2);
+        assert!(payouts[1].1 < payouts[0].1, "{payouts:?}");
+        assert!(db::read_payouts(&conn, seed.tasks["dishes"])
+            .unwrap()
+            .is_empty());
+
+        let mut io = ui::MockIo::new(&["1\n"]);
+        task_history(&mut io, &conn);
+        let output = io.output.borrow().join("\n");
+        assert!(output.contains("Do the laundry"));
+        assert!(output.contains("█"));
+    }
+
+    #[test]
+    fn test_organize_sets_priority_of_selected_tasks() {
+        let (conn, seed) = fixtures::seeded();
+        // Tasks are listed in the order they were added:
+        // laundry, water_plants, dishes, report
+        let mut io = ui::MockIo::new(&["1, 3\n", "1\n", "3\n"]);
+
+        organize(&mut io, &conn);
+
+        for name in ["laundry", "dishes"] {
+            assert_eq!(
+                fixtures::read_task(&conn, seed.tasks[name]).priority,
+                Priority::P3
+            );
+        }
+        for name in ["water_plants", "report"] {
+            assert_ne!(
+                fixtures::read_task(&conn, seed.tasks[name]).priority,
+                Priority::P3
+            );
+        }
+    }
+
+    #[test]
+    fn test_organize_archive_and_cancel() {
+        let (conn, seed) = fixtures::seeded();
+
+        organize(&mut ui::MockIo::new(&["4 2\n", "q\n"]), &conn);
+        fixtures::assert_archived(&conn, seed.tasks["report"], false);
+
+        organize(&mut ui::MockIo::new(&["4 2\n", "3\n"]), &conn);
+        fixtures::assert_archived(&conn, seed.tasks["report"], true);
+        fixtures::assert_archived(&conn, seed.tasks["water_plants"], true);
+        fixtures::assert_archived(&conn, seed.tasks["laundry"], false);
+    }
+
+    #[test]
+    fn test_organize_delete_asks_first() {
+        let (conn, seed) = fixtures::seeded();
+        let count = |conn: &Connection| db::read_all_tasks(conn).unwrap().len();
+        let before = count(&conn);
+
+        organize(&mut ui::MockIo::new(&["3\n", "4\n", "n\n"]), &conn);
+        assert_eq!(count(&conn), before);
+
+        organize(&mut ui::MockIo::new(&["3\n", "4\n", "y\n"]), &conn);
+        assert_eq!(count(&conn), before - 1);
+        assert!(db::read_all_tasks(&conn)
+            .unwrap()
+            .iter()
+            .all(|task| task.id != seed.tasks["dishes"]));
+    }
+
+    #[test]
+    fn test_archive_pages() {
+        let (conn, seed) = fixtures::seeded();
+        for i in 0..ARCHIVE_PAGE_LENGTH {
+            fixtures::insert_task(
+                &conn,
+                Task {
+                    is_archived: true,
+                    ..fixtures::task(seed.folders["home"], &format!("Done {i}"))
+                },
+            );
+        }
+        // Recurring tasks are listed too, even if they wouldn't be due yet
+        db::archive_task(&conn, seed.tasks["water_plants"]).unwrap();
+        let total = ARCHIVE_PAGE_LENGTH + 2;
+        let listed = |io: &ui::MockIo| {
+            io.output
+                .borrow()
+                .iter()
+                .filter(|line| {
+                    line.split_once(". ")
+                        .is_some_and(|(number, _)| number.parse::<usize>().is_ok())
+                })
+                .count()
+        };
+
+        let mut io = ui::MockIo::new(&["\n"]);
+        archive(&mut io, &conn);
+        assert_eq!(listed(&io), ARCHIVE_PAGE_LENGTH);
+
+        let mut io = ui::MockIo::new(&["m\n", "\n"]);
+        archive(&mut io, &conn);
+        assert_eq!(listed(&io), total);
+        let output = io.output.borrow().join("\n");
+        assert!(output.contains("Fix the sink") && output.contains("Water the plants"));
+    }
+
+    #[test]
+    fn test_restore_archived_task() {
+        let (conn, seed) = fixtures::seeded();
+        let dishes = fixtures::read_task(&conn, seed.tasks["dishes"]);
+        complete_task(&conn, &dishes, 2.0).unwrap();
+        fixtures::assert_funds(&conn, 2.0);
+
+        // Fix the sink was archived with a later from_date, so it's first
+        let mut io = ui::MockIo::new(&["2\n", "n\n"]);
+        archive(&mut io, &conn);
+        fixtures::assert_archived(&conn, dishes.id, false);
+        fixtures::assert_funds(&conn, 2.0);
+        let restored = fixtures::read_task(&conn, dishes.id);
+        assert!(restored.from_date > Utc::now() - Duration::minutes(1));
+
+        // Restoring gave dishes a fresh from_date, so now it's listed first
+        complete_task(&conn, &restored, 1.5).unwrap();
+        archive(&mut ui::MockIo::new(&["1\n", "y\n"]), &conn);
+        fixtures::assert_archived(&conn, dishes.id, false);
+        fixtures::assert_funds(&conn, 2.0);
+        assert_eq!(db::read_payouts(&conn, dishes.id).unwrap().len(), 2);
+        assert_eq!(db::completions_by_day(&conn, 1).unwrap()[0].1, 2);
+    }
+
+    #[test]
+    fn test_completions_count_with_finance_disabled() {
+        let (conn, seed) = fixtures::seeded();
+        db::update_setting(&conn, "finance_enabled", 0).unwrap();
+        // Funds added in the Shop aren't completions
+        db::add_transaction(&conn, 5.0).unwrap();
+
+        let dishes = fixtures::read_task(&conn, seed.tasks["dishes"]);
+        assert_eq!(complete_task(&conn, &dishes, 2.0).unwrap(), 0.0);
+
+        let heatmap = db::completions_by_day(&conn, HEATMAP_DAYS).unwrap();
+        assert_eq!(heatmap.last().unwrap().1, 1);
+        assert_eq!(heatmap.iter().map(|(_, count)| count).sum::<u32>(), 1);
+        assert_eq!(finance::completion_streak(&conn).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_provisional_rank() {
+        let (conn, _) = fixtures::seeded();
+        let times_shown: Vec<u32> = db::read_all_tasks(&conn)
+            .unwrap()
+            .iter()
+            .map(|task| task.times_shown)
+            .collect();
+        // laundry, dishes and report are active; water_plants isn't due yet
+        let deadline = |days: i64, priority: Priority| Task {
+            due_date: Some(Utc::now() + Duration::days(days)),
+            lead_days: Some(3),
+            priority,
+            ..fixtures::task(1, "Deadline")
+        };
+
+        assert_eq!(
+            provisional_rank(&conn, &deadline(-2, Priority::P3)).unwrap(),
+            (1, 4)
+        );
+        assert_eq!(
+            provisional_rank(&conn, &deadline(100, Priority::P0)).unwrap(),
+            (4, 4)
+        );
+
+        // Previewing shouldn't touch the db
+        assert_eq!(
+            db::read_all_tasks(&conn)
+                .unwrap()
+                .iter()
+                .map(|task| task.times_shown)
+                .collect::<Vec<_>>(),
+            times_shown
+        );
+    }
+
+    #[test]
+    fn test_duplicate_summary_warning() {
+        let (conn, seed) = fixtures::seeded();
+        let home = seed.folders["home"];
+        db::update_setting(&conn, "quick_capture_folder", home).unwrap();
+        let count = |conn: &Connection| db::read_all_tasks(conn).unwrap().len();
+        let before = count(&conn);
+
+        let mut io = ui::MockIo::new(&["  do the LAUNDRY \n", "n\n"]);
+        quick_add(&mut io, &conn);
+        assert!(io
+            .output
+            .borrow()
+            .iter()
+            .any(|line| line.contains("already a task called \"Do the laundry\"")));
+        assert_eq!(count(&conn), before);
+
+        let mut io = ui::MockIo::new(&["do the laundry\n", "y\n"]);
+        quick_add(&mut io, &conn);
+        assert_eq!(count(&conn), before + 1);
+
+        // Other folders and archived tasks don't count
+        assert!(
+            db::find_duplicate_task(&conn, seed.folders["office"], "Do the laundry")
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            db::find_duplicate_task(&conn, seed.folders["chores"], "Fix the sink")
+                .unwrap()
+                .is_none()
+        );
+
+        db::update_setting(&conn, "warn_duplicate_summaries", 0).unwrap();
+        let mut io = ui::MockIo::new(&["Do the laundry\n"]);
+        quick_add(&mut io, &conn);
+        assert_eq!(count(&conn), before + 2);
+    }
+
+    #[test]
+    fn test_quick_add() {
+        let (conn, seed) = fixtures::seeded();
+        db::update_setting(&conn, "quick_capture_folder", seed.folders["office"]).unwrap();
+
+        let mut io = ui::MockIo::new(&["Call the bank\n"]);
+        quick_add(&mut io, &conn);
+
+        let added = db::read_all_tasks(&conn).unwrap().pop().unwrap();
+        assert_eq!(added.summary, "Call the bank");
+        assert_eq!(added.parent_id, seed.folders["office"]);
+        assert_eq!(added.priority, Priority::P1);
+        assert_eq!(added.repeat_interval, None);
+        assert_eq!(added.due_date, None);
+        assert_eq!(added.description, None);
+
+        // A deleted folder falls back to General
+        db::update_setting(&conn, "quick_capture_folder", 999).unwrap();
+        assert_eq!(quick_capture_folder(&conn), 1);
+    }
+
+    #[test]
+    fn test_work_time_averages_completions() {
+        let (conn, seed) = fixtures::seeded();
+        assert_eq!(work_session_length(&conn), None);
+        db::update_setting(&conn, "work_sessions_enabled", 1).unwrap();
+        assert_eq!(work_session_length(&conn), Some(Duration::minutes(25)));
+
+        let laundry = fixtures::read_task(&conn, seed.tasks["laundry"]);
+        record_work_time(&conn, &laundry, Duration::minutes(30)).unwrap();
+        complete_task(&conn, &fixtures::read_task(&conn, laundry.id), 0.0).unwrap();
+
+        // One earlier completion at 30 minutes, so 30 and 60 average to 45
+        let laundry = fixtures::read_task(&conn, laundry.id);
+        record_work_time(&conn, &laundry, Duration::minutes(60)).unwrap();
+        assert_eq!(
+            fixtures::read_task(&conn, laundry.id).average_duration,
+            Some(Duration::minutes(45))
+        );
+
+        // A task left on screen overnight doesn't count
+        let laundry = fixtures::read_task(&conn, laundry.id);
+        record_work_time(&conn, &laundry, Duration::hours(30)).unwrap();
+        assert_eq!(
+            fixtures::read_task(&conn, laundry.id).average_duration,
+            Some(Duration::minutes(45))
+        );
+    }
+
+    #[test]
+    fn test_archive_matching() {
+        let (conn, seed) = fixtures::seeded();
+        let office = seed.folders["office"];
+        let logo = fixtures::insert_task(&conn, fixtures::task(office, "Rebrand: new logo"));
+        let printer = fixtures::insert_task(
+            &conn,
+            Task {
+                description: Some("Reprint cards for the rebrand".into()),
+                ..fixtures::task(office, "Call the printer")
+            },
+        );
+        fixtures::insert_task(
+            &conn,
+            Task {
+                is_archived: true,
+                ..fixtures::task(office, "Rebrand kickoff")
+            },
+        );
+
+        // Declining leaves everything as it was
+        let mut io = ui::MockIo::new(&["REBRAND\n", "n\n"]);
+        archive_matching(&mut io, &conn);
+        fixtures::assert_archived(&conn, logo, false);
+
+        let mut io = ui::MockIo::new(&["REBRAND\n", "y\n"]);
+        archive_matching(&mut io, &conn);
+        assert!(io
+            .output
+            .borrow()
+            .iter()
+            .any(|line| line.contains("2 unarchived task(s)")));
+        fixtures::assert_archived(&conn, logo, true);
+        fixtures::assert_archived(&conn, printer, true);
+        for (_, id) in seed
+            .tasks
+            .iter()
+            .filter(|(name, _)| **name != "old_archived")
+        {
+            fixtures::assert_archived(&conn, *id, false);
+        }
+    }
+
+    #[test]
+    fn test_quick_add_then_triage() {
+        let (conn, seed) = fixtures::seeded();
+
+        let mut io = ui::MockIo::new(&["Renew passport\n"]);
+        quick_add(&mut io, &conn);
+        let inbox = db::read_untriaged(&conn).unwrap();
+        assert_eq!(inbox.len(), 1);
+        assert_eq!(inbox[0].summary, "Renew passport");
+
+        let office = db::read_all_folders_sorted(&conn)
+            .unwrap()
+            .iter()
+            .position(|(id, _)| *id == seed.folders["office"])
+            .unwrap();
+        let office = format!("{}\n", office + 1);
+        let mut io = ui::MockIo::new(&[&office, "3\n", "2\n", "1\n", "30\n", "2\n"]);
+        triage(&mut io, &conn);
+
+        assert!(db::read_untriaged(&conn).unwrap().is_empty());
+        let triaged = fixtures::read_task(&conn, inbox[0].id);
+        assert_eq!(triaged.parent_id, seed.folders["office"]);
+        assert_eq!(triaged.priority, Priority::P3);
+        assert_eq!(triaged.repeat_interval, Some(30));
+        assert_eq!(triaged.repeat_unit, RepeatUnit::Days);
+        assert_eq!(triaged.recurrence_anchor, RecurrenceAnchor::Schedule);
+        assert_eq!(triaged.summary, "Renew passport");
+    }
+
+    #[test]
+    fn test_selector_offers_one_task() {
+        let (conn, seed) = fixtures::seeded();
+        let workouts = fixtures::insert_styled_folder(
+            &conn,
+            Some(seed.folders["home"]),
+            "Workouts",
+            Style::Selector,
+        );
+        let workout = |summary, days_ago| {
+            fixtures::insert_task(
+                &conn,
+                Task {
+                    repeat_interval: Some(2),
+                    from_date: Utc::now() - Duration::days(days_ago),
+                    ..fixtures::task(workouts, summary)
+                },
+            )
+        };
+        let run = workout("Go for a run", 5);
+        let swim = workout("Go for a swim", 4);
+        let lift = workout("Lift weights", 3);
+        let shortlisted = |conn: &Connection| -> Vec<u32> {
+            shortlist(conn, seed.folders["general"])
+                .unwrap()
+                .iter()
+                .map(|(task, _)| task.id)
+                .collect()
+        };
+
+        // Only the most overdue workout is offered, alongside the usual tasks
+        let ids = shortlisted(&conn);
+        assert!(ids.contains(&run) && !ids.contains(&swim) && !ids.contains(&lift));
+        assert!(ids.contains(&seed.tasks["laundry"]) && ids.contains(&seed.tasks["dishes"]));
+
+        let now = Utc::now();
+        complete_task_at(&conn, &fixtures::read_task(&conn, run), 0.0, now).unwrap();
+        for id in [run, swim, lift] {
+            assert_eq!(fixtures::read_task(&conn, id).from_date, now);
+        }
+        let ids = shortlisted(&conn);
+        assert!(!ids.contains(&run) && !ids.contains(&swim) && !ids.contains(&lift));
+
+        // Completing a task in a Directory leaves its siblings alone
+        let dishes = fixtures::read_task(&conn, seed.tasks["dishes"]);
+        let water_plants = fixtures::read_task(&conn, seed.tasks["water_plants"]);
+        complete_task_at(
+            &conn,
+            &fixtures::read_task(&conn, seed.tasks["laundry"]),
+            0.0,
+            now,
+        )
+        .unwrap();
+        assert_eq!(
+            fixtures::read_task(&conn, dishes.id).from_date,
+            dishes.from_date
+        );
+        assert_eq!(
+            fixtures::read_task(&conn, water_plants.id).from_date,
+            water_plants.from_date
+        );
+    }
+
+    #[test]
+    fn test_iterator_advances_one_at_a_time() {
+        let (conn, seed) = fixtures::seeded();
+        let morning = fixtures::insert_styled_folder(
+            &conn,
+            Some(seed.folders["home"]),
+            "Morning",
+            Style::Iterator,
+        );
+        let step = |summary| {
+            fixtures::insert_task(
+                &conn,
+                Task {
+                    repeat_interval: Some(1),
+                    from_date: Utc::now() - Duration::days(2),
+                    ..fixtures::task(morning, summary)
+                },
+            )
+        };
+        let stretch = step("Stretch");
+        let shower = step("Shower");
+        let coffee = step("Make coffee");
+        let offered = |conn: &Connection| -> Vec<u32> {
+            shortlist(conn, seed.folders["general"])
+                .unwrap()
+                .iter()
+                .map(|(task, _)| task.id)
+                .filter(|id| [stretch, shower, coffee].contains(id))
+                .collect()
+        };
+        assert_eq!(offered(&conn), vec![stretch]);
+
+        let now = Utc::now();
+        complete_task_at(&conn, &fixtures::read_task(&conn, stretch), 0.0, now).unwrap();
+        assert_eq!(offered(&conn), vec![shower]);
+
+        // Archiving the current task moves the folder on without finishing it
+        db::archive_task(&conn, shower).unwrap();
+        assert_eq!(offered(&conn), vec![coffee]);
+
+        // Finishing the last task starts the folder over, all at once
+        complete_task_at(&conn, &fixtures::read_task(&conn, coffee), 0.0, now).unwrap();
+        for id in [stretch, coffee] {
+            assert_eq!(fixtures::read_task(&conn, id).from_date, now);
+        }
+        assert!(offered(&conn).is_empty());
+        assert_eq!(
+            db::next_iterator_task(&conn, morning).unwrap().unwrap().id,
+            stretch
+        );
+    }
+
+    #[test]
+    fn test_boost_outcome() {
+        let (conn, seed) = fixtures::seeded();
+        let dishes = fixtures::read_task(&conn, seed.tasks["dishes"]);
+        let curve = overdue_curve(&conn);
+        let before = calculate_weight(&dishes, &curve, &clock::SystemClock);
+
+        record_outcome(&conn, &dishes, 0.0, &ui::TaskOutcome::Boost).unwrap();
+        let boosted = fixtures::read_task(&conn, dishes.id);
+        assert_eq!(boosted.boost_factor, weighting::BOOST_FACTOR);
+        assert!(boosted.boost_until.unwrap() > Utc::now());
+        assert!(calculate_weight(&boosted, &curve, &clock::SystemClock) > before * 1.9);
+    }
+
+    #[test]
+    fn test_snooze() {
+        let (conn, seed) = fixtures::seeded();
+        let in_shortlist = |conn: &Connection, id: u32| {
+            shortlist(conn, seed.folders["general"])
+                .unwrap()
+                .iter()
+                .any(|(task, _)| task.id == id)
+        };
+        let dishes = fixtures::read_task(&conn, seed.tasks["dishes"]);
+        let laundry = fixtures::read_task(&conn, seed.tasks["laundry"]);
+        assert!(in_shortlist(&conn, dishes.id) && in_shortlist(&conn, laundry.id));
+
+        db::increment_times_shown(&conn, dishes.id).unwrap();
+        record_outcome(&conn, &dishes, 0.0, &ui::TaskOutcome::Snooze(2)).unwrap();
+        record_outcome(&conn, &laundry, 0.0, &ui::TaskOutcome::Snooze(2)).unwrap();
+        assert!(!in_shortlist(&conn, dishes.id) && !in_shortlist(&conn, laundry.id));
+
+        let snoozed = fixtures::read_task(&conn, dishes.id);
+        assert_eq!((snoozed.times_shown, snoozed.times_selected), (1, 0));
+        assert_eq!(snoozed.from_date, dishes.from_date);
+        fixtures::assert_archived(&conn, dishes.id, false);
+        // A recurring task skips the occurrence it was snoozed on
+        assert!(fixtures::read_task(&conn, laundry.id).from_date > laundry.from_date);
+
+        // Once the snooze is over, one-offs come straight back
+        conn.execute(
+            "UPDATE tasks SET snoozed_until=?",
+            [Utc::now() - Duration::minutes(1)],
+        )
+        .unwrap();
+        assert!(in_shortlist(&conn, dishes.id));
+        assert!(!in_shortlist(&conn, laundry.id));
+
+        // Snoozing past the last representable date is refused, not a panic
+        assert!(matches!(
+            db::snooze_task(&conn, dishes.id, u32::MAX),
+            Err(db::DbError::SnoozeTooLong(u32::MAX))
+        ));
+        assert!(in_shortlist(&conn, dishes.id));
+    }
+
+    #[test]
+    fn test_defer_and_promote() {
+        let (conn, seed) = fixtures::seeded();
+        let dishes = fixtures::read_task(&conn, seed.tasks["dishes"]);
+        let in_shortlist = |conn: &Connection| {
+            shortlist(conn, seed.folders["general"])
+                .unwrap()
+                .iter()
+                .any(|(task, _)| task.id == dishes.id)
+        };
+        assert!(in_shortlist(&conn));
+
+        record_outcome(&conn, &dishes, 0.0, &ui::TaskOutcome::Defer).unwrap();
+        assert!(!in_shortlist(&conn));
+        assert!(db::read_active_tasks(&conn)
+            .unwrap()
+            .iter()
+            .all(|task| task.id != dishes.id));
+        fixtures::assert_archived(&conn, dishes.id, false);
+
+        let mut io = ui::MockIo::new(&["1\n"]);
+        later(&mut io, &conn);
+        assert!(db::read_deferred(&conn).unwrap().is_empty());
+        assert!(in_shortlist(&conn));
+    }
+
+    #[test]
+    fn test_add_task_from_template() {
+        let (conn, seed) = fixtures::seeded();
+
+        // Add a recurring task from scratch and save it as a template
+        let mut io = ui::MockIo::new(&[
+            "1\n",
+            "Water the garden\n",
+            "Front and back\n",
+            "20m\n",
+            "2\n",
+            "2\n",
+            "2\n",
+            "4\n",
+            "1\n",
+            "y\n",
+            "Garden\n",
+        ]);
+        add_new_task(&mut io, &conn);
+        assert_eq!(db::read_templates(&conn).unwrap().len(), 1);
+
+        // Then only the summary is needed the next time
+        let mut io = ui::MockIo::new(&["1\n", "Weed the garden\n"]);
+        add_new_task(&mut io, &conn);
+
+        let [.., original, from_template] = &db::read_all_tasks(&conn).unwrap()[..] else {
+            panic!("Expected the new tasks to be added");
+        };
+        assert_eq!(from_template.summary, "Weed the garden");
+        assert_eq!(original.average_duration, Some(Duration::minutes(20)));
+        assert_eq!(from_template.parent_id, original.parent_id);
+        assert_eq!(from_template.description, Some("Front and back".into()));
+        assert_eq!(from_template.priority, Priority::P2);
+        assert_eq!(from_template.repeat_interval, Some(4));
+        assert_eq!(from_template.repeat_unit, RepeatUnit::Hours);
+        assert_eq!(db::read_templates(&conn).unwrap().len(), 1);
+        assert_ne!(from_template.parent_id, seed.folders["office"]);
+    }
+
+    #[test]
+    fn test_in_progress_lifecycle() {
+        let (conn, seed) = fixtures::seeded();
+        let dishes = fixtures::read_task(&conn, seed.tasks["dishes"]);
+
+        // Selecting without completing marks the task as started
+        record_outcome(&conn, &dishes, 1.0, &ui::TaskOutcome::InProgress).unwrap();
+        let dishes = fixtures::read_task(&conn, dishes.id);
+        assert!(dishes.in_progress);
+        fixtures::assert_archived(&conn, dishes.id, false);
+        fixtures::assert_funds(&conn, 0.0);
+
+        // Abandoning clears it
+        record_outcome(&conn, &dishes, 1.0, &ui::TaskOutcome::Abandon).unwrap();
+        assert!(!fixtures::read_task(&conn, dishes.id).in_progress);
+
+        // And so does completing
+        let laundry = fixtures::read_task(&conn, seed.tasks["laundry"]);
+        record_outcome(&conn, &laundry, 1.0, &ui::TaskOutcome::InProgress).unwrap();
+        let laundry = fixtures::read_task(&conn, laundry.id);
+        record_outcome(&conn, &laundry, 1.0, &ui::TaskOutcome::Complete).unwrap();
+        assert!(!fixtures::read_task(&conn, laundry.id).in_progress);
+        fixtures::assert_funds(&conn, 1.0);
+    }
+
+    #[test]
+    fn test_in_progress_tasks_listed_separately() {
+        let (conn, seed) = fixtures::seeded();
+        let general = seed.folders["general"];
+        for i in 0..6 {
+            fixtures::insert_task(&conn, fixtures::task(general, &format!("Fresh {i}")));
+        }
+        let started = fixtures::insert_task(
+            &conn,
+            Task {
+                in_progress: true,
+                priority: Priority::P0,
+                ..fixtures::task(general, "Started")
+            },
+        );
+
+        let tasks = shortlist(&conn, general).unwrap();
+        assert_eq!(tasks.len(), 6);
+        assert_eq!(tasks[0].0.id, started);
+        assert!(tasks[1..].iter().all(|(task, _)| !task.in_progress));
+
+        // With the setting off it has to compete, and loses to the P1 tasks
+        db::update_setting(&conn, "exclude_in_progress", 0).unwrap();
+        let tasks = shortlist(&conn, general).unwrap();
+        assert_eq!(tasks.len(), 5);
+        assert!(tasks.iter().all(|(task, _)| task.id != started));
+    }
+
+    #[test]
+    fn test_folder_diversity_cap() {
+        let (conn, seed) = fixtures::seeded();
+        let (general, home) = (seed.folders["general"], seed.folders["home"]);
+        for i in 0..6 {
+            fixtures::insert_task(
+                &conn,
+                Task {
+                    priority: Priority::P3,
+                    ..fixtures::task(home, &format!("Urgent {i}"))
+                },
+            );
+        }
+        for i in 0..3 {
+            fixtures::insert_task(
+                &conn,
+                Task {
+                    priority: Priority::P0,
+                    ..fixtures::task(general, &format!("Someday {i}"))
+                },
+            );
+        }
+        let from_home = |tasks: &[(Task, f64)]| {
+            tasks
+                .iter()
+                .filter(|(task, _)| task.parent_id == home)
+                .count()
+        };
+
+        // Without a cap, the urgent tasks take every spot
+        assert_eq!(from_home(&shortlist(&conn, general).unwrap()), 5);
+
+        // With one, the rest are filled from other folders by weight
+        db::update_setting(&conn, "max_tasks_per_folder", 2).unwrap();
+        let tasks = shortlist(&conn, general).unwrap();
+        assert_eq!(tasks.len(), 5);
+        assert_eq!(from_home(&tasks), 2);
+        assert_eq!(tasks[0].0.parent_id, home);
+        assert_eq!(tasks[2].0.id, seed.tasks["dishes"]);
+
+        // But a single folder still fills the list
+        assert_eq!(shortlist(&conn, home).unwrap().len(), 5);
+    }
+
+    #[test]
+    fn test_candidate_count_setting() {
+        let (conn, seed) = fixtures::seeded();
+        let general = seed.folders["general"];
+        for i in 0..6 {
+            fixtures::insert_task(&conn, fixtures::task(general, &format!("Task {i}")));
+        }
+
+        assert_eq!(shortlist(&conn, general).unwrap().len(), 5);
+
+        db::update_setting(&conn, "todo_candidate_count", 3).unwrap();
+        assert_eq!(shortlist(&conn, general).unwrap().len(), 3);
+
+        // Asking for more than there are just offers all of them: these six,
+        // the laundry and the dishes
+        db::update_setting(&conn, "todo_candidate_count", 50).unwrap();
+        assert_eq!(shortlist(&conn, general).unwrap().len(), 8);
+    }
+
+    #[test]
+    fn test_choose_task_shows_urgency() {
+        let (conn, seed) = fixtures::seeded();
+        let tasks = shortlist(&conn, seed.folders["general"]).unwrap();
+        let options = ui::ListOptions {
+            show_urgency: true,
+            ..list_options(&conn)
+        };
+
+        let mut io = ui::MockIo::new(&["1\n"]);
+        choose_task(&mut io, &conn, &tasks, &options, &mut HashSet::new()).unwrap();
+        let output = io.output.borrow();
+        let scored: Vec<&String> = output
+            .iter()
+            .filter(|line| line.contains("(urgency "))
+            .collect();
+        assert_eq!(scored.len(), tasks.len());
+        assert!(scored[0].contains("(urgency 100)"));
+    }
+
+    #[test]
+    fn test_reshuffle_suppresses_shown_tasks() {
+        let (conn, seed) = fixtures::seeded();
+        fixtures::insert_task(&conn, fixtures::task(seed.folders["home"], "Vacuum"));
+        db::update_setting(&conn, "reshuffle_suppression_minutes", 30).unwrap();
+        let tasks = shortlist(&conn, seed.folders["general"]).unwrap();
+        let options = ui::ListOptions {
+            per_view: 1,
+            ..list_options(&conn)
+        };
+
+        let mut io = ui::MockIo::new(&["1\n"]);
+        let (shown, _) =
+            choose_task(&mut io, &conn, &tasks, &options, &mut HashSet::new()).unwrap();
+        let rerolled = shortlist(&conn, seed.folders["general"]).unwrap();
+        assert_eq!(rerolled.len(), tasks.len() - 1);
+        assert!(rerolled.iter().all(|(task, _)| task.id != shown.id));
+
+        // Once everything has been seen the full list comes back
+        for (task, _) in &rerolled {
+            db::increment_times_shown(&conn, task.id).unwrap();
+        }
+        let shortlisted = shortlist(&conn, seed.folders["general"]).unwrap();
+        assert_eq!(shortlisted.len(), tasks.len());
+
+        db::update_setting(&conn, "reshuffle_suppression_minutes", 0).unwrap();
+        db::increment_times_shown(&conn, shown.id).unwrap();
+        let shortlisted = shortlist(&conn, seed.folders["general"]).unwrap();
+        assert_eq!(shortlisted.len(), tasks.len());
+    }
+
+    #[test]
+    fn test_choose_task_shows_more_on_request() {
+        let (conn, seed) = fixtures::seeded();
+        fixtures::insert_task(&conn, fixtures::task(seed.folders["home"], "Vacuum"));
+        let tasks = shortlist(&conn, seed.folders["general"]).unwrap();
+        assert_eq!(tasks.len(), 3);
+        let options = ui::ListOptions {
+            per_view: 1,
+            ..list_options(&conn)
+        };
+
+        // Only one task is offered until the user asks for more
+        let mut io = ui::MockIo::new(&["2\n", "m\n", "2\n"]);
+        let (chosen, _) =
+            choose_task(&mut io, &conn, &tasks, &options, &mut HashSet::new()).unwrap();
+        assert_eq!(chosen.id, tasks[1].0.id);
+
+        let times_shown: Vec<u32> = tasks
+            .iter()
+            .map(|(task, _)| fixtures::read_task(&conn, task.id).times_shown)
+            .collect();
+        assert_eq!(times_shown, [1, 1, 0]);
+
+        // Once everything is visible, "show more" isn't offered
+        let mut io = ui::MockIo::new(&["m\n", "m\n", "m\n", "3\n"]);
+        let (chosen, _) =
+            choose_task(&mut io, &conn, &tasks, &options, &mut HashSet::new()).unwrap();
+        assert_eq!(chosen.id, tasks[2].0.id);
+        assert_eq!(
+            io.output
+                .borrow()
+                .iter()
+                .filter(|line| line.contains("Show more"))
+                .count(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_complete_task_with_finance_disabled() {
+        let (conn, seed) = fixtures::seeded();
+        db::update_setting(&conn, "finance_enabled", 0).unwrap();
+
+        let (task, bounty) = shortlist(&conn, seed.folders["chores"]).unwrap().remove(0);
+        assert_eq!(bounty, 0.0);
+
+        complete_task(&conn, &task, 3.0).unwrap();
+
+        assert!(db::read_transactions(&conn).is_empty());
+        fixtures::assert_archived(&conn, task.id, true);
+    }
+
+    #[test]
+    fn test_cancel_add_task_mid_flow() {
+        let (conn, _) = fixtures::seeded();
+        let task_count = db::read_all_tasks(&conn).unwrap().len();
+
+        // Select a folder, enter a summary, then back out at the description
+        let mut io = ui::MockIo::new(&["1\n", "Half-finished task\n", "back\n"]);
+        add_new_task(&mut io, &conn);
+
+        assert_eq!(db::read_all_tasks(&conn).unwrap().len(), task_count);
+        assert!(io
+            .output
+            .borrow()
+            .contains(&"\nNo task was added.".to_string()));
+
+        // And the same for folders, cancelling at the very first prompt
+        let folder_count = db::read_all_folders(&conn, None, "".to_string())
+            .unwrap()
+            .len();
+        let mut io = ui::MockIo::new(&["Q\n"]);
+        add_folder(&mut io, &conn);
+
+        assert_eq!(
+            db::read_all_folders(&conn, None, "".to_string())
+                .unwrap()
+                .len(),
+            folder_count
+        );
+    }
+
+    #[test]
+    fn test_money_format_from_settings() {
+        let conn = fixtures::setup();
+        assert_eq!(money_format(&conn), ui::MoneyFormat::default());
+
+        db::update_setting(&conn, "number_style", 1).unwrap();
+        db::update_setting(&conn, "currency_decimals", 99).unwrap();
+
+        assert_eq!(
+            money_format(&conn),
+            ui::MoneyFormat {
+                style: ui::NumberStyle::PeriodGrouped,
+                decimals: 6,
+            }
+        );
+    }
+
+    /// Adds a task that `db::read_stale_tasks().unwrap()` will pick up with the
+    /// default threshold.
+    fn insert_stale_task(conn: &Connection) -> u32 {
+        fixtures::insert_task(
+            conn,
+            Task {
+                created_at: Utc::now() - Duration::days(90),
+                ..fixtures::task(1, "Learn the banjo")
+            },
+        )
+    }
+
+    #[test]
+    fn test_stale_prompt_cooldown() {
+        let (conn, _) = fixtures::seeded();
+        assert!(stale_tasks_to_review(&conn, Utc::now()).0.is_empty());
+
+        let stale = insert_stale_task(&conn);
+        let (tasks, threshold_days) = stale_tasks_to_review(&conn, Utc::now());
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(threshold_days, 60);
+
+        // Decline the prompt, then check it's suppressed for the next day
+        review_stale_tasks(&mut ui::MockIo::new(&["n\n"]), &conn);
+        fixtures::assert_archived(&conn, stale, false);
+        assert!(stale_tasks_to_review(&conn, Utc::now()).0.is_empty());
+        assert!(
+            stale_tasks_to_review(&conn, Utc::now() + Duration::hours(23))
+                .0
+                .is_empty()
+        );
+        assert_eq!(
+            stale_tasks_to_review(&conn, Utc::now() + Duration::hours(25))
+                .0
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_stale_prompt_archives_on_confirm() {
+        let (conn, seed) = fixtures::seeded();
+        let stale = insert_stale_task(&conn);
+
+        review_stale_tasks(&mut ui::MockIo::new(&["y\n"]), &conn);
+
+        fixtures::assert_archived(&conn, stale, true);
+        fixtures::assert_archived(&conn, seed.tasks["dishes"], false);
+    }
+
+    #[test]
+    fn test_stale_prompt_disabled() {
+        let (conn, _) = fixtures::seeded();
+        insert_stale_task(&conn);
+        db::update_setting(&conn, "stale_prompt_enabled", 0).unwrap();
+
+        assert!(stale_tasks_to_review(&conn, Utc::now()).0.is_empty());
+    }
+
+    #[test]
+    fn test_shortlist_includes_descendants() {
+        let (conn, seed) = fixtures::seeded();
+
+        let ids: Vec<u32> = shortlist(&conn, seed.folders["home"])
+            .unwrap()
+            .iter()
+            .map(|(task, _)| task.id)
+            .collect();
+
+        assert!(ids.contains(&seed.tasks["dishes"]));
+        assert!(ids.contains(&seed.tasks["laundry"]));
+        assert!(!ids.contains(&seed.tasks["report"]));
+        assert!(!ids.contains(&seed.tasks["old_archived"]));
+    }
+
+    #[test]
+    fn test_session_penalty_rotates_the_shortlist() {
+        let (conn, seed) = fixtures::seeded();
+        let general = seed.folders["general"];
+        db::update_setting(&conn, "session_repeat_penalty", 100).unwrap();
+        let ids = |shown: &HashSet<u32>| -> Vec<u32> {
+            session_shortlist(&conn, general, shown)
+                .unwrap()
+                .iter()
+                .map(|(task, _)| task.id)
+                .collect()
+        };
+
+        let fresh = ids(&HashSet::new());
+        assert!(fresh.len() > 1);
+        let shown = HashSet::from([fresh[0]]);
+        let rotated = ids(&shown);
+        assert_ne!(rotated[0], fresh[0]);
+        assert_eq!(rotated.last(), Some(&fresh[0]));
+
+        // With everything shown already, nothing is held back
+        let everything: HashSet<u32> = db::read_all_tasks(&conn)
+            .unwrap()
+            .iter()
+            .map(|task| task.id)
+            .collect();
+        assert_eq!(ids(&everything), fresh);
+
+        db::update_setting(&conn, "session_repeat_penalty", 0).unwrap();
+        assert_eq!(ids(&shown), fresh);
+    }
+
+    #[test]
+    fn test_top_tasks_spans_every_folder() {
+        let (conn, seed) = fixtures::seeded();
+        let filing = fixtures::insert_task(
+            &conn,
+            fixtures::task(seed.folders["office"], "File the receipts"),
+        );
+
+        let ids: Vec<u32> = top_tasks(&conn, 5)
+            .unwrap()
+            .iter()
+            .map(|(task, _)| task.id)
+            .collect();
+        assert!(ids.contains(&seed.tasks["laundry"]));
+        assert!(ids.contains(&filing));
+        assert!(!ids.contains(&seed.tasks["old_archived"]));
+        assert_eq!(top_tasks(&conn, 1).unwrap().len(), 1);
+
+        // Printing the list isn't the same as showing it in ToDo
+        assert!(db::read_all_tasks(&conn)
+            .unwrap()
+            .iter()
+            .all(|task| task.times_shown == 0));
+    }
+}
@@ -1,6 +1,13 @@
-use backlist::startup;
+use backlist::{add_from_args, export_config, notify, print_next, startup};
 
 fn main() {
     // println!("Welcome to Backlist!");
-    startup()
+    let args: Vec<String> = std::env::args().collect();
+    match args.get(1).map(String::as_str) {
+        Some("add") => std::process::exit(add_from_args(&args[2..])),
+        Some("next") => std::process::exit(print_next(&args[2..])),
+        Some("notify") => notify(),
+        Some("config") => export_config(),
+        _ => startup(),
+    }
 }
@@ -0,0 +1,155 @@
+//! # Notify
+//!
+//! Checks for deadline tasks that need attention and raises a desktop
+//! notification listing them. Meant to be run on a schedule, e.g. from cron
+//! as `nextup notify`, so it never writes to the db.
+//!
+//! The desktop backend is behind the `notifications` feature. Without it, the
+//! notification is printed instead.
+
+use chrono::{DateTime, Utc};
+use rusqlite::Connection;
+
+use crate::{
+    db::{self, DbError},
+    finance,
+    tasks::Task,
+    ui,
+};
+
+/// Finds the active tasks that are due soon or overdue. See
+/// `Task::in_lead_window()`.
+///
+/// # Arguments
+///
+/// * `conn: &Connection` - Allows us to access the SQLite db.
+/// * `now: DateTime<Utc>` - The time to check against.
+///
+/// # Returns
+///
+/// The tasks ordered by due date, soonest first.
+pub fn overdue_tasks(conn: &Connection, now: DateTime<Utc>) -> Result<Vec<Task>, DbError> {
+    let mut overdue: Vec<Task> = db::read_active_tasks(conn)?
+        .into_iter()
+        .filter(|task| task.in_lead_window(now))
+        .collect();
+
+    overdue.sort_by_key(|task| (task.due_date, task.id));
+    Ok(overdue)
+}
+
+/// Renders each of `tasks` with `ui::render_task_line()`.
+fn render_tasks(conn: &Connection, tasks: &[Task], now: DateTime<Utc>) -> Vec<String> {
+    let folder_paths = db::read_all_folders(conn, None, "".to_string()).unwrap_or_default();
+    let finance_enabled = finance::is_enabled(conn);
+
+    tasks
+        .iter()
+        .map(|task| {
+            let folder_path = folder_paths
+                .get(&task.parent_id)
+                .map(String::as_str)
+                .unwrap_or_default();
+            let bounty = finance_enabled.then(|| finance::adjusted_value(conn, task));
+            ui::render_task_line(task, folder_path, bounty, now)
+        })
+        .collect()
+}
+
+/// Raises a notification for any overdue tasks. Does nothing if there are
+/// none.
+///
+/// # Arguments
+///
+/// * `conn: &Connection` - Allows us to access the SQLite db.
+pub fn run(conn: &Connection) {
+    let now = Utc::now();
+    let overdue = match overdue_tasks(conn, now) {
+        Ok(overdue) if overdue.is_empty() => return,
+        Ok(overdue) => overdue,
+        Err(e) => return eprintln!("Problem reading tasks: {e}"),
+    };
+
+    let title = format!("{} task(s) need attention", overdue.len());
+    let body = render_tasks(conn, &overdue, now).join("\n");
+
+    if let Err(e) = show(&title, &body) {
+        eprintln!("Problem showing notification: {e}");
+    }
+}
+
+#[cfg(feature = "notifications")]
+fn show(title: &str, body: &str) -> Result<(), Box<dyn std::error::Error>> {
+    notify_rust::Notification::new()
+        .appname("NextUp")
+        .summary(title)
+        .body(body)
+        .show()?;
+
+    Ok(())
+}
+
+#[cfg(not(feature = "notifications"))]
+fn show(title: &str, body: &str) -> Result<(), Box<dyn std::error::Error>> {
+    println!("{title}\n{body}");
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures;
+    use chrono::Duration;
+
+    #[test]
+    fn test_overdue_tasks() {
+        let (conn, seed) = fixtures::seeded();
+        let now = Utc::now();
+        let overdue = fixtures::insert_task(
+            &conn,
+            Task {
+                due_date: Some(now - Duration::days(1)),
+                lead_days: Some(2),
+                ..fixtures::task(seed.folders["office"], "File taxes")
+            },
+        );
+        fixtures::insert_task(
+            &conn,
+            Task {
+                due_date: Some(now + Duration::days(30)),
+                lead_days: Some(3),
+                ..fixtures::task(seed.folders["office"], "Book flights")
+            },
+        );
+        fixtures::insert_task(
+            &conn,
+            Task {
+                due_date: Some(now - Duration::days(3)),
+                lead_days: Some(1),
+                is_archived: true,
+                ..fixtures::task(seed.folders["office"], "Already done")
+            },
+        );
+
+        // The report is due in 5 days with 3 lead days, so it isn't yet
+        let ids: Vec<u32> = overdue_tasks(&conn, now)
+            .unwrap()
+            .iter()
+            .map(|t| t.id)
+            .collect();
+        assert_eq!(ids, vec![overdue]);
+
+        // Two days later it is
+        let ids: Vec<u32> = overdue_tasks(&conn, now + Duration::days(2))
+            .unwrap()
+            .iter()
+            .map(|t| t.id)
+            .collect();
+        assert_eq!(ids, vec![overdue, seed.tasks["report"]]);
+
+        let lines = render_tasks(&conn, &overdue_tasks(&conn, now).unwrap(), now);
+        assert_eq!(lines.len(), 1);
+        assert!(lines[0].starts_with("[P1] Office — File taxes (overdue by 1d) $"));
+    }
+}
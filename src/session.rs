@@ -0,0 +1,237 @@
+//! # Session
+//!
+//! A pomodoro style countdown for working on the selected task. The elapsed
+//! time feeds the task's average_duration once it's completed.
+
+use std::io::{self, Write};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+
+use chrono::{DateTime, Duration, Utc};
+
+use crate::clock::Clock;
+
+/// How often the countdown is redrawn while it runs.
+const TICK: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// Where a session is up to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SessionState {
+    /// Counting down.
+    Running,
+    /// Out of time, waiting to be extended or finished.
+    Elapsed,
+    /// Stopped, either early or after running out of time.
+    Finished,
+}
+
+/// A timed stretch of work on one task.
+pub struct Session {
+    started_at: DateTime<Utc>,
+    ends_at: DateTime<Utc>,
+    finished_at: Option<DateTime<Utc>>,
+}
+
+impl Session {
+    /// Starts a session that runs for `length` from the clock's current time.
+    pub fn start(clock: &impl Clock, length: Duration) -> Self {
+        let now = clock.now();
+        Session {
+            started_at: now,
+            ends_at: now + length,
+            finished_at: None,
+        }
+    }
+
+    pub fn state(&self, clock: &impl Clock) -> SessionState {
+        if self.finished_at.is_some() {
+            SessionState::Finished
+        } else if clock.now() >= self.ends_at {
+            SessionState::Elapsed
+        } else {
+            SessionState::Running
+        }
+    }
+
+    /// How long is left before the session elapses, never negative.
+    pub fn remaining(&self, clock: &impl Clock) -> Duration {
+        (self.ends_at - clock.now()).max(Duration::zero())
+    }
+
+    /// Gives an elapsed session another `length` from now. A running session
+    /// has `length` added to what's left. Finished sessions can't be extended.
+    pub fn extend(&mut self, clock: &impl Clock, length: Duration) {
+        if self.finished_at.is_none() {
+            self.ends_at = self.ends_at.max(clock.now()) + length;
+        }
+    }
+
+    /// Stops the session, which may be before it elapses.
+    ///
+    /// # Returns
+    ///
+    /// How long the session ran. Finishing twice keeps the first time.
+    pub fn finish(&mut self, clock: &impl Clock) -> Duration {
+        let finished_at = *self.finished_at.get_or_insert(clock.now());
+        finished_at - self.started_at
+    }
+}
+
+/// How a session run through the terminal ended.
+pub struct SessionEnd {
+    /// How long was spent working.
+    pub elapsed: Duration,
+    /// Whether the user said the task was done when time ran out.
+    pub completed: bool,
+}
+
+/// Counts down in the terminal until the session elapses, then asks whether
+/// the task is done or needs more time. Hitting <ENTER> while it runs finishes
+/// early.
+///
+/// # Arguments
+///
+/// * `clock: &impl Clock` - Supplies the current time.
+/// * `length: Duration` - How long the session and each extension last.
+///
+/// # Notes
+///
+/// Stdin is read on a separate thread so the countdown can sleep between
+/// ticks instead of polling. Every line read is waited for before returning,
+/// so no input is lost to the next prompt.
+pub fn run(clock: &impl Clock, length: Duration) -> io::Result<SessionEnd> {
+    let mut session = Session::start(clock, length);
+    let mut lines = LineReader::default();
+    println!("\nWorking... hit <ENTER> to finish early.");
+
+    loop {
+        match session.state(clock) {
+            SessionState::Running => {
+                let remaining = session.remaining(clock);
+                print!("\r{}  ", format_countdown(remaining));
+                io::stdout().flush()?;
+
+                let wait = remaining.to_std().unwrap_or_default().min(TICK);
+                if lines.recv_timeout(wait)?.is_some() {
+                    return Ok(SessionEnd {
+                        elapsed: session.finish(clock),
+                        completed: false,
+                    });
+                }
+            }
+            SessionState::Elapsed => {
+                println!(
+                    "\r00:00  \n\nTime's up!\n1. Done\n2. Keep going for another {} minutes\n3. Stop the timer",
+                    length.num_minutes()
+                );
+                match lines.recv()?.trim() {
+                    "1" => {
+                        return Ok(SessionEnd {
+                            elapsed: session.finish(clock),
+                            completed: true,
+                        })
+                    }
+                    "2" => session.extend(clock, length),
+                    "3" => {
+                        return Ok(SessionEnd {
+                            elapsed: session.finish(clock),
+                            completed: false,
+                        })
+                    }
+                    _ => println!("Invalid input!"),
+                }
+            }
+            SessionState::Finished => unreachable!("Sessions only finish by returning"),
+        }
+    }
+}
+
+/// Formats a duration as mm:ss.
+fn format_countdown(remaining: Duration) -> String {
+    let seconds = remaining.num_seconds();
+    format!("{:02}:{:02}", seconds / 60, seconds % 60)
+}
+
+/// Reads stdin one line at a time on a background thread.
+#[derive(Default)]
+struct LineReader {
+    pending: Option<Receiver<io::Result<String>>>,
+}
+
+impl LineReader {
+    /// Waits up to `timeout` for a line.
+    fn recv_timeout(&mut self, timeout: std::time::Duration) -> io::Result<Option<String>> {
+        let rx = self.pending.get_or_insert_with(read_line_in_background);
+        match rx.recv_timeout(timeout) {
+            Ok(line) => {
+                self.pending = None;
+                line.map(Some)
+            }
+            Err(RecvTimeoutError::Timeout) => Ok(None),
+            Err(RecvTimeoutError::Disconnected) => {
+                Err(io::Error::new(io::ErrorKind::BrokenPipe, "stdin closed"))
+            }
+        }
+    }
+
+    /// Waits as long as it takes for a line.
+    fn recv(&mut self) -> io::Result<String> {
+        let rx = self.pending.take().unwrap_or_else(read_line_in_background);
+        rx.recv()
+            .unwrap_or_else(|_| Err(io::Error::new(io::ErrorKind::BrokenPipe, "stdin closed")))
+    }
+}
+
+fn read_line_in_background() -> Receiver<io::Result<String>> {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let mut line = String::new();
+        let result = io::stdin().read_line(&mut line).map(|_| line);
+        // The receiver only goes away once the session is over
+        let _ = tx.send(result);
+    });
+    rx
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::FixedClock;
+
+    #[test]
+    fn test_session_transitions() {
+        let clock = FixedClock::new(Utc::now());
+        let mut session = Session::start(&clock, Duration::minutes(25));
+        assert_eq!(session.state(&clock), SessionState::Running);
+        assert_eq!(session.remaining(&clock), Duration::minutes(25));
+
+        clock.advance(Duration::minutes(25));
+        assert_eq!(session.state(&clock), SessionState::Elapsed);
+        assert_eq!(session.remaining(&clock), Duration::zero());
+
+        // Extending after a break counts from now, not from when time ran out
+        clock.advance(Duration::minutes(3));
+        session.extend(&clock, Duration::minutes(10));
+        assert_eq!(session.state(&clock), SessionState::Running);
+        assert_eq!(session.remaining(&clock), Duration::minutes(10));
+
+        // Extending while running adds to what's left
+        session.extend(&clock, Duration::minutes(5));
+        assert_eq!(session.remaining(&clock), Duration::minutes(15));
+
+        clock.advance(Duration::minutes(4));
+        assert_eq!(session.finish(&clock), Duration::minutes(32));
+        assert_eq!(session.state(&clock), SessionState::Finished);
+
+        clock.advance(Duration::minutes(1));
+        session.extend(&clock, Duration::minutes(10));
+        assert_eq!(session.state(&clock), SessionState::Finished);
+        assert_eq!(session.finish(&clock), Duration::minutes(32));
+    }
+
+    #[test]
+    fn test_format_countdown() {
+        assert_eq!(format_countdown(Duration::minutes(25)), "25:00");
+        assert_eq!(format_countdown(Duration::seconds(61)), "01:01");
+    }
+}
@@ -0,0 +1,122 @@
+//! # Simulation
+//!
+//! Fast-forwards through a month of completions against a copy of the db, to
+//! see how the weighting and finance systems behave over time without
+//! touching the user's real data.
+
+use std::collections::HashMap;
+
+use chrono::Duration;
+use rusqlite::{backup::Backup, Connection};
+
+use crate::clock::{Clock, FixedClock};
+use crate::db::DbError;
+use crate::{complete_task_at, top_tasks_at};
+
+/// What happened over the course of a simulation.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SimulationReport {
+    pub days: u32,
+    pub tasks_completed: u32,
+    pub funds_earned: f64,
+    /// The task completed most often and how many times, which makes it easy
+    /// to spot one task dominating.
+    pub most_completed: Option<(String, u32)>,
+}
+
+/// Simulates the user completing the top task ToDo would offer once a day.
+///
+/// # Arguments
+///
+/// * `conn: &Connection` - The db to copy. It's never written to.
+/// * `days: u32` - How many days to simulate.
+/// * `clock: &FixedClock` - Supplies the simulated time. It's advanced by a
+///   day after each completion.
+///
+/// # Notes
+///
+/// Each day's task comes from the same shortlist as ToDo, built at the
+/// simulated time, so snoozed, deferred and vacation suppressed tasks are
+/// left alone just like they would be for the user. Days with nothing
+/// eligible to complete are skipped.
+pub fn simulate(
+    conn: &Connection,
+    days: u32,
+    clock: &FixedClock,
+) -> Result<SimulationReport, DbError> {
+    let mut sim = Connection::open_in_memory()?;
+    Backup::new(conn, &mut sim)?.run_to_completion(64, std::time::Duration::ZERO, None)?;
+
+    let mut tasks_completed = 0;
+    let mut funds_earned = 0.0;
+    let mut completions: HashMap<String, u32> = HashMap::new();
+
+    for _ in 0..days {
+        let now = clock.now();
+        if let Some((task, bounty)) = top_tasks_at(&sim, 1, now)?.into_iter().next() {
+            funds_earned += complete_task_at(&sim, &task, bounty, now)?;
+            tasks_completed += 1;
+            *completions.entry(task.summary).or_insert(0) += 1;
+        }
+
+        clock.advance(Duration::days(1));
+    }
+
+    let most_completed = completions
+        .into_iter()
+        .max_by(|a, b| a.1.cmp(&b.1).then(b.0.cmp(&a.0)));
+
+    Ok(SimulationReport {
+        days,
+        tasks_completed,
+        funds_earned,
+        most_completed,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{db, fixtures};
+    use chrono::Utc;
+
+    #[test]
+    fn test_simulate_month() {
+        let (conn, seed) = fixtures::seeded();
+        let clock = FixedClock::new(Utc::now());
+
+        let report = simulate(&conn, 30, &clock).unwrap();
+
+        // laundry every 8 days, water_plants every 4, plus dishes and report
+        assert_eq!(report.days, 30);
+        assert!((10..=16).contains(&report.tasks_completed), "{report:?}");
+        assert!(report.funds_earned > 0.0);
+        assert_eq!(
+            report.most_completed.map(|(summary, _)| summary),
+            Some("Water the plants".to_string())
+        );
+        assert!(clock.now() >= Utc::now() + Duration::days(29));
+
+        // The real db is untouched
+        fixtures::assert_funds(&conn, 0.0);
+        fixtures::assert_archived(&conn, seed.tasks["dishes"], false);
+        assert_eq!(
+            fixtures::read_task(&conn, seed.tasks["laundry"]).times_selected,
+            0
+        );
+    }
+
+    #[test]
+    fn test_simulate_skips_snoozed_and_deferred() {
+        let conn = fixtures::setup();
+        let snoozed = fixtures::insert_task(&conn, fixtures::task(1, "Snoozed"));
+        let deferred = fixtures::insert_task(&conn, fixtures::task(1, "Deferred"));
+        db::snooze_task(&conn, snoozed, 60).unwrap();
+        db::defer_task(&conn, deferred).unwrap();
+
+        let report = simulate(&conn, 30, &FixedClock::new(Utc::now())).unwrap();
+
+        assert_eq!(report.tasks_completed, 0);
+        assert_eq!(report.most_completed, None);
+    }
+}
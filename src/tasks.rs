@@ -1,13 +1,18 @@
-use chrono::{DateTime, Duration, Utc};
+use chrono::{DateTime, Days, Duration, Local, Utc};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::clone::Clone;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Task {
     pub id: u32,
     pub parent_id: u32,
     pub is_archived: bool,
     pub summary: String,
     pub description: Option<String>,
+    #[serde(with = "seconds")]
     pub average_duration: Option<Duration>,
     pub bounty_modifier: f32,
     pub due_date: Option<DateTime<Utc>>,
@@ -15,14 +20,457 @@ pub struct Task {
     pub lead_days: Option<u32>,
     pub priority: Priority,
     pub repeat_interval: Option<u32>,
+    /// What repeat_interval counts. Only meaningful for recurring tasks.
+    #[serde(default)]
+    pub repeat_unit: RepeatUnit,
     pub times_selected: u32,
     pub times_shown: u32,
+    /// When the task was added. Unlike from_date, this never changes.
+    pub created_at: DateTime<Utc>,
+    /// Set when the user has started the task but not finished it.
+    pub in_progress: bool,
+    /// Only meaningful for recurring tasks.
+    pub recurrence_anchor: RecurrenceAnchor,
+    /// A manual multiplier on the task's weight that fades to 1.0 by
+    /// boost_until. See `weighting::boost_multiplier()`.
+    pub boost_factor: f32,
+    pub boost_until: Option<DateTime<Utc>>,
 }
 
-#[derive(Clone, Debug, PartialEq)]
+impl Task {
+    /// Builds an active, one-off P1 task created now, with every optional
+    /// detail left blank.
+    ///
+    /// Use struct update syntax to adjust anything else.
+    pub fn new(parent_id: u32, summary: String) -> Self {
+        Task {
+            id: 0, // This will be ignored by db::add_task()
+            parent_id,
+            is_archived: false,
+            summary,
+            description: None,
+            average_duration: None,
+            bounty_modifier: 0.0,
+            due_date: None,
+            from_date: Utc::now(),
+            lead_days: None,
+            priority: Priority::P1,
+            repeat_interval: None,
+            repeat_unit: RepeatUnit::Days,
+            times_selected: 0,
+            times_shown: 0,
+            created_at: Utc::now(),
+            in_progress: false,
+            recurrence_anchor: RecurrenceAnchor::Completion,
+            boost_factor: 1.0,
+            boost_until: None,
+        }
+    }
+
+    /// The next few dates a recurring task comes around, found by adding
+    /// repeat_interval to its from_date again and again.
+    ///
+    /// # Returns
+    ///
+    /// Up to `count` dates, soonest first. Empty for tasks that don't repeat.
+    ///
+    /// # Notes
+    ///
+    /// Intervals in days are added in calendar days on the local clock, so a
+    /// daylight saving change doesn't shift the time of day.
+    pub fn projected_dates(&self, count: usize) -> Vec<DateTime<Utc>> {
+        let Some(interval) = self.repeat_interval.filter(|interval| *interval > 0) else {
+            return vec![];
+        };
+        let start = self.effective_from_date();
+
+        match self.repeat_unit {
+            RepeatUnit::Days => {
+                let start = start.with_timezone(&Local);
+                (1..=count as u64)
+                    .map_while(|n| start.checked_add_days(Days::new(n * u64::from(interval))))
+                    .map(|date| date.with_timezone(&Utc))
+                    .collect()
+            }
+            RepeatUnit::Hours => (1..=count as u32)
+                .map(|n| start + self.repeat_unit.duration(n * interval))
+                .collect(),
+        }
+    }
+
+    /// How long a recurring task waits between occurrences. None for tasks
+    /// that don't repeat.
+    pub fn repeat_duration(&self) -> Option<Duration> {
+        self.repeat_interval
+            .map(|interval| self.repeat_unit.duration(interval))
+    }
+
+    /// The task's average_duration once `elapsed` is counted as another
+    /// completion. Each earlier completion (times_selected) counts equally.
+    pub fn average_with(&self, elapsed: Duration) -> Duration {
+        match self.average_duration {
+            Some(average) => {
+                let completions = self.times_selected as i32;
+                (average * completions + elapsed) / (completions + 1)
+            }
+            None => elapsed,
+        }
+    }
+
+    /// The task's from_date, treating one in the future as now.
+    ///
+    /// A from_date can end up in the future if the system clock jumps back or
+    /// an import sets one. Taking it at face value would hide a recurring task
+    /// until long after its interval has passed.
+    pub fn effective_from_date(&self) -> DateTime<Utc> {
+        self.effective_from_date_at(Utc::now())
+    }
+
+    /// Same as `effective_from_date()`, but as if the current time were `now`.
+    pub fn effective_from_date_at(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        self.from_date.min(now)
+    }
+
+    /// When the task next starts competing for a spot in the ToDo list.
+    ///
+    /// # Returns
+    ///
+    /// The end of a recurring task's interval or the start of a deadline
+    /// task's lead days. None for one-off tasks, which are always eligible.
+    pub fn next_eligible_date(&self) -> Option<DateTime<Utc>> {
+        self.next_eligible_date_at(Utc::now())
+    }
+
+    /// Same as `next_eligible_date()`, but as if the current time were `now`.
+    pub fn next_eligible_date_at(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match (self.due_date, self.lead_days, self.repeat_interval) {
+            (Some(due_date), Some(lead_days), _) => {
+                Some(due_date - Duration::days(i64::from(lead_days)))
+            }
+            (None, _, Some(_)) => Some(self.effective_from_date_at(now) + self.repeat_duration()?),
+            _ => None,
+        }
+    }
+
+    /// Whether a deadline task has reached its lead days, i.e. it should be
+    /// underway by `now`. Always false for tasks without a deadline.
+    pub fn in_lead_window(&self, now: DateTime<Utc>) -> bool {
+        self.due_date.is_some()
+            && self
+                .next_eligible_date_at(now)
+                .is_some_and(|date| date <= now)
+    }
+
+    /// Whether a deadline task's due date has passed by `now`. Always false
+    /// for tasks without a deadline.
+    pub fn is_overdue(&self, now: DateTime<Utc>) -> bool {
+        self.due_date.is_some_and(|due_date| due_date < now)
+    }
+
+    /// The from_date a recurring task should be given when it's completed at
+    /// `now`.
+    ///
+    /// # Notes
+    ///
+    /// Schedule anchored tasks move forward by whole intervals, skipping any
+    /// occurrences that were missed entirely, so they keep their cadence no
+    /// matter how late they're completed.
+    pub fn next_from_date(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        match (&self.recurrence_anchor, self.repeat_duration()) {
+            (RecurrenceAnchor::Schedule, Some(interval)) if interval > Duration::zero() => {
+                let from_date = self.from_date.min(now);
+                let lapsed = (now - from_date).num_seconds() / interval.num_seconds();
+
+                from_date + interval * lapsed.max(1) as i32
+            }
+            _ => now,
+        }
+    }
+}
+
+/// How much a task matters. Priorities compare in the order they're declared,
+/// which matches how much each one adds to a task's weight, so Neutral is the
+/// lowest and P3 the highest.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum Priority {
+    /// Leaves the weight untouched, so the task is ranked purely by its age or
+    /// due date.
+    Neutral,
     P0,
     P1,
     P2,
     P3,
 }
+
+/// Provides the label each priority is shown with.
+impl fmt::Display for Priority {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Priority::Neutral => write!(f, "Neutral"),
+            Priority::P0 => write!(f, "Deprioritized"),
+            Priority::P1 => write!(f, "Default"),
+            Priority::P2 => write!(f, "High Priority"),
+            Priority::P3 => write!(f, "Top Priority"),
+        }
+    }
+}
+
+/// Implements parsing a priority back from its label.
+impl FromStr for Priority {
+    type Err = ParsePriorityError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Neutral" => Ok(Priority::Neutral),
+            "Deprioritized" => Ok(Priority::P0),
+            "Default" => Ok(Priority::P1),
+            "High Priority" => Ok(Priority::P2),
+            "Top Priority" => Ok(Priority::P3),
+            _ => Err(ParsePriorityError::InvalidInput(s.to_string())),
+        }
+    }
+}
+
+/// The number each priority is stored in the db as, and entered as on the
+/// priority prompt.
+impl From<&Priority> for u8 {
+    fn from(priority: &Priority) -> Self {
+        match priority {
+            Priority::P0 => 0,
+            Priority::P1 => 1,
+            Priority::P2 => 2,
+            Priority::P3 => 3,
+            Priority::Neutral => 4,
+        }
+    }
+}
+
+/// The reverse of `u8::from(&Priority)`.
+impl TryFrom<u8> for Priority {
+    type Error = ParsePriorityError;
+
+    fn try_from(number: u8) -> Result<Self, Self::Error> {
+        match number {
+            0 => Ok(Priority::P0),
+            1 => Ok(Priority::P1),
+            2 => Ok(Priority::P2),
+            3 => Ok(Priority::P3),
+            4 => Ok(Priority::Neutral),
+            _ => Err(ParsePriorityError::InvalidInput(number.to_string())),
+        }
+    }
+}
+
+/// Defines errors that can occur when converting a label or number into a
+/// `Priority`.
+#[derive(Debug, Clone)]
+pub enum ParsePriorityError {
+    /// Contains the input that doesn't correspond to any priority.
+    InvalidInput(String),
+}
+
+/// Implements display formatting for `ParsePriorityError`, providing a human-readable description of the error.
+impl fmt::Display for ParsePriorityError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParsePriorityError::InvalidInput(input) => write!(f, "Invalid input: {}", input),
+        }
+    }
+}
+
+/// Allows `ParsePriorityError` to integrate with Rust's standard error handling mechanisms.
+impl Error for ParsePriorityError {}
+
+/// What a recurring task's next occurrence is counted from.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum RecurrenceAnchor {
+    /// When it was last completed, so completing it late pushes back every
+    /// later occurrence.
+    Completion,
+    /// When it was last scheduled, so it stays on a fixed cadence.
+    Schedule,
+}
+
+/// What a recurring task's repeat_interval is counted in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum RepeatUnit {
+    #[default]
+    Days,
+    /// For habits that come around several times a day.
+    Hours,
+}
+
+impl RepeatUnit {
+    /// How long `count` of this unit lasts.
+    pub fn duration(&self, count: u32) -> Duration {
+        match self {
+            RepeatUnit::Days => Duration::days(i64::from(count)),
+            RepeatUnit::Hours => Duration::hours(i64::from(count)),
+        }
+    }
+
+    /// The unit's plural name, e.g. for "every 4 hours".
+    pub fn name(&self) -> &'static str {
+        match self {
+            RepeatUnit::Days => "days",
+            RepeatUnit::Hours => "hours",
+        }
+    }
+}
+
+/// Serializes a task's average_duration as a whole number of seconds, the
+/// same way the db stores it.
+mod seconds {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        duration: &Option<Duration>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        duration.map(|d| d.num_seconds()).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Duration>, D::Error> {
+        Ok(Option::<i64>::deserialize(deserializer)?.map(Duration::seconds))
+    }
+}
+
+/// The reusable parts of a task, saved so that similar tasks can be added
+/// later by entering only a summary (and due date, for deadline tasks).
+#[derive(Clone, Debug, PartialEq)]
+pub struct TaskTemplate {
+    pub id: u32,
+    pub name: String,
+    pub parent_id: u32,
+    pub description: Option<String>,
+    pub bounty_modifier: f32,
+    /// Some if tasks made from this template have a deadline.
+    pub lead_days: Option<u32>,
+    pub priority: Priority,
+    pub repeat_interval: Option<u32>,
+    pub repeat_unit: RepeatUnit,
+    pub recurrence_anchor: RecurrenceAnchor,
+}
+
+impl TaskTemplate {
+    /// Copies the reusable fields of `task` into a new template.
+    pub fn from_task(task: &Task, name: String) -> Self {
+        TaskTemplate {
+            id: 0, // This will be ignored by db::save_template()
+            name,
+            parent_id: task.parent_id,
+            description: task.description.clone(),
+            bounty_modifier: task.bounty_modifier,
+            lead_days: task.lead_days,
+            priority: task.priority.clone(),
+            repeat_interval: task.repeat_interval,
+            repeat_unit: task.repeat_unit,
+            recurrence_anchor: task.recurrence_anchor.clone(),
+        }
+    }
+
+    /// Builds a fresh, unsaved task from this template.
+    ///
+    /// # Arguments
+    ///
+    /// * `summary: String` - The new task's summary.
+    /// * `due_date: Option<DateTime<Utc>>` - The new task's due date. Ignored
+    ///   unless the template has lead_days.
+    pub fn instantiate(&self, summary: String, due_date: Option<DateTime<Utc>>) -> Task {
+        Task {
+            id: 0,
+            parent_id: self.parent_id,
+            is_archived: false,
+            summary,
+            description: self.description.clone(),
+            average_duration: None,
+            bounty_modifier: self.bounty_modifier,
+            due_date: self.lead_days.and(due_date),
+            from_date: Utc::now(),
+            lead_days: self.lead_days,
+            priority: self.priority.clone(),
+            repeat_interval: self.repeat_interval,
+            repeat_unit: self.repeat_unit,
+            times_selected: 0,
+            times_shown: 0,
+            created_at: Utc::now(),
+            in_progress: false,
+            recurrence_anchor: self.recurrence_anchor.clone(),
+            boost_factor: 1.0,
+            boost_until: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_priority_conversions() {
+        for priority in [
+            Priority::Neutral,
+            Priority::P0,
+            Priority::P1,
+            Priority::P2,
+            Priority::P3,
+        ] {
+            assert_eq!(Priority::try_from(u8::from(&priority)).unwrap(), priority);
+            assert_eq!(priority.to_string().parse::<Priority>().unwrap(), priority);
+        }
+        assert!(Priority::try_from(5).is_err());
+        assert!("Urgent".parse::<Priority>().is_err());
+    }
+
+    #[test]
+    fn test_projected_dates() {
+        let from_date = Utc.with_ymd_and_hms(2024, 6, 3, 9, 0, 0).unwrap();
+        let weekly = Task {
+            repeat_interval: Some(7),
+            from_date,
+            ..Task::new(1, "Weekly".into())
+        };
+
+        // No daylight saving changes fall in June, so each is exactly a week on
+        let expected: Vec<DateTime<Utc>> = (1..=3)
+            .map(|weeks| from_date + Duration::weeks(weeks))
+            .collect();
+        assert_eq!(weekly.projected_dates(3), expected);
+
+        let hourly = Task {
+            repeat_unit: RepeatUnit::Hours,
+            ..weekly
+        };
+        assert_eq!(
+            hourly.projected_dates(2),
+            [
+                from_date + Duration::hours(7),
+                from_date + Duration::hours(14)
+            ]
+        );
+
+        let one_off = Task::new(1, "One-off".into());
+        assert!(one_off.projected_dates(3).is_empty());
+    }
+
+    #[test]
+    fn test_lead_window_and_overdue() {
+        let now = Utc.with_ymd_and_hms(2024, 6, 3, 9, 0, 0).unwrap();
+        let due_in = |days: i64| Task {
+            due_date: Some(now + Duration::days(days)),
+            lead_days: Some(3),
+            ..Task::new(1, "Taxes".into())
+        };
+
+        let (early, underway, late) = (due_in(5), due_in(2), due_in(-1));
+        assert!(!early.in_lead_window(now) && !early.is_overdue(now));
+        assert!(underway.in_lead_window(now) && !underway.is_overdue(now));
+        assert!(late.in_lead_window(now) && late.is_overdue(now));
+
+        let no_deadline = Task::new(1, "Dishes".into());
+        assert!(!no_deadline.in_lead_window(now) && !no_deadline.is_overdue(now));
+    }
+}
@@ -3,17 +3,26 @@
 //! This module contains functions related to printing to terminal I/O. Anything
 //! that the user interacts with will be created here.
 
-use chrono::{DateTime, Duration, Utc};
+use chrono::{DateTime, Duration, Local, NaiveDate, NaiveTime, Utc};
 use rusqlite::Connection;
 
 use crate::folders::{Folder, Style};
-use crate::{db, tasks::Task, ToString};
+use crate::{
+    backup::ImportSummary,
+    db,
+    finance::{BountyBreakdown, LedgerEntry},
+    simulation::SimulationReport,
+    tasks::{RecurrenceAnchor, RepeatUnit, Task, TaskTemplate},
+    ToString,
+};
 
 use super::{AppState, Priority};
 // use super::{Action, AppState, Priority};
 
 use std::error::Error;
-use std::io;
+use std::fmt;
+use std::io::{self, IsTerminal};
+use std::path::PathBuf;
 
 /// Print the Backlist logo to terminal.
 ///
@@ -94,474 +103,2646 @@ pub fn print_header(app_state: AppState) {
 ///
 /// # Arguments
 ///
+/// * `io: &mut impl IoPort` - Where prompts are written and input read.
 /// * `states: &[AppState]` - The slice of AppStates for the user to select
-/// from. Will display in the order provided.
+///   from. Will display in the order provided.
 ///
 /// # Returns
 ///
-/// The selected AppState
+/// The selected AppState, or an `UnexpectedEof` error once there's no more
+/// input to read.
 ///
 /// # Notes
 ///
 /// Will inform the user and retry if the user attempts to select a bad input.
-pub fn select_app_state(states: &[AppState]) -> AppState {
+pub fn select_app_state(io: &mut impl IoPort, states: &[AppState]) -> io::Result<AppState> {
     // We loop to retry invalid inputs
     loop {
-        println!(
-            "
-What would you like to do?\n"
-        );
+        io.write_line("\nWhat would you like to do?\n");
 
         // Print the ordered list for the user to select from
         for (index, state) in states.iter().enumerate() {
-            println!("{}. {}", index + 1, state.to_string());
+            io.write_line(&format!("{}. {}", index + 1, state.to_string()));
         }
-        print!("\n");
+        io.write_line("");
 
-        // Request user input
-        let mut input = String::new();
-        io::stdin()
-            .read_line(&mut input)
-            .expect("Failed to read line");
+        // Request user input. Nothing at all, not even a newline, means stdin
+        // was closed
+        let input = io.read_line()?;
+        if input.is_empty() {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
 
         // Check that the input is valid, then return the AppState or continue the loop.
         match input.trim().parse::<usize>() {
-            Ok(num) => {
-                // Here we make sure the value selected fits into the array before continuing.
-                if num > 0 && num <= states.len() {
-                    return states[num - 1].clone();
-                } else {
-                    println!("\nInvallid Input!");
-                    continue;
-                }
-            }
-            Err(_) => {
-                println!("\nInvalid Input!");
-                continue;
-            }
-        };
+            // Here we make sure the value selected fits into the array before continuing.
+            Ok(num) if num > 0 && num <= states.len() => return Ok(states[num - 1].clone()),
+            _ => io.write_line("\nInvalid input!"),
+        }
+    }
+}
+
+/// Shortens a string to a single line of at most `max` characters, ending in
+/// an ellipsis if anything was cut off.
+///
+/// # Arguments
+///
+/// * `s: &str` - The string to shorten.
+/// * `max: usize` - The maximum number of characters to keep, including the
+///   ellipsis.
+///
+/// # Notes
+///
+/// Counts chars rather than bytes, so multibyte characters are never split.
+pub fn truncate_to(s: &str, max: usize) -> String {
+    let first_line = s.lines().next().unwrap_or("");
+    let has_more_lines = first_line.len() < s.trim_end().len();
+
+    if first_line.chars().count() <= max && !has_more_lines {
+        return first_line.to_string();
+    }
+    if max == 0 {
+        return String::new();
+    }
+
+    let mut preview: String = first_line.chars().take(max - 1).collect();
+    preview.push('…');
+    preview
+}
+
+/// Enumerates the ways the digits of a number can be grouped and punctuated.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NumberStyle {
+    /// Groups thousands with commas and uses a decimal point, e.g. 12,345.60
+    CommaGrouped,
+    /// Groups thousands with periods and uses a decimal comma, e.g. 12.345,60
+    PeriodGrouped,
+}
+
+/// Describes how money amounts are rendered.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MoneyFormat {
+    pub style: NumberStyle,
+    pub decimals: usize,
+}
+
+/// Matches the `number_style` and `currency_decimals` default settings.
+impl Default for MoneyFormat {
+    fn default() -> Self {
+        MoneyFormat {
+            style: NumberStyle::CommaGrouped,
+            decimals: 2,
+        }
+    }
+}
+
+/// Formats an amount of money with a dollar sign, thousands separators, and
+/// a fixed number of decimal places.
+///
+/// # Arguments
+///
+/// * `amount: f64` - The amount to format.
+/// * `format: &MoneyFormat` - Controls the separators and decimal places.
+///
+/// # Examples
+///
+/// ```text
+/// format_money(-12345.6, &MoneyFormat::default())
+/// // Should return "-$12,345.60"
+/// ```
+pub fn format_money(amount: f64, format: &MoneyFormat) -> String {
+    let (group_separator, decimal_separator) = match format.style {
+        NumberStyle::CommaGrouped => (',', '.'),
+        NumberStyle::PeriodGrouped => ('.', ','),
+    };
+
+    let fixed = format!("{:.*}", format.decimals, amount.abs());
+    let (whole, fraction) = match fixed.split_once('.') {
+        Some((whole, fraction)) => (whole, Some(fraction)),
+        None => (fixed.as_str(), None),
+    };
+
+    // Insert a separator before every group of 3 digits, counting from the right
+    let mut grouped = String::new();
+    for (i, digit) in whole.chars().enumerate() {
+        if i > 0 && (whole.len() - i) % 3 == 0 {
+            grouped.push(group_separator);
+        }
+        grouped.push(digit);
+    }
+    if let Some(fraction) = fraction {
+        grouped.push(decimal_separator);
+        grouped.push_str(fraction);
+    }
+
+    // Amounts that round to zero shouldn't show up as "-$0.00"
+    let is_negative = amount < 0.0 && fixed.chars().any(|c| c.is_ascii_digit() && c != '0');
+    let sign = if is_negative { "-" } else { "" };
+
+    format!("{}${}", sign, grouped)
+}
+
+/// Settings that control how task lists are rendered.
+pub struct ListOptions {
+    /// Descriptions longer than this are truncated.
+    pub preview_length: usize,
+    /// Summaries longer than this are truncated.
+    pub summary_length: usize,
+    /// How many tasks to show at first, and how many more each time the
+    /// user asks to see more.
+    pub per_view: usize,
+    /// Set false to hide the bounty column, e.g. when finance is disabled.
+    pub show_bounties: bool,
+    /// How bounties are formatted.
+    pub money: MoneyFormat,
+    /// Set true to show each task's 0-100 urgency score.
+    pub show_urgency: bool,
+}
+
+const RED: &str = "\x1b[31m";
+const YELLOW: &str = "\x1b[33m";
+const RESET: &str = "\x1b[0m";
+
+/// Colours text by priority: red for top priority and yellow for high
+/// priority. Anything else is left plain.
+///
+/// # Notes
+///
+/// Always plain when output isn't going to a terminal, or while the
+/// `NO_COLOR` environment variable is set to anything but an empty string. See
+/// https://no-color.org.
+pub fn colorize_priority(p: &Priority, s: &str) -> String {
+    let no_color = std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+    if no_color || !io::stdout().is_terminal() {
+        return s.to_string();
+    }
+    paint_priority(p, s)
+}
+
+/// `colorize_priority()` without the terminal and `NO_COLOR` checks.
+fn paint_priority(p: &Priority, s: &str) -> String {
+    match p {
+        Priority::P3 => format!("{RED}{s}{RESET}"),
+        Priority::P2 => format!("{YELLOW}{s}{RESET}"),
+        _ => s.to_string(),
+    }
+}
+
+/// What the user did with the task they selected.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TaskOutcome {
+    Complete,
+    /// Started, but not finished.
+    InProgress,
+    /// Put back without being started.
+    Abandon,
+    /// Moved onto the later list.
+    Defer,
+    /// Put back, but ranked higher for the next few days.
+    Boost,
+    /// Put back, and kept out of the list for this many days.
+    Snooze(u32),
+}
+
+/// Asks the user how they got on with the task they selected.
+///
+/// # Arguments
+///
+/// * `io: &mut impl IoPort` - Where prompts are written and input read.
+pub fn request_task_outcome(io: &mut impl IoPort) -> Result<TaskOutcome, InputError> {
+    loop {
+        let input = read_trimmed_line(
+            io,
+            "\nHow did it go?\n1. Done\n2. Started, but not finished\n3. Put it back\n4. Save it for later\n5. Put it back, but bump it up for a few days\n6. Not today, snooze it\n",
+        )?;
+        match input.parse::<u32>() {
+            Ok(1) => return Ok(TaskOutcome::Complete),
+            Ok(2) => return Ok(TaskOutcome::InProgress),
+            Ok(3) => return Ok(TaskOutcome::Abandon),
+            Ok(4) => return Ok(TaskOutcome::Defer),
+            Ok(5) => return Ok(TaskOutcome::Boost),
+            Ok(6) => return Ok(TaskOutcome::Snooze(request_snooze_days(io)?)),
+            _ => io.write_line("Invalid input!"),
+        }
+    }
+}
+
+/// The longest a task can be snoozed for in one go.
+pub const MAX_SNOOZE_DAYS: u32 = 365;
+
+/// Asks how many days to snooze a task for, from 1 to `MAX_SNOOZE_DAYS`.
+fn request_snooze_days(io: &mut impl IoPort) -> Result<u32, InputError> {
+    loop {
+        let input = read_trimmed_line(io, "\nHow many days should it be snoozed for?\n")?;
+        match input.parse::<u32>() {
+            Ok(days) if (1..=MAX_SNOOZE_DAYS).contains(&days) => return Ok(days),
+            Ok(days) if days > MAX_SNOOZE_DAYS => io.write_line(&format!(
+                "A task can be snoozed for at most {MAX_SNOOZE_DAYS} days!"
+            )),
+            _ => io.write_line("Invalid input!"),
+        }
     }
 }
 
-pub fn select_task(tasks: &[(Task, f64)]) -> (Task, f64) {
+/// What the user picked from a list of tasks.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Selection {
+    /// The index of the chosen task.
+    Pick(usize),
+    /// The user wants to see more of the list.
+    ShowMore,
+}
+
+/// Asks the user to select from a list of tasks and their bounties.
+///
+/// # Arguments
+///
+/// * `io: &mut impl IoPort` - Where prompts are written and input read.
+/// * `tasks: &[(Task, f64)]` - The tasks to select from, paired with their
+///   bounties. Will display in the order provided.
+/// * `scores: &[f32]` - Each task's urgency, in the same order as `tasks`.
+///   Only shown when `options.show_urgency` is set.
+/// * `can_show_more: bool` - Set true to offer a "show more" option, for
+///   when `tasks` is only part of the list.
+/// * `options: &ListOptions` - Controls how each task is rendered.
+///
+/// # Notes
+///
+/// If the list starts with in progress tasks, they're shown under their own
+/// heading.
+pub fn select_task(
+    io: &mut impl IoPort,
+    tasks: &[(Task, f64)],
+    scores: &[f32],
+    can_show_more: bool,
+    options: &ListOptions,
+) -> Result<Selection, InputError> {
     // We loop to retry bad inputs
     loop {
-        println!(
+        io.write_line(
             "
-Select a task to complete.\n"
+Select a task to complete.\n",
         );
 
+        let has_in_progress_section = tasks.first().is_some_and(|(task, _)| task.in_progress);
+        let now = Utc::now();
+
         // Print the ordered list for the user to select from
         for (index, tup) in tasks.iter().enumerate() {
             // Unwrap the tuple
             let (task, bounty) = tup;
 
+            if has_in_progress_section && index == 0 {
+                io.write_line("In progress:");
+            } else if has_in_progress_section && !task.in_progress && tasks[index - 1].0.in_progress
+            {
+                io.write_line("\nUp next:");
+            }
+
+            let urgency = match scores.get(index) {
+                Some(score) if options.show_urgency => format!("  (urgency {:.0})", score),
+                _ => String::new(),
+            };
+
+            let mut summary = colorize_priority(
+                &task.priority,
+                &truncate_to(&task.summary, options.summary_length),
+            );
+            // Marked in words too, so it stands out without colour
+            if task.is_overdue(now) {
+                summary += " (overdue)";
+            }
+
             // Display the tasks index, bounty, and summary
-            println!("{}. ${}\n  - {}", index + 1, bounty, task.summary);
+            if options.show_bounties {
+                io.write_line(&format!(
+                    "{}. {}{}\n  - {}",
+                    index + 1,
+                    format_money(*bounty, &options.money),
+                    urgency,
+                    summary
+                ));
+            } else {
+                io.write_line(&format!("{}. {}{}", index + 1, summary, urgency));
+            }
 
             // Display the description only if it exists
-            if task.description.is_some() {
-                println!("        {}", task.description.as_ref().unwrap());
+            if let Some(description) = &task.description {
+                io.write_line(&format!(
+                    "        {}",
+                    truncate_to(description, options.preview_length)
+                ));
             }
         }
-        print!("\n");
+        if can_show_more {
+            io.write_line("\nm. Show more");
+        }
 
         // Request user input
-        let mut input = String::new();
-        io::stdin()
-            .read_line(&mut input)
-            .expect("Failed to read line");
+        let input = read_trimmed_line(io, "")?;
 
-        // Check that the input is valid, then return the AppState or continue the loop.
-        match input.trim().parse::<usize>() {
+        if can_show_more && input.eq_ignore_ascii_case("m") {
+            return Ok(Selection::ShowMore);
+        }
+
+        // Check that the input is valid, then return the index or continue the loop.
+        match input.parse::<usize>() {
             Ok(num) => {
                 // Here we make sure the value selected fits into the array before continuing.
                 if num > 0 && num <= tasks.len() {
-                    return tasks[num - 1].clone();
+                    return Ok(Selection::Pick(num - 1));
                 } else {
-                    println!("\nInvallid Input!");
+                    io.write_line("\nInvallid Input!");
                     continue;
                 }
             }
             Err(_) => {
-                println!("\nInvalid Input!");
+                io.write_line("\nInvalid Input!");
                 continue;
             }
         };
     }
 }
 
-/// Reads a line of text from stdin after displaying a prompt, trims the input, and returns it.
+/// Where user input is read from and prompts are written to.
+///
+/// Prompt flows take an `IoPort` rather than touching stdin directly, so they
+/// can be driven by scripted input in tests.
+pub trait IoPort {
+    /// Reads one line of input, including the trailing newline if any.
+    fn read_line(&mut self) -> io::Result<String>;
+    /// Writes one line of output.
+    fn write_line(&self, line: &str);
+}
+
+/// Reads from stdin and writes to stdout.
+pub struct StdIo;
+
+impl IoPort for StdIo {
+    fn read_line(&mut self) -> io::Result<String> {
+        let mut input = String::new();
+        io::stdin().read_line(&mut input)?;
+        Ok(input)
+    }
+
+    fn write_line(&self, line: &str) {
+        println!("{}", line);
+    }
+}
+
+/// Feeds scripted inputs to a prompt flow and captures everything it writes.
+#[cfg(test)]
+pub struct MockIo {
+    inputs: std::collections::VecDeque<String>,
+    pub output: std::cell::RefCell<Vec<String>>,
+}
+
+#[cfg(test)]
+impl MockIo {
+    /// Creates a MockIo that returns each of `inputs` in turn.
+    pub fn new(inputs: &[&str]) -> Self {
+        MockIo {
+            inputs: inputs.iter().map(|input| input.to_string()).collect(),
+            output: std::cell::RefCell::new(Vec::new()),
+        }
+    }
+}
+
+#[cfg(test)]
+impl IoPort for MockIo {
+    /// Fails with `UnexpectedEof` once the scripted inputs run out.
+    fn read_line(&mut self) -> io::Result<String> {
+        self.inputs
+            .pop_front()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::UnexpectedEof, "Out of scripted input"))
+    }
+
+    fn write_line(&self, line: &str) {
+        self.output.borrow_mut().push(line.to_string());
+    }
+}
+
+/// Typing any of these at a prompt cancels the current flow. Matching ignores
+/// case and surrounding whitespace.
+/// See `read_text_line()` for typing one as text.
+const CANCEL_KEYWORDS: [&str; 2] = ["q", "back"];
+
+/// Defines the ways a prompt flow can end without producing a value.
+#[derive(Debug)]
+pub enum InputError {
+    /// The user typed one of the `CANCEL_KEYWORDS` to back out of the flow.
+    Cancelled,
+    /// Reading the input failed.
+    Io(io::Error),
+}
+
+/// Implements display formatting for `InputError`, providing a human-readable description of the error.
+impl fmt::Display for InputError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InputError::Cancelled => write!(f, "Cancelled by user"),
+            InputError::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+/// Allows `InputError` to integrate with Rust's standard error handling mechanisms.
+impl Error for InputError {}
+
+impl From<io::Error> for InputError {
+    fn from(e: io::Error) -> Self {
+        InputError::Io(e)
+    }
+}
+
+/// Reads a line of text after displaying a prompt, trims the input, and returns it.
 ///
 /// # Arguments
 ///
+/// * `io: &mut impl IoPort` - Where the prompt is written and the input read.
 /// * `prompt: &str` - A string slice that holds the prompt message displayed to the user.
 ///
 /// # Returns
 ///
-/// * `Result<String, io::Error>` which is Ok containing the trimmed string if read successfully, or an Err otherwise.
-fn read_trimmed_line(prompt: &str) -> Result<String, io::Error> {
-    println!("{}", prompt);
-    let mut input = String::new();
-    io::stdin().read_line(&mut input)?;
-    Ok(input.trim().to_string())
+/// * `Result<String, InputError>` which is Ok containing the trimmed string if read successfully,
+///   `InputError::Cancelled` if the user typed a cancel keyword, or an Err otherwise.
+fn read_trimmed_line(io: &mut impl IoPort, prompt: &str) -> Result<String, InputError> {
+    io.write_line(prompt);
+    let input = io.read_line()?;
+    let input = input.trim();
+
+    if CANCEL_KEYWORDS.contains(&input.to_lowercase().as_str()) {
+        return Err(InputError::Cancelled);
+    }
+
+    Ok(input.to_string())
 }
 
-/// Requests and returns the parent_id from the user.
+/// Put in front of a cancel keyword to enter it as text, so `\q` is read as `q`.
+const ESCAPE: char = '\\';
+
+/// Reads free text, such as a summary or a name, with `read_trimmed_line()`.
+///
+/// # Notes
+///
+/// The cancel keywords still back out, but can be entered as text by putting
+/// `ESCAPE` in front of them. Anything else is read exactly as typed.
+fn read_text_line(io: &mut impl IoPort, prompt: &str) -> Result<String, InputError> {
+    let input = read_trimmed_line(io, prompt)?;
+
+    match input.strip_prefix(ESCAPE) {
+        Some(keyword) if CANCEL_KEYWORDS.contains(&keyword.to_lowercase().as_str()) => {
+            Ok(keyword.to_string())
+        }
+        _ => Ok(input),
+    }
+}
+
+/// A change that can be applied to several tasks at once.
+#[derive(Clone, Debug, PartialEq)]
+pub enum BulkAction {
+    SetPriority(Priority),
+    /// Moves the tasks into the folder with this id.
+    Move(u32),
+    Archive,
+    /// Removes the tasks for good. Meant for mistakes, since completed tasks
+    /// are archived.
+    Delete,
+}
+
+/// Parses a list of 1-based indices separated by commas and/or spaces, such
+/// as "1, 3 4".
 ///
 /// # Arguments
 ///
-/// * `conn: &Connection` - A conncetion to the db. `db::read_all_folders()` requires
-/// it, so it's required here too.
+/// * `input: &str` - The user's input.
+/// * `len: usize` - The length of the list being indexed into.
 ///
 /// # Returns
 ///
-/// * `Result<u32, io::Error>` containing the parent_id if successfully read, or an Err otherwise.
-pub fn request_parent_id(conn: &Connection) -> Result<u32, io::Error> {
-    loop {
-        let hm = db::read_all_folders(conn, None, "".to_string()).unwrap();
-
-        // Collect HashMap entries into a vector
-        let mut entries: Vec<(_, _)> = hm.into_iter().collect();
-
-        // Sort the vector by value alphabetically
-        entries.sort_by_key(|entry| entry.1.clone());
-
-        // Print sorted results
-        for (i, (_, value)) in entries.iter().enumerate() {
-            println!("{}. {}", i + 1, value);
+/// The 0-based indices in the order given with duplicates removed, or None if
+/// the input is empty or any index is invalid.
+fn parse_indices(input: &str, len: usize) -> Option<Vec<usize>> {
+    let mut indices = Vec::new();
+
+    for part in input.split(|c: char| c == ',' || c.is_whitespace()) {
+        if part.is_empty() {
+            continue;
         }
 
-        let selection = read_trimmed_line("\nSelect a folder.\n")?;
-        // TODO: Error handling for unwrap()
-
-        match selection.parse::<usize>() {
-            Ok(n) => {
-                if n >= 1 && n <= entries.len() {
-                    let (real_id, _) = entries[n - 1];
-                    return Ok(real_id);
-                } else {
-                    println!("Invalid input!")
+        match part.parse::<usize>() {
+            Ok(n) if n >= 1 && n <= len => {
+                if !indices.contains(&(n - 1)) {
+                    indices.push(n - 1);
                 }
             }
-            Err(_) => println!("Invalid input!"),
+            _ => return None,
         }
     }
+
+    if indices.is_empty() {
+        None
+    } else {
+        Some(indices)
+    }
 }
 
-/// Requests and returns the task summary from the user.
+/// Asks the user to pick any number of tasks from a list.
+///
+/// # Arguments
+///
+/// * `io: &mut impl IoPort` - Where prompts are written and input read.
+/// * `tasks: &[Task]` - The tasks to select from. Will display in the order
+///   provided.
 ///
 /// # Returns
 ///
-/// * `Result<String, io::Error>` containing the task summary if successfully read, or an Err otherwise.
-fn request_task_summary() -> Result<String, io::Error> {
+/// * `Result<Vec<Task>, InputError>` containing the selected tasks in the
+///   order the user entered them.
+pub fn select_tasks(io: &mut impl IoPort, tasks: &[Task]) -> Result<Vec<Task>, InputError> {
     loop {
-        let summary = read_trimmed_line("\nEnter task summary\n")?;
+        for (index, task) in tasks.iter().enumerate() {
+            io.write_line(&format!(
+                "{}. {}",
+                index + 1,
+                truncate_to(&task.summary, DEFAULT_MAX_SUMMARY_LENGTH)
+            ));
+        }
 
-        if !summary.is_empty() {
-            return Ok(summary);
-        } else {
-            println!("The task's summary cannot be empty!")
+        let input = read_trimmed_line(
+            io,
+            "\nSelect tasks by number, separated by commas or spaces.\n",
+        )?;
+
+        match parse_indices(&input, tasks.len()) {
+            Some(indices) => return Ok(indices.iter().map(|&i| tasks[i].clone()).collect()),
+            None => io.write_line("Invalid input!"),
         }
     }
 }
 
-/// Requests an optional description from the user. Returns None if the user enters an empty string.
+/// Writes a numbered list of task summaries.
 ///
-/// # Returns
+/// # Arguments
 ///
-/// * `Result<Option<String>, io::Error>` containing the task description if provided, or None if left blank.
-fn request_optional_description() -> Result<Option<String>, io::Error> {
-    let description = read_trimmed_line("\nEnter description (or hit <ENTER> to leave blank)\n")?;
-    if description.is_empty() {
-        Ok(None)
-    } else {
-        Ok(Some(description))
+/// * `io: &mut impl IoPort` - Where the list is written.
+/// * `tasks: &[Task]` - The tasks to list.
+/// * `first: usize` - The number given to the first task, so a long list can
+///   be written a page at a time.
+pub fn display_task_page(io: &mut impl IoPort, tasks: &[Task], first: usize) {
+    for (index, task) in tasks.iter().enumerate() {
+        io.write_line(&format!(
+            "{}. {}",
+            first + index,
+            truncate_to(&task.summary, DEFAULT_MAX_SUMMARY_LENGTH)
+        ));
     }
 }
 
-/// Requests the priority of the task from the user and converts it to a `Priority` enum.
+/// Lists tasks a page at a time and lets the user pick one to restore.
+///
+/// # Arguments
+///
+/// * `io: &mut impl IoPort` - Where prompts are written and input read.
+/// * `tasks: &[Task]` - The archived tasks, in the order to list them.
+/// * `page_length: usize` - How many tasks to list at a time.
 ///
 /// # Returns
 ///
-/// * `Result<Priority, Box<dyn Error>>` which is Ok containing the priority if successfully parsed, or an Err otherwise.
-fn request_priority() -> Result<Priority, Box<dyn Error>> {
-    loop {
-        let input = read_trimmed_line(
-            "\nEnter priority\n0. Deprioritized\n1. Default\n2. High Priority\n3. Top Priority\n",
-        )?;
-        match input.parse::<usize>() {
-            Ok(0) => return Ok(Priority::P0),
-            Ok(1) => return Ok(Priority::P1),
-            Ok(2) => return Ok(Priority::P2),
-            Ok(3) => return Ok(Priority::P3),
-            Ok(_) | Err(_) => println!("Invalid input!"),
+/// * `Result<Option<usize>, InputError>` containing the index of the task
+///   picked, or None if the user picked nothing.
+pub fn request_task_to_restore(
+    io: &mut impl IoPort,
+    tasks: &[Task],
+    page_length: usize,
+) -> Result<Option<usize>, InputError> {
+    for (page, rows) in tasks.chunks(page_length.max(1)).enumerate() {
+        let first = page * page_length.max(1);
+        display_task_page(io, rows, first + 1);
+
+        let shown = first + rows.len();
+        let more = shown < tasks.len();
+        let prompt = if more {
+            "\nEnter a number to restore that task, m to show more, or nothing to go back.\n"
+        } else {
+            "\nEnter a number to restore that task, or nothing to go back.\n"
+        };
+        loop {
+            let input = read_trimmed_line(io, prompt)?;
+            match input.parse::<usize>() {
+                Ok(n) if (1..=shown).contains(&n) => return Ok(Some(n - 1)),
+                _ if input.is_empty() => return Ok(None),
+                _ if more && input.eq_ignore_ascii_case("m") => break,
+                _ => io.write_line("Invalid input!"),
+            }
         }
     }
+
+    Ok(None)
 }
 
-/// Requests the type of task from the user, ensuring only valid options (1, 2, or 3) are accepted.
-/// Reprompts the user until a valid option is entered.
+/// Asks the user what to do with a set of selected tasks.
+///
+/// # Arguments
+///
+/// * `io: &mut impl IoPort` - Where prompts are written and input read.
+/// * `conn: &Connection` - Needed to list folders if the user moves the tasks.
 ///
 /// # Returns
 ///
-/// * `Result<u32, Box<dyn Error>>` which is Ok containing the task type if successfully parsed, or an Err otherwise.
-fn request_task_type() -> Result<u32, Box<dyn Error>> {
+/// * `Result<BulkAction, InputError>` containing the change to apply.
+pub fn request_bulk_action(
+    io: &mut impl IoPort,
+    conn: &Connection,
+) -> Result<BulkAction, InputError> {
     loop {
         let input = read_trimmed_line(
-            "\nWhat type of task is this?\n1. One Time\n2. Recurring\n3. Hard Deadline\n",
+            io,
+            "\nWhat would you like to do with them?\n1. Set priority\n2. Move to a folder\n3. Archive\n4. Delete\n",
         )?;
         match input.parse::<u32>() {
-            Ok(1) | Ok(2) | Ok(3) => return Ok(input.parse().unwrap()),
-            _ => println!("Invalid input!"),
+            Ok(1) => return Ok(BulkAction::SetPriority(request_priority(io)?)),
+            Ok(2) => return Ok(BulkAction::Move(request_parent_id(io, conn)?)),
+            Ok(3) => return Ok(BulkAction::Archive),
+            Ok(4) => return Ok(BulkAction::Delete),
+            _ => io.write_line("Invalid input!"),
         }
     }
 }
 
-/// Requests the interval for recurring tasks from the user, ensuring that only positive integers are accepted.
+/// Asks which task to move within its folder and in which direction.
+///
+/// # Arguments
+///
+/// * `io: &mut impl IoPort` - Where the prompts are written and read.
+/// * `tasks: &[Task]` - The folder's tasks in their current order.
 ///
 /// # Returns
 ///
-/// * `Result<Option<u32>, Box<dyn Error>>` which is Ok containing the interval in days if a valid input is provided.
-fn request_recurring_details() -> Result<Option<u32>, Box<dyn Error>> {
+/// The index of the chosen task and the index it should move to.
+pub fn request_move(io: &mut impl IoPort, tasks: &[Task]) -> Result<(usize, usize), InputError> {
     loop {
-        let input = read_trimmed_line("\nHow many days would you like between recurrences?\n")?;
-        match input.parse::<u32>() {
-            Ok(num) if num > 0 => return Ok(Some(num)),
-            _ => println!("Invalid input!"),
+        for (index, task) in tasks.iter().enumerate() {
+            io.write_line(&format!(
+                "{}. {}",
+                index + 1,
+                truncate_to(&task.summary, DEFAULT_MAX_SUMMARY_LENGTH)
+            ));
         }
+
+        let input = read_trimmed_line(io, "\nSelect a task to move.\n")?;
+        let index = match input.parse::<usize>() {
+            Ok(number) if (1..=tasks.len()).contains(&number) => number - 1,
+            _ => {
+                io.write_line("Invalid input!");
+                continue;
+            }
+        };
+
+        let input = read_trimmed_line(io, "\n1. Move up\n2. Move down\n")?;
+        let new_index = match input.parse::<u32>() {
+            Ok(1) if index > 0 => index - 1,
+            Ok(2) if index + 1 < tasks.len() => index + 1,
+            Ok(1) | Ok(2) => {
+                io.write_line("It can't move any further that way.");
+                continue;
+            }
+            _ => {
+                io.write_line("Invalid input!");
+                continue;
+            }
+        };
+
+        return Ok((index, new_index));
     }
 }
 
-/// Requests deadline details for tasks with a hard deadline, ensuring that the provided values are valid.
-///
-/// # Returns
-///
-/// * `Result<(Option<DateTime<Utc>>, Option<u32>), Box<dyn Error>>` containing the due date and lead days if valid inputs are provided, or None for each if not applicable.
-fn request_deadline_details() -> Result<(Option<DateTime<Utc>>, Option<u32>), Box<dyn Error>> {
-    let days_until_deadline = loop {
-        let input = read_trimmed_line("\nHow many days until the deadline?\n")?;
-        match input.parse::<i64>() {
-            Ok(num) if num >= 0 => break num, // Ensuring positive value
-            _ => println!("Invalid input. Please enter a non-negative number of days."),
-        }
-    };
-    // TODO: This should be set to last midnight + duration
-    let due_date = Utc::now() + Duration::days(days_until_deadline);
-
-    let lead_days = loop {
-        let input =
-            read_trimmed_line("\nHow many days before the deadline would you like to start?\n")?;
-        match input.parse::<u32>() {
-            Ok(num) if num > 0 => break num, // Ensuring positive value
-            _ => println!("Invalid input. Please enter a positive number of days."),
+/// Asks the user for a keyword to search tasks for.
+pub fn request_keyword(io: &mut impl IoPort) -> Result<String, InputError> {
+    loop {
+        let input = read_text_line(io, "\nWhat should the tasks mention?\n")?;
+        if input.is_empty() {
+            io.write_line("Invalid input!");
+        } else {
+            return Ok(input);
         }
-    };
-
-    Ok((Some(due_date), Some(lead_days)))
+    }
 }
 
-/// Constructs a `Task` object based on user input. Prompts the user for various task details,
-/// including summary, description, priority, and type. Depending on the task type, additional
-/// information such as recurrence interval or deadline details may also be requested.
+/// Asks the user where to save an exported file.
 ///
 /// # Arguments
 ///
-/// * `conn: &Connection` - A conncetion to the db. `db::read_all_folders()` requires
-/// it, so it's required here too.
+/// * `io: &mut impl IoPort` - Where prompts are written and input read.
+/// * `default: &str` - Used if the user leaves the path blank.
+pub fn request_export_path(io: &mut impl IoPort, default: &str) -> Result<PathBuf, InputError> {
+    let input = read_text_line(
+        io,
+        &format!("\nWhere should the file be saved? (hit <ENTER> for {default})\n"),
+    )?;
+
+    if input.is_empty() {
+        Ok(PathBuf::from(default))
+    } else {
+        Ok(PathBuf::from(input))
+    }
+}
+
+/// Asks the user which file to import.
 ///
-/// # Returns
+/// # Arguments
 ///
-/// * `Result<Task, Box<dyn Error>>` which is Ok containing the constructed Task object if all inputs are successfully gathered and parsed, or an Err otherwise.
-pub fn request_task_input(conn: &Connection) -> Result<Task, Box<dyn Error>> {
-    let patent_id = request_parent_id(conn)?;
-    let summary = request_task_summary()?;
-    let description = request_optional_description()?;
-    let priority = request_priority()?;
-    let task_type = request_task_type()?;
-
-    let mut repeat_interval: Option<u32> = None;
-    let mut due_date: Option<DateTime<Utc>> = None;
-    let mut lead_days: Option<u32> = None;
-
-    match task_type {
-        2 => repeat_interval = request_recurring_details()?,
-        3 => {
-            let details = request_deadline_details()?;
-            due_date = details.0;
-            lead_days = details.1;
-        }
-        _ => {}
+/// * `io: &mut impl IoPort` - Where prompts are written and input read.
+/// * `default: &str` - Used if the user leaves the path blank.
+pub fn request_import_path(io: &mut impl IoPort, default: &str) -> Result<PathBuf, InputError> {
+    let input = read_text_line(
+        io,
+        &format!("\nWhich file should be imported? (hit <ENTER> for {default})\n"),
+    )?;
+
+    if input.is_empty() {
+        Ok(PathBuf::from(default))
+    } else {
+        Ok(PathBuf::from(input))
     }
+}
 
-    Ok(Task {
-        id: 0, // This value is ignored
-        parent_id: patent_id,
-        is_archived: false,
-        summary,
-        description,
-        average_duration: None,
-        bounty_modifier: 0.0,
-        due_date,
-        from_date: Utc::now(), // TODO: Set to last midnight
-        lead_days,
-        priority,
-        repeat_interval,
-        times_selected: 0,
-        times_shown: 0,
-    })
+/// What the user wants to import.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ImportKind {
+    SettingsAndStatistics,
+    /// Folders and tasks from a JSON backup.
+    Backup,
 }
 
-/// Requests and returns the folder name from the user.
-///
-/// # Returns
-///
-/// * `Result<String, io::Error>` containing the folder name if successfully read, or an Err otherwise.
-fn request_folder_name() -> Result<String, io::Error> {
+/// Asks the user what to import.
+pub fn request_import_kind(io: &mut impl IoPort) -> Result<ImportKind, InputError> {
     loop {
-        let name = read_trimmed_line("\nEnter folder name\n")?;
-
-        if !name.is_empty() {
-            return Ok(name);
-        } else {
-            println!("The folder's name cannot be empty!")
+        let input = read_trimmed_line(
+            io,
+            "\nWhat would you like to import?\n1. Settings and statistics\n2. Folders and tasks from a backup\n",
+        )?;
+        match input.parse::<u32>() {
+            Ok(1) => return Ok(ImportKind::SettingsAndStatistics),
+            Ok(2) => return Ok(ImportKind::Backup),
+            _ => io.write_line("Invalid input!"),
         }
     }
 }
 
-/// Requests the style of the folder from the user and converts it to a `Style` enum.
+/// What the user wants to export.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ExportKind {
+    Tasks,
+    SettingsAndStatistics,
+    /// Every folder and task as JSON.
+    Backup,
+}
+
+/// Asks the user what to export.
+pub fn request_export_kind(io: &mut impl IoPort) -> Result<ExportKind, InputError> {
+    loop {
+        let input = read_trimmed_line(
+            io,
+            "\nWhat would you like to export?\n1. Tasks\n2. Settings and statistics\n3. A backup of every folder and task\n",
+        )?;
+        match input.parse::<u32>() {
+            Ok(1) => return Ok(ExportKind::Tasks),
+            Ok(2) => return Ok(ExportKind::SettingsAndStatistics),
+            Ok(3) => return Ok(ExportKind::Backup),
+            _ => io.write_line("Invalid input!"),
+        }
+    }
+}
+
+/// Asks the user how imported settings should treat the ones they already
+/// have. Overwriting has to be confirmed.
+pub fn request_import_mode(io: &mut impl IoPort) -> Result<db::ImportMode, InputError> {
+    loop {
+        let input = read_trimmed_line(
+            io,
+            "\nHow should the file be imported?\n1. Merge (keep anything the file doesn't mention)\n2. Overwrite (replace everything)\n",
+        )?;
+        match input.parse::<u32>() {
+            Ok(1) => return Ok(db::ImportMode::Merge),
+            Ok(2) => {
+                if confirm(
+                    io,
+                    "Settings missing from the file will go back to their defaults. Continue?",
+                )? {
+                    return Ok(db::ImportMode::Overwrite);
+                }
+            }
+            _ => io.write_line("Invalid input!"),
+        }
+    }
+}
+
+/// Asks the user a yes/no question.
+///
+/// # Arguments
+///
+/// * `io: &mut impl IoPort` - Where the prompt is written and the input read.
+/// * `prompt: &str` - The question to ask. "(y/N)" is appended.
+///
+/// # Returns
+///
+/// * `Result<bool, InputError>` which is Ok(true) only if the user answered
+///   "y" or "yes". Anything else, including a blank line, counts as no.
+pub fn confirm(io: &mut impl IoPort, prompt: &str) -> Result<bool, InputError> {
+    let answer = read_trimmed_line(io, &format!("{} (y/N)", prompt))?;
+    Ok(matches!(answer.to_lowercase().as_str(), "y" | "yes"))
+}
+
+/// Lists stale tasks and asks the user whether to archive all of them.
+///
+/// # Arguments
+///
+/// * `io: &mut impl IoPort` - Where prompts are written and input read.
+/// * `tasks: &[Task]` - The stale tasks.
+/// * `threshold_days: u32` - How old a task must be to count as stale.
+pub fn confirm_archive_stale(
+    io: &mut impl IoPort,
+    tasks: &[Task],
+    threshold_days: u32,
+) -> Result<bool, InputError> {
+    io.write_line(&format!(
+        "\n{} one-off task(s) are over {} days old and have never been selected:\n",
+        tasks.len(),
+        threshold_days
+    ));
+    for task in tasks {
+        io.write_line(&format!(
+            "  - {}",
+            truncate_to(&task.summary, DEFAULT_MAX_SUMMARY_LENGTH)
+        ));
+    }
+
+    confirm(io, "\nArchive them all now?")
+}
+
+/// Requests and returns the parent_id from the user.
+///
+/// # Arguments
+///
+/// * `io: &mut impl IoPort` - Where prompts are written and input read.
+/// * `conn: &Connection` - A conncetion to the db. `db::read_all_folders_sorted()` requires
+///   it, so it's required here too.
 ///
 /// # Returns
 ///
-/// * `Result<Style, Box<dyn Error>>` which is Ok containing the style if successfully parsed, or an Err otherwise.
-fn request_style() -> Result<Style, Box<dyn Error>> {
+/// * `Result<u32, InputError>` containing the parent_id if successfully read, or an Err otherwise.
+pub fn request_parent_id(io: &mut impl IoPort, conn: &Connection) -> Result<u32, InputError> {
     loop {
-        let input =
-            read_trimmed_line("\nEnter folder type\n1. Directory\n2. Selector\n3. Iterator\n")?;
-        match input.parse::<usize>() {
-            Ok(1) => return Ok(Style::Directory),
-            Ok(2) => return Ok(Style::Selector),
-            Ok(3) => return Ok(Style::Iterator),
-            Ok(_) | Err(_) => println!("Invalid input!"),
+        let entries = db::read_all_folders_sorted(conn).unwrap();
+
+        // Print sorted results
+        for (i, (_, value)) in entries.iter().enumerate() {
+            io.write_line(&format!("{}. {}", i + 1, value));
+        }
+
+        let selection = read_trimmed_line(io, "\nSelect a folder.\n")?;
+        // TODO: Error handling for unwrap()
+
+        match selection.parse::<usize>() {
+            Ok(n) => {
+                if n >= 1 && n <= entries.len() {
+                    let (real_id, _) = entries[n - 1];
+                    return Ok(real_id);
+                } else {
+                    io.write_line("Invalid input!")
+                }
+            }
+            Err(_) => io.write_line("Invalid input!"),
         }
     }
 }
 
-/// Constructs a `Folder` object based on user input. Prompts the user for various folder details,
-/// including TODO.
+/// Matches the max_summary_length default setting. Also used to keep lists
+/// that aren't configurable tidy.
+pub const DEFAULT_MAX_SUMMARY_LENGTH: usize = 120;
+
+/// Reads the longest summary the user may enter from the settings table.
+pub fn max_summary_length(conn: &Connection) -> usize {
+    match db::setting_or_default(conn, "max_summary_length") {
+        0 => db::default_setting("max_summary_length") as usize,
+        n => n as usize,
+    }
+}
+
+/// Requests and returns the task summary from the user.
+///
+/// # Arguments
+///
+/// * `io: &mut impl IoPort` - Where prompts are written and input read.
+/// * `max_length: usize` - Summaries with more characters than this are
+///   rejected.
 ///
 /// # Returns
 ///
-/// * `Result<Folder, Box<dyn Error>>` which is Ok containing the constructed Folder object if all inputs are successfully gathered and parsed, or an Err otherwise.
-pub fn request_folder_input(conn: &Connection) -> Result<Folder, Box<dyn Error>> {
-    let parent_id = request_parent_id(conn)?;
-    let name = request_folder_name()?;
-    let style = request_style()?;
-
-    Ok(Folder {
-        id: 0,                      // Assuming these values are still hardcoded or otherwise set
-        parent_id: Some(parent_id), // TODO: Allow top-level folders to be added
-        name: name,
-        style: style,
-        status: None,
-    })
+/// * `Result<String, InputError>` containing the task summary if successfully read, or an Err otherwise.
+pub fn request_task_summary(io: &mut impl IoPort, max_length: usize) -> Result<String, InputError> {
+    loop {
+        let summary = read_text_line(io, "\nEnter task summary\n")?;
+        let length = summary.chars().count();
+
+        if summary.is_empty() {
+            io.write_line("The task's summary cannot be empty!")
+        } else if length > max_length {
+            io.write_line(&format!(
+                "The task's summary can't be longer than {} characters (yours is {}). Put the details in the description instead.",
+                max_length, length
+            ))
+        } else {
+            return Ok(summary);
+        }
+    }
 }
 
-// pub fn select_task() -> Option<Action> {
-//     println!("\nSelect a task to complete, or select 0 to edit tasks\n");
+/// Asks how long the task usually takes, in a form like "1h30m" or "45m".
+fn request_optional_estimate(io: &mut impl IoPort) -> Result<Option<Duration>, InputError> {
+    loop {
+        let input = read_trimmed_line(
+            io,
+            "\nHow long does it usually take? e.g. 45m or 1h30m (or hit <ENTER> to skip)\n",
+        )?;
+        if input.is_empty() {
+            return Ok(None);
+        }
+        match parse_duration(&input) {
+            Some(duration) if duration > Duration::zero() => return Ok(Some(duration)),
+            _ => io.write_line("Invalid input!"),
+        }
+    }
+}
 
-//     let mut input = String::new();
+/// Requests an optional description from the user. Returns None if the user enters an empty string.
+///
+/// # Returns
+///
+/// * `Result<Option<String>, InputError>` containing the task description if provided, or None if left blank.
+fn request_optional_description(io: &mut impl IoPort) -> Result<Option<String>, InputError> {
+    let description = read_text_line(io, "\nEnter description (or hit <ENTER> to leave blank)\n")?;
+    if description.is_empty() {
+        Ok(None)
+    } else {
+        Ok(Some(description))
+    }
+}
 
-//     io::stdin()
-//         .read_line(&mut input)
-//         .expect("Failed to read line");
+/// Requests the priority of the task from the user and converts it to a `Priority` enum.
+///
+/// # Returns
+///
+/// * `Result<Priority, InputError>` which is Ok containing the priority if successfully parsed, or an Err otherwise.
+fn request_priority(io: &mut impl IoPort) -> Result<Priority, InputError> {
+    let mut prompt = String::from("\nEnter priority\n");
+    for priority in (0..).map_while(|n| Priority::try_from(n).ok()) {
+        prompt += &format!("{}. {}", u8::from(&priority), priority);
+        if priority == Priority::Neutral {
+            prompt += " (ranked by age or due date only)";
+        }
+        prompt += "\n";
+    }
 
-//     let selection: Option<usize> = match input.trim().parse() {
-//         Ok(num) => Some(num),
-//         Err(_) => None,
-//     };
+    loop {
+        let input = read_trimmed_line(io, &prompt)?;
+        match input.parse::<u8>().map(Priority::try_from) {
+            Ok(Ok(priority)) => return Ok(priority),
+            _ => io.write_line("Invalid input!"),
+        }
+    }
+}
 
-//     match selection {
-//         Some(num) => {
-//             if num == 0 {
-//                 Some(Action::EditMode)
-//             } else if num <= 5 {
-//                 Some(Action::SelectTask(num))
-//             } else {
-//                 None
-//             }
-//         }
-//         None => Some(Action::ReturnToStart),
-//     }
-// }
+/// Requests the type of task from the user, ensuring only valid options (1, 2, or 3) are accepted.
+/// Reprompts the user until a valid option is entered.
+///
+/// # Returns
+///
+/// * `Result<u32, InputError>` which is Ok containing the task type if successfully parsed, or an Err otherwise.
+fn request_task_type(io: &mut impl IoPort) -> Result<u32, InputError> {
+    loop {
+        let input = read_trimmed_line(
+            io,
+            "\nWhat type of task is this?\n1. One Time\n2. Recurring\n3. Hard Deadline\n",
+        )?;
+        match input.parse::<u32>() {
+            Ok(1) | Ok(2) | Ok(3) => return Ok(input.parse().unwrap()),
+            _ => io.write_line("Invalid input!"),
+        }
+    }
+}
 
-pub fn display_task(task: &Task) {
-    println!(
-        "
-============================
-  Backlist > Task Selected
-============================
+/// The repeat interval, its unit and the recurrence anchor of a recurring task.
+type RecurringDetails = (Option<u32>, RepeatUnit, RecurrenceAnchor);
 
-You have selected:
+/// Requests the interval for recurring tasks from the user, ensuring that only positive integers are accepted, and what each recurrence is counted from.
+///
+/// # Returns
+///
+/// * `Result<RecurringDetails, InputError>` which is Ok containing the interval, whether it's in days or hours, and the anchor if valid inputs are provided.
+fn request_recurring_details(io: &mut impl IoPort) -> Result<RecurringDetails, InputError> {
+    let repeat_unit = loop {
+        let input = read_trimmed_line(io, "\nDoes it repeat every few\n1. Days\n2. Hours\n")?;
+        match input.parse::<u32>() {
+            Ok(1) => break RepeatUnit::Days,
+            Ok(2) => break RepeatUnit::Hours,
+            _ => io.write_line("Invalid input!"),
+        }
+    };
 
-{}",
-        task.summary
+    let prompt = format!(
+        "\nHow many {} would you like between recurrences?\n",
+        repeat_unit.name()
     );
+    let repeat_interval = loop {
+        let input = read_trimmed_line(io, &prompt)?;
+        match input.parse::<u32>() {
+            Ok(num) if num > 0 => break Some(num),
+            _ => io.write_line("Invalid input!"),
+        }
+    };
 
-    if task.description.is_some() {
-        println!("    {}", task.description.clone().unwrap());
+    loop {
+        let input = read_trimmed_line(
+            io,
+            "\nCount each recurrence from\n1. When it's completed\n2. A fixed schedule\n",
+        )?;
+        match input.parse::<u32>() {
+            Ok(1) => return Ok((repeat_interval, repeat_unit, RecurrenceAnchor::Completion)),
+            Ok(2) => return Ok((repeat_interval, repeat_unit, RecurrenceAnchor::Schedule)),
+            _ => io.write_line("Invalid input!"),
+        }
     }
+}
 
-    println!("\n\n(Debug) ID: {}\n", task.id);
+/// The due date and lead days of a task with a hard deadline.
+type DeadlineDetails = (Option<DateTime<Utc>>, Option<u32>);
+
+/// Requests deadline details for tasks with a hard deadline, ensuring that the provided values are valid.
+///
+/// # Returns
+///
+/// * `Result<DeadlineDetails, InputError>` containing the due date and lead days if valid inputs are provided, or None for each if not applicable.
+fn request_deadline_details(
+    io: &mut impl IoPort,
+    max_lead_days: u32,
+) -> Result<DeadlineDetails, InputError> {
+    let (due_date, days_until_deadline) = request_deadline(io)?;
+    // Starting before today would put the task straight into its lead days.
+    // A deadline of today still needs a lead of at least 1
+    let longest_lead = max_lead_days.min(days_until_deadline.max(1));
+
+    let lead_days = loop {
+        let input = read_trimmed_line(
+            io,
+            "\nHow many days before the deadline would you like to start?\n",
+        )?;
+        match input.parse::<u32>() {
+            Ok(num) if num > longest_lead => io.write_line(&format!(
+                "The lead can't be more than {} days for this deadline.",
+                longest_lead
+            )),
+            Ok(num) if num > 0 => break num, // Ensuring positive value
+            _ => io.write_line("Invalid input. Please enter a positive number of days."),
+        }
+    };
+
+    Ok((Some(due_date), Some(lead_days)))
 }
 
-// pub fn display_shop_banner() {
-//     println!(
-//         "
-// ===================
-//   Backlist > Shop
-// ===================
-// \n"
-//     );
-// }
+/// Reads the longest lead the user may give a deadline task from the
+/// settings table.
+pub fn max_lead_days(conn: &Connection) -> u32 {
+    match db::setting_or_default(conn, "max_lead_days") {
+        0 => db::default_setting("max_lead_days"),
+        n => n,
+    }
+}
 
-/// Displays the funds out to 2 decimal places, includes a line of context.
+/// Requests a deadline and converts it to a due date.
 ///
-/// # Arguments
+/// # Returns
 ///
-/// * `funds: f64` - The funds to be displayed.
-pub fn display_funds(funds: f64) {
-    // Only displays funds to 2 decimal places
-    println!("\nYou have ${:.2} remaining", funds);
+/// * `Result<DateTime<Utc>, InputError>` containing the due date.
+fn request_due_date(io: &mut impl IoPort) -> Result<DateTime<Utc>, InputError> {
+    Ok(request_deadline(io)?.0)
 }
 
-/// Prompts the user to input a transaction amount. Calls `db::add_transaction()`
-/// if a vaild input is found.
+/// Asks for a deadline as either a number of days or a date, until the input
+/// is valid. See `parse_due_input()`.
+///
+/// # Returns
+///
+/// * `Result<(DateTime<Utc>, u32), InputError>` containing the due date and
+///   how many days away it is.
+fn request_deadline(io: &mut impl IoPort) -> Result<(DateTime<Utc>, u32), InputError> {
+    loop {
+        let input = read_trimmed_line(
+            io,
+            "\nWhen is the deadline? Enter a number of days, or a date as YYYY-MM-DD.\n",
+        )?;
+        let now = Utc::now();
+        match parse_due_input(&input, now) {
+            Ok(due_date) => {
+                let days = (due_date.date_naive() - now.date_naive()).num_days();
+                return Ok((due_date, days.max(0) as u32));
+            }
+            Err(DueInputError::InPast) => io.write_line("That date has already passed."),
+            Err(DueInputError::Invalid) => io.write_line(
+                "Invalid input. Please enter a non-negative number of days or a YYYY-MM-DD date.",
+            ),
+        }
+    }
+}
+
+/// Why `parse_due_input()` rejected a deadline.
+#[derive(Debug, PartialEq)]
+pub enum DueInputError {
+    /// Neither a number of days nor a YYYY-MM-DD date.
+    Invalid,
+    /// A date before today.
+    InPast,
+}
+
+/// The start of the current day in UTC.
+///
+/// New tasks count from here rather than the moment they're added, so a task
+/// added late in the day doesn't lag behind one added early.
+pub fn midnight_today_utc() -> DateTime<Utc> {
+    midnight_utc(Utc::now())
+}
+
+/// The start of the UTC day that `now` falls on.
+fn midnight_utc(now: DateTime<Utc>) -> DateTime<Utc> {
+    now.date_naive().and_time(NaiveTime::MIN).and_utc()
+}
+
+/// Reads a deadline written as a number of days from the start of today, or
+/// as an ISO date.
 ///
 /// # Arguments
 ///
-/// * `conn: &Connection` - A conncetion to the db. `db::add_transaction()` requires
-/// it, so it's required here too.
-pub fn request_transaction(conn: &Connection) {
-    println!("\nHow much would you like to spend?");
+/// * `input: &str` - The user's input, already trimmed.
+/// * `now: DateTime<Utc>` - Decides which day is today.
+///
+/// # Returns
+///
+/// The due date, at midnight UTC at the start of its day. Today is allowed,
+/// but earlier dates are not.
+pub fn parse_due_input(input: &str, now: DateTime<Utc>) -> Result<DateTime<Utc>, DueInputError> {
+    if let Ok(days) = input.parse::<u32>() {
+        return Ok(midnight_utc(now) + Duration::days(i64::from(days)));
+    }
 
-    let mut input = String::new();
+    let date = NaiveDate::parse_from_str(input, "%Y-%m-%d").map_err(|_| DueInputError::Invalid)?;
+    if date < now.date_naive() {
+        return Err(DueInputError::InPast);
+    }
 
-    io::stdin()
-        .read_line(&mut input)
-        .expect("Failed to read line");
+    Ok(date.and_time(NaiveTime::MIN).and_utc())
+}
 
-    let selection: Option<f64> = match input.trim().parse() {
-        Ok(num) => Some(num),
-        Err(_) => None,
-    };
+/// Asks the user whether to start from one of their saved templates.
+///
+/// # Arguments
+///
+/// * `io: &mut impl IoPort` - Where prompts are written and input read.
+/// * `templates: &[TaskTemplate]` - The templates to choose from.
+///
+/// # Returns
+///
+/// * `Result<Option<TaskTemplate>, InputError>` containing the chosen
+///   template, or None if the user wants to start from scratch.
+pub fn request_template_choice(
+    io: &mut impl IoPort,
+    templates: &[TaskTemplate],
+) -> Result<Option<TaskTemplate>, InputError> {
+    loop {
+        io.write_line("(Type 'q' at any prompt to go back, or '\\q' to type it as text)\n");
+        io.write_line("0. Start from scratch");
+        for (index, template) in templates.iter().enumerate() {
+            io.write_line(&format!("{}. {}", index + 1, template.name));
+        }
 
-    match selection {
-        Some(num) => {
-            if num != 0.0 {
-                db::add_transaction(conn, num * -1.0)
-            }
+        let input = read_trimmed_line(io, "\nSelect a template.\n")?;
+        match input.parse::<usize>() {
+            Ok(0) => return Ok(None),
+            Ok(n) if n <= templates.len() => return Ok(Some(templates[n - 1].clone())),
+            _ => io.write_line("Invalid input!"),
         }
-        None => (),
+    }
+}
+
+/// Requests the details that differ between tasks made from the same
+/// template.
+///
+/// # Arguments
+///
+/// * `io: &mut impl IoPort` - Where prompts are written and input read.
+/// * `conn: &Connection` - Used to read the maximum summary length.
+/// * `template: &TaskTemplate` - The template being used.
+///
+/// # Returns
+///
+/// * `Result<(String, Option<DateTime<Utc>>), InputError>` containing the
+///   summary, and the due date if the template is for deadline tasks.
+pub fn request_template_details(
+    io: &mut impl IoPort,
+    conn: &Connection,
+    template: &TaskTemplate,
+) -> Result<(String, Option<DateTime<Utc>>), InputError> {
+    let summary = request_task_summary(io, max_summary_length(conn))?;
+    let due_date = match template.lead_days {
+        Some(_) => Some(request_due_date(io)?),
+        None => None,
+    };
+
+    Ok((summary, due_date))
+}
+
+/// Requests a name for a new template.
+pub fn request_template_name(io: &mut impl IoPort) -> Result<String, InputError> {
+    loop {
+        let name = read_text_line(io, "\nEnter a name for the template\n")?;
+
+        if !name.is_empty() {
+            return Ok(name);
+        } else {
+            io.write_line("The template's name cannot be empty!")
+        }
+    }
+}
+
+/// Constructs a `Task` object based on user input. Prompts the user for various task details,
+/// including summary, description, priority, and type. Depending on the task type, additional
+/// information such as recurrence interval or deadline details may also be requested.
+///
+/// # Arguments
+///
+/// * `io: &mut impl IoPort` - Where prompts are written and input read.
+/// * `conn: &Connection` - A conncetion to the db. `db::read_all_folders_sorted()` requires
+///   it, so it's required here too.
+///
+/// # Returns
+///
+/// * `Result<Task, InputError>` which is Ok containing the constructed Task object if all inputs are successfully gathered and parsed, or an Err otherwise.
+pub fn request_task_input(io: &mut impl IoPort, conn: &Connection) -> Result<Task, InputError> {
+    io.write_line("(Type 'q' at any prompt to go back, or '\\q' to type it as text)\n");
+    let patent_id = request_parent_id(io, conn)?;
+    let summary = request_task_summary(io, max_summary_length(conn))?;
+    let description = request_optional_description(io)?;
+    let average_duration = request_optional_estimate(io)?;
+    let priority = request_priority(io)?;
+
+    let task = Task {
+        description,
+        average_duration,
+        priority,
+        from_date: midnight_today_utc(),
+        ..Task::new(patent_id, summary)
+    };
+
+    request_task_type_details(io, task, max_lead_days(conn))
+}
+
+/// Asks for the type of `task`, along with the details that type needs, and
+/// fills them in.
+///
+/// # Returns
+///
+/// * `Result<Task, InputError>` containing `task` with its repeat_interval, recurrence_anchor, due_date and lead_days set to match the chosen type.
+fn request_task_type_details(
+    io: &mut impl IoPort,
+    task: Task,
+    max_lead_days: u32,
+) -> Result<Task, InputError> {
+    let mut task = Task {
+        repeat_interval: None,
+        repeat_unit: RepeatUnit::Days,
+        recurrence_anchor: RecurrenceAnchor::Completion,
+        due_date: None,
+        lead_days: None,
+        ..task
+    };
+
+    match request_task_type(io)? {
+        2 => {
+            (
+                task.repeat_interval,
+                task.repeat_unit,
+                task.recurrence_anchor,
+            ) = request_recurring_details(io)?
+        }
+        3 => (task.due_date, task.lead_days) = request_deadline_details(io, max_lead_days)?,
+        _ => {}
+    }
+
+    Ok(task)
+}
+
+/// Walks the user through filing a quick captured task: its real folder,
+/// priority and type.
+///
+/// # Arguments
+///
+/// * `io: &mut impl IoPort` - Where prompts are written and input read.
+/// * `conn: &Connection` - Needed to list the folders.
+/// * `task: &Task` - The task being triaged.
+///
+/// # Returns
+///
+/// * `Result<Task, InputError>` containing a copy of `task` with the new details.
+pub fn request_triage_details(
+    io: &mut impl IoPort,
+    conn: &Connection,
+    task: &Task,
+) -> Result<Task, InputError> {
+    io.write_line(&format!("\nTriaging: {}\n", task.summary));
+
+    let parent_id = request_parent_id(io, conn)?;
+    let priority = request_priority(io)?;
+
+    request_task_type_details(
+        io,
+        Task {
+            parent_id,
+            priority,
+            ..task.clone()
+        },
+        max_lead_days(conn),
+    )
+}
+
+/// Requests and returns the folder name from the user.
+///
+/// # Returns
+///
+/// * `Result<String, InputError>` containing the folder name if successfully read, or an Err otherwise.
+pub fn request_folder_name(io: &mut impl IoPort) -> Result<String, InputError> {
+    loop {
+        let name = read_text_line(io, "\nEnter folder name\n")?;
+
+        if !name.is_empty() {
+            return Ok(name);
+        } else {
+            io.write_line("The folder's name cannot be empty!")
+        }
+    }
+}
+
+/// Requests the style of the folder from the user and converts it to a `Style` enum.
+///
+/// # Returns
+///
+/// * `Result<Style, InputError>` which is Ok containing the style if successfully parsed, or an Err otherwise.
+fn request_style(io: &mut impl IoPort) -> Result<Style, InputError> {
+    loop {
+        let input = read_trimmed_line(
+            io,
+            "\nEnter folder type\n1. Directory\n2. Selector\n3. Iterator\n",
+        )?;
+        match input.parse::<usize>() {
+            Ok(1) => return Ok(Style::Directory),
+            Ok(2) => return Ok(Style::Selector),
+            Ok(3) => return Ok(Style::Iterator),
+            Ok(_) | Err(_) => io.write_line("Invalid input!"),
+        }
+    }
+}
+
+/// Constructs a `Folder` object based on user input. Prompts the user for various folder details,
+/// including TODO.
+///
+/// # Returns
+///
+/// * `Result<Folder, InputError>` which is Ok containing the constructed Folder object if all inputs are successfully gathered and parsed, or an Err otherwise.
+pub fn request_folder_input(io: &mut impl IoPort, conn: &Connection) -> Result<Folder, InputError> {
+    io.write_line("(Type 'q' at any prompt to go back, or '\\q' to type it as text)\n");
+    let parent_id = request_parent_id(io, conn)?;
+    let name = request_folder_name(io)?;
+    let style = request_style(io)?;
+
+    Ok(Folder {
+        id: 0,                      // Assuming these values are still hardcoded or otherwise set
+        parent_id: Some(parent_id), // TODO: Allow top-level folders to be added
+        name,
+        style,
+        status: None,
+    })
+}
+
+// pub fn select_task() -> Option<Action> {
+//     println!("\nSelect a task to complete, or select 0 to edit tasks\n");
+
+//     let mut input = String::new();
+
+//     io::stdin()
+//         .read_line(&mut input)
+//         .expect("Failed to read line");
+
+//     let selection: Option<usize> = match input.trim().parse() {
+//         Ok(num) => Some(num),
+//         Err(_) => None,
+//     };
+
+//     match selection {
+//         Some(num) => {
+//             if num == 0 {
+//                 Some(Action::EditMode)
+//             } else if num <= 5 {
+//                 Some(Action::SelectTask(num))
+//             } else {
+//                 None
+//             }
+//         }
+//         None => Some(Action::ReturnToStart),
+//     }
+// }
+
+pub fn display_task(task: &Task) {
+    println!(
+        "
+============================
+  Backlist > Task Selected
+============================
+
+You have selected:
+
+{}",
+        task.summary
+    );
+
+    if task.description.is_some() {
+        println!("    {}", task.description.clone().unwrap());
+    }
+
+    // Matches FORECAST_LENGTH in lib.rs
+    const PROJECTED_DATES: usize = 5;
+    let upcoming: Vec<String> = task
+        .projected_dates(PROJECTED_DATES)
+        .iter()
+        .map(|date| date.with_timezone(&Local).format("%a %b %d").to_string())
+        .collect();
+    if !upcoming.is_empty() {
+        println!("\nComes around again: {}", upcoming.join(", "));
+    }
+
+    if let Some(average_duration) = task.average_duration {
+        println!("\nUsually takes {}", format_duration(average_duration));
+    }
+
+    println!("\n\n(Debug) ID: {}\n", task.id);
+}
+
+// pub fn display_shop_banner() {
+//     println!(
+//         "
+// ===================
+//   Backlist > Shop
+// ===================
+// \n"
+//     );
+// }
+
+/// Displays the funds, includes a line of context.
+///
+/// # Arguments
+///
+/// * `funds: f64` - The funds to be displayed.
+/// * `format: &MoneyFormat` - Controls the separators and decimal places.
+pub fn display_funds(funds: f64, format: &MoneyFormat) {
+    println!("\nYou have {} remaining", format_money(funds, format));
+}
+
+/// Renders how a bounty was calculated, one factor per line.
+///
+/// # Arguments
+///
+/// * `breakdown: &BountyBreakdown` - The factors that make up the bounty.
+/// * `format: &MoneyFormat` - Controls how the amounts are formatted.
+pub fn render_bounty_breakdown(breakdown: &BountyBreakdown, format: &MoneyFormat) -> String {
+    let mut rendered = format!(
+        "Why this bounty?\n  {} base value\n  × {:.2} for how often it's been picked\n  × {:.2} bounty modifier",
+        format_money(breakdown.base, format),
+        breakdown.selection_factor,
+        breakdown.modifier,
+    );
+
+    let (unclamped, total) = (breakdown.unclamped(), breakdown.total());
+    if total > unclamped {
+        rendered.push_str(&format!(
+            "\n  = {}, raised to this priority's floor",
+            format_money(unclamped, format)
+        ));
+    } else if total < unclamped {
+        rendered.push_str(&format!(
+            "\n  = {}, capped at this priority's ceiling",
+            format_money(unclamped, format)
+        ));
+    }
+
+    rendered.push_str(&format!("\n  = {}", format_money(total, format)));
+    rendered
+}
+
+/// Renders a task on a single line, for status bars and notifications, e.g.
+/// `[P2] Work::Clients — Review the draft (due in 2d) $1.50`.
+///
+/// # Arguments
+///
+/// * `task: &Task` - The task to render.
+/// * `folder_path: &str` - The path of the task's folder. Left out if empty.
+/// * `bounty: Option<f64>` - Left out if None, e.g. when finance is disabled.
+/// * `now: DateTime<Utc>` - What the due date is counted from.
+///
+/// # Notes
+///
+/// Other tools may parse this, so it never contains colour codes and always
+/// uses the default money format. The days until the due date are rounded to
+/// the nearest day.
+pub fn render_task_line(
+    task: &Task,
+    folder_path: &str,
+    bounty: Option<f64>,
+    now: DateTime<Utc>,
+) -> String {
+    // Spelled out rather than taken from Debug, so the line stays the same
+    // for tools parsing it even if the variants are renamed
+    let label = match task.priority {
+        Priority::Neutral => "Neutral",
+        Priority::P0 => "P0",
+        Priority::P1 => "P1",
+        Priority::P2 => "P2",
+        Priority::P3 => "P3",
+    };
+    let mut line = format!("[{}] ", label);
+
+    if !folder_path.is_empty() {
+        line.push_str(&format!("{} — ", folder_path));
+    }
+    line.push_str(&task.summary);
+
+    if let Some(due_date) = task.due_date {
+        let days = ((due_date - now).num_hours() + 12).div_euclid(24);
+        line.push_str(&match days {
+            0 => " (due today)".to_string(),
+            d if d > 0 => format!(" (due in {}d)", d),
+            d => format!(" (overdue by {}d)", -d),
+        });
+    }
+
+    if let Some(bounty) = bounty {
+        line.push_str(&format!(
+            " {}",
+            format_money(bounty, &MoneyFormat::default())
+        ));
+    }
+
+    line
+}
+
+/// Renders a series of amounts as a row of bars, scaled between the smallest
+/// and largest. A flat series is drawn at mid height.
+pub fn render_trend(values: &[f64]) -> String {
+    const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+    let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+
+    values
+        .iter()
+        .map(|value| {
+            if (max - min).abs() < f64::EPSILON {
+                BARS[BARS.len() / 2]
+            } else {
+                BARS[(((value - min) / (max - min)) * 7.0).round() as usize]
+            }
+        })
+        .collect()
+}
+
+/// Shows the bounty paid each time a task was completed, with a trend line.
+///
+/// # Arguments
+///
+/// * `io: &mut impl IoPort` - Where the history is written.
+/// * `task: &Task` - The task the payouts belong to.
+/// * `payouts: &[db::Payout]` - Each payout, oldest first.
+/// * `format: &MoneyFormat` - Controls how the amounts are formatted.
+pub fn display_task_history(
+    io: &mut impl IoPort,
+    task: &Task,
+    payouts: &[db::Payout],
+    format: &MoneyFormat,
+) {
+    io.write_line(&format!("\n{}", task.summary));
+
+    let amounts: Vec<f64> = payouts.iter().map(|(_, amount)| *amount).collect();
+    if let (Some(first), Some(last)) = (amounts.first(), amounts.last()) {
+        io.write_line(&format!(
+            "  {}  {} → {}",
+            render_trend(&amounts),
+            format_money(*first, format),
+            format_money(*last, format)
+        ));
+    }
+
+    for (date, amount) in payouts {
+        io.write_line(&format!(
+            "  {}  {}",
+            date.with_timezone(&Local).format("%b %d %Y"),
+            format_money(*amount, format)
+        ));
+    }
+}
+
+/// Lists what was completed recently, oldest first.
+///
+/// # Arguments
+///
+/// * `io: &mut impl IoPort` - Where the list is written.
+/// * `completions: &[(Task, DateTime<Utc>)]` - See
+///   `db::read_completions_between()`.
+/// * `days: u32` - How far back the list goes, for the heading.
+pub fn display_completions(io: &mut impl IoPort, completions: &[(Task, DateTime<Utc>)], days: u32) {
+    if completions.is_empty() {
+        io.write_line(&format!("\nNothing was completed in the last {days} days."));
+        return;
+    }
+
+    io.write_line(&format!("\nCompleted in the last {days} days:"));
+    for (task, completed_at) in completions {
+        io.write_line(&format!(
+            "{}  {}",
+            completed_at.with_timezone(&Local).format("%a %b %d"),
+            truncate_to(&task.summary, DEFAULT_MAX_SUMMARY_LENGTH)
+        ));
+    }
+}
+
+/// Shows the transaction history newest first, a page at a time, with the
+/// balance after each transaction.
+///
+/// # Arguments
+///
+/// * `io: &mut impl IoPort` - Where the history is written and input read.
+/// * `entries: &[LedgerEntry]` - The history in date order. See
+///   `finance::ledger()`.
+/// * `format: &MoneyFormat` - How amounts are formatted.
+pub fn display_transaction_log(
+    io: &mut impl IoPort,
+    entries: &[LedgerEntry],
+    format: &MoneyFormat,
+) -> Result<(), InputError> {
+    const PAGE_LENGTH: usize = 15;
+
+    if entries.is_empty() {
+        io.write_line("\nThere are no transactions yet.");
+        return Ok(());
+    }
+
+    let newest_first: Vec<&LedgerEntry> = entries.iter().rev().collect();
+    for (page, rows) in newest_first.chunks(PAGE_LENGTH).enumerate() {
+        if page > 0 {
+            let input = read_trimmed_line(io, "\nm. Show more\n")?;
+            if !input.eq_ignore_ascii_case("m") {
+                return Ok(());
+            }
+        }
+
+        io.write_line(&format!(
+            "\n{:<12}{:>14}{:>14}",
+            "Date", "Amount", "Balance"
+        ));
+        for entry in rows {
+            let sign = if entry.amount >= 0.0 { "+" } else { "" };
+            io.write_line(&format!(
+                "{:<12}{:>14}{:>14}",
+                entry.date.with_timezone(&Local).format("%Y-%m-%d"),
+                format!("{}{}", sign, format_money(entry.amount, format)),
+                format_money(entry.balance, format)
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// What completing a queue of tasks added up to.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct QueueSummary {
+    pub tasks_completed: u32,
+    pub funds_earned: f64,
+}
+
+/// Tells the user how many tasks they completed and, if finance is enabled,
+/// what they earned in total.
+pub fn display_queue_summary(
+    io: &mut impl IoPort,
+    summary: &QueueSummary,
+    show_funds: bool,
+    format: &MoneyFormat,
+) {
+    let tasks = match summary.tasks_completed {
+        1 => "1 task".to_string(),
+        n => format!("{n} tasks"),
+    };
+
+    if show_funds {
+        io.write_line(&format!(
+            "\nYou completed {} and earned {}.",
+            tasks,
+            format_money(summary.funds_earned, format)
+        ));
+    } else {
+        io.write_line(&format!("\nYou completed {}.", tasks));
+    }
+}
+
+/// The running totals shown in Stats.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StatsSummary {
+    pub total_tasks_completed: u32,
+    pub average_completion: Option<Duration>,
+    /// Only read while finance is enabled.
+    pub funds: Option<FundsSummary>,
+}
+
+/// Where the user's funds stand, for `StatsSummary`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FundsSummary {
+    pub remaining: f64,
+    pub earned_this_month: f64,
+    pub unlocked: f64,
+    pub loaded: f64,
+    /// None until a baseline has been worked out.
+    pub baseline_bounty: Option<f64>,
+}
+
+/// Lists the running totals kept in the statistics table alongside the
+/// figures worked out from them.
+pub fn display_stats(io: &mut impl IoPort, stats: &StatsSummary, format: &MoneyFormat) {
+    io.write_line(&format!(
+        "\nTasks completed: {}",
+        stats.total_tasks_completed
+    ));
+    io.write_line(&format!(
+        "Average time per task: {}",
+        stats
+            .average_completion
+            .map_or("not tracked yet".to_string(), format_duration)
+    ));
+
+    if let Some(funds) = &stats.funds {
+        io.write_line(&format!(
+            "\nFunds remaining: {}",
+            format_money(funds.remaining, format)
+        ));
+        io.write_line(&format!(
+            "Earned this month: {}",
+            format_money(funds.earned_this_month, format)
+        ));
+        io.write_line(&format!(
+            "Funds unlocked: {}",
+            format_money(funds.unlocked, format)
+        ));
+        io.write_line(&format!(
+            "Funds loaded: {}",
+            format_money(funds.loaded, format)
+        ));
+        io.write_line(&format!(
+            "Baseline bounty: {}",
+            funds
+                .baseline_bounty
+                .map_or("not worked out yet".to_string(), |bounty| format_money(
+                    bounty, format
+                ))
+        ));
+    }
+}
+
+/// Sums up what an import would change, so it can be checked before
+/// anything is written.
+pub fn display_import_plan(io: &mut impl IoPort, plan: &db::ImportPlan) {
+    io.write_line(&format!(
+        "\nThe file has {} setting(s) and {} statistic(s).",
+        plan.settings.len(),
+        plan.statistics.len()
+    ));
+    io.write_line(&format!("  New: {}", plan.new_keys));
+    io.write_line(&format!("  Changed: {}", plan.changed));
+    if plan.mode == db::ImportMode::Overwrite {
+        io.write_line(&format!("  Removed or reset to default: {}", plan.removed));
+    }
+    if plan.skipped > 0 {
+        io.write_line(&format!("  Blank lines skipped: {}", plan.skipped));
+    }
+}
+
+/// Sums up what importing a backup would add, so it can be checked before
+/// anything is written.
+pub fn display_backup_plan(io: &mut impl IoPort, summary: &ImportSummary) {
+    io.write_line(&format!(
+        "\nThe backup would add {} folder(s) and {} task(s).",
+        summary.folders_added, summary.tasks_added
+    ));
+    io.write_line(&format!(
+        "  Already there, so skipped: {} folder(s) and {} task(s)",
+        summary.folders_skipped, summary.tasks_skipped
+    ));
+    io.write_line(&format!("  Given a new id: {}", summary.ids_remapped));
+}
+
+/// Tells the user there's nothing to do right now and what's coming up.
+///
+/// # Arguments
+///
+/// * `io: &mut impl IoPort` - Where the forecast is written.
+/// * `upcoming: &[(Task, DateTime<Utc>)]` - Tasks with the date each becomes
+///   eligible, soonest first. See `forecast()` in lib.rs.
+pub fn display_forecast(io: &mut impl IoPort, upcoming: &[(Task, DateTime<Utc>)]) {
+    if upcoming.is_empty() {
+        io.write_line("\nThere's nothing here to do. Add a task to get started.");
+        return;
+    }
+
+    io.write_line("\nNothing to do right now. Coming up:");
+    for (task, date) in upcoming {
+        io.write_line(&format!(
+            "  {}  {}",
+            date.with_timezone(&Local).format("%a %b %d"),
+            task.summary
+        ));
+    }
+}
+
+/// Prints the results of a simulated month.
+pub fn display_simulation(report: &SimulationReport, format: &MoneyFormat) {
+    println!(
+        "\nOver {} simulated days you would complete {} task(s) and earn {}.",
+        report.days,
+        report.tasks_completed,
+        format_money(report.funds_earned, format)
+    );
+
+    if let Some((summary, count)) = &report.most_completed {
+        println!("Most completed: {} ({} times)", summary, count);
+    }
+}
+
+/// Prints the output of `render_bounty_breakdown()`.
+pub fn display_bounty_breakdown(breakdown: &BountyBreakdown, format: &MoneyFormat) {
+    println!("{}\n", render_bounty_breakdown(breakdown, format));
+}
+
+/// Renders a per-day history as a single row of shaded characters, with the
+/// busiest day drawn darkest.
+///
+/// # Arguments
+///
+/// * `history: &[(NaiveDate, u32)]` - Completions per day, oldest first.
+pub fn render_heatmap(history: &[(NaiveDate, u32)]) -> String {
+    const SHADES: [char; 5] = ['·', '░', '▒', '▓', '█'];
+
+    let max = history.iter().map(|(_, count)| *count).max().unwrap_or(0);
+
+    history
+        .iter()
+        .map(|(_, count)| match count {
+            0 => SHADES[0],
+            // Scale the remaining shades between 1 and the busiest day
+            n => SHADES[((n * 4).div_ceil(max)) as usize],
+        })
+        .collect()
+}
+
+/// Displays the user's completion streak above a heatmap of recent activity.
+///
+/// # Arguments
+///
+/// * `streak: u32` - Consecutive days with at least one completion.
+/// * `history: &[(NaiveDate, u32)]` - Completions per day, oldest first.
+pub fn display_streak(streak: u32, history: &[(NaiveDate, u32)]) {
+    let plural = if streak == 1 { "" } else { "s" };
+    println!("\nCurrent streak: {} day{}", streak, plural);
+
+    if let (Some((first, _)), Some((last, _))) = (history.first(), history.last()) {
+        println!("\n{}", render_heatmap(history));
+        println!("{} to {}", first.format("%b %d"), last.format("%b %d"));
+    }
+}
+
+/// Reads a duration written as hours, minutes and seconds, e.g. "1h30m",
+/// "45m" or "1h 5s". A number on its own is taken as minutes.
+///
+/// # Returns
+///
+/// None if anything but h, m or s follows a number, or there's no number.
+pub fn parse_duration(input: &str) -> Option<Duration> {
+    let input = input.trim().to_lowercase();
+    if let Ok(minutes) = input.parse::<i64>() {
+        return (minutes >= 0).then(|| Duration::minutes(minutes));
+    }
+
+    let mut total = Duration::zero();
+    let mut number = String::new();
+    let mut found_unit = false;
+    for c in input.chars() {
+        match c {
+            '0'..='9' => number.push(c),
+            'h' | 'm' | 's' => {
+                let amount: i64 = number.parse().ok()?;
+                total = total
+                    + match c {
+                        'h' => Duration::hours(amount),
+                        'm' => Duration::minutes(amount),
+                        _ => Duration::seconds(amount),
+                    };
+                number.clear();
+                found_unit = true;
+            }
+            ' ' => {}
+            _ => return None,
+        }
+    }
+
+    // A trailing number without a unit is ambiguous, e.g. "1h30"
+    (found_unit && number.is_empty()).then_some(total)
+}
+
+/// Writes a duration the way `parse_duration()` reads it, e.g. "1h30m".
+/// Seconds are only shown for durations under a minute.
+pub fn format_duration(duration: Duration) -> String {
+    let total = duration.num_seconds().max(0);
+    let (hours, minutes) = (total / 3600, total % 3600 / 60);
+
+    match (hours, minutes) {
+        (0, 0) => format!("{}s", total),
+        (0, _) => format!("{}m", minutes),
+        (_, 0) => format!("{}h", hours),
+        _ => format!("{}h{}m", hours, minutes),
+    }
+}
+
+/// Parses a transaction amount. A leading "+" adds funds and a leading "-"
+/// spends them.
+///
+/// # Arguments
+///
+/// * `input: &str` - The user's input, already trimmed.
+/// * `require_sign: bool` - Set true to reject amounts without a sign.
+///   Otherwise, a bare amount is treated as spending.
+///
+/// # Returns
+///
+/// The signed change to the user's funds, or None if the input is invalid.
+fn parse_transaction(input: &str, require_sign: bool) -> Option<f64> {
+    let (sign, amount) = match input.chars().next() {
+        Some('+') => (1.0, &input[1..]),
+        Some('-') => (-1.0, &input[1..]),
+        _ if require_sign => return None,
+        _ => (-1.0, input),
+    };
+
+    // Don't let a second sign sneak through, e.g. "+-50"
+    if amount.starts_with(['+', '-']) {
+        return None;
+    }
+
+    match amount.trim().parse::<f64>() {
+        Ok(num) if num.is_finite() && num >= 0.0 => Some(sign * num),
+        _ => None,
+    }
+}
+
+/// Prompts the user to input a transaction amount. Calls `db::add_transaction()`
+/// if a vaild input is found.
+///
+/// # Arguments
+///
+/// * `io: &mut impl IoPort` - Where prompts are written and input read.
+/// * `conn: &Connection` - A conncetion to the db. `db::add_transaction()` requires
+///   it, so it's required here too.
+/// * `require_sign: bool` - Set true to only accept amounts with an explicit
+///   "+" or "-".
+///
+/// # Notes
+///
+/// Entering nothing leaves the user's funds untouched.
+pub fn request_transaction(
+    io: &mut impl IoPort,
+    conn: &Connection,
+    require_sign: bool,
+) -> Result<(), InputError> {
+    let prompt = if require_sign {
+        "\nEnter +amount to add funds or -amount to spend (or hit <ENTER> to skip)."
+    } else {
+        "\nHow much would you like to spend? (Use +amount to add funds instead)"
+    };
+
+    loop {
+        let input = read_trimmed_line(io, prompt)?;
+        if input.is_empty() {
+            return Ok(());
+        }
+
+        match parse_transaction(&input, require_sign) {
+            Some(num) => {
+                if num != 0.0 {
+                    if let Err(e) = db::add_transaction(conn, num) {
+                        eprintln!("Problem saving transaction: {}", e);
+                    }
+                }
+                return Ok(());
+            }
+            None if require_sign && !input.starts_with(['+', '-']) => {
+                io.write_line("Please start the amount with + to add funds or - to spend.")
+            }
+            None => io.write_line("Invalid input!"),
+        }
+    }
+}
+
+/// What the user wants to do in the Shop.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ShopAction {
+    /// Spend or add funds.
+    Transaction,
+    /// Save the transaction history to a file.
+    Export,
+}
+
+/// Asks the user what they'd like to do in the Shop.
+pub fn request_shop_action(io: &mut impl IoPort) -> Result<ShopAction, InputError> {
+    loop {
+        let input = read_trimmed_line(
+            io,
+            "\nWhat would you like to do?\n1. Spend or add funds\n2. Save your transactions as CSV\n",
+        )?;
+        match input.parse::<u32>() {
+            Ok(1) => return Ok(ShopAction::Transaction),
+            Ok(2) => return Ok(ShopAction::Export),
+            _ => io.write_line("Invalid input!"),
+        }
+    }
+}
+
+/// Lists every setting with its value and asks which to change.
+///
+/// # Arguments
+///
+/// * `io: &mut impl IoPort` - Where prompts are written and input read.
+/// * `settings: &[(&str, u32)]` - Each setting's key and current value.
+///
+/// # Returns
+///
+/// The index of the chosen setting in `settings`.
+pub fn request_setting(
+    io: &mut impl IoPort,
+    settings: &[(&str, u32)],
+) -> Result<usize, InputError> {
+    io.write_line("");
+    for (i, (key, value)) in settings.iter().enumerate() {
+        io.write_line(&format!("{}. {}: {}", i + 1, key, value));
+    }
+
+    loop {
+        let input = read_trimmed_line(io, "\nSelect a setting to change.\n")?;
+        match input.parse::<usize>() {
+            Ok(n) if (1..=settings.len()).contains(&n) => return Ok(n - 1),
+            _ => io.write_line("Invalid input!"),
+        }
+    }
+}
+
+/// Requests a new value for a setting, asking again until it's a whole
+/// number.
+pub fn request_setting_value(io: &mut impl IoPort, key: &str) -> Result<u32, InputError> {
+    loop {
+        let input = read_trimmed_line(io, &format!("\nEnter the new value for {key}\n"))?;
+        match input.parse::<u32>() {
+            Ok(value) => return Ok(value),
+            Err(_) => io.write_line("The value must be a whole number of 0 or more!"),
+        }
+    }
+}
+
+/// What the user wants to do to the folder they picked.
+#[derive(Clone, Debug, PartialEq)]
+pub enum FolderAction {
+    Rename,
+    Delete(db::DeleteMode),
+}
+
+/// Asks the user what they'd like to do to a folder.
+pub fn request_folder_action(io: &mut impl IoPort) -> Result<FolderAction, InputError> {
+    loop {
+        let input = read_trimmed_line(
+            io,
+            "\nWhat would you like to do?\n1. Rename it\n2. Delete it, moving its tasks and subfolders up a level\n3. Delete it and its subfolders, archiving every task in them\n",
+        )?;
+        match input.parse::<u32>() {
+            Ok(1) => return Ok(FolderAction::Rename),
+            Ok(2) => return Ok(FolderAction::Delete(db::DeleteMode::Reparent)),
+            Ok(3) => return Ok(FolderAction::Delete(db::DeleteMode::Cascade)),
+            _ => io.write_line("Invalid input!"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::finance::BountyLimits;
+    use chrono::{TimeZone, Timelike};
+
+    #[test]
+    fn test_paint_priority() {
+        assert_eq!(
+            paint_priority(&Priority::P3, "Taxes"),
+            "\x1b[31mTaxes\x1b[0m"
+        );
+        assert_eq!(
+            paint_priority(&Priority::P2, "Taxes"),
+            "\x1b[33mTaxes\x1b[0m"
+        );
+        for priority in [Priority::P1, Priority::P0, Priority::Neutral] {
+            assert_eq!(paint_priority(&priority, "Taxes"), "Taxes");
+        }
+    }
+
+    #[test]
+    fn test_select_task_marks_overdue_tasks() {
+        let overdue = Task {
+            due_date: Some(Utc::now() - Duration::days(1)),
+            priority: Priority::P3,
+            ..Task::new(1, "Taxes".into())
+        };
+        let tasks = [(overdue, 1.0), (Task::new(1, "Dishes".into()), 1.0)];
+        let options = ListOptions {
+            preview_length: 60,
+            summary_length: DEFAULT_MAX_SUMMARY_LENGTH,
+            per_view: 5,
+            show_bounties: false,
+            money: MoneyFormat::default(),
+            show_urgency: false,
+        };
+
+        let mut io = MockIo::new(&["1\n"]);
+        select_task(&mut io, &tasks, &[], false, &options).unwrap();
+        let output = io.output.borrow();
+        assert!(output.contains(&"1. Taxes (overdue)".to_string()));
+        assert!(output.contains(&"2. Dishes".to_string()));
+    }
+
+    #[test]
+    fn test_request_setting_value_reprompts() {
+        let mut io = MockIo::new(&["lots\n", "-5\n", " 450 \n"]);
+        assert_eq!(
+            request_setting_value(&mut io, "target_monthly_allowance").unwrap(),
+            450
+        );
+        assert_eq!(
+            io.output
+                .borrow()
+                .iter()
+                .filter(|line| line.contains("whole number"))
+                .count(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_display_stats() {
+        let mut stats = StatsSummary {
+            total_tasks_completed: 3,
+            average_completion: Some(Duration::minutes(10)),
+            funds: None,
+        };
+        let mut io = MockIo::new(&[]);
+        display_stats(&mut io, &stats, &MoneyFormat::default());
+        assert_eq!(
+            *io.output.borrow(),
+            ["\nTasks completed: 3", "Average time per task: 10m"]
+        );
+
+        stats.funds = Some(FundsSummary {
+            remaining: 12.5,
+            loaded: 400.0,
+            ..FundsSummary::default()
+        });
+        let mut io = MockIo::new(&[]);
+        display_stats(&mut io, &stats, &MoneyFormat::default());
+        let output = io.output.borrow();
+        assert!(output.contains(&"\nFunds remaining: $12.50".to_string()));
+        assert!(output.contains(&"Baseline bounty: not worked out yet".to_string()));
+    }
+
+    #[test]
+    fn test_request_priority_reprompts() {
+        let mut io = MockIo::new(&["5\n", "2\n"]);
+
+        assert_eq!(request_priority(&mut io).unwrap(), Priority::P2);
+        let invalid = io
+            .output
+            .borrow()
+            .iter()
+            .filter(|line| *line == "Invalid input!")
+            .count();
+        assert_eq!(invalid, 1);
+    }
+
+    #[test]
+    fn test_request_task_type() {
+        let mut io = MockIo::new(&["0\n", "recurring\n", "3\n", "q\n"]);
+
+        assert_eq!(request_task_type(&mut io).unwrap(), 3);
+        assert!(matches!(
+            request_task_type(&mut io),
+            Err(InputError::Cancelled)
+        ));
+    }
+
+    #[test]
+    fn test_request_snooze_outcome() {
+        let mut io = MockIo::new(&["6\n", "0\n", "100000000\n", "366\n", "3\n"]);
+
+        assert_eq!(
+            request_task_outcome(&mut io).unwrap(),
+            TaskOutcome::Snooze(3)
+        );
+        assert_eq!(
+            io.output
+                .borrow()
+                .iter()
+                .filter(|line| line.contains("at most 365 days"))
+                .count(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_select_app_state() {
+        let states = [AppState::ToDo, AppState::Shop];
+        let mut io = MockIo::new(&["3\n", "shop\n", " 2 \n"]);
+
+        assert_eq!(select_app_state(&mut io, &states).unwrap(), AppState::Shop);
+        assert!(io.output.borrow().iter().any(|line| line == "2. Shop"));
+
+        // Stdin reads nothing at all once it's closed
+        let mut io = MockIo::new(&[""]);
+        let err = select_app_state(&mut io, &states).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn test_read_trimmed_line_cancel_keywords() {
+        let mut io = MockIo::new(&["  hello  \n", "q\n", " BACK \n", "quit\n"]);
+
+        assert_eq!(read_trimmed_line(&mut io, "").unwrap(), "hello");
+        assert!(matches!(
+            read_trimmed_line(&mut io, ""),
+            Err(InputError::Cancelled)
+        ));
+        assert!(matches!(
+            read_trimmed_line(&mut io, ""),
+            Err(InputError::Cancelled)
+        ));
+        // Only exact keywords cancel
+        assert_eq!(read_trimmed_line(&mut io, "").unwrap(), "quit");
+    }
+
+    #[test]
+    fn test_read_text_line_escapes_cancel_keywords() {
+        let mut io = MockIo::new(&["q\n", "\\q\n", " \\Back \n", "\\quit\n"]);
+
+        assert!(matches!(
+            read_text_line(&mut io, ""),
+            Err(InputError::Cancelled)
+        ));
+        assert_eq!(read_text_line(&mut io, "").unwrap(), "q");
+        assert_eq!(read_text_line(&mut io, "").unwrap(), "Back");
+        // Anything that isn't an escaped keyword is left as typed
+        assert_eq!(read_text_line(&mut io, "").unwrap(), "\\quit");
+
+        let mut io = MockIo::new(&["\\back\n"]);
+        assert_eq!(request_folder_name(&mut io).unwrap(), "back");
+    }
+
+    #[test]
+    fn test_request_task_summary_rejects_long_summaries() {
+        let too_long = "a".repeat(11);
+        let mut io = MockIo::new(&[&too_long, "Shorter\n"]);
+
+        assert_eq!(request_task_summary(&mut io, 10).unwrap(), "Shorter");
+        assert!(io
+            .output
+            .borrow()
+            .iter()
+            .any(|line| line.contains("can't be longer than 10 characters")));
+    }
+
+    #[test]
+    fn test_request_deadline_details_bounds_lead_days() {
+        let mut io = MockIo::new(&["30\n", "10000\n", "31\n", "21\n", "14\n"]);
+
+        let (due_date, lead_days) = request_deadline_details(&mut io, 20).unwrap();
+
+        assert_eq!(lead_days, Some(14));
+        assert!(due_date.unwrap() > Utc::now() + Duration::days(29));
+        let rejections = io
+            .output
+            .borrow()
+            .iter()
+            .filter(|line| line.contains("can't be more than 20 days"))
+            .count();
+        assert_eq!(rejections, 3);
+
+        // The deadline is closer than the setting, so it's the limit
+        let mut io = MockIo::new(&["5\n", "6\n", "5\n"]);
+        let (_, lead_days) = request_deadline_details(&mut io, 20).unwrap();
+        assert_eq!(lead_days, Some(5));
+    }
+
+    #[test]
+    fn test_midnight_today_utc() {
+        let midnight = midnight_today_utc();
+        assert_eq!(
+            (midnight.hour(), midnight.minute(), midnight.second()),
+            (0, 0, 0)
+        );
+        assert_eq!(midnight.nanosecond(), 0);
+        assert!(Utc::now() - midnight < Duration::days(1));
+    }
+
+    #[test]
+    fn test_parse_due_input() {
+        let now = Utc.with_ymd_and_hms(2025, 3, 10, 15, 30, 0).unwrap();
+
+        assert_eq!(
+            parse_due_input("4", now),
+            Ok(Utc.with_ymd_and_hms(2025, 3, 14, 0, 0, 0).unwrap())
+        );
+        assert_eq!(
+            parse_due_input("2025-03-14", now),
+            Ok(Utc.with_ymd_and_hms(2025, 3, 14, 0, 0, 0).unwrap())
+        );
+        assert_eq!(
+            parse_due_input("2025-03-10", now),
+            Ok(Utc.with_ymd_and_hms(2025, 3, 10, 0, 0, 0).unwrap())
+        );
+        assert_eq!(
+            parse_due_input("2025-03-09", now),
+            Err(DueInputError::InPast)
+        );
+        for junk in ["", "-3", "soon", "2025-02-30", "14/03/2025"] {
+            assert_eq!(
+                parse_due_input(junk, now),
+                Err(DueInputError::Invalid),
+                "{junk}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_request_deadline_accepts_a_date() {
+        let in_ten_days = (Utc::now() + Duration::days(10)).date_naive();
+        let mut io = MockIo::new(&[
+            "whenever\n",
+            "2000-01-01\n",
+            &format!("{in_ten_days}\n"),
+            "4\n",
+        ]);
+
+        let (due_date, lead_days) = request_deadline_details(&mut io, 20).unwrap();
+
+        assert_eq!(due_date.unwrap().date_naive(), in_ten_days);
+        assert_eq!(lead_days, Some(4));
+        let output = io.output.borrow();
+        assert!(output.iter().any(|line| line.starts_with("Invalid input")));
+        assert!(output.iter().any(|line| line.contains("already passed")));
+    }
+
+    #[test]
+    fn test_render_trend() {
+        assert_eq!(render_trend(&[1.0, 2.0, 3.0, 4.0, 8.0]), "▁▂▃▄█");
+        assert_eq!(render_trend(&[2.5, 2.5]), "▅▅");
+        assert_eq!(render_trend(&[]), "");
+    }
+
+    #[test]
+    fn test_render_task_line() {
+        let now = Utc::now();
+        let task = Task {
+            priority: Priority::P2,
+            due_date: Some(now + Duration::days(2) - Duration::minutes(5)),
+            lead_days: Some(3),
+            ..crate::fixtures::task(1, "Review the draft")
+        };
+
+        assert_eq!(
+            render_task_line(&task, "Work::Clients", Some(1.5), now),
+            "[P2] Work::Clients — Review the draft (due in 2d) $1.50"
+        );
+
+        let overdue = Task {
+            due_date: Some(now - Duration::days(3)),
+            ..task.clone()
+        };
+        assert_eq!(
+            render_task_line(&overdue, "", None, now),
+            "[P2] Review the draft (overdue by 3d)"
+        );
+
+        let one_off = Task {
+            due_date: None,
+            lead_days: None,
+            ..task
+        };
+        assert_eq!(
+            render_task_line(&one_off, "Work", Some(1234.0), now),
+            "[P2] Work — Review the draft $1,234.00"
+        );
+    }
+
+    #[test]
+    fn test_long_summary_truncated_in_lists() {
+        let summary = "word ".repeat(100);
+        let mut io = MockIo::new(&["1\n"]);
+        let task = Task {
+            summary: summary.clone(),
+            ..crate::fixtures::task(1, "")
+        };
+
+        select_tasks(&mut io, &[task]).unwrap();
+
+        let output = io.output.borrow();
+        let line = output.iter().find(|line| line.starts_with("1. ")).unwrap();
+        assert_eq!(line.chars().count(), DEFAULT_MAX_SUMMARY_LENGTH + 3);
+        assert!(line.ends_with('…'));
+    }
+
+    #[test]
+    fn test_duration_round_trip() {
+        assert_eq!(parse_duration("1h30m"), Some(Duration::seconds(5400)));
+        assert_eq!(format_duration(Duration::seconds(5400)), "1h30m");
+        for text in ["45m", "2h", "30s", "10h5m"] {
+            assert_eq!(format_duration(parse_duration(text).unwrap()), text);
+        }
+
+        assert_eq!(parse_duration("1H 30M"), Some(Duration::minutes(90)));
+        assert_eq!(parse_duration("20"), Some(Duration::minutes(20)));
+        for invalid in ["", "h", "1h30", "90 minutes", "-5m", "1.5h"] {
+            assert_eq!(parse_duration(invalid), None, "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_parse_transaction() {
+        assert_eq!(parse_transaction("+50", true), Some(50.0));
+        assert_eq!(parse_transaction("-20", true), Some(-20.0));
+        assert_eq!(parse_transaction("50", true), None);
+
+        assert_eq!(parse_transaction("+50", false), Some(50.0));
+        assert_eq!(parse_transaction("50", false), Some(-50.0));
+        assert_eq!(parse_transaction("+-50", false), None);
+        assert_eq!(parse_transaction("-", false), None);
+        assert_eq!(parse_transaction("--5", false), None);
+    }
+
+    #[test]
+    fn test_strict_transaction_rejects_bare_amount() {
+        let conn = crate::fixtures::setup();
+        let mut io = MockIo::new(&["50\n", "+50\n"]);
+
+        request_transaction(&mut io, &conn, true).unwrap();
+
+        crate::fixtures::assert_funds(&conn, 50.0);
+        assert!(io
+            .output
+            .borrow()
+            .iter()
+            .any(|line| line.starts_with("Please start the amount with +")));
+    }
+
+    #[test]
+    fn test_parse_indices() {
+        assert_eq!(parse_indices("1, 3 4", 5), Some(vec![0, 2, 3]));
+        assert_eq!(parse_indices("2,2,1", 5), Some(vec![1, 0]));
+        assert_eq!(parse_indices("1, 6", 5), None);
+        assert_eq!(parse_indices("0", 5), None);
+        assert_eq!(parse_indices("one", 5), None);
+        assert_eq!(parse_indices(" , ", 5), None);
+    }
+
+    #[test]
+    fn test_render_bounty_breakdown() {
+        let breakdown = BountyBreakdown {
+            base: 2.0,
+            selection_factor: 1.0,
+            modifier: 1.5,
+            limits: BountyLimits::default(),
+        };
+
+        let rendered = render_bounty_breakdown(&breakdown, &MoneyFormat::default());
+
+        assert!(rendered.contains("$2.00 base value"));
+        assert!(rendered.contains("× 1.50 bounty modifier"));
+        assert!(rendered.ends_with("= $3.00"));
+
+        let floored = BountyBreakdown {
+            limits: BountyLimits {
+                floor: Some(5.0),
+                ceiling: None,
+            },
+            ..breakdown
+        };
+        let rendered = render_bounty_breakdown(&floored, &MoneyFormat::default());
+        assert!(rendered.contains("= $3.00, raised to this priority's floor"));
+        assert!(rendered.ends_with("= $5.00"));
+    }
+
+    #[test]
+    fn test_confirm() {
+        let mut io = MockIo::new(&["y\n", "YES\n", "\n", "nope\n"]);
+
+        assert!(confirm(&mut io, "").unwrap());
+        assert!(confirm(&mut io, "").unwrap());
+        assert!(!confirm(&mut io, "").unwrap());
+        assert!(!confirm(&mut io, "").unwrap());
+    }
+
+    #[test]
+    fn test_render_heatmap_scales_by_count() {
+        let day = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let history: Vec<(NaiveDate, u32)> = [0, 1, 2, 3, 4, 8].iter().map(|n| (day, *n)).collect();
+
+        assert_eq!(render_heatmap(&history), "·░░▒▒█");
+        assert_eq!(render_heatmap(&[]), "");
+    }
+
+    #[test]
+    fn test_format_money_comma_grouped() {
+        let format = MoneyFormat::default();
+
+        assert_eq!(format_money(0.0, &format), "$0.00");
+        assert_eq!(format_money(5.5, &format), "$5.50");
+        assert_eq!(format_money(999.994, &format), "$999.99");
+        assert_eq!(format_money(999.995, &format), "$1,000.00");
+        assert_eq!(format_money(12345.6, &format), "$12,345.60");
+        assert_eq!(format_money(1234567.891, &format), "$1,234,567.89");
+        assert_eq!(format_money(-12345.6, &format), "-$12,345.60");
+        assert_eq!(format_money(-0.001, &format), "$0.00");
+    }
+
+    #[test]
+    fn test_format_money_period_grouped() {
+        let format = MoneyFormat {
+            style: NumberStyle::PeriodGrouped,
+            decimals: 2,
+        };
+
+        assert_eq!(format_money(12.3, &format), "$12,30");
+        assert_eq!(format_money(12345.6, &format), "$12.345,60");
+        assert_eq!(format_money(-1234567.0, &format), "-$1.234.567,00");
+    }
+
+    #[test]
+    fn test_format_money_decimals() {
+        let whole_dollars = MoneyFormat {
+            style: NumberStyle::CommaGrouped,
+            decimals: 0,
+        };
+        let three_places = MoneyFormat {
+            decimals: 3,
+            ..whole_dollars
+        };
+
+        assert_eq!(format_money(12345.6, &whole_dollars), "$12,346");
+        assert_eq!(format_money(-1000.0, &whole_dollars), "-$1,000");
+        assert_eq!(format_money(1234.5678, &three_places), "$1,234.568");
+    }
+
+    #[test]
+    fn test_truncate_to_short_string_unchanged() {
+        assert_eq!(truncate_to("Short", 10), "Short");
+        assert_eq!(truncate_to("Exactly10!", 10), "Exactly10!");
+    }
+
+    #[test]
+    fn test_truncate_to_multibyte_boundary() {
+        // Each of these characters is more than one byte long
+        let s = "héllo wörld ✓✓✓";
+
+        assert_eq!(truncate_to(s, 9), "héllo wö…");
+        assert_eq!(truncate_to("✓✓✓✓", 3), "✓✓…");
+        assert_eq!(truncate_to("✓✓✓✓", 0), "");
+    }
+
+    #[test]
+    fn test_truncate_to_single_line() {
+        assert_eq!(truncate_to("First line\nSecond line", 60), "First line…");
+        assert_eq!(truncate_to("Trailing newline\n", 60), "Trailing newline");
     }
 }
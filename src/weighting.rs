@@ -1,83 +1,479 @@
+use super::clock::Clock;
 use super::tasks::{Priority, Task};
 use chrono::{DateTime, Duration, Utc};
 
-pub fn calculate_weight(task: &Task) -> f32 {
+/// Controls how far a deadline task's weight keeps growing once it's
+/// overdue.
+///
+/// The weight (before priority) climbs from 1.0 at the start of the lead days
+/// to `WEIGHT_AT_DUE_DATE` on the due date. Once overdue it keeps climbing,
+/// reaching `cap` times that after `critical_days` and staying there, so a
+/// long-forgotten deadline can't drown out every other task.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OverdueCurve {
+    pub cap: f32,
+    pub critical_days: u32,
+}
+
+impl Default for OverdueCurve {
+    fn default() -> Self {
+        OverdueCurve {
+            cap: 3.0,
+            critical_days: 14,
+        }
+    }
+}
+
+/// What a deadline task weighs (before priority) on its due date.
+pub const WEIGHT_AT_DUE_DATE: f32 = 101.0;
+
+/// What a task's weight is multiplied by when the user first boosts it.
+pub const BOOST_FACTOR: f32 = 2.0;
+
+/// How many days a boost takes to fade out.
+pub const BOOST_DAYS: i64 = 3;
+
+/// Calculates how strongly a task should be recommended. Higher is stronger.
+///
+/// # Notes
+///
+/// Never returns a negative or NaN weight. A from_date in the future is
+/// treated as now, see `Task::effective_from_date()`.
+pub fn calculate_weight(task: &Task, curve: &OverdueCurve, clock: &impl Clock) -> f32 {
+    calculate_weight_at(task, curve, clock.now())
+}
+
+/// Same as `calculate_weight()`, but as if the current time were `now`.
+pub fn calculate_weight_at(task: &Task, curve: &OverdueCurve, now: DateTime<Utc>) -> f32 {
     // I'm sure there is a more elegant way to structure this logic in Rust
-    match task.due_date {
-        Some(_) => weight_due_task(task),
+    let weight = match task.due_date {
+        Some(due_date) => weight_due_task(task, due_date, curve, now),
         None => match task.repeat_interval {
-            Some(_) => weight_repeat_task(task),
-            None => weight_oneoff_task(task),
+            Some(_) => weight_repeat_task(task, now),
+            None => weight_oneoff_task(task, now),
         },
+    } * boost_multiplier(task, now);
+
+    if weight.is_nan() {
+        0.0
+    } else {
+        weight.max(0.0)
     }
 }
 
-fn weight_due_task(task: &Task) -> f32 {
+/// How much a manual boost still counts for. It fades linearly from the
+/// task's boost_factor, `BOOST_DAYS` before boost_until, down to 1.0 at
+/// boost_until.
+///
+/// # Returns
+///
+/// 1.0 if the task has no boost, or its boost has run out.
+pub fn boost_multiplier(task: &Task, now: DateTime<Utc>) -> f32 {
+    let Some(until) = task.boost_until else {
+        return 1.0;
+    };
+    let left = (until - now).num_seconds() as f32 / (BOOST_DAYS * 86400) as f32;
+
+    1.0 + (task.boost_factor - 1.0) * left.clamp(0.0, 1.0)
+}
+
+/// Shifts a task's weight from its bounty towards its priority as the user
+/// runs out of monthly budget, since extra pay stops mattering near the cap.
+///
+/// # Arguments
+///
+/// * `weight: f32` - The task's weight from `calculate_weight_at()`.
+/// * `task: &Task` - The task being weighed.
+/// * `budget_remaining: f32` - The fraction of the maximum monthly allowance
+///   still to be earned, from 0.0 to 1.0. See `finance::budget_remaining()`.
+///
+/// # Notes
+///
+/// With the whole budget left, the bounty_modifier scales the weight the same
+/// way it scales the bounty and priority is untouched. With none left, the
+/// bounty_modifier is ignored and priority counts twice.
+pub fn apply_budget_pressure(weight: f32, task: &Task, budget_remaining: f32) -> f32 {
+    let remaining = budget_remaining.clamp(0.0, 1.0);
+    let bounty_factor = (1.0 + task.bounty_modifier * remaining).max(0.0);
+    let priority_factor = adjust_for_priority(task).powf(1.0 - remaining);
+
+    weight * bounty_factor * priority_factor
+}
+
+/// Rescales the weights of a set of tasks to a 0-100 urgency score, so the
+/// heaviest task scores 100 and the rest are in proportion to it.
+///
+/// # Returns
+///
+/// One score per task, in the same order. Every score is 0 if no task has
+/// any weight.
+///
+/// # Notes
+///
+/// The scores are only for display. Rank by the raw weights.
+pub fn normalized_scores(tasks: &[Task], curve: &OverdueCurve, clock: &impl Clock) -> Vec<f32> {
+    let now = clock.now();
+    let weights: Vec<f32> = tasks
+        .iter()
+        .map(|task| calculate_weight_at(task, curve, now))
+        .collect();
+    let max = weights.iter().copied().fold(0.0, f32::max);
+
+    weights
+        .iter()
+        .map(|weight| if max > 0.0 { weight / max * 100.0 } else { 0.0 })
+        .collect()
+}
+
+/// The weight of a task with a deadline.
+///
+/// # Notes
+///
+/// A task without lead_days is treated as having none, so it only ramps up
+/// once it's due.
+fn weight_due_task(
+    task: &Task,
+    due_date: DateTime<Utc>,
+    curve: &OverdueCurve,
+    now: DateTime<Utc>,
+) -> f32 {
     let mut weight: f32;
+    let lead_days = task.lead_days.unwrap_or(0);
 
-    if DateTime::<Utc>::timestamp(&Utc::now())
-        <= DateTime::<Utc>::timestamp(&task.due_date.unwrap())
-            - (task.lead_days.unwrap() as i64 * 86400)
+    if DateTime::<Utc>::timestamp(&now)
+        <= DateTime::<Utc>::timestamp(&due_date) - (lead_days as i64 * 86400)
     {
         // y = now / ( due_date - lead_days[as seconds] )
-        weight = DateTime::<Utc>::timestamp(&Utc::now()) as f32
-            / (DateTime::<Utc>::timestamp(&task.due_date.unwrap()) as f32
-                - (task.lead_days.unwrap() as f32 * 86400.0));
+        weight = DateTime::<Utc>::timestamp(&now) as f32
+            / (DateTime::<Utc>::timestamp(&due_date) as f32 - (lead_days as f32 * 86400.0));
     } else {
-        // y = 1 + 100(now-due_date+lead_days[as seconds])/lead_days[as seconds]
-        // this will panic if you have a due date and no lead days... very non-rust
-        weight = (100.0
-            * ((DateTime::<Utc>::timestamp(&Utc::now()) as f32)
-                - (DateTime::<Utc>::timestamp(&task.due_date.unwrap()) as f32)
-                + (task.lead_days.unwrap() as f32 * 86400.0))
-            / (task.lead_days.unwrap() as f32 * 86400.0))
-            + 1.0;
+        weight = weight_within_lead_days(now - due_date, lead_days, curve);
     }
 
-    weight = weight * adjust_for_priority(task);
+    weight *= adjust_for_priority(task);
 
     weight
 }
 
-fn weight_repeat_task(task: &Task) -> f32 {
+/// The weight of a deadline task that has entered its lead days. Until the
+/// due date it ramps up as y = 1 + 100(fraction of the lead days lapsed).
+/// `OverdueCurve` only shapes what happens after that.
+///
+/// # Arguments
+///
+/// * `overdue: Duration` - How long ago the due date was. Negative if it's
+///   still to come.
+/// * `lead_days: u32` - How many days before the due date the task ramps up.
+/// * `curve: &OverdueCurve` - The limits of the ramp.
+fn weight_within_lead_days(overdue: Duration, lead_days: u32, curve: &OverdueCurve) -> f32 {
+    let at_due_date = WEIGHT_AT_DUE_DATE;
+    let cap = at_due_date * curve.cap.max(1.0);
+    let days_overdue = overdue.num_seconds() as f32 / 86400.0;
+
+    if days_overdue < 0.0 {
+        // y = 1 + 100(now-due_date+lead_days)/lead_days
+        let lead_days = lead_days.max(1) as f32;
+        let lapsed = ((lead_days + days_overdue) / lead_days).max(0.0);
+        1.0 + (at_due_date - 1.0) * lapsed
+    } else if days_overdue < curve.critical_days as f32 {
+        // y = at_due_date + (cap - at_due_date)(fraction of critical_days lapsed)
+        at_due_date + (cap - at_due_date) * days_overdue / curve.critical_days as f32
+    } else {
+        cap
+    }
+}
+
+fn weight_repeat_task(task: &Task, now: DateTime<Utc>) -> f32 {
+    let from_date = task.from_date.min(now);
+
     // Returning a weight of 0.0 if the task isn't old enough to be selected
-    if task.from_date + Duration::days(i64::from(task.repeat_interval.unwrap())) >= Utc::now() {
+    let interval = task.repeat_duration().unwrap();
+    if from_date + interval >= now {
         return 0.0;
     }
 
     let mut weight: f32 = 1.0;
-    weight = weight * adjust_for_priority(task);
+    weight *= adjust_for_priority(task);
 
     // y=0.667x+0.333 where x is the number of repeat_intervals lapsed
-    weight = weight
-        * (0.667
-            * (DateTime::<Utc>::timestamp(&Utc::now()) as f32
-                / (task.from_date + Duration::days(i64::from(task.repeat_interval.unwrap())))
-                    .timestamp() as f32)
-            + 0.333);
+    weight *= 0.667
+        * (DateTime::<Utc>::timestamp(&now) as f32 / (from_date + interval).timestamp() as f32)
+        + 0.333;
 
     weight
 }
 
-fn weight_oneoff_task(task: &Task) -> f32 {
+fn weight_oneoff_task(task: &Task, now: DateTime<Utc>) -> f32 {
     let mut weight: f32 = 1.0;
-    weight = weight * adjust_for_priority(task);
+    weight *= adjust_for_priority(task);
 
     // y=0.667x+1 where x is the number of 20 day periods lapsed
-    weight = weight
-        * (0.667
-            * (DateTime::<Utc>::timestamp(&Utc::now()) as f32
-                / (task.from_date + Duration::days(20)).timestamp() as f32)
-            + 1.0);
+    weight *= 0.667
+        * (DateTime::<Utc>::timestamp(&now) as f32
+            / (task.from_date.min(now) + Duration::days(20)).timestamp() as f32)
+        + 1.0;
 
     weight
 }
 
 fn adjust_for_priority(task: &Task) -> f32 {
     match task.priority {
+        Priority::Neutral => 1.0,
         Priority::P0 => 2.0,
         Priority::P1 => 3.0,
         Priority::P2 => 5.0,
         Priority::P3 => 8.0,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::{FixedClock, SystemClock};
+    use crate::fixtures;
+    use crate::tasks::RepeatUnit;
+
+    #[test]
+    fn test_budget_pressure_favours_priority_near_cap() {
+        let now = Utc::now();
+        let well_paid = Task {
+            bounty_modifier: 1.0,
+            ..fixtures::task(1, "Well paid")
+        };
+        let important = Task {
+            priority: Priority::P2,
+            ..fixtures::task(1, "Important")
+        };
+        let curve = OverdueCurve::default();
+        let weigh = |task: &Task, remaining: f32| {
+            apply_budget_pressure(calculate_weight_at(task, &curve, now), task, remaining)
+        };
+
+        assert!(weigh(&well_paid, 1.0) > weigh(&important, 1.0));
+        assert!(weigh(&well_paid, 0.0) < weigh(&important, 0.0));
+    }
+
+    #[test]
+    fn test_due_task_without_lead_days() {
+        let now = Utc::now();
+        let curve = OverdueCurve::default();
+        let weigh = |due_in: Duration| {
+            let task = Task {
+                due_date: Some(now + due_in),
+                lead_days: None,
+                ..fixtures::task(1, "No lead days")
+            };
+            calculate_weight_at(&task, &curve, now)
+        };
+
+        for due_in in [Duration::days(5), Duration::zero(), Duration::days(-5)] {
+            assert!(weigh(due_in).is_finite(), "{due_in}");
+        }
+        assert!(weigh(Duration::days(-5)) > weigh(Duration::days(5)));
+    }
+
+    #[test]
+    fn test_boost_fades_out() {
+        let now = Utc::now();
+        let curve = OverdueCurve::default();
+        let plain = fixtures::task(1, "Plain");
+        let boosted = Task {
+            boost_factor: BOOST_FACTOR,
+            boost_until: Some(now + Duration::days(BOOST_DAYS)),
+            ..plain.clone()
+        };
+        let weigh = |task: &Task, at| calculate_weight_at(task, &curve, at);
+
+        let ratio = weigh(&boosted, now) / weigh(&plain, now);
+        assert!((ratio - BOOST_FACTOR).abs() < 0.001);
+
+        let halfway = now + Duration::hours(BOOST_DAYS * 12);
+        let ratio = weigh(&boosted, halfway) / weigh(&plain, halfway);
+        assert!((ratio - (1.0 + BOOST_FACTOR) / 2.0).abs() < 0.001);
+
+        let expired = now + Duration::days(BOOST_DAYS + 1);
+        assert_eq!(weigh(&boosted, expired), weigh(&plain, expired));
+    }
+
+    #[test]
+    fn test_repeat_task_waits_for_its_interval() {
+        let clock = FixedClock::new(Utc::now());
+        let curve = OverdueCurve::default();
+        let weekly = Task {
+            repeat_interval: Some(7),
+            from_date: clock.now(),
+            ..fixtures::task(1, "Weekly")
+        };
+
+        assert_eq!(calculate_weight(&weekly, &curve, &clock), 0.0);
+        clock.advance(Duration::days(7));
+        assert_eq!(calculate_weight(&weekly, &curve, &clock), 0.0);
+
+        clock.advance(Duration::hours(1));
+        let just_due = calculate_weight(&weekly, &curve, &clock);
+        assert!(just_due > 0.0);
+
+        clock.advance(Duration::days(7));
+        assert!(calculate_weight(&weekly, &curve, &clock) > just_due);
+    }
+
+    #[test]
+    fn test_hourly_task_waits_for_its_interval() {
+        let clock = FixedClock::new(Utc::now());
+        let curve = OverdueCurve::default();
+        let stretch = Task {
+            repeat_interval: Some(4),
+            repeat_unit: RepeatUnit::Hours,
+            from_date: clock.now(),
+            ..fixtures::task(1, "Stretch")
+        };
+
+        clock.advance(Duration::hours(3));
+        assert_eq!(calculate_weight(&stretch, &curve, &clock), 0.0);
+        clock.advance(Duration::hours(2));
+        assert!(calculate_weight(&stretch, &curve, &clock) > 0.0);
+    }
+
+    #[test]
+    fn test_normalized_scores() {
+        let curve = OverdueCurve::default();
+        let tasks: Vec<Task> = [2, 8, 4]
+            .iter()
+            .map(|days| Task {
+                from_date: Utc::now() - Duration::days(*days),
+                ..fixtures::task(1, "Task")
+            })
+            .collect();
+
+        let scores = normalized_scores(&tasks, &curve, &SystemClock);
+        let heaviest = calculate_weight(&tasks[1], &curve, &SystemClock);
+        assert!((scores[1] - 100.0).abs() < 0.01);
+        for (task, score) in tasks.iter().zip(&scores) {
+            let expected = calculate_weight(task, &curve, &SystemClock) / heaviest * 100.0;
+            assert!((score - expected).abs() < 0.01, "{score} != {expected}");
+        }
+        assert!(scores[0] < scores[2] && scores[2] < scores[1]);
+
+        assert!(normalized_scores(&[], &curve, &SystemClock).is_empty());
+    }
+
+    #[test]
+    fn test_neutral_priority_multiplier() {
+        let task = Task {
+            priority: Priority::Neutral,
+            ..fixtures::task(1, "Neutral")
+        };
+
+        assert_eq!(adjust_for_priority(&task), 1.0);
+    }
+
+    #[test]
+    fn test_neutral_ranks_below_deprioritized() {
+        let neutral = Task {
+            priority: Priority::Neutral,
+            ..fixtures::task(1, "Neutral")
+        };
+        let deprioritized = Task {
+            priority: Priority::P0,
+            ..neutral.clone()
+        };
+
+        let curve = OverdueCurve::default();
+        assert!(
+            calculate_weight(&neutral, &curve, &SystemClock)
+                < calculate_weight(&deprioritized, &curve, &SystemClock)
+        );
+    }
+
+    /// Builds a neutral-priority deadline task that was due `days` ago.
+    fn overdue_task(days: i64) -> Task {
+        Task {
+            due_date: Some(Utc::now() - Duration::days(days)),
+            lead_days: Some(3),
+            priority: Priority::Neutral,
+            ..fixtures::task(1, "Overdue")
+        }
+    }
+
+    #[test]
+    fn test_overdue_weight_is_capped() {
+        let curve = OverdueCurve::default();
+        let weights: Vec<f32> = [1, 5, 50, 5000]
+            .iter()
+            .map(|&days| calculate_weight(&overdue_task(days), &curve, &SystemClock))
+            .collect();
+
+        // Still grows while the task is becoming more overdue...
+        assert!(weights[0] < weights[1]);
+        assert!(weights[1] < weights[2]);
+        // ...but never past the cap
+        for weight in &weights {
+            assert!(
+                *weight > WEIGHT_AT_DUE_DATE && *weight <= WEIGHT_AT_DUE_DATE * curve.cap,
+                "{weight}"
+            );
+        }
+        assert_eq!(weights[2], weights[3]);
+    }
+
+    #[test]
+    fn test_future_from_date_treated_as_now() {
+        let curve = OverdueCurve::default();
+        let future = Utc::now() + Duration::hours(1);
+        let oneoff = Task {
+            from_date: future,
+            ..fixtures::task(1, "From the future")
+        };
+        let fresh = fixtures::task(1, "Brand new");
+
+        let weight = calculate_weight(&oneoff, &curve, &SystemClock);
+        assert!(weight.is_finite() && weight >= 0.0);
+        assert!((weight - calculate_weight(&fresh, &curve, &SystemClock)).abs() < 0.001);
+
+        let recurring = Task {
+            repeat_interval: Some(1),
+            from_date: future + Duration::days(365),
+            ..fixtures::task(1, "Recurring")
+        };
+        assert_eq!(calculate_weight(&recurring, &curve, &SystemClock), 0.0);
+        assert!(recurring.effective_from_date() <= Utc::now());
+    }
+
+    #[test]
+    fn test_overdue_weight_ramps_through_lead_days() {
+        let curve = OverdueCurve::default();
+        let due_in = |days: i64| Task {
+            due_date: Some(Utc::now() + Duration::days(days)),
+            ..overdue_task(0)
+        };
+
+        let starting = calculate_weight(&due_in(3), &curve, &SystemClock);
+        let halfway = calculate_weight(&due_in(1), &curve, &SystemClock);
+        let due_now = calculate_weight(&due_in(0), &curve, &SystemClock);
+
+        assert!((starting - 1.0).abs() < 0.01);
+        assert!(starting < halfway && halfway < due_now);
+    }
+
+    #[test]
+    fn test_due_date_weight_matches_the_baseline_ramp() {
+        let due_now = overdue_task(0);
+        // The cap only shapes what happens after the due date
+        for cap in [1.0, 3.0, 10.0] {
+            let curve = OverdueCurve {
+                cap,
+                critical_days: 14,
+            };
+            let weight = calculate_weight_at(&due_now, &curve, due_now.due_date.unwrap());
+            assert!((weight - WEIGHT_AT_DUE_DATE).abs() < 0.01, "{weight}");
+        }
+
+        // A day into the lead days is a third of the way up the ramp
+        let in_lead = calculate_weight_at(
+            &due_now,
+            &OverdueCurve::default(),
+            due_now.due_date.unwrap() - Duration::days(2),
+        );
+        assert!((in_lead - (1.0 + 100.0 / 3.0)).abs() < 0.01, "{in_lead}");
+    }
+}